@@ -10,6 +10,7 @@
 use actix_files::NamedFile;
 use actix_http::header::{HeaderName, HeaderValue, ACCEPT, LOCATION, REFERER};
 use actix_web::{
+    body::{BodySize, BodyStream, BoxBody, MessageBody},
     dev::{ServiceFactory, ServiceRequest},
     http::header,
     test,
@@ -41,8 +42,12 @@ use leptos_router::{
 use parking_lot::RwLock;
 use send_wrapper::SendWrapper;
 use server_fn::{
-    error::ServerFnErrorErr, redirect::REDIRECT_HEADER,
+    error::ServerFnErrorErr,
+    middleware::BoxedService,
+    redirect::REDIRECT_HEADER,
     request::actix::ActixRequest,
+    response::actix::ActixResponse as ServerFnResponse,
+    server::{CancelOnDrop, CancellationToken},
 };
 use std::{
     collections::HashSet,
@@ -50,7 +55,9 @@ use std::{
     future::Future,
     ops::{Deref, DerefMut},
     path::Path,
+    pin::Pin,
     sync::{Arc, LazyLock},
+    task::{ready, Poll},
 };
 
 /// This struct lets you define headers and override the status of the Response from an Element or a Server Function
@@ -306,6 +313,7 @@ pub fn redirect(path: &str) {
 /// This function always provides context values including the following types:
 /// - [ResponseOptions]
 /// - [Request]
+/// - [CancellationToken]
 #[cfg_attr(
     feature = "tracing",
     tracing::instrument(level = "trace", fields(error), skip_all)
@@ -333,6 +341,7 @@ pub fn handle_server_fns() -> Route {
 /// This function always provides context values including the following types:
 /// - [ResponseOptions]
 /// - [Request]
+/// - [CancellationToken]
 #[cfg_attr(
     feature = "tracing",
     tracing::instrument(level = "trace", fields(error), skip_all)
@@ -347,71 +356,30 @@ pub fn handle_server_fns_with_context(
 
             let path = req.path();
             let method = req.method();
-            if let Some(mut service) =
+            if path == server_fn::batch::batch_path() {
+                // each call in the batch runs with its own context
+                server_fn::actix::handle_batch(
+                    req,
+                    payload,
+                    move |service, req| {
+                        run_server_fn(additional_context.clone(), service, req)
+                    },
+                )
+                .await
+            } else if let Some(service) =
                 server_fn::actix::get_server_fn_service(path, method)
             {
-                let owner = Owner::new();
-                owner
-                    .with(|| {
-                        ScopedFuture::new(async move {
-                            provide_context(Request::new(&req));
-                            let res_options = ResponseOptions::default();
-                            provide_context(res_options.clone());
-                            additional_context();
-
-                            // store Accepts and Referer in case we need them for redirect (below)
-                            let accepts_html = req
-                                .headers()
-                                .get(ACCEPT)
-                                .and_then(|v| v.to_str().ok())
-                                .map(|v| v.contains("text/html"))
-                                .unwrap_or(false);
-                            let referrer = req.headers().get(REFERER).cloned();
-
-                            // actually run the server fn
-                            let mut res = ActixResponse(
-                                service
-                                    .run(ActixRequest::from((req, payload)))
-                                    .await
-                                    .take(),
-                            );
-
-                            // if it accepts text/html (i.e., is a plain form post) and doesn't already have a
-                            // Location set, then redirect to the Referer
-                            if accepts_html {
-                                if let Some(referrer) = referrer {
-                                    let has_location =
-                                        res.0.headers().get(LOCATION).is_some();
-                                    if !has_location {
-                                        *res.0.status_mut() = StatusCode::FOUND;
-                                        res.0
-                                            .headers_mut()
-                                            .insert(LOCATION, referrer);
-                                    }
-                                }
-                            }
-
-                            // the Location header may have been set to Referer, so any redirection by the
-                            // user must overwrite it
-                            {
-                                let mut res_options = res_options.0.write();
-                                let headers = res.0.headers_mut();
-
-                                for location in
-                                    res_options.headers.remove(header::LOCATION)
-                                {
-                                    headers.insert(header::LOCATION, location);
-                                }
-                            }
-
-                            // apply status code and headers if user changed them
-                            res.extend_response(&res_options);
-                            res.0
-                        })
-                    })
-                    .await
+                let req = ActixRequest::from((req, payload));
+                run_server_fn(additional_context, service, req).await.take()
             } else {
-                HttpResponse::BadRequest().body(format!(
+                let mut res = HttpResponse::BadRequest();
+                if let Some(id) = server_fn::version::build_id() {
+                    res.insert_header((
+                        server_fn::version::BUILD_ID_HEADER,
+                        id,
+                    ));
+                }
+                res.body(format!(
                     "Could not find a server function at the route {:?}. \
                      \n\nIt's likely that either
                          1. The API prefix you specify in the `#[server]` \
@@ -428,6 +396,201 @@ pub fn handle_server_fns_with_context(
     })
 }
 
+async fn run_server_fn(
+    additional_context: impl Fn() + 'static + Clone + Send,
+    service: BoxedService<ActixRequest, ServerFnResponse>,
+    req: ActixRequest,
+) -> ServerFnResponse {
+    // each call runs in its own span, which continues the client's trace
+    #[cfg(feature = "trace-propagation")]
+    {
+        use tracing::Instrument;
+
+        let (span, trace_context) =
+            server_fn::trace::server_span(req.path(), |name| {
+                req.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+            });
+        server_fn::trace::with_trace_context(
+            trace_context,
+            run_server_fn_inner(additional_context, service, req),
+        )
+        .instrument(span)
+        .await
+    }
+    #[cfg(not(feature = "trace-propagation"))]
+    run_server_fn_inner(additional_context, service, req).await
+}
+
+async fn run_server_fn_inner(
+    additional_context: impl Fn() + 'static + Clone + Send,
+    mut service: BoxedService<ActixRequest, ServerFnResponse>,
+    req: ActixRequest,
+) -> ServerFnResponse {
+    let owner = Owner::new();
+    owner
+        .with(|| {
+            ScopedFuture::new(async move {
+                provide_context(Request::new(req.request()));
+                let res_options = ResponseOptions::default();
+                provide_context(res_options.clone());
+                // if the client disconnects, this future is dropped before it completes
+                let cancellation = CancellationToken::new();
+                let cancel_on_drop = cancellation.drop_guard();
+                provide_context(cancellation);
+                additional_context();
+
+                // store Accepts and Referer in case we need them for redirect (below)
+                let accepts_html = req
+                    .headers()
+                    .get(ACCEPT)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.contains("text/html"))
+                    .unwrap_or(false);
+                let referrer = req.headers().get(REFERER).cloned();
+
+                // actually run the server fn
+                let mut res = ActixResponse(service.run(req).await.take());
+
+                // if it accepts text/html (i.e., is a plain form post) and doesn't already have a
+                // Location set, then redirect to the Referer
+                if accepts_html {
+                    if let Some(referrer) = referrer {
+                        let has_location =
+                            res.0.headers().get(LOCATION).is_some();
+                        if !has_location {
+                            *res.0.status_mut() = StatusCode::FOUND;
+                            res.0.headers_mut().insert(LOCATION, referrer);
+                        }
+                    }
+                }
+
+                // the Location header may have been set to Referer, so any redirection by the
+                // user must overwrite it
+                {
+                    let mut res_options = res_options.0.write();
+                    let headers = res.0.headers_mut();
+
+                    for location in res_options.headers.remove(header::LOCATION)
+                    {
+                        headers.insert(header::LOCATION, location);
+                    }
+                }
+
+                // apply status code and headers if user changed them
+                res.extend_response(&res_options);
+                // a streaming body is still produced by the server fn, so the client can
+                // disconnect after the handler returns: only disarm once it has been sent
+                let res = if matches!(res.0.body().size(), BodySize::Stream) {
+                    res.0.map_body(|_, body| {
+                        cancel_on_body_drop(body, cancel_on_drop)
+                    })
+                } else {
+                    cancel_on_drop.disarm();
+                    res.0
+                };
+                #[cfg(feature = "trace-propagation")]
+                tracing::Span::current()
+                    .record("http.response.status_code", res.status().as_u16());
+                ServerFnResponse::from(res)
+            })
+        })
+        .await
+}
+
+/// Wraps a response body so that the guard cancels the server fn's [`CancellationToken`] if the
+/// body is dropped before it has been sent completely.
+fn cancel_on_body_drop(
+    mut body: BoxBody,
+    cancel_on_drop: CancelOnDrop,
+) -> BoxBody {
+    let mut cancel_on_drop = Some(cancel_on_drop);
+    let stream = futures::stream::poll_fn(move |cx| {
+        let next = ready!(Pin::new(&mut body).poll_next(cx));
+        if next.is_none() {
+            if let Some(cancel_on_drop) = cancel_on_drop.take() {
+                cancel_on_drop.disarm();
+            }
+        }
+        Poll::Ready(next)
+    });
+    BoxBody::new(BodyStream::new(stream))
+}
+
+/// Returns a [`TestServer`](server_fn::testing::actix::TestServer) that calls server functions
+/// in-process, with the same context as [`handle_server_fns`].
+///
+/// ## Provided Context Types
+/// Each call is provided context values including the following types:
+/// - [ResponseOptions]
+/// - [Request]
+/// - [CancellationToken]
+pub fn test_server() -> server_fn::testing::actix::TestServer {
+    test_server_with_context(|| {})
+}
+
+/// Returns a [`TestServer`](server_fn::testing::actix::TestServer) that calls server functions
+/// in-process, with the same context as [`handle_server_fns_with_context`].
+///
+/// This can be used to test server functions that use the [Request], the [ResponseOptions],
+/// or context provided by `additional_context`, without an HTTP server:
+/// ```rust,ignore
+/// #[actix_web::test]
+/// async fn sets_the_status() {
+///     let server = leptos_actix::test_server_with_context(|| provide_context(pool()));
+///     let (output, res) = server.call_with_response(CreatePost { title }).await;
+///     assert_eq!(res.unwrap().status(), StatusCode::CREATED);
+/// }
+/// ```
+///
+/// ## Provided Context Types
+/// Each call is provided context values including the following types:
+/// - [ResponseOptions]
+/// - [Request]
+/// - [CancellationToken]
+pub fn test_server_with_context(
+    additional_context: impl Fn() + 'static + Clone + Send + Sync,
+) -> server_fn::testing::actix::TestServer {
+    server_fn::testing::actix::TestServer::new().with_dispatch(
+        move |service, req| {
+            run_server_fn(additional_context.clone(), service, req)
+        },
+    )
+}
+
+/// An Actix [struct@Route](actix_web::Route) that serves an
+/// [OpenAPI 3.1](https://spec.openapis.org/oas/v3.1.0) document describing every
+/// registered server function.
+///
+/// The document is generated the first time it is requested, and cached afterwards.
+///
+/// ```rust,ignore
+/// use leptos_actix::openapi_route;
+/// use server_fn::openapi::OpenApi;
+///
+/// App::new().route(
+///     "/api/openapi.json",
+///     openapi_route(OpenApi::new("My App", "1.0")),
+/// )
+/// ```
+#[cfg(feature = "openapi")]
+pub fn openapi_route(api: server_fn::openapi::OpenApi) -> Route {
+    let document = Arc::new(std::sync::OnceLock::new());
+    web::get().to(move || {
+        let body = document
+            .get_or_init(|| {
+                server_fn::actix::openapi_document(&api).to_string()
+            })
+            .clone();
+        async move {
+            HttpResponse::Ok()
+                .content_type("application/json")
+                .body(body)
+        }
+    })
+}
+
 /// Returns an Actix [struct@Route](actix_web::Route) that listens for a `GET` request and tries
 /// to route it using [leptos_router], serving an HTML stream of your application. The stream
 /// will include fallback content for any `<Suspense/>` nodes, and be immediately interactive,
@@ -1653,3 +1816,31 @@ where
     })
     .await
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn streaming_body_cancels_unless_sent() {
+        let stream = || {
+            BoxBody::new(BodyStream::new(futures::stream::iter([Ok::<
+                _,
+                std::io::Error,
+            >(
+                web::Bytes::from("chunk"),
+            )])))
+        };
+
+        let token = CancellationToken::new();
+        let body = cancel_on_body_drop(stream(), token.drop_guard());
+        drop(body);
+        assert!(token.is_cancelled());
+
+        let token = CancellationToken::new();
+        let body = cancel_on_body_drop(stream(), token.drop_guard());
+        let bytes = body::to_bytes(body).await.unwrap();
+        assert_eq!(bytes, "chunk");
+        assert!(!token.is_cancelled());
+    }
+}
@@ -0,0 +1,21 @@
+use actix_web::{http::StatusCode, test, App};
+use leptos::server_fn::version::{set_build_id, BUILD_ID_HEADER};
+
+const BUILD_ID: &str = "test-build";
+
+#[actix_web::test]
+async fn unknown_server_fns_advertise_the_build_id() {
+    _ = set_build_id(BUILD_ID);
+    let app = test::init_service(
+        App::new().route("/api/{tail:.*}", leptos_actix::handle_server_fns()),
+    )
+    .await;
+
+    // a client of an older build may call a server function that no longer exists
+    let req = test::TestRequest::post()
+        .uri("/api/v0/list_posts")
+        .to_request();
+    let res = test::call_service(&app, req).await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.headers().get(BUILD_ID_HEADER).unwrap(), BUILD_ID);
+}
@@ -37,7 +37,7 @@
 #[cfg(feature = "default")]
 use axum::http::Uri;
 use axum::{
-    body::{Body, Bytes},
+    body::{Body, Bytes, HttpBody},
     extract::{FromRef, FromRequestParts, MatchedPath, State},
     http::{
         header::{self, HeaderName, HeaderValue, ACCEPT, LOCATION, REFERER},
@@ -70,12 +70,19 @@ use leptos_router::{
     RouteListing, SsrMode,
 };
 use parking_lot::RwLock;
-use server_fn::{error::ServerFnErrorErr, redirect::REDIRECT_HEADER};
+use server_fn::{
+    error::ServerFnErrorErr,
+    middleware::BoxedService,
+    redirect::REDIRECT_HEADER,
+    server::{CancelOnDrop, CancellationToken},
+};
 #[cfg(feature = "default")]
 use std::path::Path;
 #[cfg(feature = "default")]
 use std::sync::LazyLock;
-use std::{collections::HashSet, fmt::Debug, io, pin::Pin, sync::Arc};
+use std::{
+    collections::HashSet, fmt::Debug, io, pin::Pin, sync::Arc, task::Poll,
+};
 #[cfg(feature = "default")]
 use tower::util::ServiceExt;
 #[cfg(feature = "default")]
@@ -310,6 +317,7 @@ pub fn generate_request_and_parts(
 /// This function always provides context values including the following types:
 /// - [`Parts`]
 /// - [`ResponseOptions`]
+/// - [`CancellationToken`]
 #[cfg_attr(
     feature = "tracing",
     tracing::instrument(level = "trace", fields(error), skip_all)
@@ -355,6 +363,7 @@ fn init_executor() {
 /// This function always provides context values including the following types:
 /// - [`Parts`]
 /// - [`ResponseOptions`]
+/// - [`CancellationToken`]
 #[cfg_attr(
     feature = "tracing",
     tracing::instrument(level = "trace", fields(error), skip_all)
@@ -372,56 +381,24 @@ async fn handle_server_fns_inner(
 ) -> impl IntoResponse {
     let method = req.method().clone();
     let path = req.uri().path().to_string();
-    let (req, parts) = generate_request_and_parts(req);
 
-    if let Some(mut service) =
+    if path == server_fn::batch::batch_path() {
+        // each call in the batch runs with its own context
+        server_fn::axum::handle_batch(req, move |service, req| {
+            run_server_fn(additional_context.clone(), service, req)
+        })
+        .await
+    } else if let Some(service) =
         server_fn::axum::get_server_fn_service(&path, method)
     {
-        let owner = Owner::new();
-        owner
-            .with(|| {
-                ScopedFuture::new(async move {
-                    provide_context(parts);
-                    let res_options = ResponseOptions::default();
-                    provide_context(res_options.clone());
-                    additional_context();
-
-                    // store Accepts and Referer in case we need them for redirect (below)
-                    let accepts_html = req
-                        .headers()
-                        .get(ACCEPT)
-                        .and_then(|v| v.to_str().ok())
-                        .map(|v| v.contains("text/html"))
-                        .unwrap_or(false);
-                    let referrer = req.headers().get(REFERER).cloned();
-
-                    // actually run the server fn
-                    let mut res = AxumResponse(service.run(req).await);
-
-                    // if it accepts text/html (i.e., is a plain form post) and doesn't already have a
-                    // Location set, then redirect to the Referer
-                    if accepts_html {
-                        if let Some(referrer) = referrer {
-                            let has_location =
-                                res.0.headers().get(LOCATION).is_some();
-                            if !has_location {
-                                *res.0.status_mut() = StatusCode::FOUND;
-                                res.0.headers_mut().insert(LOCATION, referrer);
-                            }
-                        }
-                    }
-
-                    // apply status code and headers if user changed them
-                    res.extend_response(&res_options);
-                    Ok(res.0)
-                })
-            })
-            .await
+        run_server_fn(additional_context, service, req).await
     } else {
-        Response::builder()
-            .status(StatusCode::BAD_REQUEST)
-            .body(Body::from(format!(
-                "Could not find a server function at the route {path}. \
+        let mut res = Response::builder().status(StatusCode::BAD_REQUEST);
+        if let Some(id) = server_fn::version::build_id() {
+            res = res.header(server_fn::version::BUILD_ID_HEADER, id);
+        }
+        res.body(Body::from(format!(
+            "Could not find a server function at the route {path}. \
                  \n\nIt's likely that either
                          1. The API prefix you specify in the `#[server]` \
                  macro doesn't match the prefix at which your server function \
@@ -429,9 +406,154 @@ async fn handle_server_fns_inner(
                  doesn't support automatic server function registration and \
                  you need to call ServerFn::register_explicit() on the server \
                  function type, somewhere in your `main` function.",
-            )))
+        )))
+        .expect("could not build Response")
     }
-    .expect("could not build Response")
+}
+
+async fn run_server_fn(
+    additional_context: impl Fn() + 'static + Clone + Send,
+    service: BoxedService<Request<Body>, Response<Body>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    // each call runs in its own span, which continues the client's trace
+    #[cfg(feature = "trace-propagation")]
+    {
+        use tracing::Instrument;
+
+        let (span, trace_context) =
+            server_fn::trace::server_span(req.uri().path(), |name| {
+                req.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+            });
+        server_fn::trace::with_trace_context(
+            trace_context,
+            run_server_fn_inner(additional_context, service, req),
+        )
+        .instrument(span)
+        .await
+    }
+    #[cfg(not(feature = "trace-propagation"))]
+    run_server_fn_inner(additional_context, service, req).await
+}
+
+async fn run_server_fn_inner(
+    additional_context: impl Fn() + 'static + Clone + Send,
+    mut service: BoxedService<Request<Body>, Response<Body>>,
+    req: Request<Body>,
+) -> Response<Body> {
+    let (req, parts) = generate_request_and_parts(req);
+    let owner = Owner::new();
+    owner
+        .with(|| {
+            ScopedFuture::new(async move {
+                provide_context(parts);
+                let res_options = ResponseOptions::default();
+                provide_context(res_options.clone());
+                // if the client disconnects, this future is dropped before it completes
+                let cancellation = CancellationToken::new();
+                let cancel_on_drop = cancellation.drop_guard();
+                provide_context(cancellation);
+                additional_context();
+
+                // store Accepts and Referer in case we need them for redirect (below)
+                let accepts_html = req
+                    .headers()
+                    .get(ACCEPT)
+                    .and_then(|v| v.to_str().ok())
+                    .map(|v| v.contains("text/html"))
+                    .unwrap_or(false);
+                let referrer = req.headers().get(REFERER).cloned();
+
+                // actually run the server fn
+                let mut res = AxumResponse(service.run(req).await);
+
+                // if it accepts text/html (i.e., is a plain form post) and doesn't already have a
+                // Location set, then redirect to the Referer
+                if accepts_html {
+                    if let Some(referrer) = referrer {
+                        let has_location =
+                            res.0.headers().get(LOCATION).is_some();
+                        if !has_location {
+                            *res.0.status_mut() = StatusCode::FOUND;
+                            res.0.headers_mut().insert(LOCATION, referrer);
+                        }
+                    }
+                }
+
+                // apply status code and headers if user changed them
+                res.extend_response(&res_options);
+                let mut res = res.0;
+                // a streaming body is still produced by the server fn, so the client can
+                // disconnect after the handler returns: only disarm once it has been sent
+                if res.body().size_hint().exact().is_some() {
+                    cancel_on_drop.disarm();
+                } else {
+                    let body = std::mem::take(res.body_mut());
+                    *res.body_mut() = cancel_on_body_drop(body, cancel_on_drop);
+                }
+                #[cfg(feature = "trace-propagation")]
+                tracing::Span::current()
+                    .record("http.response.status_code", res.status().as_u16());
+                res
+            })
+        })
+        .await
+}
+
+/// Wraps a response body so that the guard cancels the server fn's [`CancellationToken`] if the
+/// body is dropped before it has been sent completely.
+fn cancel_on_body_drop(body: Body, cancel_on_drop: CancelOnDrop) -> Body {
+    let mut cancel_on_drop = Some(cancel_on_drop);
+    let end = futures::stream::poll_fn(move |_| {
+        if let Some(cancel_on_drop) = cancel_on_drop.take() {
+            cancel_on_drop.disarm();
+        }
+        Poll::Ready(None)
+    });
+    Body::from_stream(body.into_data_stream().chain(end))
+}
+
+/// Returns a [`TestServer`](server_fn::testing::axum::TestServer) that calls server functions
+/// in-process, with the same context as [`handle_server_fns`].
+///
+/// ## Provided Context Types
+/// Each call is provided context values including the following types:
+/// - [`Parts`]
+/// - [`ResponseOptions`]
+/// - [`CancellationToken`]
+pub fn test_server() -> server_fn::testing::axum::TestServer {
+    test_server_with_context(|| {})
+}
+
+/// Returns a [`TestServer`](server_fn::testing::axum::TestServer) that calls server functions
+/// in-process, with the same context as [`handle_server_fns_with_context`].
+///
+/// This can be used to test server functions that use the request [`Parts`], the
+/// [`ResponseOptions`], or context provided by `additional_context`, without an HTTP server:
+/// ```rust,ignore
+/// #[tokio::test]
+/// async fn sets_the_status() {
+///     let server = leptos_axum::test_server_with_context(|| provide_context(pool()));
+///     let (output, res) = server.call_with_response(CreatePost { title }).await;
+///     assert_eq!(res.unwrap().status(), StatusCode::CREATED);
+/// }
+/// ```
+///
+/// ## Provided Context Types
+/// Each call is provided context values including the following types:
+/// - [`Parts`]
+/// - [`ResponseOptions`]
+/// - [`CancellationToken`]
+pub fn test_server_with_context(
+    additional_context: impl Fn() + 'static + Clone + Send + Sync,
+) -> server_fn::testing::axum::TestServer {
+    server_fn::testing::axum::TestServer::new().with_dispatch(
+        move |service, req| {
+            run_server_fn(additional_context.clone(), service, req)
+        },
+    )
 }
 
 /// A stream of bytes of HTML.
@@ -2018,6 +2140,34 @@ where
         .map_err(|e| ServerFnErrorErr::ServerError(format!("{e:?}")))
 }
 
+/// Returns a route that serves an [OpenAPI 3.1](https://spec.openapis.org/oas/v3.1.0)
+/// document describing every registered server function.
+///
+/// The document is generated the first time it is requested, and cached afterwards.
+///
+/// ```rust,ignore
+/// use leptos_axum::openapi_route;
+/// use server_fn::openapi::OpenApi;
+///
+/// let app = Router::new()
+///     .route("/api/openapi.json", openapi_route(OpenApi::new("My App", "1.0")));
+/// ```
+#[cfg(feature = "openapi")]
+pub fn openapi_route<S>(
+    api: server_fn::openapi::OpenApi,
+) -> axum::routing::MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    let document = Arc::new(std::sync::OnceLock::new());
+    get(move || {
+        let body = document
+            .get_or_init(|| server_fn::axum::openapi_document(&api).to_string())
+            .clone();
+        async move { ([(header::CONTENT_TYPE, "application/json")], body) }
+    })
+}
+
 /// A reasonable handler for serving static files (like JS/WASM/CSS) and 404 errors.
 ///
 /// This is provided as a convenience, but is a fairly simple function. If you need to adapt it,
@@ -2142,3 +2292,28 @@ async fn get_static_file(
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn streaming_body_cancels_unless_sent() {
+        let stream = || {
+            Body::from_stream(futures::stream::iter([Ok::<_, io::Error>(
+                Bytes::from("chunk"),
+            )]))
+        };
+
+        let token = CancellationToken::new();
+        let body = cancel_on_body_drop(stream(), token.drop_guard());
+        drop(body);
+        assert!(token.is_cancelled());
+
+        let token = CancellationToken::new();
+        let body = cancel_on_body_drop(stream(), token.drop_guard());
+        let bytes = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(bytes, "chunk");
+        assert!(!token.is_cancelled());
+    }
+}
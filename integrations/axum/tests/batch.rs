@@ -0,0 +1,57 @@
+use axum::{
+    body::Body,
+    http::{header::CONTENT_TYPE, Request, StatusCode},
+    response::IntoResponse,
+};
+use leptos::{
+    prelude::*,
+    server_fn::{
+        batch::{batch_path, BatchCall, BatchResult},
+        middleware::limit::{ClientKey, RateLimit},
+        ServerFn,
+    },
+};
+
+#[server(rate_limit = RateLimit::per_minute(2).key(ClientKey::header("x-client")))]
+pub async fn limited() -> Result<u32, ServerFnError> {
+    Ok(42)
+}
+
+fn call() -> BatchCall {
+    BatchCall {
+        method: "POST".into(),
+        path: Limited::PATH.into(),
+        headers: vec![
+            (
+                "content-type".into(),
+                "application/x-www-form-urlencoded".into(),
+            ),
+            ("accept".into(), "application/json".into()),
+        ],
+        body: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn middleware_applies_to_each_call() {
+    let calls = vec![call(); 3];
+    let req = Request::post(batch_path())
+        .header(CONTENT_TYPE, "application/json")
+        .header("x-client", "batch")
+        .body(Body::from(serde_json::to_vec(&calls).unwrap()))
+        .unwrap();
+    let res = leptos_axum::handle_server_fns(req).await.into_response();
+    assert_eq!(res.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let results: Vec<BatchResult> = serde_json::from_slice(&body).unwrap();
+    let mut statuses = results
+        .iter()
+        .map(|result| result.status)
+        .collect::<Vec<_>>();
+    statuses.sort();
+    // the rate limit counts every call in the batch
+    assert_eq!(statuses, [200, 200, 429]);
+}
@@ -0,0 +1,57 @@
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use leptos::{prelude::*, server_fn::error::ServerFnErrorErr};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static RAN: AtomicBool = AtomicBool::new(false);
+
+async fn admins_only() -> Result<(), ServerFnErrorErr> {
+    let headers: HeaderMap = leptos_axum::extract().await?;
+    match headers.get("x-role").map(HeaderValue::as_bytes) {
+        Some(b"admin") => Ok(()),
+        Some(_) => Err(ServerFnErrorErr::Forbidden("admins only".into())),
+        None => Err(ServerFnErrorErr::Unauthorized("sign in first".into())),
+    }
+}
+
+#[server(guard = admins_only)]
+pub async fn delete_everything() -> Result<u32, ServerFnError> {
+    RAN.store(true, Ordering::SeqCst);
+    Ok(42)
+}
+
+#[tokio::test]
+async fn guard_allows_and_rejects_requests() {
+    let server = leptos_axum::test_server();
+
+    // rejected requests never reach the body of the server function
+    let (res, parts) = server.call_with_response(DeleteEverything {}).await;
+    assert_eq!(
+        res,
+        Err(ServerFnError::Unauthorized("sign in first".into()))
+    );
+    assert_eq!(parts.unwrap().status(), StatusCode::UNAUTHORIZED);
+
+    let user = server.clone().with_header(
+        "x-role".parse().unwrap(),
+        HeaderValue::from_static("user"),
+    );
+    let (res, parts) = user.call_with_response(DeleteEverything {}).await;
+    assert_eq!(res, Err(ServerFnError::Forbidden("admins only".into())));
+    assert_eq!(parts.unwrap().status(), StatusCode::FORBIDDEN);
+    assert!(!RAN.load(Ordering::SeqCst));
+
+    let admin = server.with_header(
+        "x-role".parse().unwrap(),
+        HeaderValue::from_static("admin"),
+    );
+    assert_eq!(admin.call(DeleteEverything {}).await, Ok(42));
+    assert!(RAN.load(Ordering::SeqCst));
+}
+
+#[test]
+fn guarded_server_fns_are_not_listed_as_unguarded() {
+    use leptos::server_fn::ServerFn;
+
+    assert!(!leptos::server_fn::axum::unguarded_server_fns()
+        .any(|(path, _)| path == DeleteEverything::PATH));
+}
@@ -0,0 +1,58 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    response::IntoResponse,
+};
+use leptos::{
+    prelude::*,
+    server_fn::{
+        version::{set_build_id, BUILD_ID_HEADER},
+        ServerFn,
+    },
+};
+
+const BUILD_ID: &str = "test-build";
+
+#[server(endpoint = "list_posts", version = 2)]
+pub async fn list_posts(page: u32, size: u32) -> Result<String, ServerFnError> {
+    Ok(format!("v2: page {page} of {size}"))
+}
+
+#[server(endpoint = "list_posts", version = 1)]
+pub async fn list_posts_v1(page: u32) -> Result<String, ServerFnError> {
+    Ok(format!("v1: page {page}"))
+}
+
+#[test]
+fn versions_are_part_of_the_path() {
+    assert_eq!(ListPosts::PATH, "/api/v2/list_posts");
+    assert_eq!(ListPostsV1::PATH, "/api/v1/list_posts");
+}
+
+#[tokio::test]
+async fn versions_are_served_side_by_side() {
+    _ = set_build_id(BUILD_ID);
+    let server = leptos_axum::test_server();
+
+    let (res, parts) = server
+        .call_with_response(ListPosts { page: 2, size: 10 })
+        .await;
+    assert_eq!(res.unwrap(), "v2: page 2 of 10");
+    assert_eq!(parts.unwrap().headers()[BUILD_ID_HEADER], BUILD_ID);
+
+    let (res, parts) = server.call_with_response(ListPostsV1 { page: 2 }).await;
+    assert_eq!(res.unwrap(), "v1: page 2");
+    assert_eq!(parts.unwrap().headers()[BUILD_ID_HEADER], BUILD_ID);
+}
+
+#[tokio::test]
+async fn unknown_server_fns_advertise_the_build_id() {
+    _ = set_build_id(BUILD_ID);
+    // a client of an older build may call a server function that no longer exists
+    let req = Request::post("/api/v0/list_posts")
+        .body(Body::empty())
+        .unwrap();
+    let res = leptos_axum::handle_server_fns(req).await.into_response();
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(res.headers()[BUILD_ID_HEADER], BUILD_ID);
+}
@@ -91,6 +91,17 @@
 //! - **`rkyv`** In SSR/hydrate mode, uses [`rkyv`](https://docs.rs/rkyv/latest/rkyv/) to serialize resources and send them
 //!   from the server to the client.
 //! - **`tracing`** Adds support for [`tracing`](https://docs.rs/tracing/latest/tracing/).
+//! - **`trace-propagation`** Sends the [W3C trace context](https://www.w3.org/TR/trace-context/)
+//!   with each server function call, and continues it on the server. Cross-origin calls need the
+//!   server to allow the `traceparent` and `tracestate` headers. Implies `tracing`.
+//! - **`opentelemetry`** Propagates the trace context of server function calls with
+//!   [OpenTelemetry](https://docs.rs/opentelemetry/latest/opentelemetry/). Implies
+//!   `trace-propagation`.
+//! - **`persisted`** Adds `PersistedSignal`, which keeps a signal's value in browser storage,
+//!   and `PersistedStore` along with `reactive_stores`.
+//! - **`reactive_stores`** Adds the server utilities that work with stores from
+//!   [`reactive_stores`](https://docs.rs/reactive_stores/latest/reactive_stores/), such as
+//!   `SharedStore`.
 //!
 //! **Important Note:** You must enable one of `csr`, `hydrate`, or `ssr` to tell Leptos
 //! which mode your app is operating in. You should only enable one of these per build target,
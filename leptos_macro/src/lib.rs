@@ -863,6 +863,31 @@ pub fn slot(args: proc_macro::TokenStream, s: TokenStream) -> TokenStream {
 ///   to convert from the argument type to the server function type, and vice versa, allowing you to convert
 ///   between them easily. Setting `impl_from` to `false` disables this, which can be necessary for argument types
 ///   for which this would create a conflicting implementation. (defaults to `true`)
+/// - `cache`: adds `ETag`-based HTTP caching to the responses of a `GET` server function. This
+///   takes either a [`CachePolicy`](../server_fn/middleware/cache/struct.CachePolicy.html), or an
+///   integer, which is shorthand for a `max-age` in seconds.
+/// - `retry`: how failed calls from the client are retried, overriding the global policy. This
+///   takes either a [`RetryPolicy`](../server_fn/client/retry/struct.RetryPolicy.html), or an
+///   integer, which is shorthand for a maximum number of attempts.
+/// - `websocket`: for a server function with the `Websocket` protocol, a
+///   [`WebsocketConfig`](../server_fn/websocket/struct.WebsocketConfig.html) for its heartbeats,
+///   reconnection and send buffering on the client, overriding the global configuration.
+/// - `rate_limit`: rejects requests with `429 Too Many Requests` once a client exceeds the
+///   limit. This takes either a [`RateLimit`](../server_fn/middleware/limit/struct.RateLimit.html),
+///   or an integer, which is shorthand for a number of requests per second from each IP address.
+/// - `max_concurrency`: rejects requests with `429 Too Many Requests` while this many requests
+///   to the server function are already being handled.
+/// - `guard`: the path to an `async fn() -> Result<(), E>` that checks whether the request may call
+///   the server function, before its arguments are deserialized. It can use `extract()` to read the
+///   request. Returning `ServerFnError::Unauthorized` or `ServerFnError::Forbidden` responds with a
+///   `401` or `403`. Functions without a guard are listed by `unguarded_server_fns()` in
+///   `server_fn::axum` and `server_fn::actix`.
+/// - `version`: an integer that is added to the URL after the prefix, as in `/api/v2/my_fn`. An older
+///   version can stay registered next to the new one by giving both the same `endpoint`. See
+///   [`server_fn::version`](../server_fn/version/index.html) for detecting clients from an older build.
+/// - `batch`: if `true`, calls made within a millisecond of each other are sent together as one
+///   request to the batch endpoint, which the Axum and Actix server function handlers serve. Calls
+///   with a streaming body or a streaming response are sent on their own. See [`server_fn::batch`](../server_fn/batch/index.html).
 ///
 /// ```rust,ignore
 /// #[server(
@@ -1,7 +1,7 @@
 use reactive_graph::{
-    actions::{Action, ArcAction},
+    actions::{Action, ArcAction, ArcOptimisticAction, OptimisticAction},
     owner::use_context,
-    traits::DefinedAt,
+    traits::{DefinedAt, Update, WithUntracked},
 };
 use server_fn::{
     error::{FromServerFnError, ServerFnUrlError},
@@ -39,6 +39,19 @@ impl ServerActionError {
     }
 }
 
+/// Returns the error for the server function `S` that was provided as a [`ServerActionError`]
+/// context, if any, to use as the initial value of its action.
+fn error_from_context<S>() -> Option<Result<S::Output, S::Error>>
+where
+    S: ServerFn,
+{
+    use_context::<ServerActionError>().and_then(|error| {
+        (error.path() == S::PATH)
+            .then(|| ServerFnUrlError::<S::Error>::decode_err(error.err()))
+            .map(Err)
+    })
+}
+
 /// An [`ArcAction`] that can be used to call a server function.
 pub struct ArcServerAction<S>
 where
@@ -60,15 +73,11 @@ where
     /// Creates a new [`ArcAction`] that will call the server function `S` when dispatched.
     #[track_caller]
     pub fn new() -> Self {
-        let err = use_context::<ServerActionError>().and_then(|error| {
-            (error.path() == S::PATH)
-                .then(|| ServerFnUrlError::<S::Error>::decode_err(error.err()))
-                .map(Err)
-        });
         Self {
-            inner: ArcAction::new_with_value(err, |input: &S| {
-                S::run_on_client(input.clone())
-            }),
+            inner: ArcAction::new_with_value(
+                error_from_context::<S>(),
+                |input: &S| S::run_on_client(input.clone()),
+            ),
             #[cfg(any(debug_assertions, leptos_debuginfo))]
             defined_at: Location::caller(),
         }
@@ -148,15 +157,11 @@ where
 {
     /// Creates a new [`Action`] that will call the server function `S` when dispatched.
     pub fn new() -> Self {
-        let err = use_context::<ServerActionError>().and_then(|error| {
-            (error.path() == S::PATH)
-                .then(|| ServerFnUrlError::<S::Error>::decode_err(error.err()))
-                .map(Err)
-        });
         Self {
-            inner: Action::new_with_value(err, |input: &S| {
-                S::run_on_client(input.clone())
-            }),
+            inner: Action::new_with_value(
+                error_from_context::<S>(),
+                |input: &S| S::run_on_client(input.clone()),
+            ),
             #[cfg(any(debug_assertions, leptos_debuginfo))]
             defined_at: Location::caller(),
         }
@@ -230,3 +235,259 @@ where
         }
     }
 }
+
+/// An [`ArcOptimisticAction`] that can be used to call a server function.
+///
+/// When it is dispatched, `update` is applied to the target immediately. It is kept if the
+/// server function succeeds, and reverted if it returns an error or is aborted.
+pub struct ArcOptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    inner: ArcOptimisticAction<S, S::Output, S::Error>,
+    #[cfg(any(debug_assertions, leptos_debuginfo))]
+    defined_at: &'static Location<'static>,
+}
+
+impl<S> ArcOptimisticServerAction<S>
+where
+    S: ServerFn + Clone + Send + Sync + 'static,
+    S::Output: Clone + Send + Sync + 'static,
+    S::Error: Send + Sync + 'static,
+    S::Error: FromServerFnError,
+{
+    /// Creates a new [`ArcOptimisticAction`] that will call the server function `S` when
+    /// dispatched, applying `update` to `target` until it resolves.
+    #[track_caller]
+    pub fn new<Tgt, T>(
+        target: Tgt,
+        update: impl Fn(&mut T, &S) + Send + Sync + 'static,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+    {
+        let update = Arc::new(update);
+        Self::new_with_reconcile(
+            target,
+            {
+                let update = Arc::clone(&update);
+                move |value: &mut T, input: &S| update(value, input)
+            },
+            move |value: &mut T, input: &S, _: &S::Output| update(value, input),
+        )
+    }
+
+    /// Creates a new [`ArcOptimisticAction`] that will call the server function `S` when
+    /// dispatched, applying `update` to `target` until it resolves, and `reconcile` once it has
+    /// succeeded.
+    #[track_caller]
+    pub fn new_with_reconcile<Tgt, T>(
+        target: Tgt,
+        update: impl Fn(&mut T, &S) + Send + Sync + 'static,
+        reconcile: impl Fn(&mut T, &S, &S::Output) + Send + Sync + 'static,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+    {
+        Self {
+            inner: ArcOptimisticAction::new_with_value(
+                error_from_context::<S>(),
+                target,
+                update,
+                reconcile,
+                |input: &S| S::run_on_client(input.clone()),
+            ),
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+        }
+    }
+}
+
+impl<S> Deref for ArcOptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    type Target = ArcOptimisticAction<S, S::Output, S::Error>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<S> Clone for ArcOptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: self.defined_at,
+        }
+    }
+}
+
+impl<S> DefinedAt for ArcOptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    fn defined_at(&self) -> Option<&'static Location<'static>> {
+        #[cfg(any(debug_assertions, leptos_debuginfo))]
+        {
+            Some(self.defined_at)
+        }
+        #[cfg(not(any(debug_assertions, leptos_debuginfo)))]
+        {
+            None
+        }
+    }
+}
+
+/// An [`OptimisticAction`] that can be used to call a server function.
+///
+/// When it is dispatched, `update` is applied to the target immediately. It is kept if the
+/// server function succeeds, and reverted if it returns an error or is aborted.
+pub struct OptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    inner: OptimisticAction<S, S::Output, S::Error>,
+    #[cfg(any(debug_assertions, leptos_debuginfo))]
+    defined_at: &'static Location<'static>,
+}
+
+impl<S> OptimisticServerAction<S>
+where
+    S: ServerFn + Clone + Send + Sync + 'static,
+    S::Output: Clone + Send + Sync + 'static,
+    S::Error: Send + Sync + 'static,
+    S::Error: FromServerFnError,
+{
+    /// Creates a new [`OptimisticAction`] that will call the server function `S` when
+    /// dispatched, applying `update` to `target` until it resolves.
+    #[track_caller]
+    pub fn new<Tgt, T>(
+        target: Tgt,
+        update: impl Fn(&mut T, &S) + Send + Sync + 'static,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+    {
+        let update = Arc::new(update);
+        Self::new_with_reconcile(
+            target,
+            {
+                let update = Arc::clone(&update);
+                move |value: &mut T, input: &S| update(value, input)
+            },
+            move |value: &mut T, input: &S, _: &S::Output| update(value, input),
+        )
+    }
+
+    /// Creates a new [`OptimisticAction`] that will call the server function `S` when
+    /// dispatched, applying `update` to `target` until it resolves, and `reconcile` once it has
+    /// succeeded.
+    #[track_caller]
+    pub fn new_with_reconcile<Tgt, T>(
+        target: Tgt,
+        update: impl Fn(&mut T, &S) + Send + Sync + 'static,
+        reconcile: impl Fn(&mut T, &S, &S::Output) + Send + Sync + 'static,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+    {
+        Self {
+            inner: OptimisticAction::new_with_value(
+                error_from_context::<S>(),
+                target,
+                update,
+                reconcile,
+                |input: &S| S::run_on_client(input.clone()),
+            ),
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+        }
+    }
+}
+
+impl<S> Clone for OptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for OptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+}
+
+impl<S> Deref for OptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    type Target = OptimisticAction<S, S::Output, S::Error>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<S> From<OptimisticServerAction<S>>
+    for OptimisticAction<S, S::Output, S::Error>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    fn from(value: OptimisticServerAction<S>) -> Self {
+        value.inner
+    }
+}
+
+impl<S> DefinedAt for OptimisticServerAction<S>
+where
+    S: ServerFn + 'static,
+    S::Output: 'static,
+{
+    fn defined_at(&self) -> Option<&'static Location<'static>> {
+        #[cfg(any(debug_assertions, leptos_debuginfo))]
+        {
+            Some(self.defined_at)
+        }
+        #[cfg(not(any(debug_assertions, leptos_debuginfo)))]
+        {
+            None
+        }
+    }
+}
@@ -12,9 +12,17 @@ mod multi_action;
 pub use multi_action::*;
 mod once_resource;
 pub use once_resource::*;
+#[cfg(feature = "persisted")]
+mod persisted;
+#[cfg(feature = "persisted")]
+pub use persisted::*;
 mod resource;
 pub use resource::*;
 mod shared;
+#[cfg(feature = "reactive_stores")]
+mod shared_store;
+#[cfg(feature = "reactive_stores")]
+pub use shared_store::*;
 
 use base64::{engine::general_purpose::STANDARD_NO_PAD, DecodeError, Engine};
 /// Re-export of the `codee` crate.
@@ -75,6 +83,49 @@ impl FromEncodedStr for [u8] {
     }
 }
 
+#[cfg(any(feature = "persisted", feature = "reactive_stores"))]
+pub(crate) fn encode<T, Ser>(value: &T) -> Option<String>
+where
+    Ser: codee::Encoder<T>,
+    <Ser as codee::Encoder<T>>::Error: std::fmt::Debug,
+    <Ser as codee::Encoder<T>>::Encoded: IntoEncodedString,
+{
+    match Ser::encode(value) {
+        Ok(encoded) => Some(encoded.into_encoded_string()),
+        #[allow(unused_variables)] // used in tracing
+        Err(e) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!("couldn't serialize: {e:?}");
+            None
+        }
+    }
+}
+
+#[cfg(any(feature = "persisted", feature = "reactive_stores"))]
+pub(crate) fn decode<T, Ser>(data: &str) -> Option<T>
+where
+    Ser: codee::Decoder<T>,
+    <Ser as codee::Decoder<T>>::Error: std::fmt::Debug,
+    <Ser as codee::Decoder<T>>::Encoded: FromEncodedStr,
+    <<Ser as codee::Decoder<T>>::Encoded as FromEncodedStr>::DecodingError:
+        std::fmt::Debug,
+{
+    match <Ser as codee::Decoder<T>>::Encoded::from_encoded_str(data) {
+        #[allow(unused_variables)] // used in tracing
+        Err(e) => {
+            #[cfg(feature = "tracing")]
+            tracing::error!("couldn't deserialize from {data:?}: {e:?}");
+            None
+        }
+        Ok(encoded) => {
+            let decoded = Ser::decode(encoded.borrow());
+            #[cfg(feature = "tracing")]
+            let decoded = decoded.inspect_err(|e| tracing::error!("{e:?}"));
+            decoded.ok()
+        }
+    }
+}
+
 #[cfg(feature = "tachys")]
 mod view_implementations {
     use crate::Resource;
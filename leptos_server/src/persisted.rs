@@ -0,0 +1,1230 @@
+use crate::{decode, encode, FromEncodedStr, IntoEncodedString};
+use any_spawner::Executor;
+use codee::{string::JsonSerdeCodec, Decoder, Encoder};
+use futures::{
+    future::{self, LocalBoxFuture},
+    FutureExt,
+};
+use or_poisoned::OrPoisoned;
+use reactive_graph::{
+    graph::{
+        AnySource, AnySubscriber, ReactiveNode, Source, Subscriber, ToAnySource,
+    },
+    owner::{ArenaItem, Owner},
+    signal::{
+        guards::{Plain, ReadGuard},
+        ArcRwSignal, RwSignal,
+    },
+    traits::{
+        DefinedAt, Dispose, IsDisposed, Notify, ReadUntracked, Set, Track,
+        UntrackableGuard, WithUntracked, Write,
+    },
+};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    marker::PhantomData,
+    ops::DerefMut,
+    panic::Location,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex, PoisonError, Weak,
+    },
+    time::Duration,
+};
+
+/// A place where persisted values are kept, such as `localStorage`.
+///
+/// Values are stored as strings, encoded with the serializer of the
+/// [`PersistedSignal`] or [`PersistedStore`].
+pub trait StorageBackend: Send + Sync + 'static {
+    /// Loads the value stored under `key`, if any.
+    fn load(&self, key: &str) -> LocalBoxFuture<'static, Option<String>>;
+
+    /// Stores `value` under `key`.
+    fn save(&self, key: &str, value: String);
+
+    /// Removes the value stored under `key`.
+    fn remove(&self, key: &str);
+
+    /// Calls `on_change` whenever the value stored under `key` is changed from
+    /// somewhere else, such as another browser tab. Returns a function that
+    /// stops watching.
+    ///
+    /// By default, changes are not watched.
+    fn watch(
+        &self,
+        key: &str,
+        on_change: Arc<dyn Fn(Option<String>) + Send + Sync>,
+    ) -> Box<dyn FnOnce() + Send + Sync> {
+        _ = (key, on_change);
+        Box::new(|| {})
+    }
+}
+
+/// Keeps values in memory.
+///
+/// This is the default backend outside the browser. Clones share the same
+/// values, so it can be used to test persistence in native tests, and
+/// [`set_from_other_tab`](MemoryStorage::set_from_other_tab) simulates
+/// changes made in another tab.
+#[derive(Clone, Default)]
+pub struct MemoryStorage {
+    inner: Arc<Mutex<MemoryInner>>,
+}
+
+type Watcher = (usize, Arc<dyn Fn(Option<String>) + Send + Sync>);
+
+#[derive(Default)]
+struct MemoryInner {
+    values: HashMap<String, String>,
+    watchers: HashMap<String, Vec<Watcher>>,
+    next_watcher: usize,
+}
+
+impl Debug for MemoryStorage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MemoryStorage")
+            .field("values", &self.inner.lock().or_poisoned().values)
+            .finish()
+    }
+}
+
+impl MemoryStorage {
+    /// Creates an empty storage.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the value stored under `key`, if any.
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.inner.lock().or_poisoned().values.get(key).cloned()
+    }
+
+    /// Stores or removes the value under `key`, and notifies everything that
+    /// watches it, as if the change had been made in another tab.
+    pub fn set_from_other_tab(&self, key: &str, value: Option<String>) {
+        let mut inner = self.inner.lock().or_poisoned();
+        match &value {
+            Some(value) => {
+                inner.values.insert(key.to_string(), value.clone());
+            }
+            None => {
+                inner.values.remove(key);
+            }
+        }
+        let watchers = inner
+            .watchers
+            .get(key)
+            .map(|watchers| {
+                watchers
+                    .iter()
+                    .map(|(_, f)| Arc::clone(f))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        drop(inner);
+        for watcher in watchers {
+            watcher(value.clone());
+        }
+    }
+}
+
+impl StorageBackend for MemoryStorage {
+    fn load(&self, key: &str) -> LocalBoxFuture<'static, Option<String>> {
+        Box::pin(future::ready(self.get(key)))
+    }
+
+    fn save(&self, key: &str, value: String) {
+        self.inner
+            .lock()
+            .or_poisoned()
+            .values
+            .insert(key.to_string(), value);
+    }
+
+    fn remove(&self, key: &str) {
+        self.inner.lock().or_poisoned().values.remove(key);
+    }
+
+    fn watch(
+        &self,
+        key: &str,
+        on_change: Arc<dyn Fn(Option<String>) + Send + Sync>,
+    ) -> Box<dyn FnOnce() + Send + Sync> {
+        let mut inner = self.inner.lock().or_poisoned();
+        let id = inner.next_watcher;
+        inner.next_watcher += 1;
+        inner
+            .watchers
+            .entry(key.to_string())
+            .or_default()
+            .push((id, on_change));
+        drop(inner);
+
+        let storage = Arc::downgrade(&self.inner);
+        let key = key.to_string();
+        Box::new(move || {
+            if let Some(storage) = storage.upgrade() {
+                if let Some(watchers) =
+                    storage.lock().or_poisoned().watchers.get_mut(&key)
+                {
+                    watchers.retain(|(watcher, _)| *watcher != id);
+                }
+            }
+        })
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod web {
+    use super::StorageBackend;
+    use futures::future::{self, LocalBoxFuture};
+    use send_wrapper::SendWrapper;
+    use std::{cell::RefCell, collections::HashMap, sync::Arc};
+    use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+    use web_sys::{
+        BroadcastChannel, IdbDatabase, IdbRequest, IdbTransactionMode,
+        MessageEvent, StorageEvent,
+    };
+
+    /// Keeps values in `localStorage` or `sessionStorage`.
+    ///
+    /// Changes made to `localStorage` in other tabs are picked up through
+    /// `storage` events.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct WebStorage {
+        session: bool,
+    }
+
+    impl WebStorage {
+        /// Uses `localStorage`, which is shared between tabs and kept when
+        /// the browser is closed.
+        pub fn local() -> Self {
+            Self { session: false }
+        }
+
+        /// Uses `sessionStorage`, which is kept only for the current tab.
+        pub fn session() -> Self {
+            Self { session: true }
+        }
+
+        fn storage(&self) -> Option<web_sys::Storage> {
+            let window = web_sys::window()?;
+            if self.session {
+                window.session_storage().ok().flatten()
+            } else {
+                window.local_storage().ok().flatten()
+            }
+        }
+    }
+
+    impl StorageBackend for WebStorage {
+        fn load(&self, key: &str) -> LocalBoxFuture<'static, Option<String>> {
+            let value = self
+                .storage()
+                .and_then(|storage| storage.get_item(key).ok().flatten());
+            Box::pin(future::ready(value))
+        }
+
+        fn save(&self, key: &str, value: String) {
+            if let Some(storage) = self.storage() {
+                #[allow(unused_variables)] // used in tracing
+                if let Err(e) = storage.set_item(key, &value) {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("couldn't save {key:?}: {e:?}");
+                }
+            }
+        }
+
+        fn remove(&self, key: &str) {
+            if let Some(storage) = self.storage() {
+                _ = storage.remove_item(key);
+            }
+        }
+
+        fn watch(
+            &self,
+            key: &str,
+            on_change: Arc<dyn Fn(Option<String>) + Send + Sync>,
+        ) -> Box<dyn FnOnce() + Send + Sync> {
+            let (Some(window), Some(storage)) =
+                (web_sys::window(), self.storage())
+            else {
+                return Box::new(|| {});
+            };
+            let key = key.to_string();
+            let listener = Closure::<dyn Fn(StorageEvent)>::new(
+                move |ev: StorageEvent| {
+                    let same_area =
+                        ev.storage_area().as_ref() == Some(&storage);
+                    if !same_area {
+                        return;
+                    }
+                    match ev.key() {
+                        Some(changed) if changed == key => {
+                            on_change(ev.new_value())
+                        }
+                        // the storage was cleared
+                        None => on_change(None),
+                        _ => {}
+                    }
+                },
+            );
+            _ = window.add_event_listener_with_callback(
+                "storage",
+                listener.as_ref().unchecked_ref(),
+            );
+
+            let listener = SendWrapper::new((window, listener));
+            Box::new(move || {
+                let (window, listener) = listener.take();
+                _ = window.remove_event_listener_with_callback(
+                    "storage",
+                    listener.as_ref().unchecked_ref(),
+                );
+            })
+        }
+    }
+
+    const OBJECT_STORE: &str = "values";
+
+    thread_local! {
+        static CHANNELS: RefCell<HashMap<Arc<str>, BroadcastChannel>> =
+            Default::default();
+    }
+
+    /// Keeps values in an IndexedDB database.
+    ///
+    /// Values are loaded asynchronously, so a persisted value starts out with
+    /// its initial value and updates once the stored one has been read.
+    /// Changes are shared with other tabs through a `BroadcastChannel`. If
+    /// it is not available, changes made in other tabs are not picked up.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct IndexedDb {
+        database: Arc<str>,
+    }
+
+    impl IndexedDb {
+        /// Uses the database with the given name, creating it if needed.
+        pub fn new(database: impl Into<Arc<str>>) -> Self {
+            Self {
+                database: database.into(),
+            }
+        }
+
+        async fn open(&self) -> Result<IdbDatabase, JsValue> {
+            let factory = web_sys::window()
+                .ok_or(JsValue::NULL)?
+                .indexed_db()?
+                .ok_or(JsValue::NULL)?;
+            let open = factory.open_with_u32(&self.database, 1)?;
+            let upgrade = Closure::<dyn Fn()>::new({
+                let open = open.clone();
+                move || {
+                    if let Ok(db) = open
+                        .result()
+                        .and_then(|db| db.dyn_into::<IdbDatabase>())
+                    {
+                        _ = db.create_object_store(OBJECT_STORE);
+                    }
+                }
+            });
+            open.set_onupgradeneeded(Some(upgrade.as_ref().unchecked_ref()));
+            let db = request(&open).await?;
+            db.dyn_into()
+        }
+
+        async fn write(
+            &self,
+            key: &str,
+            value: Option<&str>,
+        ) -> Result<(), JsValue> {
+            let db = self.open().await?;
+            let store = db
+                .transaction_with_str_and_mode(
+                    OBJECT_STORE,
+                    IdbTransactionMode::Readwrite,
+                )?
+                .object_store(OBJECT_STORE)?;
+            let key = JsValue::from_str(key);
+            let req = match value {
+                Some(value) => {
+                    store.put_with_key(&JsValue::from_str(value), &key)?
+                }
+                None => store.delete(&key)?,
+            };
+            request(&req).await?;
+            match self.channel() {
+                Some(channel) => channel.post_message(&key),
+                None => Ok(()),
+            }
+        }
+
+        /// Returns the channel used to tell other tabs about changes, or
+        /// `None` if `BroadcastChannel` is not available.
+        fn channel(&self) -> Option<BroadcastChannel> {
+            CHANNELS.with_borrow_mut(|channels| {
+                if let Some(channel) = channels.get(&self.database) {
+                    return Some(channel.clone());
+                }
+                let channel = BroadcastChannel::new(&format!(
+                    "leptos-persisted:{}",
+                    self.database
+                ))
+                .ok()?;
+                channels.insert(Arc::clone(&self.database), channel.clone());
+                Some(channel)
+            })
+        }
+    }
+
+    impl StorageBackend for IndexedDb {
+        fn load(&self, key: &str) -> LocalBoxFuture<'static, Option<String>> {
+            let this = self.clone();
+            let key = JsValue::from_str(key);
+            Box::pin(async move {
+                let db = this.open().await.ok()?;
+                let req = db
+                    .transaction_with_str(OBJECT_STORE)
+                    .and_then(|tx| tx.object_store(OBJECT_STORE))
+                    .and_then(|store| store.get(&key))
+                    .ok()?;
+                request(&req).await.ok()?.as_string()
+            })
+        }
+
+        fn save(&self, key: &str, value: String) {
+            let this = self.clone();
+            let key = key.to_string();
+            wasm_bindgen_futures::spawn_local(async move {
+                #[allow(unused_variables)] // used in tracing
+                if let Err(e) = this.write(&key, Some(&value)).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::error!("couldn't save {key:?}: {e:?}");
+                }
+            });
+        }
+
+        fn remove(&self, key: &str) {
+            let this = self.clone();
+            let key = key.to_string();
+            wasm_bindgen_futures::spawn_local(async move {
+                _ = this.write(&key, None).await;
+            });
+        }
+
+        fn watch(
+            &self,
+            key: &str,
+            on_change: Arc<dyn Fn(Option<String>) + Send + Sync>,
+        ) -> Box<dyn FnOnce() + Send + Sync> {
+            let Some(channel) = self.channel() else {
+                return Box::new(|| {});
+            };
+            let this = self.clone();
+            let key = key.to_string();
+            let listener = Closure::<dyn Fn(MessageEvent)>::new(
+                move |ev: MessageEvent| {
+                    if ev.data().as_string().as_deref() == Some(&key) {
+                        let load = this.load(&key);
+                        let on_change = Arc::clone(&on_change);
+                        wasm_bindgen_futures::spawn_local(async move {
+                            on_change(load.await)
+                        });
+                    }
+                },
+            );
+            _ = channel.add_event_listener_with_callback(
+                "message",
+                listener.as_ref().unchecked_ref(),
+            );
+
+            let listener = SendWrapper::new((channel, listener));
+            Box::new(move || {
+                let (channel, listener) = listener.take();
+                _ = channel.remove_event_listener_with_callback(
+                    "message",
+                    listener.as_ref().unchecked_ref(),
+                );
+            })
+        }
+    }
+
+    /// Resolves once the request has succeeded or failed.
+    async fn request(req: &IdbRequest) -> Result<JsValue, JsValue> {
+        let (tx, rx) = futures::channel::oneshot::channel();
+        let tx = RefCell::new(Some(tx));
+        let on_done = Closure::<dyn Fn()>::new({
+            let req = req.clone();
+            move || {
+                if let Some(tx) = tx.take() {
+                    let result = match req.error() {
+                        Ok(Some(e)) => Err(e.into()),
+                        _ => req.result(),
+                    };
+                    _ = tx.send(result);
+                }
+            }
+        });
+        req.set_onsuccess(Some(on_done.as_ref().unchecked_ref()));
+        req.set_onerror(Some(on_done.as_ref().unchecked_ref()));
+        rx.await.unwrap_or(Err(JsValue::NULL))
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use web::*;
+
+/// Options for a [`PersistedSignal`] or [`PersistedStore`].
+#[derive(Clone)]
+pub struct PersistOptions {
+    backend: Arc<dyn StorageBackend>,
+    debounce: Duration,
+}
+
+impl Debug for PersistOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersistOptions")
+            .field("debounce", &self.debounce)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for PersistOptions {
+    fn default() -> Self {
+        #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+        let backend = Arc::new(WebStorage::local());
+        #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+        let backend = Arc::new(MemoryStorage::new());
+        Self {
+            backend,
+            debounce: Duration::from_millis(100),
+        }
+    }
+}
+
+impl PersistOptions {
+    /// Sets where values are stored.
+    ///
+    /// Defaults to `localStorage` in the browser, and to a new
+    /// [`MemoryStorage`] everywhere else.
+    pub fn backend(mut self, backend: impl StorageBackend) -> Self {
+        self.backend = Arc::new(backend);
+        self
+    }
+
+    /// Sets how long to wait after the last change before writing the value
+    /// to storage. Defaults to 100ms.
+    ///
+    /// With a zero duration, every change is written immediately.
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+}
+
+/// A signal whose value is loaded from and saved to a [`StorageBackend`].
+///
+/// When it is created, the signal starts with the stored value, if there is
+/// one. Changes are written back once no further change has been made for
+/// the [debounce](PersistOptions::debounce) duration, and changes made in
+/// other tabs are applied to the signal.
+///
+/// During server rendering, the signal keeps its initial value and storage is
+/// not used. While hydrating, it also uses the initial value, so the
+/// hydrated page matches the server-rendered HTML, and loads the stored value
+/// once hydration has completed.
+///
+/// ```rust
+/// # use leptos_server::{MemoryStorage, PersistOptions, PersistedSignal};
+/// # use reactive_graph::traits::{GetUntracked, Set};
+/// # use std::time::Duration;
+/// let storage = MemoryStorage::new();
+/// let options = PersistOptions::default()
+///     .backend(storage.clone())
+///     .debounce(Duration::ZERO);
+///
+/// let theme = PersistedSignal::new_with_options(
+///     "theme",
+///     String::from("light"),
+///     options.clone(),
+/// );
+/// theme.set("dark".into());
+/// assert_eq!(storage.get("theme").as_deref(), Some(r#""dark""#));
+///
+/// // the next time it is created, the stored value is used
+/// let theme: PersistedSignal<String> =
+///     PersistedSignal::new_with_options("theme", "light".into(), options);
+/// assert_eq!(theme.get_untracked(), "dark");
+/// ```
+pub struct PersistedSignal<T, Ser = JsonSerdeCodec> {
+    signal: RwSignal<T>,
+    persister: ArenaItem<Arc<Persister>>,
+    ser: PhantomData<fn() -> Ser>,
+}
+
+impl<T, Ser> Clone for PersistedSignal<T, Ser> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, Ser> Copy for PersistedSignal<T, Ser> {}
+
+impl<T, Ser> Debug for PersistedSignal<T, Ser> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PersistedSignal")
+            .field("signal", &self.signal)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> PersistedSignal<T, JsonSerdeCodec>
+where
+    T: Send + Sync + 'static,
+    JsonSerdeCodec: Encoder<T> + Decoder<T>,
+    <JsonSerdeCodec as Encoder<T>>::Error: Debug,
+    <JsonSerdeCodec as Decoder<T>>::Error: Debug,
+    <JsonSerdeCodec as Encoder<T>>::Encoded: IntoEncodedString,
+    <JsonSerdeCodec as Decoder<T>>::Encoded: FromEncodedStr,
+    <<JsonSerdeCodec as Decoder<T>>::Encoded as FromEncodedStr>::DecodingError:
+        Debug,
+{
+    /// Creates a signal that is persisted under `key`, using the default
+    /// [`PersistOptions`].
+    ///
+    /// The value is stored as JSON, using [`JsonSerdeCodec`].
+    #[track_caller]
+    pub fn new(key: impl Into<String>, initial: T) -> Self {
+        Self::new_with_encoding(key, initial, PersistOptions::default())
+    }
+
+    /// Creates a signal that is persisted under `key`.
+    ///
+    /// The value is stored as JSON, using [`JsonSerdeCodec`].
+    #[track_caller]
+    pub fn new_with_options(
+        key: impl Into<String>,
+        initial: T,
+        options: PersistOptions,
+    ) -> Self {
+        Self::new_with_encoding(key, initial, options)
+    }
+}
+
+impl<T, Ser> PersistedSignal<T, Ser>
+where
+    T: Send + Sync + 'static,
+    Ser: Encoder<T> + Decoder<T>,
+    <Ser as Encoder<T>>::Error: Debug,
+    <Ser as Decoder<T>>::Error: Debug,
+    <Ser as Encoder<T>>::Encoded: IntoEncodedString,
+    <Ser as Decoder<T>>::Encoded: FromEncodedStr,
+    <<Ser as Decoder<T>>::Encoded as FromEncodedStr>::DecodingError: Debug,
+{
+    /// Creates a signal that is persisted under `key`.
+    ///
+    /// The value is stored using `Ser` as an encoding.
+    #[track_caller]
+    pub fn new_with_encoding(
+        key: impl Into<String>,
+        initial: T,
+        options: PersistOptions,
+    ) -> Self {
+        let signal = ArcRwSignal::new(initial);
+        let persister = Persister::new(
+            key.into(),
+            options,
+            signal.to_any_source(),
+            {
+                let signal = signal.clone();
+                move || {
+                    signal
+                        .try_with_untracked(|value| encode::<T, Ser>(value))
+                        .flatten()
+                }
+            },
+            {
+                let signal = signal.clone();
+                move |data| {
+                    if let Some(value) = decode::<T, Ser>(&data) {
+                        signal.try_set(value);
+                    }
+                }
+            },
+        );
+        Self {
+            signal: signal.into(),
+            persister: ArenaItem::new(persister),
+            ser: PhantomData,
+        }
+    }
+}
+
+impl<T, Ser> PersistedSignal<T, Ser> {
+    /// Writes any change that is waiting for the debounce duration to storage
+    /// immediately.
+    pub fn flush(&self) {
+        if let Some(persister) = self.persister.try_get_value() {
+            persister.flush();
+        }
+    }
+
+    /// Removes the stored value. The value of the signal does not change.
+    pub fn clear_storage(&self) {
+        if let Some(persister) = self.persister.try_get_value() {
+            persister.clear();
+        }
+    }
+}
+
+impl<T, Ser> DefinedAt for PersistedSignal<T, Ser> {
+    fn defined_at(&self) -> Option<&'static Location<'static>> {
+        self.signal.defined_at()
+    }
+}
+
+impl<T: 'static, Ser> IsDisposed for PersistedSignal<T, Ser> {
+    fn is_disposed(&self) -> bool {
+        self.signal.is_disposed()
+    }
+}
+
+impl<T, Ser> Dispose for PersistedSignal<T, Ser> {
+    fn dispose(self) {
+        self.signal.dispose();
+        self.persister.dispose();
+    }
+}
+
+impl<T, Ser> ReadUntracked for PersistedSignal<T, Ser>
+where
+    T: Send + Sync + 'static,
+{
+    type Value = ReadGuard<T, Plain<T>>;
+
+    fn try_read_untracked(&self) -> Option<Self::Value> {
+        self.signal.try_read_untracked()
+    }
+}
+
+impl<T, Ser> Track for PersistedSignal<T, Ser>
+where
+    T: Send + Sync + 'static,
+{
+    fn track(&self) {
+        self.signal.track();
+    }
+}
+
+impl<T, Ser> Notify for PersistedSignal<T, Ser>
+where
+    T: Send + Sync + 'static,
+{
+    fn notify(&self) {
+        self.signal.notify();
+    }
+}
+
+impl<T, Ser> Write for PersistedSignal<T, Ser>
+where
+    T: Send + Sync + 'static,
+{
+    type Value = T;
+
+    fn try_write(&self) -> Option<impl UntrackableGuard<Target = Self::Value>> {
+        self.signal.try_write()
+    }
+
+    fn try_write_untracked(
+        &self,
+    ) -> Option<impl DerefMut<Target = Self::Value>> {
+        self.signal.try_write_untracked()
+    }
+}
+
+#[cfg(feature = "reactive_stores")]
+mod store {
+    use super::{decode, encode, PersistOptions, Persister};
+    use crate::{FromEncodedStr, IntoEncodedString};
+    use codee::{string::JsonSerdeCodec, Decoder, Encoder};
+    use reactive_graph::{
+        graph::ToAnySource,
+        owner::ArenaItem,
+        signal::guards::{Plain, ReadGuard},
+        traits::{
+            DefinedAt, Dispose, IsDisposed, Notify, ReadUntracked, Track,
+            UntrackableGuard, Write,
+        },
+    };
+    use reactive_stores::{
+        ArcStore, KeyMap, Store, StoreField, StoreFieldTrigger, StorePath,
+        StorePathSegment,
+    };
+    use std::{
+        fmt::Debug, marker::PhantomData, ops::DerefMut, panic::Location,
+        sync::Arc,
+    };
+
+    /// A [`Store`](struct@Store) whose value is loaded from and saved to a
+    /// [`StorageBackend`](super::StorageBackend).
+    ///
+    /// This behaves like a [`PersistedSignal`](super::PersistedSignal): a
+    /// write to the store or any of its fields is saved once the debounce
+    /// duration has passed. Fields are accessed as they would be on the
+    /// store.
+    pub struct PersistedStore<T, Ser = JsonSerdeCodec> {
+        store: Store<T>,
+        persister: ArenaItem<Arc<Persister>>,
+        ser: PhantomData<fn() -> Ser>,
+    }
+
+    impl<T, Ser> Clone for PersistedStore<T, Ser> {
+        fn clone(&self) -> Self {
+            *self
+        }
+    }
+
+    impl<T, Ser> Copy for PersistedStore<T, Ser> {}
+
+    impl<T: Debug, Ser> Debug for PersistedStore<T, Ser> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("PersistedStore")
+                .field("store", &self.store)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<T> PersistedStore<T, JsonSerdeCodec>
+    where
+        T: Send + Sync + 'static,
+        JsonSerdeCodec: Encoder<T> + Decoder<T>,
+        <JsonSerdeCodec as Encoder<T>>::Error: Debug,
+        <JsonSerdeCodec as Decoder<T>>::Error: Debug,
+        <JsonSerdeCodec as Encoder<T>>::Encoded: IntoEncodedString,
+        <JsonSerdeCodec as Decoder<T>>::Encoded: FromEncodedStr,
+        <<JsonSerdeCodec as Decoder<T>>::Encoded as FromEncodedStr>::DecodingError:
+            Debug,
+    {
+        /// Creates a store that is persisted under `key`, using the default
+        /// [`PersistOptions`].
+        ///
+        /// The value is stored as JSON, using [`JsonSerdeCodec`].
+        pub fn new(key: impl Into<String>, initial: T) -> Self {
+            Self::new_with_encoding(key, initial, PersistOptions::default())
+        }
+
+        /// Creates a store that is persisted under `key`.
+        ///
+        /// The value is stored as JSON, using [`JsonSerdeCodec`].
+        pub fn new_with_options(
+            key: impl Into<String>,
+            initial: T,
+            options: PersistOptions,
+        ) -> Self {
+            Self::new_with_encoding(key, initial, options)
+        }
+    }
+
+    impl<T, Ser> PersistedStore<T, Ser>
+    where
+        T: Send + Sync + 'static,
+        Ser: Encoder<T> + Decoder<T>,
+        <Ser as Encoder<T>>::Error: Debug,
+        <Ser as Decoder<T>>::Error: Debug,
+        <Ser as Encoder<T>>::Encoded: IntoEncodedString,
+        <Ser as Decoder<T>>::Encoded: FromEncodedStr,
+        <<Ser as Decoder<T>>::Encoded as FromEncodedStr>::DecodingError: Debug,
+    {
+        /// Creates a store that is persisted under `key`.
+        ///
+        /// The value is stored using `Ser` as an encoding.
+        pub fn new_with_encoding(
+            key: impl Into<String>,
+            initial: T,
+            options: PersistOptions,
+        ) -> Self {
+            let store = ArcStore::new(initial);
+            // every write to the store or one of its fields notifies the root's children
+            let source = store
+                .get_trigger(StorePath::default())
+                .children()
+                .to_any_source();
+            let persister = Persister::new(
+                key.into(),
+                options,
+                source,
+                {
+                    let store = store.clone();
+                    move || {
+                        store
+                            .try_read_untracked()
+                            .and_then(|value| encode::<T, Ser>(&value))
+                    }
+                },
+                {
+                    let store = store.clone();
+                    move |data| {
+                        if let Some(value) = decode::<T, Ser>(&data) {
+                            if let Some(mut guard) = store.try_write() {
+                                *guard = value;
+                            }
+                        }
+                    }
+                },
+            );
+            Self {
+                store: store.into(),
+                persister: ArenaItem::new(persister),
+                ser: PhantomData,
+            }
+        }
+    }
+
+    impl<T, Ser> PersistedStore<T, Ser> {
+        /// Writes any change that is waiting for the debounce duration to
+        /// storage immediately.
+        pub fn flush(&self) {
+            if let Some(persister) = self.persister.try_get_value() {
+                persister.flush();
+            }
+        }
+
+        /// Removes the stored value. The value of the store does not change.
+        pub fn clear_storage(&self) {
+            if let Some(persister) = self.persister.try_get_value() {
+                persister.clear();
+            }
+        }
+    }
+
+    impl<T, Ser> StoreField for PersistedStore<T, Ser>
+    where
+        T: Send + Sync + 'static,
+    {
+        type Value = T;
+        type Reader = <Store<T> as StoreField>::Reader;
+        type Writer = <Store<T> as StoreField>::Writer;
+
+        fn get_trigger(&self, path: StorePath) -> StoreFieldTrigger {
+            self.store.get_trigger(path)
+        }
+
+        fn path(&self) -> impl IntoIterator<Item = StorePathSegment> {
+            self.store.path()
+        }
+
+        fn reader(&self) -> Option<Self::Reader> {
+            self.store.reader()
+        }
+
+        fn writer(&self) -> Option<Self::Writer> {
+            self.store.writer()
+        }
+
+        fn keys(&self) -> Option<KeyMap> {
+            self.store.keys()
+        }
+    }
+
+    impl<T, Ser> DefinedAt for PersistedStore<T, Ser> {
+        fn defined_at(&self) -> Option<&'static Location<'static>> {
+            self.store.defined_at()
+        }
+    }
+
+    impl<T: Send + Sync + 'static, Ser> IsDisposed for PersistedStore<T, Ser> {
+        fn is_disposed(&self) -> bool {
+            self.store.is_disposed()
+        }
+    }
+
+    impl<T: Send + Sync + 'static, Ser> Dispose for PersistedStore<T, Ser> {
+        fn dispose(self) {
+            self.store.dispose();
+            self.persister.dispose();
+        }
+    }
+
+    impl<T: Send + Sync + 'static, Ser> ReadUntracked for PersistedStore<T, Ser> {
+        type Value = ReadGuard<T, Plain<T>>;
+
+        fn try_read_untracked(&self) -> Option<Self::Value> {
+            self.store.try_read_untracked()
+        }
+    }
+
+    impl<T: Send + Sync + 'static, Ser> Track for PersistedStore<T, Ser> {
+        fn track(&self) {
+            self.store.track();
+        }
+    }
+
+    impl<T: Send + Sync + 'static, Ser> Notify for PersistedStore<T, Ser> {
+        fn notify(&self) {
+            self.store.notify();
+        }
+    }
+
+    impl<T: Send + Sync + 'static, Ser> Write for PersistedStore<T, Ser> {
+        type Value = T;
+
+        fn try_write(
+            &self,
+        ) -> Option<impl UntrackableGuard<Target = Self::Value>> {
+            self.store.try_write()
+        }
+
+        fn try_write_untracked(
+            &self,
+        ) -> Option<impl DerefMut<Target = Self::Value>> {
+            self.store.try_write_untracked()
+        }
+    }
+}
+
+#[cfg(feature = "reactive_stores")]
+pub use store::*;
+
+/// Subscribes to a persisted value, and writes it to storage when it changes.
+struct Persister {
+    key: String,
+    backend: Arc<dyn StorageBackend>,
+    debounce: Duration,
+    encode: Box<dyn Fn() -> Option<String> + Send + Sync>,
+    source: AnySource,
+    subscriber: AnySubscriber,
+    this: Weak<Persister>,
+    /// Set while a value from storage is being applied, so it is not written back.
+    applying: AtomicBool,
+    /// Whether there is a change that has not been written yet.
+    dirty: AtomicBool,
+    /// Counts changes, so that a scheduled write only happens for the latest one, and a stored
+    /// value that finishes loading after a change does not replace it.
+    changes: AtomicUsize,
+    unwatch: Mutex<Option<Box<dyn FnOnce() + Send + Sync>>>,
+}
+
+impl Persister {
+    fn new(
+        key: String,
+        options: PersistOptions,
+        source: AnySource,
+        encode: impl Fn() -> Option<String> + Send + Sync + 'static,
+        apply: impl Fn(String) + Send + Sync + 'static,
+    ) -> Arc<Self> {
+        let persister = Arc::new_cyclic(|this: &Weak<Persister>| Persister {
+            key,
+            backend: options.backend,
+            debounce: options.debounce,
+            encode: Box::new(encode),
+            source,
+            subscriber: AnySubscriber(
+                this.as_ptr() as usize,
+                Weak::clone(this) as Weak<dyn Subscriber + Send + Sync>,
+            ),
+            this: Weak::clone(this),
+            applying: AtomicBool::new(false),
+            dirty: AtomicBool::new(false),
+            changes: AtomicUsize::new(0),
+            unwatch: Mutex::new(None),
+        });
+
+        // when rendering on the server, keep the initial value and don't touch storage
+        let sc = Owner::current_shared_context();
+        if sc.as_ref().is_some_and(|sc| !sc.is_browser()) {
+            return persister;
+        }
+        persister
+            .source
+            .add_subscriber(persister.subscriber.clone());
+
+        let apply: Arc<dyn Fn(Option<String>) + Send + Sync> = {
+            let this = Arc::downgrade(&persister);
+            Arc::new(move |data| {
+                if let (Some(this), Some(data)) = (this.upgrade(), data) {
+                    this.applying.store(true, Ordering::Relaxed);
+                    apply(data);
+                    this.applying.store(false, Ordering::Relaxed);
+                }
+            })
+        };
+
+        // while hydrating, the initial value has to match what was rendered on the server, so
+        // the stored value is only loaded after hydration
+        let hydrating = sc.is_some_and(|sc| sc.during_hydration());
+        let mut load = persister.backend.load(&persister.key);
+        match (!hydrating).then(|| (&mut load).now_or_never()).flatten() {
+            Some(data) => apply(data),
+            None => Executor::spawn_local({
+                let this = Arc::downgrade(&persister);
+                let apply = Arc::clone(&apply);
+                async move {
+                    let data = load.await;
+                    // a change made while loading is newer than the stored value, so it is
+                    // kept, and written back instead
+                    let changed = this.upgrade().is_none_or(|this| {
+                        this.changes.load(Ordering::Relaxed) > 0
+                    });
+                    if !changed {
+                        apply(data);
+                    }
+                }
+            }),
+        }
+
+        let unwatch = persister.backend.watch(&persister.key, apply);
+        *persister.unwatch.lock().or_poisoned() = Some(unwatch);
+        persister
+    }
+
+    fn schedule(&self) {
+        let change = self.changes.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.debounce.is_zero() {
+            self.flush();
+            return;
+        }
+        let this = Weak::clone(&self.this);
+        set_timeout(
+            move || {
+                if let Some(this) = this.upgrade() {
+                    if this.changes.load(Ordering::Relaxed) == change {
+                        this.flush();
+                    }
+                }
+            },
+            self.debounce,
+        );
+    }
+
+    fn flush(&self) {
+        if self.dirty.swap(false, Ordering::Relaxed) {
+            if let Some(value) = (self.encode)() {
+                self.backend.save(&self.key, value);
+            }
+        }
+    }
+
+    fn clear(&self) {
+        self.dirty.store(false, Ordering::Relaxed);
+        self.backend.remove(&self.key);
+    }
+}
+
+impl Drop for Persister {
+    fn drop(&mut self) {
+        self.source.remove_subscriber(&self.subscriber);
+        if let Some(unwatch) = self
+            .unwatch
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            unwatch();
+        }
+        self.flush();
+    }
+}
+
+impl ReactiveNode for Persister {
+    fn mark_dirty(&self) {
+        if !self.applying.load(Ordering::Relaxed) {
+            self.dirty.store(true, Ordering::Relaxed);
+            self.schedule();
+        }
+        // some sources drop their subscribers when they notify them
+        self.source.add_subscriber(self.subscriber.clone());
+    }
+
+    fn mark_check(&self) {}
+
+    fn mark_subscribers_check(&self) {}
+
+    fn update_if_necessary(&self) -> bool {
+        false
+    }
+}
+
+impl Subscriber for Persister {
+    fn add_source(&self, _source: AnySource) {}
+
+    fn clear_sources(&self, _subscriber: &AnySubscriber) {}
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn set_timeout(f: impl FnOnce() + 'static, delay: Duration) {
+    use wasm_bindgen::{closure::Closure, JsCast};
+
+    if let Some(window) = web_sys::window() {
+        _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            Closure::once_into_js(f).unchecked_ref(),
+            delay.as_millis() as i32,
+        );
+    }
+}
+
+/// Runs `f` after `delay` on a single timer thread that is shared by all persisted values.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn set_timeout(f: impl FnOnce() + Send + 'static, delay: Duration) {
+    use std::{
+        collections::BTreeMap,
+        sync::{Condvar, LazyLock},
+        time::Instant,
+    };
+
+    type Timeouts = BTreeMap<(Instant, usize), Box<dyn FnOnce() + Send>>;
+
+    #[derive(Default)]
+    struct Timer {
+        /// The pending callbacks, ordered by when they are due, and the next id used to tell
+        /// apart callbacks that are due at the same time.
+        timeouts: Mutex<(Timeouts, usize)>,
+        wake: Condvar,
+    }
+
+    static TIMER: LazyLock<Arc<Timer>> = LazyLock::new(|| {
+        let timer = Arc::new(Timer::default());
+        std::thread::spawn({
+            let timer = Arc::clone(&timer);
+            move || {
+                let mut guard = timer.timeouts.lock().or_poisoned();
+                loop {
+                    let now = Instant::now();
+                    match guard.0.first_key_value().map(|((at, _), _)| *at) {
+                        None => {
+                            guard = timer
+                                .wake
+                                .wait(guard)
+                                .unwrap_or_else(PoisonError::into_inner);
+                        }
+                        Some(at) if at > now => {
+                            guard = timer
+                                .wake
+                                .wait_timeout(guard, at - now)
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .0;
+                        }
+                        Some(_) => {
+                            let Some((_, f)) = guard.0.pop_first() else {
+                                continue;
+                            };
+                            // run the callback without holding the lock, so it can set a timeout
+                            drop(guard);
+                            f();
+                            guard = timer.timeouts.lock().or_poisoned();
+                        }
+                    }
+                }
+            }
+        });
+        timer
+    });
+
+    let mut guard = TIMER.timeouts.lock().or_poisoned();
+    let (timeouts, next_id) = &mut *guard;
+    timeouts.insert((Instant::now() + delay, *next_id), Box::new(f));
+    *next_id = next_id.wrapping_add(1);
+    drop(guard);
+    TIMER.wake.notify_one();
+}
@@ -0,0 +1,238 @@
+use crate::{decode, encode, FromEncodedStr, IntoEncodedString};
+use codee::{string::JsonSerdeCodec, Decoder, Encoder};
+use reactive_graph::{
+    owner::Owner,
+    signal::guards::{Plain, ReadGuard},
+    traits::{
+        DefinedAt, Dispose, IsDisposed, Notify, ReadUntracked, Track,
+        UntrackableGuard, Write,
+    },
+};
+use reactive_stores::{
+    ArcStore, KeyMap, Patch, PatchField, Store, StoreField, StoreFieldTrigger,
+    StorePath, StorePathSegment,
+};
+use std::{fmt::Debug, marker::PhantomData, ops::DerefMut, panic::Location};
+
+/// A [`Store`](struct@Store) whose state is transferred from the server to the client.
+///
+/// If this is constructed on the server, its initial value is serialized into the shared
+/// context. If it is constructed on the client during hydration, its initial value is read from
+/// the shared context instead. At any other time, it is simply a store with the initial value.
+///
+/// Like [`SharedValue`](crate::SharedValue), only the value at creation is transferred. Changes
+/// made to the store afterwards, such as writes made while the page renders, are not sent to
+/// the client, so they should be made again on the client (for example, by the same resource
+/// that made them on the server) for hydration to match.
+///
+/// [`snapshot`](SharedStore::snapshot) and [`restore`](SharedStore::restore) encode and decode
+/// the value of the store with the same encoding, which can be used to persist it.
+pub struct SharedStore<T, Ser = JsonSerdeCodec> {
+    store: Store<T>,
+    ser: PhantomData<fn() -> Ser>,
+}
+
+impl<T, Ser> Clone for SharedStore<T, Ser> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T, Ser> Copy for SharedStore<T, Ser> {}
+
+impl<T: Debug, Ser> Debug for SharedStore<T, Ser> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedStore")
+            .field("store", &self.store)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> SharedStore<T, JsonSerdeCodec>
+where
+    T: Send + Sync + 'static,
+    JsonSerdeCodec: Encoder<T> + Decoder<T>,
+    <JsonSerdeCodec as Encoder<T>>::Error: Debug,
+    <JsonSerdeCodec as Decoder<T>>::Error: Debug,
+    <JsonSerdeCodec as Encoder<T>>::Encoded: IntoEncodedString,
+    <JsonSerdeCodec as Decoder<T>>::Encoded: FromEncodedStr,
+    <<JsonSerdeCodec as Decoder<T>>::Encoded as FromEncodedStr>::DecodingError:
+        Debug,
+{
+    /// Creates a store whose state is transferred from the server to the client.
+    ///
+    /// `initial` is not called on the client during hydration, as long as the serialized value
+    /// can be read.
+    ///
+    /// The value is serialized as JSON, using [`JsonSerdeCodec`].
+    pub fn new(initial: impl FnOnce() -> T) -> Self {
+        Self::new_with_encoding(initial)
+    }
+}
+
+impl<T, Ser> SharedStore<T, Ser>
+where
+    T: Send + Sync + 'static,
+    Ser: Encoder<T> + Decoder<T>,
+    <Ser as Encoder<T>>::Error: Debug,
+    <Ser as Decoder<T>>::Error: Debug,
+    <Ser as Encoder<T>>::Encoded: IntoEncodedString,
+    <Ser as Decoder<T>>::Encoded: FromEncodedStr,
+    <<Ser as Decoder<T>>::Encoded as FromEncodedStr>::DecodingError: Debug,
+{
+    /// Creates a store whose state is transferred from the server to the client.
+    ///
+    /// `initial` is not called on the client during hydration, as long as the serialized value
+    /// can be read.
+    ///
+    /// The value is serialized using `Ser` as an encoding.
+    pub fn new_with_encoding(initial: impl FnOnce() -> T) -> Self {
+        let sc = Owner::current_shared_context();
+        let id = sc.as_ref().map(|sc| sc.next_id()).unwrap_or_default();
+
+        let hydrated = sc
+            .as_ref()
+            .filter(|sc| sc.during_hydration())
+            .and_then(|sc| sc.read_data(&id))
+            .and_then(|data| decode::<T, Ser>(&data));
+        let store = ArcStore::new(hydrated.unwrap_or_else(initial));
+
+        #[cfg(feature = "ssr")]
+        if let Some(sc) = sc {
+            if sc.get_is_hydrating() {
+                // encode the value now, so the client reads the same value during hydration
+                // that the server started from
+                if let Some(value) = store
+                    .try_read_untracked()
+                    .and_then(|value| encode::<T, Ser>(&value))
+                {
+                    sc.write_async(id, Box::pin(async move { value }));
+                }
+            }
+        }
+
+        Self {
+            store: store.into(),
+            ser: PhantomData,
+        }
+    }
+
+    /// Encodes the current value of the store, or returns `None` if it could not be encoded or
+    /// the store has been disposed.
+    pub fn snapshot(&self) -> Option<String> {
+        self.store
+            .try_read_untracked()
+            .and_then(|value| encode::<T, Ser>(&value))
+    }
+
+    /// Decodes a value created by [`snapshot`](SharedStore::snapshot) and patches the store
+    /// with it, so that only the fields that changed are notified.
+    ///
+    /// Returns `false` if the value could not be decoded, in which case the store is unchanged.
+    pub fn restore(&self, data: &str) -> bool
+    where
+        T: PatchField,
+    {
+        match decode::<T, Ser>(data) {
+            Some(value) => {
+                self.patch(value);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl<T, Ser> SharedStore<T, Ser> {
+    /// Returns the underlying store.
+    pub fn store(&self) -> Store<T> {
+        self.store
+    }
+}
+
+impl<T, Ser> From<SharedStore<T, Ser>> for Store<T> {
+    fn from(value: SharedStore<T, Ser>) -> Self {
+        value.store
+    }
+}
+
+impl<T, Ser> StoreField for SharedStore<T, Ser>
+where
+    T: Send + Sync + 'static,
+{
+    type Value = T;
+    type Reader = <Store<T> as StoreField>::Reader;
+    type Writer = <Store<T> as StoreField>::Writer;
+
+    fn get_trigger(&self, path: StorePath) -> StoreFieldTrigger {
+        self.store.get_trigger(path)
+    }
+
+    fn path(&self) -> impl IntoIterator<Item = StorePathSegment> {
+        self.store.path()
+    }
+
+    fn reader(&self) -> Option<Self::Reader> {
+        self.store.reader()
+    }
+
+    fn writer(&self) -> Option<Self::Writer> {
+        self.store.writer()
+    }
+
+    fn keys(&self) -> Option<KeyMap> {
+        self.store.keys()
+    }
+}
+
+impl<T, Ser> DefinedAt for SharedStore<T, Ser> {
+    fn defined_at(&self) -> Option<&'static Location<'static>> {
+        self.store.defined_at()
+    }
+}
+
+impl<T: Send + Sync + 'static, Ser> IsDisposed for SharedStore<T, Ser> {
+    fn is_disposed(&self) -> bool {
+        self.store.is_disposed()
+    }
+}
+
+impl<T: Send + Sync + 'static, Ser> Dispose for SharedStore<T, Ser> {
+    fn dispose(self) {
+        self.store.dispose();
+    }
+}
+
+impl<T: Send + Sync + 'static, Ser> ReadUntracked for SharedStore<T, Ser> {
+    type Value = ReadGuard<T, Plain<T>>;
+
+    fn try_read_untracked(&self) -> Option<Self::Value> {
+        self.store.try_read_untracked()
+    }
+}
+
+impl<T: Send + Sync + 'static, Ser> Track for SharedStore<T, Ser> {
+    fn track(&self) {
+        self.store.track();
+    }
+}
+
+impl<T: Send + Sync + 'static, Ser> Notify for SharedStore<T, Ser> {
+    fn notify(&self) {
+        self.store.notify();
+    }
+}
+
+impl<T: Send + Sync + 'static, Ser> Write for SharedStore<T, Ser> {
+    type Value = T;
+
+    fn try_write(&self) -> Option<impl UntrackableGuard<Target = Self::Value>> {
+        self.store.try_write()
+    }
+
+    fn try_write_untracked(
+        &self,
+    ) -> Option<impl DerefMut<Target = Self::Value>> {
+        self.store.try_write_untracked()
+    }
+}
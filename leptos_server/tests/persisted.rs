@@ -0,0 +1,203 @@
+#![cfg(feature = "persisted")]
+
+use any_spawner::Executor;
+use futures::{channel::oneshot, future::LocalBoxFuture};
+use hydration_context::SsrSharedContext;
+use leptos_server::{
+    MemoryStorage, PersistOptions, PersistedSignal, StorageBackend,
+};
+use reactive_graph::{
+    owner::Owner,
+    traits::{GetUntracked, Set, Update},
+};
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+fn options(storage: &MemoryStorage) -> PersistOptions {
+    PersistOptions::default()
+        .backend(storage.clone())
+        .debounce(Duration::ZERO)
+}
+
+#[test]
+fn loads_stored_value_and_saves_changes() {
+    let owner = Owner::new();
+    owner.set();
+
+    let storage = MemoryStorage::new();
+    storage.set_from_other_tab("count", Some("5".into()));
+    let count =
+        PersistedSignal::new_with_options("count", 0, options(&storage));
+    assert_eq!(count.get_untracked(), 5);
+
+    count.update(|n| *n += 1);
+    assert_eq!(storage.get("count").as_deref(), Some("6"));
+
+    count.clear_storage();
+    assert_eq!(storage.get("count"), None);
+    assert_eq!(count.get_untracked(), 6);
+}
+
+#[test]
+fn debounces_writes() {
+    let owner = Owner::new();
+    owner.set();
+
+    let storage = MemoryStorage::new();
+    let name = PersistedSignal::new_with_options(
+        "name",
+        String::new(),
+        options(&storage).debounce(Duration::from_millis(20)),
+    );
+    for value in ["a", "ab", "abc"] {
+        name.set(value.into());
+    }
+    assert_eq!(storage.get("name"), None);
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(storage.get("name").as_deref(), Some(r#""abc""#));
+
+    name.set("abcd".into());
+    name.flush();
+    assert_eq!(storage.get("name").as_deref(), Some(r#""abcd""#));
+}
+
+#[test]
+fn applies_changes_from_other_tabs() {
+    let owner = Owner::new();
+    owner.set();
+
+    let storage = MemoryStorage::new();
+    let count = PersistedSignal::new_with_options(
+        "count",
+        0,
+        options(&storage).debounce(Duration::from_secs(60)),
+    );
+    storage.set_from_other_tab("count", Some("3".into()));
+    assert_eq!(count.get_untracked(), 3);
+    // values from storage are not written back
+    storage.set_from_other_tab("count", None);
+    count.flush();
+    assert_eq!(storage.get("count"), None);
+
+    // once the owner is cleaned up, the signal stops watching storage
+    owner.cleanup();
+    storage.set_from_other_tab("count", Some("4".into()));
+}
+
+/// Finishes loading values only once `finish_loading` is called.
+#[derive(Clone, Default)]
+struct SlowStorage {
+    storage: MemoryStorage,
+    loading: Arc<Mutex<Vec<oneshot::Sender<()>>>>,
+}
+
+impl SlowStorage {
+    fn finish_loading(&self) {
+        for tx in self.loading.lock().unwrap().drain(..) {
+            _ = tx.send(());
+        }
+        Executor::poll_local();
+    }
+}
+
+impl StorageBackend for SlowStorage {
+    fn load(&self, key: &str) -> LocalBoxFuture<'static, Option<String>> {
+        let (tx, rx) = oneshot::channel();
+        self.loading.lock().unwrap().push(tx);
+        let value = self.storage.get(key);
+        Box::pin(async move {
+            _ = rx.await;
+            value
+        })
+    }
+
+    fn save(&self, key: &str, value: String) {
+        self.storage.save(key, value);
+    }
+
+    fn remove(&self, key: &str) {
+        self.storage.remove(key);
+    }
+}
+
+#[test]
+fn changes_made_while_loading_are_kept() {
+    _ = Executor::init_futures_executor();
+    let owner = Owner::new();
+    owner.set();
+
+    let storage = SlowStorage::default();
+    storage.storage.set_from_other_tab("a", Some("5".into()));
+    storage.storage.set_from_other_tab("b", Some("5".into()));
+    let options = PersistOptions::default()
+        .backend(storage.clone())
+        .debounce(Duration::ZERO);
+    let a = PersistedSignal::new_with_options("a", 0, options.clone());
+    let b = PersistedSignal::new_with_options("b", 0, options);
+    assert_eq!(a.get_untracked(), 0);
+
+    a.set(1);
+    storage.finish_loading();
+    assert_eq!(a.get_untracked(), 1);
+    assert_eq!(storage.storage.get("a").as_deref(), Some("1"));
+    // without a change, the stored value is used once it has loaded
+    assert_eq!(b.get_untracked(), 5);
+}
+
+#[test]
+fn server_rendering_uses_initial_value() {
+    let owner = Owner::new_root(Some(Arc::new(SsrSharedContext::new())));
+    owner.set();
+
+    let storage = MemoryStorage::new();
+    storage.set_from_other_tab("theme", Some(r#""dark""#.into()));
+    let theme = PersistedSignal::new_with_options(
+        "theme",
+        String::from("light"),
+        options(&storage),
+    );
+    assert_eq!(theme.get_untracked(), "light");
+    theme.set("blue".into());
+    assert_eq!(storage.get("theme").as_deref(), Some(r#""dark""#));
+}
+
+#[cfg(feature = "reactive_stores")]
+#[test]
+fn stores_are_persisted() {
+    use leptos_server::PersistedStore;
+    use reactive_stores::Store;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Store, Serialize, Deserialize)]
+    struct Settings {
+        theme: String,
+        font_size: u8,
+    }
+
+    let owner = Owner::new();
+    owner.set();
+
+    let storage = MemoryStorage::new();
+    storage.set_from_other_tab(
+        "settings",
+        Some(r#"{"theme":"dark","font_size":12}"#.into()),
+    );
+    let settings = PersistedStore::new_with_options(
+        "settings",
+        Settings {
+            theme: "light".into(),
+            font_size: 14,
+        },
+        options(&storage),
+    );
+    assert_eq!(settings.theme().get_untracked(), "dark");
+
+    settings.font_size().set(16);
+    assert_eq!(
+        storage.get("settings").as_deref(),
+        Some(r#"{"theme":"dark","font_size":16}"#)
+    );
+}
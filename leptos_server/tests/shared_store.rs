@@ -0,0 +1,177 @@
+#![cfg(feature = "reactive_stores")]
+
+use hydration_context::{
+    PinnedFuture, PinnedStream, SerializedDataId, SharedContext,
+};
+use leptos_server::SharedStore;
+use reactive_graph::{
+    owner::Owner,
+    traits::{GetUntracked, Set},
+};
+use reactive_stores::{Patch, Store};
+use serde::{Deserialize, Serialize};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use throw_error::{Error, ErrorId};
+
+#[derive(Debug, Clone, PartialEq, Store, Patch, Serialize, Deserialize)]
+struct Cart {
+    user: String,
+    items: Vec<String>,
+}
+
+fn cart() -> Cart {
+    Cart {
+        user: "alice".into(),
+        items: vec!["apple".into()],
+    }
+}
+
+#[test]
+fn snapshots_are_restored() {
+    let owner = Owner::new();
+    owner.set();
+
+    let cart = SharedStore::new(cart);
+    let snapshot = cart.snapshot().unwrap();
+    assert_eq!(snapshot, r#"{"user":"alice","items":["apple"]}"#);
+
+    cart.user().set("bob".into());
+    cart.items().set(vec!["pear".into()]);
+
+    // only the fields that differ from the snapshot are notified
+    let paths = Arc::new(Mutex::new(Vec::new()));
+    let _subscription = cart.store().subscribe({
+        let paths = Arc::clone(&paths);
+        move |change| paths.lock().unwrap().push(change.path.clone())
+    });
+    assert!(cart.restore(r#"{"user":"bob","items":["apple"]}"#));
+    assert_eq!(cart.user().get_untracked(), "bob");
+    assert_eq!(cart.items().get_untracked(), ["apple"]);
+    assert_eq!(paths.lock().unwrap().len(), 1);
+
+    assert!(!cart.restore("not json"));
+    assert_eq!(cart.items().get_untracked(), ["apple"]);
+}
+
+/// A shared context that is hydrating from data that was serialized by the server.
+#[derive(Debug)]
+struct Hydrating(Vec<String>, AtomicUsize);
+
+impl SharedContext for Hydrating {
+    fn is_browser(&self) -> bool {
+        true
+    }
+
+    fn next_id(&self) -> SerializedDataId {
+        SerializedDataId::new(self.1.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn write_async(&self, _id: SerializedDataId, _fut: PinnedFuture<String>) {}
+
+    fn read_data(&self, id: &SerializedDataId) -> Option<String> {
+        self.0.get(id.clone().into_inner()).cloned()
+    }
+
+    fn await_data(&self, id: &SerializedDataId) -> Option<String> {
+        self.read_data(id)
+    }
+
+    fn pending_data(&self) -> Option<PinnedStream<String>> {
+        None
+    }
+
+    fn during_hydration(&self) -> bool {
+        true
+    }
+
+    fn hydration_complete(&self) {}
+
+    fn get_is_hydrating(&self) -> bool {
+        true
+    }
+
+    fn set_is_hydrating(&self, _is_hydrating: bool) {}
+
+    fn take_errors(&self) -> Vec<(SerializedDataId, ErrorId, Error)> {
+        Vec::new()
+    }
+
+    fn errors(&self, _boundary_id: &SerializedDataId) -> Vec<(ErrorId, Error)> {
+        Vec::new()
+    }
+
+    fn seal_errors(&self, _boundary_id: &SerializedDataId) {}
+
+    fn register_error(
+        &self,
+        _error_boundary: SerializedDataId,
+        _error_id: ErrorId,
+        _error: Error,
+    ) {
+    }
+
+    fn defer_stream(&self, _wait_for: PinnedFuture<()>) {}
+
+    fn await_deferred(&self) -> Option<PinnedFuture<()>> {
+        None
+    }
+
+    fn set_incomplete_chunk(&self, _id: SerializedDataId) {}
+
+    fn get_incomplete_chunk(&self, _id: &SerializedDataId) -> bool {
+        false
+    }
+}
+
+fn hydrate(data: Vec<String>) -> Owner {
+    let sc = Hydrating(data, AtomicUsize::new(0));
+    let owner = Owner::new_root(Some(
+        Arc::new(sc) as Arc<dyn SharedContext + Send + Sync>
+    ));
+    owner.set();
+    owner
+}
+
+#[test]
+fn client_reads_serialized_value() {
+    let _owner =
+        hydrate(vec![r#"{"user":"bob","items":["pear"]}"#.to_string()]);
+
+    let hydrated = SharedStore::new(|| -> Cart {
+        panic!("the initial value is not needed while hydrating")
+    });
+    assert_eq!(hydrated.user().get_untracked(), "bob");
+    assert_eq!(hydrated.items().get_untracked(), ["pear"]);
+
+    // anything that was not serialized falls back to the initial value
+    let other = SharedStore::new(cart);
+    assert_eq!(other.user().get_untracked(), "alice");
+}
+
+#[cfg(feature = "ssr")]
+#[test]
+fn server_serializes_value_at_creation() {
+    use hydration_context::SsrSharedContext;
+
+    let sc = Arc::new(SsrSharedContext::new());
+    let owner = Owner::new_root(Some(
+        Arc::clone(&sc) as Arc<dyn SharedContext + Send + Sync>
+    ));
+    owner.set();
+
+    let cart = SharedStore::new(cart);
+    // writes made after creation, such as while rendering, are not sent
+    cart.items().set(vec!["apple".into(), "pear".into()]);
+
+    let data = futures::executor::block_on(sc.consume_buffers());
+    assert_eq!(data.len(), 1);
+    assert_eq!(data[0].1, r#"{"user":"alice","items":["apple"]}"#);
+
+    // so the client hydrates with the same value the server started from
+    let _owner = hydrate(data.into_iter().map(|(_, data)| data).collect());
+    let hydrated = SharedStore::new(|| -> Cart { unreachable!() });
+    assert_eq!(hydrated.items().get_untracked(), ["apple"]);
+}
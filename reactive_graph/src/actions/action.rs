@@ -232,10 +232,15 @@ impl ActionAbortHandle {
     /// Aborts the action.
     ///
     /// This will cause the dispatched task to complete, without updating the action's value. The
-    /// dispatched action's `Future` will no longer be polled. This does not guarantee that side
-    /// effects created by that `Future` no longer run: for example, if the action dispatches an
-    /// HTTP request, whether that request is actually canceled or not depends on whether the
-    /// request library actually cancels a request when its `Future` is dropped.
+    /// dispatched action's `Future` will no longer be polled, and is dropped. This does not
+    /// guarantee that side effects created by that `Future` no longer run: for example, if the
+    /// action dispatches an HTTP request, whether that request is actually canceled or not
+    /// depends on whether the request library actually cancels a request when its `Future` is
+    /// dropped.
+    ///
+    /// Server functions called with the built-in clients do cancel their request: in the
+    /// browser, the `fetch` is aborted through its `AbortSignal`. On the server, the integrations
+    /// then cancel the `CancellationToken` they provide to the server function.
     pub fn abort(self) {
         let _ = self.0.send(());
     }
@@ -1082,6 +1087,21 @@ impl<I, O> DefinedAt for Action<I, O> {
     }
 }
 
+impl<I, O> From<ArcAction<I, O>> for Action<I, O>
+where
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+{
+    #[track_caller]
+    fn from(value: ArcAction<I, O>) -> Self {
+        Self {
+            inner: ArenaItem::new(value),
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+        }
+    }
+}
+
 impl<I, O> Clone for Action<I, O> {
     fn clone(&self) -> Self {
         *self
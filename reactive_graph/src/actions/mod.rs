@@ -2,5 +2,7 @@
 
 mod action;
 mod multi_action;
+mod optimistic;
 pub use action::*;
 pub use multi_action::*;
+pub use optimistic::*;
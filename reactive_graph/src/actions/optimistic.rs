@@ -0,0 +1,561 @@
+use super::{Action, ArcAction};
+use crate::traits::{DefinedAt, Dispose, Update, WithUntracked};
+use or_poisoned::OrPoisoned;
+use std::{
+    collections::VecDeque,
+    future::Future,
+    mem,
+    ops::Deref,
+    panic::Location,
+    sync::{Arc, Mutex},
+};
+
+/// An action that optimistically updates some reactive value as soon as it is dispatched, before
+/// its `async` function has resolved.
+///
+/// When it is dispatched, the `update` function is applied to the target, which can be a signal,
+/// a store, or anything else that can be read and updated. Once the `async` function resolves,
+/// the result is reconciled with the target:
+/// * if it returns `Ok(_)`, the optimistic update is replaced by the `reconcile` function, which
+///   by default applies `update` again;
+/// * if it returns `Err(_)`, or the dispatch is aborted, the optimistic update is reverted.
+///
+/// Overlapping dispatches are reconciled in the order they were dispatched, regardless of the
+/// order in which they resolve: each one is applied on top of the ones before it, and reverting
+/// one does not revert the others.
+///
+/// Writes made to the target by anything else while dispatches are pending are kept: the next
+/// dispatch or settled dispatch uses the target's current value as its new starting point. Such
+/// a write is assumed to have been made on top of the optimistic updates that were showing at
+/// the time, so those updates become part of the target's value, and are no longer reverted or
+/// reconciled. Noticing these writes is why the target's value must implement `PartialEq`.
+///
+/// This dereferences to the underlying [`ArcAction`], so its `input`, `value`, `pending`, and
+/// `version` can be used as usual.
+///
+/// The arena-allocated, `Copy` version of an `ArcOptimisticAction` is an [`OptimisticAction`].
+///
+/// ```rust
+/// # use reactive_graph::actions::*;
+/// # use reactive_graph::prelude::*;
+/// # use reactive_graph::signal::ArcRwSignal;
+/// # tokio_test::block_on(async move {
+/// # any_spawner::Executor::init_tokio(); let owner = reactive_graph::owner::Owner::new(); owner.set();
+/// # let _guard = reactive_graph::diagnostics::SpecialNonReactiveZone::enter();
+/// async fn add_todo_to_api(task: String) -> Result<usize, String> {
+///     Err(format!("couldn't add {task:?}"))
+/// }
+///
+/// let todos = ArcRwSignal::new(Vec::<String>::new());
+/// let add_todo = ArcOptimisticAction::new(
+///     todos.clone(),
+///     |todos: &mut Vec<String>, task: &String| todos.push(task.clone()),
+///     |task: &String| add_todo_to_api(task.clone()),
+/// );
+///
+/// add_todo.dispatch("Buy milk".to_string());
+/// // the todo is shown immediately
+/// assert_eq!(todos.get(), vec!["Buy milk".to_string()]);
+///
+/// # any_spawner::Executor::tick().await;
+/// // it is removed again when the request fails
+/// assert!(todos.get().is_empty());
+/// assert!(matches!(add_todo.value().get(), Some(Err(_))));
+/// # });
+/// ```
+pub struct ArcOptimisticAction<I, O, E> {
+    inner: ArcAction<I, Result<O, E>>,
+    #[cfg(any(debug_assertions, leptos_debuginfo))]
+    defined_at: &'static Location<'static>,
+}
+
+impl<I, O, E> Clone for ArcOptimisticAction<I, O, E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: self.defined_at,
+        }
+    }
+}
+
+impl<I, O, E> ArcOptimisticAction<I, O, E>
+where
+    I: Clone + Send + Sync + 'static,
+    O: Clone + Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    /// Creates a new optimistic action, which applies `update` to `target` when it is dispatched.
+    ///
+    /// If the `async` function succeeds, the update is kept. If it fails or is aborted, the
+    /// update is reverted.
+    #[track_caller]
+    pub fn new<Tgt, T, F, Fu>(
+        target: Tgt,
+        update: impl Fn(&mut T, &I) + Send + Sync + 'static,
+        action_fn: F,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+        F: Fn(&I) -> Fu + Send + Sync + 'static,
+        Fu: Future<Output = Result<O, E>> + Send + 'static,
+    {
+        let update = Arc::new(update);
+        Self::new_with_reconcile(
+            target,
+            {
+                let update = Arc::clone(&update);
+                move |value: &mut T, input: &I| update(value, input)
+            },
+            move |value: &mut T, input: &I, _: &O| update(value, input),
+            action_fn,
+        )
+    }
+
+    /// Creates a new optimistic action, which applies `update` to `target` when it is dispatched.
+    ///
+    /// If the `async` function succeeds, the update is replaced by `reconcile`, which is called
+    /// with its input and output: for example, to replace a temporary ID with the one created
+    /// by the server. If it fails or is aborted, the update is reverted.
+    #[track_caller]
+    pub fn new_with_reconcile<Tgt, T, F, Fu>(
+        target: Tgt,
+        update: impl Fn(&mut T, &I) + Send + Sync + 'static,
+        reconcile: impl Fn(&mut T, &I, &O) + Send + Sync + 'static,
+        action_fn: F,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+        F: Fn(&I) -> Fu + Send + Sync + 'static,
+        Fu: Future<Output = Result<O, E>> + Send + 'static,
+    {
+        Self::new_with_value(None, target, update, reconcile, action_fn)
+    }
+
+    /// Creates a new optimistic action with an initial value, which applies `update` to
+    /// `target` when it is dispatched, and `reconcile` once it succeeds.
+    ///
+    /// See [`ArcAction::new_with_value`] and [`ArcOptimisticAction::new_with_reconcile`].
+    #[track_caller]
+    pub fn new_with_value<Tgt, T, F, Fu>(
+        value: Option<Result<O, E>>,
+        target: Tgt,
+        update: impl Fn(&mut T, &I) + Send + Sync + 'static,
+        reconcile: impl Fn(&mut T, &I, &O) + Send + Sync + 'static,
+        action_fn: F,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+        F: Fn(&I) -> Fu + Send + Sync + 'static,
+        Fu: Future<Output = Result<O, E>> + Send + 'static,
+    {
+        let optimistic = Arc::new(Optimistic {
+            target,
+            update: Box::new(update),
+            reconcile: Box::new(reconcile),
+            state: Mutex::new(State {
+                base: None,
+                applied: None,
+                next_id: 0,
+                dispatches: VecDeque::new(),
+            }),
+        });
+        Self {
+            inner: ArcAction::new_with_value(value, move |input: &I| {
+                let id = optimistic.dispatch(input);
+                let fut = action_fn(input);
+                let mut settle = Settle {
+                    id,
+                    optimistic: Arc::clone(&optimistic),
+                    outcome: Outcome::Failed,
+                };
+                async move {
+                    let result = fut.await;
+                    if let Ok(output) = &result {
+                        settle.outcome = Outcome::Confirmed(output.clone());
+                    }
+                    // the dispatch is settled when `settle` is dropped, which also happens if
+                    // the action is aborted
+                    drop(settle);
+                    result
+                }
+            }),
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+        }
+    }
+}
+
+impl<I, O, E> Deref for ArcOptimisticAction<I, O, E> {
+    type Target = ArcAction<I, Result<O, E>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<I, O, E> From<ArcOptimisticAction<I, O, E>>
+    for ArcAction<I, Result<O, E>>
+{
+    fn from(value: ArcOptimisticAction<I, O, E>) -> Self {
+        value.inner
+    }
+}
+
+impl<I, O, E> DefinedAt for ArcOptimisticAction<I, O, E> {
+    fn defined_at(&self) -> Option<&'static Location<'static>> {
+        #[cfg(any(debug_assertions, leptos_debuginfo))]
+        {
+            Some(self.defined_at)
+        }
+        #[cfg(not(any(debug_assertions, leptos_debuginfo)))]
+        {
+            None
+        }
+    }
+}
+
+/// An action that optimistically updates some reactive value as soon as it is dispatched, before
+/// its `async` function has resolved.
+///
+/// See [`ArcOptimisticAction`] for how updates are applied and reverted.
+///
+/// This dereferences to the underlying [`Action`], so its `input`, `value`, `pending`, and
+/// `version` can be used as usual.
+///
+/// The reference-counted, `Clone` (but not `Copy`) version of an `OptimisticAction` is an
+/// [`ArcOptimisticAction`].
+pub struct OptimisticAction<I, O, E> {
+    inner: Action<I, Result<O, E>>,
+    #[cfg(any(debug_assertions, leptos_debuginfo))]
+    defined_at: &'static Location<'static>,
+}
+
+impl<I, O, E> Dispose for OptimisticAction<I, O, E> {
+    fn dispose(self) {
+        self.inner.dispose()
+    }
+}
+
+impl<I, O, E> OptimisticAction<I, O, E>
+where
+    I: Clone + Send + Sync + 'static,
+    O: Clone + Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    /// Creates a new optimistic action, which applies `update` to `target` when it is dispatched.
+    ///
+    /// See [`ArcOptimisticAction::new`].
+    #[track_caller]
+    pub fn new<Tgt, T, F, Fu>(
+        target: Tgt,
+        update: impl Fn(&mut T, &I) + Send + Sync + 'static,
+        action_fn: F,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+        F: Fn(&I) -> Fu + Send + Sync + 'static,
+        Fu: Future<Output = Result<O, E>> + Send + 'static,
+    {
+        ArcOptimisticAction::new(target, update, action_fn).into()
+    }
+
+    /// Creates a new optimistic action, which applies `update` to `target` when it is
+    /// dispatched, and `reconcile` once it succeeds.
+    ///
+    /// See [`ArcOptimisticAction::new_with_reconcile`].
+    #[track_caller]
+    pub fn new_with_reconcile<Tgt, T, F, Fu>(
+        target: Tgt,
+        update: impl Fn(&mut T, &I) + Send + Sync + 'static,
+        reconcile: impl Fn(&mut T, &I, &O) + Send + Sync + 'static,
+        action_fn: F,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+        F: Fn(&I) -> Fu + Send + Sync + 'static,
+        Fu: Future<Output = Result<O, E>> + Send + 'static,
+    {
+        ArcOptimisticAction::new_with_reconcile(
+            target, update, reconcile, action_fn,
+        )
+        .into()
+    }
+
+    /// Creates a new optimistic action with an initial value, which applies `update` to
+    /// `target` when it is dispatched, and `reconcile` once it succeeds.
+    ///
+    /// See [`ArcOptimisticAction::new_with_value`].
+    #[track_caller]
+    pub fn new_with_value<Tgt, T, F, Fu>(
+        value: Option<Result<O, E>>,
+        target: Tgt,
+        update: impl Fn(&mut T, &I) + Send + Sync + 'static,
+        reconcile: impl Fn(&mut T, &I, &O) + Send + Sync + 'static,
+        action_fn: F,
+    ) -> Self
+    where
+        Tgt: Update<Value = T>
+            + WithUntracked<Value = T>
+            + Send
+            + Sync
+            + 'static,
+        T: Clone + PartialEq + Send + Sync + 'static,
+        F: Fn(&I) -> Fu + Send + Sync + 'static,
+        Fu: Future<Output = Result<O, E>> + Send + 'static,
+    {
+        ArcOptimisticAction::new_with_value(
+            value, target, update, reconcile, action_fn,
+        )
+        .into()
+    }
+}
+
+impl<I, O, E> From<ArcOptimisticAction<I, O, E>> for OptimisticAction<I, O, E>
+where
+    I: Send + Sync + 'static,
+    O: Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    #[track_caller]
+    fn from(value: ArcOptimisticAction<I, O, E>) -> Self {
+        Self {
+            inner: value.inner.into(),
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+        }
+    }
+}
+
+impl<I, O, E> Deref for OptimisticAction<I, O, E> {
+    type Target = Action<I, Result<O, E>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl<I, O, E> From<OptimisticAction<I, O, E>> for Action<I, Result<O, E>> {
+    fn from(value: OptimisticAction<I, O, E>) -> Self {
+        value.inner
+    }
+}
+
+impl<I, O, E> Clone for OptimisticAction<I, O, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I, O, E> Copy for OptimisticAction<I, O, E> {}
+
+impl<I, O, E> DefinedAt for OptimisticAction<I, O, E> {
+    fn defined_at(&self) -> Option<&'static Location<'static>> {
+        #[cfg(any(debug_assertions, leptos_debuginfo))]
+        {
+            Some(self.defined_at)
+        }
+        #[cfg(not(any(debug_assertions, leptos_debuginfo)))]
+        {
+            None
+        }
+    }
+}
+
+type UpdateFn<T, I> = Box<dyn Fn(&mut T, &I) + Send + Sync>;
+type ReconcileFn<T, I, O> = Box<dyn Fn(&mut T, &I, &O) + Send + Sync>;
+
+/// The target of an optimistic action, and the dispatches that have not yet been reconciled.
+struct Optimistic<Tgt, T, I, O> {
+    target: Tgt,
+    update: UpdateFn<T, I>,
+    reconcile: ReconcileFn<T, I, O>,
+    state: Mutex<State<T, I, O>>,
+}
+
+struct State<T, I, O> {
+    /// The value of the target before the earliest dispatch that has not been reconciled.
+    base: Option<T>,
+    /// The value the target was last set to by this action, used to notice writes made by
+    /// anything else.
+    applied: Option<T>,
+    next_id: usize,
+    dispatches: VecDeque<Dispatch<I, O>>,
+}
+
+struct Dispatch<I, O> {
+    id: usize,
+    input: I,
+    outcome: Outcome<O>,
+    /// Whether the optimistic update has become part of the base, because the target was
+    /// written by something else after it was applied.
+    in_base: bool,
+}
+
+enum Outcome<O> {
+    Pending,
+    Confirmed(O),
+    Failed,
+}
+
+impl<Tgt, T, I, O> Optimistic<Tgt, T, I, O>
+where
+    Tgt: Update<Value = T> + WithUntracked<Value = T>,
+    T: Clone + PartialEq,
+    I: Clone,
+{
+    /// Applies the optimistic update for a new dispatch, and returns its ID.
+    fn dispatch(&self, input: &I) -> usize {
+        let id = {
+            let mut state = self.state.lock().or_poisoned();
+            if state.dispatches.is_empty() {
+                state.base = self.target.try_with_untracked(T::clone);
+            } else {
+                self.rebase(&mut state);
+            }
+            let id = state.next_id;
+            state.next_id += 1;
+            state.dispatches.push_back(Dispatch {
+                id,
+                input: input.clone(),
+                outcome: Outcome::Pending,
+                in_base: false,
+            });
+            id
+        };
+        let applied = self.target.try_update(|value| {
+            (self.update)(value, input);
+            value.clone()
+        });
+        self.state.lock().or_poisoned().applied = applied;
+        id
+    }
+
+    /// If the target has been written by something else since this action last set it, uses
+    /// its current value as the base, with the updates that were showing already applied.
+    fn rebase(&self, state: &mut State<T, I, O>) {
+        let Some(applied) = &state.applied else {
+            return;
+        };
+        let written = self
+            .target
+            .try_with_untracked(|value| {
+                (value != applied).then(|| value.clone())
+            })
+            .flatten();
+        if let Some(value) = written {
+            state.base = Some(value);
+            for dispatch in &mut state.dispatches {
+                dispatch.in_base = true;
+            }
+        }
+    }
+
+    /// Records the outcome of a dispatch, and updates the target to the base value with every
+    /// dispatch that has not been reverted applied on top of it, in order.
+    fn settle(&self, id: usize, outcome: Outcome<O>) {
+        let value = {
+            let mut state = self.state.lock().or_poisoned();
+            self.rebase(&mut state);
+            let State {
+                base, dispatches, ..
+            } = &mut *state;
+            let Some(dispatch) = dispatches.iter_mut().find(|d| d.id == id)
+            else {
+                return;
+            };
+            dispatch.outcome = outcome;
+
+            // dispatches that have settled, and all those before them, are folded into the base
+            while dispatches
+                .front()
+                .is_some_and(|d| !matches!(d.outcome, Outcome::Pending))
+            {
+                let dispatch = dispatches.pop_front().unwrap();
+                if let (false, Some(base), Outcome::Confirmed(output)) =
+                    (dispatch.in_base, base.as_mut(), &dispatch.outcome)
+                {
+                    (self.reconcile)(base, &dispatch.input, output);
+                }
+            }
+
+            let mut value = if dispatches.is_empty() {
+                base.take()
+            } else {
+                base.clone()
+            };
+            if let Some(value) = &mut value {
+                for dispatch in dispatches.iter().filter(|d| !d.in_base) {
+                    match &dispatch.outcome {
+                        Outcome::Pending => {
+                            (self.update)(value, &dispatch.input)
+                        }
+                        Outcome::Confirmed(output) => {
+                            (self.reconcile)(value, &dispatch.input, output)
+                        }
+                        Outcome::Failed => {}
+                    }
+                }
+            }
+            state.applied = if state.dispatches.is_empty() {
+                None
+            } else {
+                value.clone()
+            };
+            value
+        };
+        if let Some(value) = value {
+            self.target.try_update(|current| *current = value);
+        }
+    }
+}
+
+/// Settles a dispatch when it is dropped: either because its `async` function has resolved, or
+/// because it was aborted.
+struct Settle<Tgt, T, I, O>
+where
+    Tgt: Update<Value = T> + WithUntracked<Value = T>,
+    T: Clone + PartialEq,
+    I: Clone,
+{
+    id: usize,
+    optimistic: Arc<Optimistic<Tgt, T, I, O>>,
+    outcome: Outcome<O>,
+}
+
+impl<Tgt, T, I, O> Drop for Settle<Tgt, T, I, O>
+where
+    Tgt: Update<Value = T> + WithUntracked<Value = T>,
+    T: Clone + PartialEq,
+    I: Clone,
+{
+    fn drop(&mut self) {
+        let outcome = mem::replace(&mut self.outcome, Outcome::Failed);
+        self.optimistic.settle(self.id, outcome);
+    }
+}
@@ -237,6 +237,12 @@ macro_rules! spawn_derived {
             suspenses: Vec::new(),
             pending_suspenses: Vec::new()
         }));
+        #[cfg(feature = "devtools")]
+        crate::devtools::register(
+            crate::devtools::NodeKind::AsyncDerived,
+            &inner,
+            Some(Location::caller()),
+        );
         let value = Arc::new(AsyncRwLock::new($initial));
         let wakers = Arc::new(RwLock::new(Vec::new()));
 
@@ -249,6 +255,8 @@ macro_rules! spawn_derived {
             loading: Arc::new(AtomicBool::new(!is_ready)),
         };
         let any_subscriber = this.to_any_subscriber();
+        #[cfg(feature = "devtools")]
+        crate::devtools::record_run(any_subscriber.0);
         let initial_fut = if $should_track {
             owner.with_cleanup(|| {
                 any_subscriber
@@ -337,6 +345,8 @@ macro_rules! spawn_derived {
                                     // generate new Future
                                     let owner = inner.read().or_poisoned().owner.clone();
                                     let fut = initial_fut.take().unwrap_or_else(|| {
+                                        #[cfg(feature = "devtools")]
+                                        crate::devtools::record_run(any_subscriber.0);
                                         let fut = if $should_track {
                                             owner.with_cleanup(|| {
                                                 any_subscriber
@@ -105,3 +105,16 @@ impl Subscriber for RwLock<ArcAsyncDerivedInner> {
         self.write().or_poisoned().sources.clear_sources(subscriber);
     }
 }
+
+#[cfg(feature = "devtools")]
+impl crate::devtools::Inspect for RwLock<ArcAsyncDerivedInner> {
+    fn sources(&self) -> Vec<usize> {
+        let lock = self.read().or_poisoned();
+        (&lock.sources).into_iter().map(|source| source.0).collect()
+    }
+
+    fn subscribers(&self) -> Vec<usize> {
+        let lock = self.read().or_poisoned();
+        (&lock.subscribers).into_iter().map(|sub| sub.0).collect()
+    }
+}
@@ -130,6 +130,8 @@ where
                 any_subscriber
             }
             let any_subscriber = inner_1(&self.reactivity);
+            #[cfg(feature = "devtools")]
+            crate::devtools::record_run(any_subscriber.0);
 
             let (new_value, changed) = self.owner.with_cleanup(|| {
                 any_subscriber.with_observer(|| {
@@ -220,3 +222,19 @@ where
             .clear_sources(subscriber);
     }
 }
+
+#[cfg(feature = "devtools")]
+impl<T, S> crate::devtools::Inspect for MemoInner<T, S>
+where
+    S: Storage<T>,
+{
+    fn sources(&self) -> Vec<usize> {
+        let lock = self.reactivity.read().or_poisoned();
+        (&lock.sources).into_iter().map(|source| source.0).collect()
+    }
+
+    fn subscribers(&self) -> Vec<usize> {
+        let lock = self.reactivity.read().or_poisoned();
+        (&lock.subscribers).into_iter().map(|sub| sub.0).collect()
+    }
+}
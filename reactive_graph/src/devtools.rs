@@ -0,0 +1,289 @@
+//! Inspection of the live reactive graph, for building devtools and detecting leaks.
+//!
+//! With the `devtools` feature enabled, every [`Owner`](crate::owner::Owner) and every signal,
+//! memo, async derived and effect is registered when it is created. [`snapshot`] returns the
+//! parts of the graph that are still alive: the owner tree, each reactive node with its sources
+//! and subscribers, where it was defined, how many times it has run, and when it last updated.
+//!
+//! Snapshots can be serialized with `serde`, for example to send them to a browser devtools
+//! panel, or to compare the number of live nodes before and after a test.
+//!
+//! ```rust
+//! # any_spawner::Executor::init_futures_executor();
+//! # let owner = reactive_graph::owner::Owner::new(); owner.set();
+//! use reactive_graph::{
+//!     computed::ArcMemo,
+//!     devtools::{snapshot, NodeKind},
+//!     prelude::*,
+//!     signal::ArcRwSignal,
+//! };
+//!
+//! let count = ArcRwSignal::new(1);
+//! let double = ArcMemo::new({
+//!     let count = count.clone();
+//!     move |_| count.get() * 2
+//! });
+//! assert_eq!(double.get(), 2);
+//!
+//! let graph = snapshot();
+//! let memo = graph
+//!     .nodes
+//!     .iter()
+//!     .find(|node| node.kind == NodeKind::Memo)
+//!     .unwrap();
+//! let signal = graph.node(memo.sources[0]).unwrap();
+//! assert_eq!(signal.kind, NodeKind::Signal);
+//! assert_eq!(signal.subscribers, vec![memo.id]);
+//! assert_eq!(memo.runs, 1);
+//! ```
+
+use crate::owner::OwnerInner;
+use or_poisoned::OrPoisoned;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    panic::Location,
+    sync::{Arc, LazyLock, Mutex, RwLock, Weak},
+};
+
+/// The registry is pruned of dropped entries whenever it doubles in size.
+const MIN_PRUNE_LEN: usize = 1024;
+
+static NODES: LazyLock<Mutex<Registry<NodeEntry>>> =
+    LazyLock::new(Default::default);
+
+static OWNERS: LazyLock<Mutex<Registry<Weak<RwLock<OwnerInner>>>>> =
+    LazyLock::new(Default::default);
+
+struct Registry<T> {
+    entries: FxHashMap<usize, T>,
+    prune_at: usize,
+}
+
+impl<T> Default for Registry<T> {
+    fn default() -> Self {
+        Self {
+            entries: Default::default(),
+            prune_at: MIN_PRUNE_LEN,
+        }
+    }
+}
+
+impl<T> Registry<T> {
+    fn insert(&mut self, id: usize, entry: T, is_alive: impl Fn(&T) -> bool) {
+        self.entries.insert(id, entry);
+        if self.entries.len() >= self.prune_at {
+            self.entries.retain(|_, entry| is_alive(entry));
+            self.prune_at = (self.entries.len() * 2).max(MIN_PRUNE_LEN);
+        }
+    }
+}
+
+#[derive(Clone)]
+struct NodeEntry {
+    kind: NodeKind,
+    node: Weak<dyn Inspect + Send + Sync>,
+    owner: Option<usize>,
+    defined_at: Option<&'static Location<'static>>,
+    runs: u64,
+    last_update: Option<u64>,
+}
+
+/// Gives access to the edges of a reactive node.
+pub(crate) trait Inspect {
+    /// The ids of the sources this node is subscribed to.
+    fn sources(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
+    /// The ids of the subscribers to this node.
+    fn subscribers(&self) -> Vec<usize> {
+        Vec::new()
+    }
+}
+
+/// The type of a reactive node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NodeKind {
+    /// A signal, which holds a value.
+    Signal,
+    /// A trigger, which notifies without holding a value.
+    Trigger,
+    /// A memo, which derives a value from other nodes.
+    Memo,
+    /// An async derived value, such as a resource.
+    AsyncDerived,
+    /// An effect, which runs on the next tick after its sources change.
+    Effect,
+    /// A render effect, which runs when created and then on the next tick after its sources
+    /// change.
+    RenderEffect,
+    /// An effect that runs immediately when its sources change.
+    ImmediateEffect,
+}
+
+/// The live reactive graph, at the time [`snapshot`] was called.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    /// All live owners.
+    pub owners: Vec<OwnerSnapshot>,
+    /// All live reactive nodes.
+    pub nodes: Vec<NodeSnapshot>,
+}
+
+impl GraphSnapshot {
+    /// Returns the node with the given id, if it is alive.
+    pub fn node(&self, id: usize) -> Option<&NodeSnapshot> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Returns the owner with the given id, if it is alive.
+    pub fn owner(&self, id: usize) -> Option<&OwnerSnapshot> {
+        self.owners.iter().find(|owner| owner.id == id)
+    }
+}
+
+/// An [`Owner`](crate::owner::Owner) in a [`GraphSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnerSnapshot {
+    /// The [`Owner::debug_id`](crate::owner::Owner::debug_id) of the owner.
+    pub id: usize,
+    /// The id of the parent owner, if it is alive.
+    pub parent: Option<usize>,
+    /// The ids of the live child owners.
+    pub children: Vec<usize>,
+    /// The number of arena-allocated values owned by this owner.
+    pub arena_items: usize,
+    /// The number of context values provided by this owner.
+    pub contexts: usize,
+    /// The number of cleanup functions registered with this owner.
+    pub cleanups: usize,
+    /// Whether this owner has been paused.
+    pub paused: bool,
+}
+
+/// A signal, memo, async derived or effect in a [`GraphSnapshot`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeSnapshot {
+    /// A unique identifier for this node.
+    ///
+    /// Like [`Owner::debug_id`](crate::owner::Owner::debug_id), this is not stable between runs.
+    pub id: usize,
+    /// The type of the node.
+    pub kind: NodeKind,
+    /// The id of the owner that was current when the node was created, if any.
+    pub owner: Option<usize>,
+    /// The location at which the node was created, if it is known.
+    pub defined_at: Option<String>,
+    /// The ids of the nodes this node is subscribed to.
+    pub sources: Vec<usize>,
+    /// The ids of the nodes that are subscribed to this node.
+    pub subscribers: Vec<usize>,
+    /// The number of times a signal has notified its subscribers, or a memo or effect has run.
+    pub runs: u64,
+    /// The time of the last run, in milliseconds since the Unix epoch.
+    pub last_update: Option<u64>,
+}
+
+/// Returns a snapshot of all live owners and reactive nodes.
+pub fn snapshot() -> GraphSnapshot {
+    // entries are copied out so that no node is locked while the registry lock is held
+    let owners = OWNERS
+        .lock()
+        .or_poisoned()
+        .entries
+        .iter()
+        .map(|(id, owner)| (*id, owner.clone()))
+        .collect::<Vec<_>>();
+    let nodes = NODES
+        .lock()
+        .or_poisoned()
+        .entries
+        .iter()
+        .map(|(id, entry)| (*id, entry.clone()))
+        .collect::<Vec<_>>();
+
+    let mut owners = owners
+        .into_iter()
+        .filter_map(|(id, owner)| {
+            Some(owner.upgrade()?.read().or_poisoned().snapshot(id))
+        })
+        .collect::<Vec<_>>();
+    owners.sort_by_key(|owner| owner.id);
+
+    let mut nodes = nodes
+        .into_iter()
+        .filter_map(|(id, entry)| {
+            let node = entry.node.upgrade()?;
+            Some(NodeSnapshot {
+                id,
+                kind: entry.kind,
+                owner: entry.owner,
+                defined_at: entry.defined_at.map(ToString::to_string),
+                sources: node.sources(),
+                subscribers: node.subscribers(),
+                runs: entry.runs,
+                last_update: entry.last_update,
+            })
+        })
+        .collect::<Vec<_>>();
+    nodes.sort_by_key(|node| node.id);
+
+    GraphSnapshot { owners, nodes }
+}
+
+pub(crate) fn register_owner(inner: &Arc<RwLock<OwnerInner>>) {
+    OWNERS.lock().or_poisoned().insert(
+        Arc::as_ptr(inner) as usize,
+        Arc::downgrade(inner),
+        |owner| owner.strong_count() > 0,
+    );
+}
+
+/// Registers a reactive node. Its id is the address of `node`, which matches the id of its
+/// [`AnySource`](crate::graph::AnySource) and [`AnySubscriber`](crate::graph::AnySubscriber).
+pub(crate) fn register<N>(
+    kind: NodeKind,
+    node: &Arc<N>,
+    defined_at: Option<&'static Location<'static>>,
+) where
+    N: Inspect + Send + Sync + 'static,
+{
+    let entry = NodeEntry {
+        kind,
+        node: Arc::downgrade(node) as Weak<dyn Inspect + Send + Sync>,
+        owner: crate::owner::Owner::current().map(|owner| owner.debug_id()),
+        defined_at,
+        runs: 0,
+        last_update: None,
+    };
+    NODES.lock().or_poisoned().insert(
+        Arc::as_ptr(node) as usize,
+        entry,
+        |entry| entry.node.strong_count() > 0,
+    );
+}
+
+/// Records that the node with the given id has run.
+pub(crate) fn record_run(id: usize) {
+    let now = now();
+    if let Some(entry) = NODES.lock().or_poisoned().entries.get_mut(&id) {
+        entry.runs += 1;
+        entry.last_update = Some(now);
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn now() -> u64 {
+    web_sys::js_sys::Date::now() as u64
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn now() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default()
+}
@@ -13,6 +13,7 @@ use futures::StreamExt;
 use or_poisoned::OrPoisoned;
 use std::{
     mem,
+    panic::Location,
     sync::{atomic::AtomicBool, Arc, RwLock},
 };
 
@@ -92,7 +93,9 @@ impl<S> Dispose for Effect<S> {
     }
 }
 
-fn effect_base() -> (Receiver, Owner, Arc<RwLock<EffectInner>>) {
+fn effect_base(
+    defined_at: &'static Location<'static>,
+) -> (Receiver, Owner, Arc<RwLock<EffectInner>>) {
     let (mut observer, rx) = channel();
 
     // spawn the effect asynchronously
@@ -106,6 +109,14 @@ fn effect_base() -> (Receiver, Owner, Arc<RwLock<EffectInner>>) {
         observer,
         sources: SourceSet::new(),
     }));
+    #[cfg(feature = "devtools")]
+    crate::devtools::register(
+        crate::devtools::NodeKind::Effect,
+        &inner,
+        Some(defined_at),
+    );
+    #[cfg(not(feature = "devtools"))]
+    let _ = defined_at;
 
     (rx, owner, inner)
 }
@@ -155,12 +166,14 @@ impl Effect<LocalStorage> {
     /// This spawns a task on the local thread using
     /// [`spawn_local`](any_spawner::Executor::spawn_local). For an effect that can be spawned on
     /// any thread, use [`new_sync`](Effect::new_sync).
+    #[track_caller]
     pub fn new<T, M>(mut fun: impl EffectFunction<T, M> + 'static) -> Self
     where
         T: 'static,
     {
+        let defined_at = Location::caller();
         let inner = cfg!(feature = "effects").then(|| {
-            let (mut rx, owner, inner) = effect_base();
+            let (mut rx, owner, inner) = effect_base(defined_at);
             let value = Arc::new(RwLock::new(None::<T>));
             let mut first_run = true;
 
@@ -177,6 +190,8 @@ impl Effect<LocalStorage> {
                         {
                             first_run = false;
                             subscriber.clear_sources(&subscriber);
+                            #[cfg(feature = "devtools")]
+                            crate::devtools::record_run(subscriber.0);
 
                             let old_value =
                                 mem::take(&mut *value.write().or_poisoned());
@@ -300,6 +315,7 @@ impl Effect<LocalStorage> {
     /// # }).await;
     /// # });
     /// ```
+    #[track_caller]
     pub fn watch<D, T>(
         mut dependency_fn: impl FnMut() -> D + 'static,
         mut handler: impl FnMut(&D, Option<&D>, Option<T>) -> T + 'static,
@@ -309,8 +325,9 @@ impl Effect<LocalStorage> {
         D: 'static,
         T: 'static,
     {
+        let defined_at = Location::caller();
         let inner = cfg!(feature = "effects").then(|| {
-            let (mut rx, owner, inner) = effect_base();
+            let (mut rx, owner, inner) = effect_base(defined_at);
             let mut first_run = true;
             let dep_value = Arc::new(RwLock::new(None::<D>));
             let watch_value = Arc::new(RwLock::new(None::<T>));
@@ -328,6 +345,8 @@ impl Effect<LocalStorage> {
                             }) || first_run)
                         {
                             subscriber.clear_sources(&subscriber);
+                            #[cfg(feature = "devtools")]
+                            crate::devtools::record_run(subscriber.0);
 
                             let old_dep_value = mem::take(
                                 &mut *dep_value.write().or_poisoned(),
@@ -373,6 +392,7 @@ impl Effect<SyncStorage> {
     ///
     /// This spawns a task that can be run on any thread. For an effect that will be spawned on
     /// the current thread, use [`new`](Effect::new).
+    #[track_caller]
     pub fn new_sync<T, M>(
         fun: impl EffectFunction<T, M> + Send + Sync + 'static,
     ) -> Self
@@ -390,13 +410,15 @@ impl Effect<SyncStorage> {
     /// that are read inside it change.
     ///
     /// This will run whether the `effects` feature is enabled or not.
+    #[track_caller]
     pub fn new_isomorphic<T, M>(
         mut fun: impl EffectFunction<T, M> + Send + Sync + 'static,
     ) -> Self
     where
         T: Send + Sync + 'static,
     {
-        let (mut rx, owner, inner) = effect_base();
+        let defined_at = Location::caller();
+        let (mut rx, owner, inner) = effect_base(defined_at);
         let mut first_run = true;
         let value = Arc::new(RwLock::new(None::<T>));
 
@@ -413,6 +435,8 @@ impl Effect<SyncStorage> {
                     {
                         first_run = false;
                         subscriber.clear_sources(&subscriber);
+                        #[cfg(feature = "devtools")]
+                        crate::devtools::record_run(subscriber.0);
 
                         let old_value =
                             mem::take(&mut *value.write().or_poisoned());
@@ -435,6 +459,7 @@ impl Effect<SyncStorage> {
     }
 
     /// This is to [`Effect::watch`] what [`Effect::new_sync`] is to [`Effect::new`].
+    #[track_caller]
     pub fn watch_sync<D, T>(
         mut dependency_fn: impl FnMut() -> D + Send + Sync + 'static,
         mut handler: impl FnMut(&D, Option<&D>, Option<T>) -> T
@@ -447,7 +472,8 @@ impl Effect<SyncStorage> {
         D: Send + Sync + 'static,
         T: Send + Sync + 'static,
     {
-        let (mut rx, owner, inner) = effect_base();
+        let defined_at = Location::caller();
+        let (mut rx, owner, inner) = effect_base(defined_at);
         let mut first_run = true;
         let dep_value = Arc::new(RwLock::new(None::<D>));
         let watch_value = Arc::new(RwLock::new(None::<T>));
@@ -466,6 +492,8 @@ impl Effect<SyncStorage> {
                             }) || first_run)
                         {
                             subscriber.clear_sources(&subscriber);
+                            #[cfg(feature = "devtools")]
+                            crate::devtools::record_run(subscriber.0);
 
                             let old_dep_value = mem::take(
                                 &mut *dep_value.write().or_poisoned(),
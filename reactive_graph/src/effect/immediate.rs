@@ -203,7 +203,7 @@ mod inner {
         ) -> Arc<RwLock<EffectInner>> {
             let owner = Owner::new();
 
-            Arc::new_cyclic(|weak| {
+            let inner = Arc::new_cyclic(|weak| {
                 let any_subscriber = AnySubscriber(
                     weak.as_ptr() as usize,
                     Weak::clone(weak) as Weak<dyn Subscriber + Send + Sync>,
@@ -222,7 +222,14 @@ mod inner {
                     sources: SourceSet::new(),
                     any_subscriber,
                 })
-            })
+            });
+            #[cfg(feature = "devtools")]
+            crate::devtools::register(
+                crate::devtools::NodeKind::ImmediateEffect,
+                &inner,
+                Some(Location::caller()),
+            );
+            inner
         }
     }
 
@@ -284,6 +291,9 @@ mod inner {
 
                 drop(guard);
 
+                #[cfg(feature = "devtools")]
+                crate::devtools::record_run(any_subscriber.0);
+
                 // We execute the effect.
                 // Note that *this could happen in parallel across threads*.
                 owner.with_cleanup(|| any_subscriber.with_observer(|| fun()));
@@ -341,6 +351,14 @@ mod inner {
         }
     }
 
+    #[cfg(feature = "devtools")]
+    impl crate::devtools::Inspect for RwLock<EffectInner> {
+        fn sources(&self) -> Vec<usize> {
+            let lock = self.read().or_poisoned();
+            (&lock.sources).into_iter().map(|source| source.0).collect()
+        }
+    }
+
     impl DefinedAt for EffectInner {
         fn defined_at(&self) -> Option<&'static Location<'static>> {
             #[cfg(any(debug_assertions, leptos_debuginfo))]
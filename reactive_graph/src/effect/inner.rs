@@ -65,3 +65,11 @@ impl Subscriber for RwLock<EffectInner> {
         self.write().or_poisoned().sources.clear_sources(subscriber);
     }
 }
+
+#[cfg(feature = "devtools")]
+impl crate::devtools::Inspect for RwLock<EffectInner> {
+    fn sources(&self) -> Vec<usize> {
+        let lock = self.read().or_poisoned();
+        (&lock.sources).into_iter().map(|source| source.0).collect()
+    }
+}
@@ -13,6 +13,7 @@ use std::{
     fmt::Debug,
     future::{Future, IntoFuture},
     mem,
+    panic::Location,
     pin::Pin,
     sync::{Arc, RwLock, Weak},
 };
@@ -49,16 +50,40 @@ impl<T> Debug for RenderEffect<T> {
     }
 }
 
+// codegen optimisation:
+fn prep(
+    defined_at: Option<&'static Location<'static>>,
+) -> (Owner, Arc<RwLock<EffectInner>>, crate::channel::Receiver) {
+    let (observer, rx) = channel();
+    let owner = Owner::new();
+    let inner = Arc::new(RwLock::new(EffectInner {
+        dirty: false,
+        observer,
+        sources: SourceSet::new(),
+    }));
+    #[cfg(feature = "devtools")]
+    crate::devtools::register(
+        crate::devtools::NodeKind::RenderEffect,
+        &inner,
+        defined_at,
+    );
+    #[cfg(not(feature = "devtools"))]
+    let _ = defined_at;
+    (owner, inner, rx)
+}
+
 impl<T> RenderEffect<T>
 where
     T: 'static,
 {
     /// Creates a new render effect, which immediately runs `fun`.
+    #[track_caller]
     pub fn new(fun: impl FnMut(Option<T>) -> T + 'static) -> Self {
         Self::new_with_value_erased(Box::new(fun), None)
     }
 
     /// Creates a new render effect with an initial value.
+    #[track_caller]
     pub fn new_with_value(
         fun: impl FnMut(Option<T>) -> T + 'static,
         initial_value: Option<T>,
@@ -78,24 +103,12 @@ where
         .await
     }
 
+    #[track_caller]
     fn new_with_value_erased(
         mut fun: Box<dyn FnMut(Option<T>) -> T + 'static>,
         initial_value: Option<T>,
     ) -> Self {
-        // codegen optimisation:
-        fn prep() -> (Owner, Arc<RwLock<EffectInner>>, crate::channel::Receiver)
-        {
-            let (observer, rx) = channel();
-            let owner = Owner::new();
-            let inner = Arc::new(RwLock::new(EffectInner {
-                dirty: false,
-                observer,
-                sources: SourceSet::new(),
-            }));
-            (owner, inner, rx)
-        }
-
-        let (owner, inner, mut rx) = prep();
+        let (owner, inner, mut rx) = prep(Some(Location::caller()));
 
         let value = Arc::new(RwLock::new(None::<T>));
 
@@ -110,6 +123,8 @@ where
         #[cfg(feature = "effects")]
         {
             let subscriber = inner.to_any_subscriber();
+            #[cfg(feature = "devtools")]
+            crate::devtools::record_run(subscriber.0);
             *value.write().or_poisoned() = Some(
                 owner.with(|| subscriber.with_observer(|| fun(initial_value))),
             );
@@ -125,6 +140,8 @@ where
                             })
                         {
                             subscriber.clear_sources(&subscriber);
+                            #[cfg(feature = "devtools")]
+                            crate::devtools::record_run(subscriber.0);
 
                             let old_value =
                                 mem::take(&mut *value.write().or_poisoned());
@@ -145,20 +162,7 @@ where
         mut fun: Box<dyn FnMut(Option<T>) -> T + 'static>,
         initial_value: Pin<Box<dyn Future<Output = T>>>,
     ) -> Self {
-        // codegen optimisation:
-        fn prep() -> (Owner, Arc<RwLock<EffectInner>>, crate::channel::Receiver)
-        {
-            let (observer, rx) = channel();
-            let owner = Owner::new();
-            let inner = Arc::new(RwLock::new(EffectInner {
-                dirty: false,
-                observer,
-                sources: SourceSet::new(),
-            }));
-            (owner, inner, rx)
-        }
-
-        let (owner, inner, mut rx) = prep();
+        let (owner, inner, mut rx) = prep(None);
 
         let value = Arc::new(RwLock::new(None::<T>));
 
@@ -176,6 +180,8 @@ where
 
             let subscriber = inner.to_any_subscriber();
 
+            #[cfg(feature = "devtools")]
+            crate::devtools::record_run(subscriber.0);
             let initial = subscriber
                 .with_observer(|| ScopedFuture::new(initial_value))
                 .await;
@@ -192,6 +198,8 @@ where
                             })
                         {
                             subscriber.clear_sources(&subscriber);
+                            #[cfg(feature = "devtools")]
+                            crate::devtools::record_run(subscriber.0);
 
                             let old_value =
                                 mem::take(&mut *value.write().or_poisoned());
@@ -227,21 +235,19 @@ where
     T: Send + Sync + 'static,
 {
     /// Creates a render effect that will run whether the `effects` feature is enabled or not.
+    #[track_caller]
     pub fn new_isomorphic(
         fun: impl FnMut(Option<T>) -> T + Send + Sync + 'static,
     ) -> Self {
+        #[track_caller]
         fn erased<T: Send + Sync + 'static>(
             mut fun: Box<dyn FnMut(Option<T>) -> T + Send + Sync + 'static>,
         ) -> RenderEffect<T> {
-            let (observer, mut rx) = channel();
+            let (owner, inner, mut rx) = prep(Some(Location::caller()));
             let value = Arc::new(RwLock::new(None::<T>));
-            let owner = Owner::new();
-            let inner = Arc::new(RwLock::new(EffectInner {
-                dirty: false,
-                observer,
-                sources: SourceSet::new(),
-            }));
 
+            #[cfg(feature = "devtools")]
+            crate::devtools::record_run(inner.to_any_subscriber().0);
             let initial_value = owner
                 .with(|| inner.to_any_subscriber().with_observer(|| fun(None)));
             *value.write().or_poisoned() = Some(initial_value);
@@ -258,6 +264,8 @@ where
                             })
                         {
                             subscriber.clear_sources(&subscriber);
+                            #[cfg(feature = "devtools")]
+                            crate::devtools::record_run(subscriber.0);
 
                             let old_value =
                                 mem::take(&mut *value.write().or_poisoned());
@@ -0,0 +1,507 @@
+//! Records the writes to selected signals, so that you can step back and forward through their
+//! values, and export the recorded trace to replay it somewhere else.
+//!
+//! A [`History`] records a value each time a tracked signal is written to, up to a maximum
+//! number of entries. Values are stored as JSON, so any signal whose value implements
+//! [`Serialize`](serde::Serialize) and [`Deserialize`](serde::Deserialize) can be tracked.
+//!
+//! ```rust
+//! # let owner = reactive_graph::owner::Owner::new(); owner.set();
+//! use reactive_graph::{history::History, prelude::*, signal::RwSignal};
+//!
+//! let history = History::new(100);
+//! let count = RwSignal::new(0);
+//! history.track("count", count);
+//!
+//! count.set(1);
+//! count.set(2);
+//! assert_eq!(history.len(), 2);
+//!
+//! // step back through the writes
+//! history.back().unwrap();
+//! assert_eq!(count.get_untracked(), 1);
+//! history.go_to(0).unwrap();
+//! assert_eq!(count.get_untracked(), 0);
+//!
+//! // export the trace, and replay it later
+//! let trace = serde_json::to_string(&history.export()).unwrap();
+//! history.import(serde_json::from_str(&trace).unwrap()).unwrap();
+//! history.go_to(history.len()).unwrap();
+//! assert_eq!(count.get_untracked(), 2);
+//! ```
+
+use crate::{
+    graph::{
+        AnySource, AnySubscriber, ReactiveNode, Source, Subscriber, ToAnySource,
+    },
+    log_warning,
+    owner::Storage,
+    signal::{ArcRwSignal, RwSignal},
+    traits::{Set, WithUntracked},
+};
+use or_poisoned::OrPoisoned;
+use rustc_hash::FxHashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError, Weak,
+    },
+};
+use thiserror::Error;
+
+/// A reactive value whose writes can be recorded by a [`History`].
+pub trait Recordable {
+    /// The source that is notified whenever the value is written to.
+    fn source(&self) -> AnySource;
+
+    /// Serializes the current value, or returns `None` if it has been disposed.
+    fn snapshot(&self) -> Option<Result<Value, serde_json::Error>>;
+
+    /// Sets the value from one returned by [`Recordable::snapshot`].
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error>;
+}
+
+impl<T> Recordable for ArcRwSignal<T>
+where
+    T: Serialize + DeserializeOwned + 'static,
+{
+    fn source(&self) -> AnySource {
+        self.to_any_source()
+    }
+
+    fn snapshot(&self) -> Option<Result<Value, serde_json::Error>> {
+        self.try_with_untracked(|value| serde_json::to_value(value))
+    }
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error> {
+        self.set(serde_json::from_value(value)?);
+        Ok(())
+    }
+}
+
+impl<T, S> Recordable for RwSignal<T, S>
+where
+    T: Serialize + DeserializeOwned + 'static,
+    S: Storage<ArcRwSignal<T>>,
+{
+    fn source(&self) -> AnySource {
+        self.to_any_source()
+    }
+
+    fn snapshot(&self) -> Option<Result<Value, serde_json::Error>> {
+        self.try_with_untracked(|value| serde_json::to_value(value))
+    }
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error> {
+        _ = self.try_set(serde_json::from_value(value)?);
+        Ok(())
+    }
+}
+
+/// An error that occurred while moving through a [`History`].
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    /// The position is past the end of the history.
+    #[error("position {0} is past the end of the history")]
+    OutOfRange(usize),
+    /// The history contains a value for a signal that is not being tracked.
+    #[error("no signal named {0:?} is being tracked")]
+    UnknownSignal(String),
+    /// A recorded value could not be restored.
+    #[error("could not restore {signal:?}: {source}")]
+    Restore {
+        /// The name of the signal.
+        signal: String,
+        /// The error that occurred while deserializing the value.
+        source: serde_json::Error,
+    },
+}
+
+/// A single recorded write.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// The name the signal was tracked with.
+    pub signal: String,
+    /// The value of the signal after the write.
+    pub value: Value,
+}
+
+/// The recorded writes of a [`History`], which can be serialized to ship a reproducible trace
+/// of state, for example with a bug report.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryTrace {
+    /// The value of each signal before the first entry.
+    pub initial: BTreeMap<String, Value>,
+    /// The recorded writes, oldest first.
+    pub entries: Vec<HistoryEntry>,
+    /// The number of entries that had been applied when the trace was exported.
+    pub position: usize,
+}
+
+/// A bounded record of the writes to a set of tracked signals.
+///
+/// Moving through the history with [`back`](History::back), [`forward`](History::forward)
+/// and [`go_to`](History::go_to) sets the signals to their recorded values. Writing to a
+/// tracked signal after stepping back discards the entries after the current position.
+///
+/// Writes that do not change the serialized value of a signal are not recorded. Once the
+/// history is full, the oldest entry is dropped for each new one.
+#[derive(Clone)]
+pub struct History {
+    state: Arc<HistoryState>,
+}
+
+struct HistoryState {
+    replaying: AtomicBool,
+    inner: Mutex<HistoryInner>,
+}
+
+struct HistoryInner {
+    capacity: usize,
+    initial: BTreeMap<String, Value>,
+    entries: VecDeque<HistoryEntry>,
+    position: usize,
+    trackers: FxHashMap<String, Arc<Tracker>>,
+}
+
+impl HistoryInner {
+    /// The value of the signal once the first `position` entries have been applied.
+    fn value_at(&self, position: usize, signal: &str) -> Option<&Value> {
+        self.entries
+            .range(..position)
+            .rev()
+            .find(|entry| entry.signal == signal)
+            .map(|entry| &entry.value)
+            .or_else(|| self.initial.get(signal))
+    }
+
+    fn push(&mut self, signal: &str, value: Value) {
+        if self.value_at(self.position, signal) == Some(&value) {
+            return;
+        }
+        self.entries.truncate(self.position);
+        self.entries.push_back(HistoryEntry {
+            signal: signal.to_owned(),
+            value,
+        });
+        self.drop_oldest();
+        self.position = self.entries.len();
+    }
+
+    fn drop_oldest(&mut self) {
+        while self.entries.len() > self.capacity {
+            if let Some(entry) = self.entries.pop_front() {
+                self.initial.insert(entry.signal, entry.value);
+                self.position = self.position.saturating_sub(1);
+            }
+        }
+    }
+
+    /// The values to restore to move to `position`, from the current position.
+    fn restores_for(
+        &self,
+        position: usize,
+        signals: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<(Arc<Tracker>, Value)>, HistoryError> {
+        let mut restores = Vec::<(Arc<Tracker>, Value)>::new();
+        for signal in signals {
+            if restores.iter().any(|(tracker, _)| tracker.name == signal) {
+                continue;
+            }
+            let tracker = self
+                .trackers
+                .get(&signal)
+                .ok_or_else(|| HistoryError::UnknownSignal(signal.clone()))?;
+            if let Some(value) = self.value_at(position, &signal) {
+                restores.push((Arc::clone(tracker), value.clone()));
+            }
+        }
+        Ok(restores)
+    }
+}
+
+impl History {
+    /// Creates an empty history that holds up to `capacity` entries.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Arc::new(HistoryState {
+                replaying: AtomicBool::new(false),
+                inner: Mutex::new(HistoryInner {
+                    capacity,
+                    initial: Default::default(),
+                    entries: Default::default(),
+                    position: 0,
+                    trackers: Default::default(),
+                }),
+            }),
+        }
+    }
+
+    /// Starts recording the writes to the signal, under the given name.
+    ///
+    /// The name identifies the signal in exported traces, so it should be stable between runs.
+    /// Tracking another signal with the same name replaces the previous one.
+    pub fn track(
+        &self,
+        name: impl Into<String>,
+        signal: impl Recordable + Send + Sync + 'static,
+    ) {
+        let name = name.into();
+        let initial = match signal.snapshot() {
+            Some(Ok(value)) => value,
+            Some(Err(e)) => {
+                log_warning(format_args!("could not record {name:?}: {e}"));
+                return;
+            }
+            None => return,
+        };
+        let source = signal.source();
+        let tracker = Arc::new_cyclic(|this| Tracker {
+            name: name.clone(),
+            signal: Box::new(signal),
+            source,
+            history: Arc::downgrade(&self.state),
+            this: Weak::clone(this),
+        });
+
+        let prev = {
+            let mut inner = self.state.inner.lock().or_poisoned();
+            inner.initial.entry(name.clone()).or_insert(initial);
+            inner.trackers.insert(name, Arc::clone(&tracker))
+        };
+        if let Some(prev) = prev {
+            prev.source.remove_subscriber(&prev.to_any_subscriber());
+        }
+        tracker.source.add_subscriber(tracker.to_any_subscriber());
+    }
+
+    /// The number of recorded entries.
+    pub fn len(&self) -> usize {
+        self.state.inner.lock().or_poisoned().entries.len()
+    }
+
+    /// Whether no writes have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The number of entries that are currently applied. This is [`len`](History::len) unless
+    /// you have stepped back.
+    pub fn position(&self) -> usize {
+        self.state.inner.lock().or_poisoned().position
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        self.state
+            .inner
+            .lock()
+            .or_poisoned()
+            .entries
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Undoes the last applied entry. Returns `false` if there is nothing to undo.
+    pub fn back(&self) -> Result<bool, HistoryError> {
+        match self.position() {
+            0 => Ok(false),
+            position => self.go_to(position - 1).map(|_| true),
+        }
+    }
+
+    /// Reapplies the next entry after stepping back. Returns `false` if there is nothing to
+    /// reapply.
+    pub fn forward(&self) -> Result<bool, HistoryError> {
+        let (position, len) = {
+            let inner = self.state.inner.lock().or_poisoned();
+            (inner.position, inner.entries.len())
+        };
+        if position == len {
+            Ok(false)
+        } else {
+            self.go_to(position + 1).map(|_| true)
+        }
+    }
+
+    /// Sets each signal to its value once the first `position` entries have been applied.
+    pub fn go_to(&self, position: usize) -> Result<(), HistoryError> {
+        let restores = {
+            let mut inner = self.state.inner.lock().or_poisoned();
+            if position > inner.entries.len() {
+                return Err(HistoryError::OutOfRange(position));
+            }
+            let changed = inner
+                .entries
+                .range(
+                    position.min(inner.position)..position.max(inner.position),
+                )
+                .map(|entry| entry.signal.clone())
+                .collect::<Vec<_>>();
+            let restores = inner.restores_for(position, changed)?;
+            inner.position = position;
+            restores
+        };
+        self.replay(restores)
+    }
+
+    /// Removes all entries, keeping the current values as the initial ones.
+    pub fn clear(&self) {
+        let mut inner = self.state.inner.lock().or_poisoned();
+        let current = inner
+            .trackers
+            .keys()
+            .filter_map(|signal| {
+                Some((
+                    signal.clone(),
+                    inner.value_at(inner.position, signal)?.clone(),
+                ))
+            })
+            .collect::<Vec<_>>();
+        inner.initial.extend(current);
+        inner.entries.clear();
+        inner.position = 0;
+    }
+
+    /// Exports the recorded entries.
+    pub fn export(&self) -> HistoryTrace {
+        let inner = self.state.inner.lock().or_poisoned();
+        HistoryTrace {
+            initial: inner.initial.clone(),
+            entries: inner.entries.iter().cloned().collect(),
+            position: inner.position,
+        }
+    }
+
+    /// Replaces the recorded entries with an exported trace, and sets each signal to its value
+    /// at the position the trace was exported at.
+    ///
+    /// Every signal in the trace must already be tracked, under the same name.
+    pub fn import(&self, trace: HistoryTrace) -> Result<(), HistoryError> {
+        let restores = {
+            let mut inner = self.state.inner.lock().or_poisoned();
+            let signals = trace
+                .initial
+                .keys()
+                .cloned()
+                .chain(trace.entries.iter().map(|entry| entry.signal.clone()))
+                .collect::<Vec<_>>();
+            if let Some(signal) =
+                signals.iter().find(|s| !inner.trackers.contains_key(*s))
+            {
+                return Err(HistoryError::UnknownSignal(signal.clone()));
+            }
+
+            inner.initial = trace.initial;
+            inner.entries = trace.entries.into();
+            inner.position = trace.position.min(inner.entries.len());
+            inner.drop_oldest();
+            let position = inner.position;
+            inner.restores_for(position, signals)?
+        };
+        self.replay(restores)
+    }
+
+    fn replay(
+        &self,
+        restores: Vec<(Arc<Tracker>, Value)>,
+    ) -> Result<(), HistoryError> {
+        // stops replaying even if a setter panics, so later writes are still recorded
+        struct Replaying<'a>(&'a AtomicBool);
+
+        impl Drop for Replaying<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::Relaxed);
+            }
+        }
+
+        // the writes made while restoring values are not recorded
+        self.state.replaying.store(true, Ordering::Relaxed);
+        let _replaying = Replaying(&self.state.replaying);
+        restores.into_iter().try_for_each(|(tracker, value)| {
+            tracker.signal.restore(value).map_err(|source| {
+                HistoryError::Restore {
+                    signal: tracker.name.clone(),
+                    source,
+                }
+            })
+        })
+    }
+}
+
+impl Drop for HistoryState {
+    fn drop(&mut self) {
+        let inner =
+            self.inner.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let trackers = mem::take(&mut inner.trackers);
+        for tracker in trackers.into_values() {
+            tracker
+                .source
+                .remove_subscriber(&tracker.to_any_subscriber());
+        }
+    }
+}
+
+/// Subscribes to a tracked signal and records its value whenever it is notified.
+struct Tracker {
+    name: String,
+    signal: Box<dyn Recordable + Send + Sync>,
+    source: AnySource,
+    history: Weak<HistoryState>,
+    this: Weak<Tracker>,
+}
+
+impl Tracker {
+    fn to_any_subscriber(&self) -> AnySubscriber {
+        AnySubscriber(
+            self.this.as_ptr() as usize,
+            Weak::clone(&self.this) as Weak<dyn Subscriber + Send + Sync>,
+        )
+    }
+
+    fn record(&self) {
+        let Some(state) = self.history.upgrade() else {
+            return;
+        };
+        if state.replaying.load(Ordering::Relaxed) {
+            return;
+        }
+        match self.signal.snapshot() {
+            Some(Ok(value)) => {
+                state.inner.lock().or_poisoned().push(&self.name, value)
+            }
+            Some(Err(e)) => log_warning(format_args!(
+                "could not record {:?}: {e}",
+                self.name
+            )),
+            None => {}
+        }
+    }
+}
+
+impl ReactiveNode for Tracker {
+    fn mark_dirty(&self) {
+        self.record();
+        // some sources drop their subscribers when they notify them
+        self.source.add_subscriber(self.to_any_subscriber());
+    }
+
+    fn mark_check(&self) {}
+
+    fn mark_subscribers_check(&self) {}
+
+    fn update_if_necessary(&self) -> bool {
+        false
+    }
+}
+
+impl Subscriber for Tracker {
+    fn add_source(&self, _source: AnySource) {}
+
+    fn clear_sources(&self, _subscriber: &AnySubscriber) {}
+}
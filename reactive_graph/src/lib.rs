@@ -77,14 +77,20 @@ use std::{fmt::Arguments, future::Future};
 pub mod actions;
 pub(crate) mod channel;
 pub mod computed;
+#[cfg(feature = "devtools")]
+pub mod devtools;
 pub mod diagnostics;
 pub mod effect;
 pub mod graph;
+#[cfg(feature = "history")]
+pub mod history;
 pub mod owner;
 pub mod send_wrapper_ext;
 #[cfg(feature = "serde")]
 mod serde;
 pub mod signal;
+#[cfg(feature = "sync")]
+pub mod sync;
 mod trait_options;
 pub mod traits;
 pub mod transition;
@@ -178,6 +178,8 @@ impl Owner {
                 .children
                 .push(Arc::downgrade(&this.inner));
         }
+        #[cfg(feature = "devtools")]
+        crate::devtools::register_owner(&this.inner);
         this
     }
 
@@ -205,6 +207,8 @@ impl Owner {
             #[cfg(feature = "hydration")]
             shared_context,
         };
+        #[cfg(feature = "devtools")]
+        crate::devtools::register_owner(&this.inner);
         this.set();
         this
     }
@@ -231,6 +235,9 @@ impl Owner {
             shared_context: self.shared_context.clone(),
         };
         inner.children.push(Arc::downgrade(&child.inner));
+        drop(inner);
+        #[cfg(feature = "devtools")]
+        crate::devtools::register_owner(&child.inner);
         child
     }
 
@@ -463,6 +470,30 @@ pub(crate) struct OwnerInner {
     paused: bool,
 }
 
+#[cfg(feature = "devtools")]
+impl OwnerInner {
+    pub(crate) fn snapshot(&self, id: usize) -> crate::devtools::OwnerSnapshot {
+        crate::devtools::OwnerSnapshot {
+            id,
+            parent: self
+                .parent
+                .as_ref()
+                .filter(|parent| parent.strong_count() > 0)
+                .map(|parent| parent.as_ptr() as usize),
+            children: self
+                .children
+                .iter()
+                .filter(|child| child.strong_count() > 0)
+                .map(|child| child.as_ptr() as usize)
+                .collect(),
+            arena_items: self.nodes.len(),
+            contexts: self.contexts.len(),
+            cleanups: self.cleanups.len(),
+            paused: self.paused,
+        }
+    }
+}
+
 impl Debug for OwnerInner {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("OwnerInner")
@@ -22,10 +22,17 @@ impl ArcTrigger {
     /// Creates a new trigger.
     #[track_caller]
     pub fn new() -> Self {
+        let inner = Arc::default();
+        #[cfg(feature = "devtools")]
+        crate::devtools::register(
+            crate::devtools::NodeKind::Trigger,
+            &inner,
+            Some(Location::caller()),
+        );
         Self {
             #[cfg(any(debug_assertions, leptos_debuginfo))]
             defined_at: Location::caller(),
-            inner: Default::default(),
+            inner,
         }
     }
 }
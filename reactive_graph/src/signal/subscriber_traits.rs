@@ -48,6 +48,12 @@ impl DefinedAt for RwLock<SubscriberSet> {
 // Source>
 impl<T: AsSubscriberSet + DefinedAt> ReactiveNode for T {
     fn mark_dirty(&self) {
+        #[cfg(feature = "devtools")]
+        if let Some(inner) = self.as_subscriber_set() {
+            crate::devtools::record_run(inner.borrow()
+                as *const RwLock<SubscriberSet>
+                as usize);
+        }
         self.mark_subscribers_check();
     }
 
@@ -115,6 +121,8 @@ where
 
 impl ReactiveNode for RwLock<SubscriberSet> {
     fn mark_dirty(&self) {
+        #[cfg(feature = "devtools")]
+        crate::devtools::record_run(self as *const Self as usize);
         self.mark_subscribers_check();
     }
 
@@ -151,3 +159,11 @@ impl Source for RwLock<SubscriberSet> {
         self.write().or_poisoned().unsubscribe(subscriber)
     }
 }
+
+#[cfg(feature = "devtools")]
+impl crate::devtools::Inspect for RwLock<SubscriberSet> {
+    fn subscribers(&self) -> Vec<usize> {
+        let lock = self.read().or_poisoned();
+        (&*lock).into_iter().map(|sub| sub.0).collect()
+    }
+}
@@ -0,0 +1,677 @@
+//! Mirrors signals across browser tabs, workers or any other endpoints that can exchange messages.
+//!
+//! A [`Synchronizer`] sends every write to a synced signal over a [`Transport`], and applies the
+//! writes it receives from other replicas. Each value carries a logical clock, so replicas that
+//! write at the same time agree on a single value, as decided by the [`ConflictPolicy`].
+//!
+//! Any value that implements [`Recordable`] can be synced, including signals whose value
+//! implements [`Serialize`](serde::Serialize) and [`Deserialize`](serde::Deserialize), and
+//! stores from `reactive_stores` (with its `sync` feature enabled).
+//!
+//! In the browser, use [`BroadcastChannelTransport`] to sync between tabs and workers of the same
+//! origin, or [`PostMessageTransport`] to sync with a specific worker. [`LocalChannel`] connects
+//! replicas in the same process, which is useful for tests.
+//!
+//! ```rust
+//! # let owner = reactive_graph::owner::Owner::new(); owner.set();
+//! use reactive_graph::{
+//!     prelude::*,
+//!     signal::ArcRwSignal,
+//!     sync::{LocalChannel, Synchronizer},
+//! };
+//!
+//! let channel = LocalChannel::new();
+//! let (tab_a, tab_b) = (
+//!     Synchronizer::new(channel.endpoint()),
+//!     Synchronizer::new(channel.endpoint()),
+//! );
+//!
+//! let a = ArcRwSignal::new(0);
+//! let b = ArcRwSignal::new(0);
+//! tab_a.sync("count", a.clone());
+//! tab_b.sync("count", b.clone());
+//!
+//! a.set(1);
+//! assert_eq!(b.get_untracked(), 1);
+//! b.set(2);
+//! assert_eq!(a.get_untracked(), 2);
+//! ```
+
+use crate::{
+    graph::{AnySource, AnySubscriber, ReactiveNode, Source, Subscriber},
+    history::Recordable,
+    log_warning,
+};
+use or_poisoned::OrPoisoned;
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fmt::Debug,
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError, Weak,
+    },
+};
+
+/// Sends messages to, and receives messages from, other replicas.
+pub trait Transport: Send + Sync + 'static {
+    /// Sends a message to all other replicas.
+    fn send(&self, message: String);
+
+    /// Calls `on_message` with each message received from another replica. Returns a function
+    /// that stops receiving messages.
+    fn subscribe(
+        &self,
+        on_message: Arc<dyn Fn(String) + Send + Sync>,
+    ) -> Box<dyn FnOnce() + Send + Sync>;
+}
+
+/// The logical time of a write, used to order writes made by different replicas.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub struct Version {
+    /// A Lamport clock, which is greater than that of every write the replica had seen when it
+    /// wrote.
+    pub clock: u64,
+    /// The replica that wrote, which breaks ties between writes with the same clock.
+    pub replica: u64,
+}
+
+/// A value along with the version of the write that produced it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Versioned {
+    /// The version of the write.
+    pub version: Version,
+    /// The serialized value.
+    pub value: Value,
+}
+
+/// Decides whether a write received from another replica replaces the local value.
+#[derive(Clone, Default)]
+pub enum ConflictPolicy {
+    /// The write with the greatest [`Version`] wins.
+    ///
+    /// Every replica applies the same rule, so all replicas end up with the same value.
+    #[default]
+    LastWriterWins,
+    /// Calls the function with the local value and the received one, and replaces the local
+    /// value if it returns `true`.
+    ///
+    /// For replicas to agree, the function should give the same answer on every replica.
+    #[allow(clippy::type_complexity)]
+    Custom(Arc<dyn Fn(&Versioned, &Versioned) -> bool + Send + Sync>),
+}
+
+impl Debug for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LastWriterWins => write!(f, "LastWriterWins"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl ConflictPolicy {
+    fn accepts(&self, local: &Versioned, remote: &Versioned) -> bool {
+        match self {
+            Self::LastWriterWins => remote.version > local.version,
+            Self::Custom(accepts) => accepts(local, remote),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// A replica wrote to a value.
+    Update {
+        key: String,
+        version: Version,
+        value: Value,
+    },
+    /// A replica started syncing a value, and asks for its current state.
+    Request { key: String },
+}
+
+/// Keeps signals in sync with the other replicas connected to a [`Transport`].
+///
+/// Cloning a `Synchronizer` gives another handle to the same replica. Signals stop being synced
+/// once the last handle is dropped.
+#[derive(Clone)]
+pub struct Synchronizer {
+    state: Arc<SyncState>,
+}
+
+impl Debug for Synchronizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Synchronizer")
+            .field("replica", &self.replica())
+            .finish_non_exhaustive()
+    }
+}
+
+struct SyncState {
+    transport: Box<dyn Transport>,
+    inner: Mutex<SyncInner>,
+    unsubscribe: Mutex<Option<Box<dyn FnOnce() + Send + Sync>>>,
+}
+
+struct SyncInner {
+    replica: u64,
+    policy: ConflictPolicy,
+    entries: FxHashMap<String, Arc<SyncEntry>>,
+}
+
+impl Synchronizer {
+    /// Connects a new replica to the transport, with a random replica id.
+    pub fn new(transport: impl Transport) -> Self {
+        let state = Arc::new(SyncState {
+            transport: Box::new(transport),
+            inner: Mutex::new(SyncInner {
+                replica: random_id(),
+                policy: ConflictPolicy::default(),
+                entries: Default::default(),
+            }),
+            unsubscribe: Mutex::new(None),
+        });
+        let unsubscribe = state.transport.subscribe({
+            let state = Arc::downgrade(&state);
+            Arc::new(move |message| {
+                if let Some(state) = state.upgrade() {
+                    state.receive(&message);
+                }
+            })
+        });
+        *state.unsubscribe.lock().or_poisoned() = Some(unsubscribe);
+        Self { state }
+    }
+
+    /// Sets the policy used to resolve conflicting writes. Defaults to
+    /// [`ConflictPolicy::LastWriterWins`].
+    pub fn with_policy(self, policy: ConflictPolicy) -> Self {
+        self.state.inner.lock().or_poisoned().policy = policy;
+        self
+    }
+
+    /// Sets the id of this replica, which must be different from that of every other replica.
+    ///
+    /// This should be set before any value is synced.
+    pub fn with_replica_id(self, replica: u64) -> Self {
+        self.state.inner.lock().or_poisoned().replica = replica;
+        self
+    }
+
+    /// The id of this replica.
+    pub fn replica(&self) -> u64 {
+        self.state.inner.lock().or_poisoned().replica
+    }
+
+    /// Starts syncing the value under the given key.
+    ///
+    /// Other replicas are asked for their current value, so a replica that starts syncing late
+    /// catches up with the others. Syncing another value with the same key replaces the previous
+    /// one.
+    pub fn sync(
+        &self,
+        key: impl Into<String>,
+        value: impl Recordable + Send + Sync + 'static,
+    ) {
+        let key = key.into();
+        let source = value.source();
+        let entry = Arc::new_cyclic(|this: &Weak<SyncEntry>| SyncEntry {
+            key: key.clone(),
+            value: Box::new(value),
+            source,
+            subscriber: AnySubscriber(
+                this.as_ptr() as usize,
+                Weak::clone(this) as Weak<dyn Subscriber + Send + Sync>,
+            ),
+            sync: Arc::downgrade(&self.state),
+            version: Mutex::new(Version::default()),
+            applying: AtomicBool::new(false),
+        });
+
+        let prev = self
+            .state
+            .inner
+            .lock()
+            .or_poisoned()
+            .entries
+            .insert(key.clone(), Arc::clone(&entry));
+        if let Some(prev) = prev {
+            prev.source.remove_subscriber(&prev.subscriber);
+        }
+        entry.source.add_subscriber(entry.subscriber.clone());
+        self.state.send(&Message::Request { key });
+    }
+
+    /// Stops syncing the value with the given key.
+    pub fn unsync(&self, key: &str) {
+        let entry = self.state.inner.lock().or_poisoned().entries.remove(key);
+        if let Some(entry) = entry {
+            entry.source.remove_subscriber(&entry.subscriber);
+        }
+    }
+}
+
+impl SyncState {
+    fn send(&self, message: &Message) {
+        match serde_json::to_string(message) {
+            Ok(message) => self.transport.send(message),
+            Err(e) => log_warning(format_args!("could not send update: {e}")),
+        }
+    }
+
+    fn receive(&self, message: &str) {
+        let message = match serde_json::from_str::<Message>(message) {
+            Ok(message) => message,
+            Err(e) => {
+                log_warning(format_args!("could not read message: {e}"));
+                return;
+            }
+        };
+        let key = match &message {
+            Message::Update { key, .. } | Message::Request { key } => key,
+        };
+        let (entry, policy) = {
+            let inner = self.inner.lock().or_poisoned();
+            (inner.entries.get(key).cloned(), inner.policy.clone())
+        };
+        let Some(entry) = entry else {
+            return;
+        };
+
+        match message {
+            Message::Update { version, value, .. } => {
+                entry.receive(&policy, Versioned { version, value })
+            }
+            // values that have never been written are left to the replica that asks
+            Message::Request { .. } => {
+                if let Some(update) = entry.current() {
+                    if update.version.clock > 0 {
+                        self.send(&Message::Update {
+                            key: entry.key.clone(),
+                            version: update.version,
+                            value: update.value,
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for SyncState {
+    fn drop(&mut self) {
+        if let Some(unsubscribe) = self
+            .unsubscribe
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+        {
+            unsubscribe();
+        }
+        let inner =
+            self.inner.get_mut().unwrap_or_else(PoisonError::into_inner);
+        for entry in mem::take(&mut inner.entries).into_values() {
+            entry.source.remove_subscriber(&entry.subscriber);
+        }
+    }
+}
+
+/// Subscribes to a synced value, and sends it to the other replicas whenever it is written to.
+struct SyncEntry {
+    key: String,
+    value: Box<dyn Recordable + Send + Sync>,
+    source: AnySource,
+    subscriber: AnySubscriber,
+    sync: Weak<SyncState>,
+    version: Mutex<Version>,
+    /// Set while a received value is being applied, so it is not sent back.
+    applying: AtomicBool,
+}
+
+impl SyncEntry {
+    fn current(&self) -> Option<Versioned> {
+        let version = *self.version.lock().or_poisoned();
+        match self.value.snapshot()? {
+            Ok(value) => Some(Versioned { version, value }),
+            Err(e) => {
+                log_warning(format_args!(
+                    "could not serialize {:?}: {e}",
+                    self.key
+                ));
+                None
+            }
+        }
+    }
+
+    fn send(&self) {
+        let Some(sync) = self.sync.upgrade() else {
+            return;
+        };
+        let Some(Ok(value)) = self.value.snapshot() else {
+            return;
+        };
+        let version = {
+            let mut version = self.version.lock().or_poisoned();
+            version.clock += 1;
+            version.replica = sync.inner.lock().or_poisoned().replica;
+            *version
+        };
+        sync.send(&Message::Update {
+            key: self.key.clone(),
+            version,
+            value,
+        });
+    }
+
+    fn receive(&self, policy: &ConflictPolicy, remote: Versioned) {
+        let Some(local) = self.current() else {
+            return;
+        };
+        if !policy.accepts(&local, &remote) {
+            // later local writes still have to come after the one that was received
+            let mut version = self.version.lock().or_poisoned();
+            version.clock = version.clock.max(remote.version.clock);
+            return;
+        }
+
+        // stops applying even if a setter panics, so later local writes are still sent
+        struct Applying<'a>(&'a AtomicBool);
+
+        impl Drop for Applying<'_> {
+            fn drop(&mut self) {
+                self.0.store(false, Ordering::Relaxed);
+            }
+        }
+
+        *self.version.lock().or_poisoned() = remote.version;
+        self.applying.store(true, Ordering::Relaxed);
+        let restored = {
+            let _applying = Applying(&self.applying);
+            self.value.restore(remote.value)
+        };
+        if let Err(e) = restored {
+            log_warning(format_args!("could not apply {:?}: {e}", self.key));
+        }
+    }
+}
+
+impl ReactiveNode for SyncEntry {
+    fn mark_dirty(&self) {
+        if !self.applying.load(Ordering::Relaxed) {
+            self.send();
+        }
+        // some sources drop their subscribers when they notify them
+        self.source.add_subscriber(self.subscriber.clone());
+    }
+
+    fn mark_check(&self) {}
+
+    fn mark_subscribers_check(&self) {}
+
+    fn update_if_necessary(&self) -> bool {
+        false
+    }
+}
+
+impl Subscriber for SyncEntry {
+    fn add_source(&self, _source: AnySource) {}
+
+    fn clear_sources(&self, _subscriber: &AnySubscriber) {}
+}
+
+/// Connects replicas in the same process.
+///
+/// Each [`endpoint`](LocalChannel::endpoint) is a [`Transport`] that delivers the messages it
+/// sends to every other endpoint of the channel. Messages are delivered immediately, unless the
+/// channel has been [paused](LocalChannel::pause), which makes it possible to test concurrent
+/// writes.
+#[derive(Clone, Default)]
+pub struct LocalChannel {
+    inner: Arc<Mutex<LocalChannelInner>>,
+}
+
+type Receiver = Arc<dyn Fn(String) + Send + Sync>;
+
+#[derive(Default)]
+struct LocalChannelInner {
+    next_endpoint: usize,
+    receivers: Vec<(usize, Receiver)>,
+    paused: bool,
+    queued: Vec<(usize, String)>,
+}
+
+impl Debug for LocalChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalChannel").finish_non_exhaustive()
+    }
+}
+
+impl LocalChannel {
+    /// Creates a channel with no endpoints.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new endpoint.
+    pub fn endpoint(&self) -> LocalEndpoint {
+        let mut inner = self.inner.lock().or_poisoned();
+        let id = inner.next_endpoint;
+        inner.next_endpoint += 1;
+        LocalEndpoint {
+            id,
+            channel: self.clone(),
+        }
+    }
+
+    /// Holds messages until [`resume`](LocalChannel::resume) is called.
+    pub fn pause(&self) {
+        self.inner.lock().or_poisoned().paused = true;
+    }
+
+    /// Delivers the held messages in the order they were sent, and delivers later messages
+    /// immediately.
+    pub fn resume(&self) {
+        let queued = {
+            let mut inner = self.inner.lock().or_poisoned();
+            inner.paused = false;
+            mem::take(&mut inner.queued)
+        };
+        for (from, message) in queued {
+            self.deliver(from, message);
+        }
+    }
+
+    fn deliver(&self, from: usize, message: String) {
+        let receivers = {
+            let mut inner = self.inner.lock().or_poisoned();
+            if inner.paused {
+                inner.queued.push((from, message));
+                return;
+            }
+            inner
+                .receivers
+                .iter()
+                .filter(|(id, _)| *id != from)
+                .map(|(_, receiver)| Arc::clone(receiver))
+                .collect::<Vec<_>>()
+        };
+        for receiver in receivers {
+            receiver(message.clone());
+        }
+    }
+}
+
+/// An endpoint of a [`LocalChannel`].
+#[derive(Debug, Clone)]
+pub struct LocalEndpoint {
+    id: usize,
+    channel: LocalChannel,
+}
+
+impl Transport for LocalEndpoint {
+    fn send(&self, message: String) {
+        self.channel.deliver(self.id, message);
+    }
+
+    fn subscribe(
+        &self,
+        on_message: Arc<dyn Fn(String) + Send + Sync>,
+    ) -> Box<dyn FnOnce() + Send + Sync> {
+        self.channel
+            .inner
+            .lock()
+            .or_poisoned()
+            .receivers
+            .push((self.id, on_message));
+        let channel = Arc::downgrade(&self.channel.inner);
+        let id = self.id;
+        Box::new(move || {
+            if let Some(channel) = channel.upgrade() {
+                channel
+                    .lock()
+                    .or_poisoned()
+                    .receivers
+                    .retain(|(receiver, _)| *receiver != id);
+            }
+        })
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+mod web {
+    use super::Transport;
+    use send_wrapper::SendWrapper;
+    use std::sync::Arc;
+    use web_sys::{
+        js_sys::{Function, Reflect},
+        wasm_bindgen::{closure::Closure, JsCast, JsValue},
+        BroadcastChannel, EventTarget, MessageEvent,
+    };
+
+    /// Sends messages through a `BroadcastChannel`, which reaches every tab, window and worker
+    /// of the same origin that opened a channel with the same name.
+    #[derive(Debug)]
+    pub struct BroadcastChannelTransport {
+        channel: SendWrapper<BroadcastChannel>,
+    }
+
+    impl BroadcastChannelTransport {
+        /// Opens the channel with the given name.
+        pub fn new(name: &str) -> Result<Self, JsValue> {
+            Ok(Self {
+                channel: SendWrapper::new(BroadcastChannel::new(name)?),
+            })
+        }
+    }
+
+    impl Transport for BroadcastChannelTransport {
+        fn send(&self, message: String) {
+            _ = self.channel.post_message(&JsValue::from_str(&message));
+        }
+
+        fn subscribe(
+            &self,
+            on_message: Arc<dyn Fn(String) + Send + Sync>,
+        ) -> Box<dyn FnOnce() + Send + Sync> {
+            listen(&self.channel, on_message)
+        }
+    }
+
+    /// Sends messages with `postMessage`, to a `Worker`, a `MessagePort`, or from inside a worker
+    /// to the page that started it.
+    #[derive(Debug)]
+    pub struct PostMessageTransport {
+        target: SendWrapper<EventTarget>,
+    }
+
+    impl PostMessageTransport {
+        /// Uses the given object, which must have a `postMessage` method and emit `message`
+        /// events.
+        pub fn new(target: impl JsCast) -> Self {
+            let target = target.unchecked_into::<EventTarget>();
+            // message ports only emit events once they have been started
+            if let Ok(start) = Reflect::get(&target, &"start".into()) {
+                if let Some(start) = start.dyn_ref::<Function>() {
+                    _ = start.call0(&target);
+                }
+            }
+            Self {
+                target: SendWrapper::new(target),
+            }
+        }
+    }
+
+    impl Transport for PostMessageTransport {
+        fn send(&self, message: String) {
+            let target: &EventTarget = &self.target;
+            if let Ok(post) = Reflect::get(target, &"postMessage".into()) {
+                if let Some(post) = post.dyn_ref::<Function>() {
+                    _ = post.call1(target, &JsValue::from_str(&message));
+                }
+            }
+        }
+
+        fn subscribe(
+            &self,
+            on_message: Arc<dyn Fn(String) + Send + Sync>,
+        ) -> Box<dyn FnOnce() + Send + Sync> {
+            listen(&self.target, on_message)
+        }
+    }
+
+    fn listen(
+        target: &EventTarget,
+        on_message: Arc<dyn Fn(String) + Send + Sync>,
+    ) -> Box<dyn FnOnce() + Send + Sync> {
+        let listener =
+            Closure::<dyn Fn(MessageEvent)>::new(move |ev: MessageEvent| {
+                if let Some(message) = ev.data().as_string() {
+                    on_message(message);
+                }
+            });
+        _ = target.add_event_listener_with_callback(
+            "message",
+            listener.as_ref().unchecked_ref(),
+        );
+
+        let listener = SendWrapper::new((target.clone(), listener));
+        Box::new(move || {
+            let (target, listener) = listener.take();
+            _ = target.remove_event_listener_with_callback(
+                "message",
+                listener.as_ref().unchecked_ref(),
+            );
+        })
+    }
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub use web::*;
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn random_id() -> u64 {
+    (web_sys::js_sys::Math::random() * u64::MAX as f64) as u64
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn random_id() -> u64 {
+    use std::{collections::hash_map::RandomState, hash::BuildHasher};
+
+    RandomState::new().hash_one(std::time::SystemTime::now())
+}
@@ -0,0 +1,158 @@
+#[cfg(feature = "devtools")]
+use reactive_graph::{
+    computed::{ArcMemo, Memo},
+    devtools::{snapshot, NodeKind},
+    graph::ToAnySubscriber,
+    owner::Owner,
+    prelude::*,
+    signal::{ArcRwSignal, RwSignal},
+};
+
+#[cfg(feature = "devtools")]
+#[test]
+fn snapshot_has_edges_and_runs() {
+    let owner = Owner::new();
+    owner.set();
+
+    let a = ArcRwSignal::new(1);
+    let b = ArcRwSignal::new(2);
+    let sum = ArcMemo::new({
+        let a = a.clone();
+        let b = b.clone();
+        move |_| a.get() + b.get()
+    });
+    assert_eq!(sum.get(), 3);
+    a.set(2);
+    assert_eq!(sum.get(), 4);
+
+    let graph = snapshot();
+    let memo_id = sum.to_any_subscriber().0;
+    let memo = graph.node(memo_id).unwrap();
+    assert_eq!(memo.kind, NodeKind::Memo);
+    assert_eq!(memo.owner, Some(owner.debug_id()));
+    assert_eq!(memo.runs, 2);
+    assert!(memo.last_update.is_some());
+    assert!(memo.defined_at.as_ref().unwrap().contains("devtools.rs"));
+    assert!(memo.subscribers.is_empty());
+    assert_eq!(memo.sources.len(), 2);
+
+    let a = graph.node(memo.sources[0]).unwrap();
+    assert_eq!(a.kind, NodeKind::Signal);
+    assert_eq!(a.subscribers, vec![memo_id]);
+    assert_eq!(a.runs, 1);
+    let b = graph.node(memo.sources[1]).unwrap();
+    assert_eq!(b.runs, 0);
+    assert_eq!(b.last_update, None);
+
+    let owner = graph.owner(owner.debug_id()).unwrap();
+    assert_eq!(owner.parent, None);
+    assert_eq!(owner.children.len(), 1);
+    assert_eq!(
+        graph.owner(owner.children[0]).unwrap().parent,
+        Some(owner.id)
+    );
+}
+
+#[cfg(feature = "devtools")]
+#[test]
+fn disposed_nodes_are_not_in_snapshot() {
+    let owner = Owner::new();
+    owner.set();
+
+    let child = owner.child();
+    child.with(|| {
+        let count = RwSignal::new(0);
+        let double = Memo::new(move |_| count.get() * 2);
+        assert_eq!(double.get(), 0);
+    });
+
+    let graph = snapshot();
+    let memo = graph
+        .nodes
+        .iter()
+        .find(|node| {
+            node.kind == NodeKind::Memo && node.owner == Some(child.debug_id())
+        })
+        .unwrap();
+    let memo_id = memo.id;
+    let signal_id = memo.sources[0];
+    assert!(graph.node(signal_id).is_some());
+
+    let child_id = child.debug_id();
+    child.cleanup();
+    drop(child);
+
+    let graph = snapshot();
+    assert!(graph.node(memo_id).is_none());
+    assert!(graph.node(signal_id).is_none());
+    assert!(graph.owner(child_id).is_none());
+    assert!(graph.owner(owner.debug_id()).unwrap().children.is_empty());
+}
+
+#[cfg(feature = "devtools")]
+#[test]
+fn snapshot_serializes_to_json() {
+    let owner = Owner::new();
+    owner.set();
+
+    let count = ArcRwSignal::new(0);
+    let double = ArcMemo::new(move |_| count.get() * 2);
+    assert_eq!(double.get(), 0);
+
+    let graph = snapshot();
+    let json = serde_json::to_string(&graph).unwrap();
+    assert!(json.contains(r#""kind":"Memo""#));
+    assert_eq!(
+        serde_json::from_str::<reactive_graph::devtools::GraphSnapshot>(&json)
+            .unwrap(),
+        graph
+    );
+}
+
+#[cfg(all(feature = "devtools", feature = "effects"))]
+#[tokio::test]
+async fn effects_are_in_snapshot() {
+    use any_spawner::Executor;
+    use reactive_graph::effect::{Effect, RenderEffect};
+    use std::mem;
+
+    _ = Executor::init_tokio();
+    let owner = Owner::new();
+    owner.set();
+    tokio::task::LocalSet::new()
+        .run_until(async {
+            let a = RwSignal::new(0);
+            let render_effect = RenderEffect::new(move |_| {
+                a.track();
+            });
+            let render_effect_id = render_effect.to_any_subscriber().0;
+            mem::forget(render_effect);
+            let effect = Effect::new(move || {
+                a.track();
+            });
+            let effect_id = effect.to_any_subscriber().0;
+
+            Executor::tick().await;
+            a.set(1);
+            Executor::tick().await;
+
+            let graph = snapshot();
+            let render_effect = graph.node(render_effect_id).unwrap();
+            assert_eq!(render_effect.kind, NodeKind::RenderEffect);
+            assert_eq!(render_effect.runs, 2);
+            let effect = graph.node(effect_id).unwrap();
+            assert_eq!(effect.kind, NodeKind::Effect);
+            assert_eq!(effect.runs, 2);
+            assert!(effect
+                .defined_at
+                .as_ref()
+                .unwrap()
+                .contains("devtools.rs"));
+
+            let signal = graph.node(effect.sources[0]).unwrap();
+            assert_eq!(signal.kind, NodeKind::Signal);
+            assert!(signal.subscribers.contains(&effect_id));
+            assert!(signal.subscribers.contains(&render_effect_id));
+        })
+        .await
+}
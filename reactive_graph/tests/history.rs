@@ -0,0 +1,180 @@
+#[cfg(feature = "history")]
+use reactive_graph::{
+    history::{History, HistoryError},
+    owner::Owner,
+    prelude::*,
+    signal::{ArcRwSignal, RwSignal},
+};
+
+#[cfg(feature = "history")]
+#[test]
+fn steps_back_and_forward_across_signals() {
+    let owner = Owner::new();
+    owner.set();
+
+    let history = History::new(10);
+    let a = RwSignal::new(0);
+    let b = ArcRwSignal::new(String::from("x"));
+    history.track("a", a);
+    history.track("b", b.clone());
+
+    a.set(1);
+    b.set("y".into());
+    a.update(|a| *a += 1);
+    // writes that do not change the value are not recorded
+    a.set(2);
+    assert_eq!(history.len(), 3);
+    assert_eq!(history.position(), 3);
+
+    assert!(history.back().unwrap());
+    assert_eq!(a.get_untracked(), 1);
+    assert_eq!(b.get_untracked(), "y");
+    assert!(history.back().unwrap());
+    assert_eq!(b.get_untracked(), "x");
+    assert!(history.back().unwrap());
+    assert_eq!(a.get_untracked(), 0);
+    assert!(!history.back().unwrap());
+    // restoring values is not recorded
+    assert_eq!(history.len(), 3);
+
+    history.go_to(3).unwrap();
+    assert_eq!((a.get_untracked(), b.get_untracked().as_str()), (2, "y"));
+    assert!(!history.forward().unwrap());
+    assert!(matches!(history.go_to(4), Err(HistoryError::OutOfRange(4))));
+
+    // writing after stepping back discards the entries after it
+    history.go_to(1).unwrap();
+    b.set("z".into());
+    assert_eq!(history.len(), 2);
+    history.back().unwrap();
+    assert_eq!(b.get_untracked(), "x");
+    assert!(history.forward().unwrap());
+    assert_eq!(b.get_untracked(), "z");
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn drops_oldest_entries() {
+    let owner = Owner::new();
+    owner.set();
+
+    let history = History::new(2);
+    let count = RwSignal::new(0);
+    history.track("count", count);
+    for n in 1..=5 {
+        count.set(n);
+    }
+
+    assert_eq!(history.len(), 2);
+    history.go_to(0).unwrap();
+    assert_eq!(count.get_untracked(), 3);
+    assert_eq!(history.export().initial["count"], 3);
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn replays_an_imported_trace() {
+    let owner = Owner::new();
+    owner.set();
+
+    let recorded = History::new(10);
+    let count = RwSignal::new(0);
+    recorded.track("count", count);
+    count.set(1);
+    count.set(2);
+    recorded.back().unwrap();
+    let trace = serde_json::to_string(&recorded.export()).unwrap();
+
+    let replayed = History::new(10);
+    let other = RwSignal::new(100);
+    replayed.track("count", other);
+    replayed
+        .import(serde_json::from_str(&trace).unwrap())
+        .unwrap();
+    assert_eq!(other.get_untracked(), 1);
+    assert_eq!(replayed.position(), 1);
+    replayed.forward().unwrap();
+    assert_eq!(other.get_untracked(), 2);
+
+    let untracked = History::new(10);
+    assert!(matches!(
+        untracked.import(serde_json::from_str(&trace).unwrap()),
+        Err(HistoryError::UnknownSignal(signal)) if signal == "count"
+    ));
+
+    let mismatched = History::new(10);
+    mismatched.track("count", RwSignal::new(String::new()));
+    assert!(matches!(
+        mismatched.import(serde_json::from_str(&trace).unwrap()),
+        Err(HistoryError::Restore { .. })
+    ));
+}
+
+#[cfg(all(feature = "history", feature = "effects"))]
+#[tokio::test]
+async fn restored_values_notify_subscribers() {
+    use any_spawner::Executor;
+    use reactive_graph::effect::Effect;
+    use std::sync::{Arc, RwLock};
+
+    _ = Executor::init_tokio();
+    let owner = Owner::new();
+    owner.set();
+    tokio::task::LocalSet::new()
+        .run_until(async {
+            let history = History::new(10);
+            let count = RwSignal::new(0);
+            history.track("count", count);
+            let seen = Arc::new(RwLock::new(Vec::new()));
+            Effect::new({
+                let seen = Arc::clone(&seen);
+                move || seen.write().unwrap().push(count.get())
+            });
+
+            Executor::tick().await;
+            count.set(1);
+            Executor::tick().await;
+            history.back().unwrap();
+            Executor::tick().await;
+            history.forward().unwrap();
+            Executor::tick().await;
+
+            assert_eq!(*seen.read().unwrap(), vec![0, 1, 0, 1]);
+            assert_eq!(history.len(), 1);
+        })
+        .await
+}
+
+#[cfg(feature = "history")]
+#[test]
+fn records_again_after_a_restore_panics() {
+    use serde::{Deserialize, Deserializer, Serialize};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // a value that cannot be restored to 13
+    #[derive(Clone, Copy, PartialEq, Serialize)]
+    struct Fragile(i32);
+
+    impl<'de> Deserialize<'de> for Fragile {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            let n = i32::deserialize(d)?;
+            assert_ne!(n, 13, "cannot restore 13");
+            Ok(Fragile(n))
+        }
+    }
+
+    let owner = Owner::new();
+    owner.set();
+
+    let history = History::new(10);
+    let count = RwSignal::new(Fragile(13));
+    history.track("count", count);
+    count.set(Fragile(1));
+    assert!(catch_unwind(AssertUnwindSafe(|| history.back())).is_err());
+
+    // the step back was taken, and later writes are recorded after it
+    count.set(Fragile(2));
+    count.set(Fragile(3));
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.position(), 2);
+}
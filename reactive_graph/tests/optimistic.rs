@@ -0,0 +1,201 @@
+use any_spawner::Executor;
+use futures::channel::oneshot;
+use reactive_graph::{
+    actions::{ArcOptimisticAction, OptimisticAction},
+    owner::Owner,
+    signal::RwSignal,
+    traits::{Get, GetUntracked, Set, Update},
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+type Responses = Arc<Mutex<HashMap<u32, oneshot::Receiver<Result<u32, ()>>>>>;
+
+/// Creates an action function whose dispatches resolve when the matching sender is used.
+fn controlled(
+    responses: &Responses,
+) -> impl Fn(&u32) -> oneshot::Receiver<Result<u32, ()>> {
+    let responses = Arc::clone(responses);
+    move |input: &u32| responses.lock().unwrap().remove(input).unwrap()
+}
+
+fn respond(
+    responses: &Responses,
+    input: u32,
+) -> oneshot::Sender<Result<u32, ()>> {
+    let (tx, rx) = oneshot::channel();
+    responses.lock().unwrap().insert(input, rx);
+    tx
+}
+
+/// Waits for the action's spawned tasks until `done` returns `true`.
+async fn until(done: impl Fn() -> bool) {
+    while !done() {
+        Executor::tick().await;
+    }
+}
+
+#[tokio::test]
+async fn overlapping_dispatches_are_reconciled_in_order() {
+    _ = Executor::init_tokio();
+    let owner = Owner::new();
+    owner.set();
+
+    let responses = Responses::default();
+    let items = RwSignal::new(vec![0]);
+    let action = OptimisticAction::new(
+        items,
+        |items: &mut Vec<u32>, n: &u32| items.push(*n),
+        {
+            let controlled = controlled(&responses);
+            move |n: &u32| {
+                let response = controlled(n);
+                async move { response.await.unwrap() }
+            }
+        },
+    );
+
+    let first = respond(&responses, 1);
+    let second = respond(&responses, 2);
+    let third = respond(&responses, 3);
+    action.dispatch(1);
+    action.dispatch(2);
+    action.dispatch(3);
+    assert_eq!(items.get_untracked(), [0, 1, 2, 3]);
+    assert!(action.pending().get());
+
+    // a later dispatch failing only reverts its own update
+    second.send(Err(())).unwrap();
+    until(|| action.version().get_untracked() == 1).await;
+    assert_eq!(items.get_untracked(), [0, 1, 3]);
+
+    // a later dispatch succeeding before an earlier one keeps both in order
+    third.send(Ok(3)).unwrap();
+    until(|| action.version().get_untracked() == 2).await;
+    assert_eq!(items.get_untracked(), [0, 1, 3]);
+
+    first.send(Ok(1)).unwrap();
+    until(|| action.version().get_untracked() == 3).await;
+    assert_eq!(items.get_untracked(), [0, 1, 3]);
+    assert!(!action.pending().get());
+}
+
+#[tokio::test]
+async fn reconcile_replaces_optimistic_update() {
+    _ = Executor::init_tokio();
+    let owner = Owner::new();
+    owner.set();
+
+    let responses = Responses::default();
+    let ids = RwSignal::new(Vec::<i64>::new());
+    let action = ArcOptimisticAction::new_with_reconcile(
+        ids,
+        // use a temporary ID until the real one is known
+        |ids: &mut Vec<i64>, _: &u32| ids.push(-1),
+        |ids: &mut Vec<i64>, _: &u32, id: &u32| ids.push(i64::from(*id)),
+        {
+            let controlled = controlled(&responses);
+            move |n: &u32| {
+                let response = controlled(n);
+                async move { response.await.unwrap() }
+            }
+        },
+    );
+
+    let first = respond(&responses, 1);
+    let second = respond(&responses, 2);
+    action.dispatch(1);
+    action.dispatch(2);
+    assert_eq!(ids.get_untracked(), [-1, -1]);
+
+    second.send(Ok(20)).unwrap();
+    until(|| action.version().get_untracked() == 1).await;
+    assert_eq!(ids.get_untracked(), [-1, 20]);
+
+    first.send(Ok(10)).unwrap();
+    until(|| action.version().get_untracked() == 2).await;
+    assert_eq!(ids.get_untracked(), [10, 20]);
+    assert_eq!(action.value().get(), Some(Ok(10)));
+}
+
+#[tokio::test]
+async fn aborted_dispatches_are_reverted() {
+    _ = Executor::init_tokio();
+    let owner = Owner::new();
+    owner.set();
+
+    let responses = Responses::default();
+    let count = RwSignal::new(0);
+    let action = ArcOptimisticAction::new(
+        count,
+        |count: &mut u32, n: &u32| *count += n,
+        {
+            let controlled = controlled(&responses);
+            move |n: &u32| {
+                let response = controlled(n);
+                async move { response.await.unwrap() }
+            }
+        },
+    );
+
+    let _first = respond(&responses, 1);
+    let second = respond(&responses, 2);
+    let handle = action.dispatch(1);
+    action.dispatch(2);
+    assert_eq!(count.get_untracked(), 3);
+
+    second.send(Ok(2)).unwrap();
+    until(|| action.version().get_untracked() == 1).await;
+    assert_eq!(count.get_untracked(), 3);
+
+    handle.abort();
+    until(|| !action.pending().get_untracked()).await;
+    assert_eq!(count.get_untracked(), 2);
+    assert_eq!(action.value().get(), Some(Ok(2)));
+}
+
+#[tokio::test]
+async fn writes_made_while_pending_are_kept() {
+    _ = Executor::init_tokio();
+    let owner = Owner::new();
+    owner.set();
+
+    let responses = Responses::default();
+    let items = RwSignal::new(vec![0]);
+    let action = OptimisticAction::new(
+        items,
+        |items: &mut Vec<u32>, n: &u32| items.push(*n),
+        {
+            let controlled = controlled(&responses);
+            move |n: &u32| {
+                let response = controlled(n);
+                async move { response.await.unwrap() }
+            }
+        },
+    );
+
+    let first = respond(&responses, 1);
+    let second = respond(&responses, 2);
+    let third = respond(&responses, 3);
+    action.dispatch(1);
+    items.update(|items| items.push(9));
+    action.dispatch(2);
+    assert_eq!(items.get_untracked(), [0, 1, 9, 2]);
+
+    // the write is kept, and later dispatches are still reverted
+    second.send(Err(())).unwrap();
+    until(|| action.version().get_untracked() == 1).await;
+    assert_eq!(items.get_untracked(), [0, 1, 9]);
+    first.send(Ok(1)).unwrap();
+    until(|| action.version().get_untracked() == 2).await;
+    assert_eq!(items.get_untracked(), [0, 1, 9]);
+
+    // a value set while a dispatch is pending replaces the one it started from
+    action.dispatch(3);
+    items.set(vec![5]);
+    third.send(Err(())).unwrap();
+    until(|| action.version().get_untracked() == 3).await;
+    assert_eq!(items.get_untracked(), [5]);
+}
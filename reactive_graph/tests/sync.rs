@@ -0,0 +1,175 @@
+#[cfg(feature = "sync")]
+use reactive_graph::{
+    owner::Owner,
+    prelude::*,
+    signal::{ArcRwSignal, RwSignal},
+    sync::{ConflictPolicy, LocalChannel, Synchronizer},
+};
+#[cfg(feature = "sync")]
+use std::sync::Arc;
+
+#[cfg(feature = "sync")]
+#[test]
+fn late_replicas_catch_up() {
+    let owner = Owner::new();
+    owner.set();
+
+    let channel = LocalChannel::new();
+    let first = Synchronizer::new(channel.endpoint());
+    let a = RwSignal::new(String::from("a"));
+    first.sync("name", a);
+
+    // values that were never written are not sent to new replicas
+    let second = Synchronizer::new(channel.endpoint());
+    let b = RwSignal::new(String::from("b"));
+    second.sync("name", b);
+    assert_eq!(b.get_untracked(), "b");
+
+    a.set("x".into());
+    assert_eq!(b.get_untracked(), "x");
+
+    let third = Synchronizer::new(channel.endpoint());
+    let c = ArcRwSignal::new(String::new());
+    third.sync("name", c.clone());
+    assert_eq!(c.get_untracked(), "x");
+
+    // dropping a replica stops syncing its values
+    drop(third);
+    a.set("y".into());
+    assert_eq!(b.get_untracked(), "y");
+    assert_eq!(c.get_untracked(), "x");
+
+    second.unsync("name");
+    a.set("z".into());
+    assert_eq!(b.get_untracked(), "y");
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn concurrent_writes_converge() {
+    let owner = Owner::new();
+    owner.set();
+
+    let channel = LocalChannel::new();
+    let first = Synchronizer::new(channel.endpoint()).with_replica_id(1);
+    let second = Synchronizer::new(channel.endpoint()).with_replica_id(2);
+    let a = RwSignal::new(0);
+    let b = RwSignal::new(0);
+    first.sync("count", a);
+    second.sync("count", b);
+
+    // both write before seeing the other's write: the replica id breaks the tie
+    channel.pause();
+    a.set(1);
+    b.set(2);
+    channel.resume();
+    assert_eq!((a.get_untracked(), b.get_untracked()), (2, 2));
+
+    // a replica that has seen more writes wins, whatever its id
+    a.set(3);
+    a.set(4);
+    channel.pause();
+    a.set(5);
+    a.set(6);
+    b.set(7);
+    channel.resume();
+    assert_eq!((a.get_untracked(), b.get_untracked()), (6, 6));
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn custom_policy_decides_conflicts() {
+    let owner = Owner::new();
+    owner.set();
+
+    // keep the greatest value, whichever replica wrote it
+    let policy = ConflictPolicy::Custom(Arc::new(|local, remote| {
+        remote.value.as_i64() > local.value.as_i64()
+    }));
+    let channel = LocalChannel::new();
+    let first =
+        Synchronizer::new(channel.endpoint()).with_policy(policy.clone());
+    let second = Synchronizer::new(channel.endpoint()).with_policy(policy);
+    let a = RwSignal::new(0);
+    let b = RwSignal::new(0);
+    first.sync("max", a);
+    second.sync("max", b);
+
+    channel.pause();
+    a.set(10);
+    b.set(5);
+    channel.resume();
+    assert_eq!((a.get_untracked(), b.get_untracked()), (10, 10));
+}
+
+#[cfg(all(feature = "sync", feature = "effects"))]
+#[tokio::test]
+async fn received_values_notify_subscribers() {
+    use any_spawner::Executor;
+    use reactive_graph::effect::Effect;
+    use std::sync::RwLock;
+
+    _ = Executor::init_tokio();
+    let owner = Owner::new();
+    owner.set();
+    tokio::task::LocalSet::new()
+        .run_until(async {
+            let channel = LocalChannel::new();
+            let first = Synchronizer::new(channel.endpoint());
+            let second = Synchronizer::new(channel.endpoint());
+            let a = RwSignal::new(0);
+            let b = RwSignal::new(0);
+            first.sync("count", a);
+            second.sync("count", b);
+
+            let seen = Arc::new(RwLock::new(Vec::new()));
+            Effect::new({
+                let seen = Arc::clone(&seen);
+                move || seen.write().unwrap().push(b.get())
+            });
+            Executor::tick().await;
+            a.set(1);
+            Executor::tick().await;
+            a.set(2);
+            Executor::tick().await;
+
+            assert_eq!(*seen.read().unwrap(), vec![0, 1, 2]);
+        })
+        .await
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn sends_again_after_applying_a_value_panics() {
+    use serde::{Deserialize, Deserializer, Serialize};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // a value that cannot be applied as 13
+    #[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+    struct Fragile(i32);
+
+    impl<'de> Deserialize<'de> for Fragile {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            let n = i32::deserialize(d)?;
+            assert_ne!(n, 13, "cannot apply 13");
+            Ok(Fragile(n))
+        }
+    }
+
+    let owner = Owner::new();
+    owner.set();
+
+    let channel = LocalChannel::new();
+    let first = Synchronizer::new(channel.endpoint());
+    let second = Synchronizer::new(channel.endpoint());
+    let a = RwSignal::new(Fragile(0));
+    let b = RwSignal::new(Fragile(0));
+    first.sync("count", a);
+    second.sync("count", b);
+
+    assert!(catch_unwind(AssertUnwindSafe(|| a.set(Fragile(13)))).is_err());
+    assert_eq!(b.get_untracked(), Fragile(0));
+
+    b.set(Fragile(2));
+    assert_eq!(a.get_untracked(), Fragile(2));
+}
@@ -4,6 +4,7 @@ use crate::{
     StoreField, StoreFieldTrigger, Subfield,
 };
 use reactive_graph::{
+    computed::ArcMemo,
     owner::Storage,
     traits::{
         DefinedAt, IsDisposed, Notify, ReadUntracked, Track, UntrackableGuard,
@@ -50,6 +51,84 @@ where
     }
 }
 
+impl<T> ArcField<T>
+where
+    T: 'static,
+{
+    /// Erases the type of any store field.
+    #[track_caller]
+    pub fn new<F>(field: F) -> Self
+    where
+        F: StoreField<Value = T> + Clone + Send + Sync + 'static,
+    {
+        ArcField {
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+            path: Arc::new({
+                let field = field.clone();
+                move || field.path().into_iter().collect()
+            }),
+            get_trigger: Arc::new({
+                let field = field.clone();
+                move |path| field.get_trigger(path)
+            }),
+            read: Arc::new({
+                let field = field.clone();
+                move || field.reader().map(StoreFieldReader::new)
+            }),
+            write: Arc::new({
+                let field = field.clone();
+                move || field.writer().map(StoreFieldWriter::new)
+            }),
+            keys: Arc::new({
+                let field = field.clone();
+                move || field.keys()
+            }),
+            track_field: Arc::new({
+                let field = field.clone();
+                move || field.track_field()
+            }),
+            notify: Arc::new(move || {
+                let trigger =
+                    field.get_trigger(field.path().into_iter().collect());
+                trigger.this.notify();
+                trigger.children.notify();
+            }),
+        }
+    }
+}
+
+impl<T> ArcField<T>
+where
+    T: Send + Sync + 'static,
+{
+    /// Wraps a memo that is computed from `field`, as a field that can be read but not
+    /// written.
+    #[track_caller]
+    pub(crate) fn from_memo<F>(field: F, memo: Arc<ArcMemo<T>>) -> Self
+    where
+        F: StoreField + Clone + Send + Sync + 'static,
+    {
+        ArcField {
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+            path: Arc::new({
+                let field = field.clone();
+                move || field.path().into_iter().collect()
+            }),
+            get_trigger: Arc::new(move |path| field.get_trigger(path)),
+            read: Arc::new({
+                let memo = Arc::clone(&memo);
+                move || memo.try_read_untracked().map(StoreFieldReader::new)
+            }),
+            write: Arc::new(|| None),
+            keys: Arc::new(|| None),
+            track_field: Arc::new(move || memo.track()),
+            notify: Arc::new(|| {}),
+        }
+    }
+}
+
 pub struct StoreFieldReader<T>(Box<dyn Deref<Target = T>>);
 
 impl<T> StoreFieldReader<T> {
@@ -0,0 +1,472 @@
+use crate::{map::is_keys_path, path::StorePath, StoreFieldTrigger};
+use or_poisoned::OrPoisoned;
+use reactive_graph::graph::{
+    AnySource, AnySubscriber, ReactiveNode, Source, Subscriber, ToAnySource,
+};
+use rustc_hash::FxHashMap;
+use std::{
+    cell::Cell,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex, RwLock, Weak,
+    },
+};
+
+/// A change made to a field of a store, delivered to subscribers of
+/// [`ArcStore::subscribe`](crate::ArcStore::subscribe).
+#[derive(Debug)]
+pub struct StoreChange<T> {
+    /// The path of the field that was written.
+    pub path: StorePath,
+    /// The value of the whole store before the change.
+    ///
+    /// This is the snapshot taken when the previous change was delivered, or when the
+    /// subscription was created, rather than a copy taken at the moment of the write. Any
+    /// writes that did not notify in between, such as untracked writes, are included in the
+    /// difference between `old` and `new`.
+    ///
+    /// This is only captured by [`ArcStore::subscribe_with_values`](crate::ArcStore::subscribe_with_values).
+    pub old: Option<Arc<T>>,
+    /// The value of the whole store after the change.
+    ///
+    /// This is only captured by [`ArcStore::subscribe_with_values`](crate::ArcStore::subscribe_with_values).
+    pub new: Option<Arc<T>>,
+}
+
+impl<T> Clone for StoreChange<T> {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            old: self.old.clone(),
+            new: self.new.clone(),
+        }
+    }
+}
+
+type Listener = Arc<dyn Fn(&StorePath) + Send + Sync>;
+
+/// The callbacks that have subscribed to changes to a store, and the watchers that report
+/// writes to them.
+#[derive(Default)]
+pub(crate) struct ChangeListeners {
+    next_id: AtomicUsize,
+    listeners: RwLock<Vec<(usize, Listener)>>,
+    watchers: Mutex<FxHashMap<StorePath, Arc<PathWatcher>>>,
+}
+
+impl ChangeListeners {
+    fn add(&self, listener: Listener) -> usize {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.listeners.write().or_poisoned().push((id, listener));
+        id
+    }
+
+    fn remove(&self, id: usize) {
+        let watchers = {
+            let mut listeners = self.listeners.write().or_poisoned();
+            listeners.retain(|(n, _)| *n != id);
+            // once nothing is listening, stop watching the triggers, so that subscribing and
+            // unsubscribing repeatedly doesn't leave watchers behind
+            if listeners.is_empty() {
+                std::mem::take(&mut *self.watchers.lock().or_poisoned())
+            } else {
+                FxHashMap::default()
+            }
+        };
+        // the watchers unsubscribe from their triggers when dropped, so drop them after
+        // releasing the locks
+        drop(watchers);
+    }
+
+    fn notify(&self, path: &StorePath) {
+        // release the lock before calling the listeners, so they can subscribe, unsubscribe,
+        // or write to the store themselves
+        let listeners = self
+            .listeners
+            .read()
+            .or_poisoned()
+            .iter()
+            .map(|(_, listener)| Arc::clone(listener))
+            .collect::<Vec<_>>();
+        for listener in listeners {
+            listener(path);
+        }
+    }
+}
+
+/// Watches the `this` trigger of each field of a store, and reports writes to the store's
+/// listeners.
+///
+/// Watchers are only created once something has subscribed to the store, so stores without
+/// subscribers don't pay for them.
+#[derive(Default)]
+pub(crate) struct PathWatchers {
+    listeners: Arc<ChangeListeners>,
+}
+
+impl Debug for PathWatchers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PathWatchers")
+            .field("watched", &self.watched())
+            .finish_non_exhaustive()
+    }
+}
+
+impl PathWatchers {
+    /// Starts watching the trigger for this path, if anything is subscribed to the store.
+    pub fn watch(&mut self, path: &StorePath, trigger: &StoreFieldTrigger) {
+        // hold the listeners while adding the watcher, so that it can't be added after the
+        // last subscription has removed the others
+        let listeners = self.listeners.listeners.read().or_poisoned();
+        let mut watchers = self.listeners.watchers.lock().or_poisoned();
+        if listeners.is_empty()
+            || is_keys_path(path)
+            || watchers.contains_key(path)
+        {
+            return;
+        }
+        let watcher = Arc::new_cyclic(|this: &Weak<PathWatcher>| PathWatcher {
+            path: path.clone(),
+            source: trigger.this.to_any_source(),
+            listeners: Arc::downgrade(&self.listeners),
+            subscriber: AnySubscriber(
+                this.as_ptr() as usize,
+                Weak::clone(this) as Weak<dyn Subscriber + Send + Sync>,
+            ),
+        });
+        watcher.source.add_subscriber(watcher.subscriber.clone());
+        watchers.insert(path.clone(), watcher);
+    }
+
+    /// The number of fields whose triggers are being watched.
+    fn watched(&self) -> usize {
+        self.listeners.watchers.lock().or_poisoned().len()
+    }
+
+    fn subscribe<'a>(
+        &mut self,
+        listener: Listener,
+        triggers: impl IntoIterator<Item = (&'a StorePath, &'a StoreFieldTrigger)>,
+    ) -> StoreSubscription {
+        let id = self.listeners.add(listener);
+        for (path, trigger) in triggers {
+            self.watch(path, trigger);
+        }
+        StoreSubscription {
+            inner: Some((Arc::clone(&self.listeners), id)),
+        }
+    }
+}
+
+struct PathWatcher {
+    path: StorePath,
+    source: AnySource,
+    listeners: Weak<ChangeListeners>,
+    subscriber: AnySubscriber,
+}
+
+impl ReactiveNode for PathWatcher {
+    fn mark_dirty(&self) {
+        if let Some(listeners) = self.listeners.upgrade() {
+            listeners.notify(&self.path);
+        }
+        // triggers drop their subscribers when they notify them
+        self.source.add_subscriber(self.subscriber.clone());
+    }
+
+    fn mark_check(&self) {}
+
+    fn mark_subscribers_check(&self) {}
+
+    fn update_if_necessary(&self) -> bool {
+        false
+    }
+}
+
+impl Subscriber for PathWatcher {
+    fn add_source(&self, _source: AnySource) {}
+
+    fn clear_sources(&self, _subscriber: &AnySubscriber) {}
+}
+
+impl Drop for PathWatcher {
+    fn drop(&mut self) {
+        self.source.remove_subscriber(&self.subscriber);
+    }
+}
+
+/// A subscription to the changes made to a store, created by
+/// [`ArcStore::subscribe`](crate::ArcStore::subscribe).
+///
+/// The subscriber is removed when this is dropped.
+#[must_use = "the subscription is cancelled when it is dropped"]
+#[derive(Default)]
+pub struct StoreSubscription {
+    inner: Option<(Arc<ChangeListeners>, usize)>,
+}
+
+impl Debug for StoreSubscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoreSubscription").finish_non_exhaustive()
+    }
+}
+
+impl StoreSubscription {
+    /// Stops delivering changes to the subscriber.
+    pub fn unsubscribe(self) {}
+}
+
+impl Drop for StoreSubscription {
+    fn drop(&mut self) {
+        if let Some((listeners, id)) = self.inner.take() {
+            listeners.remove(id);
+        }
+    }
+}
+
+/// Subscribes to the changes to the store whose triggers are in `triggers`.
+pub(crate) fn subscribe<T>(
+    triggers: &RwLock<crate::TriggerMap>,
+    f: impl Fn(&StoreChange<T>) + Send + Sync + 'static,
+) -> StoreSubscription {
+    let mut triggers = triggers.write().or_poisoned();
+    let crate::TriggerMap { triggers, watchers } = &mut *triggers;
+    watchers.subscribe(
+        Arc::new(move |path: &StorePath| {
+            f(&StoreChange {
+                path: path.clone(),
+                old: None,
+                new: None,
+            })
+        }),
+        triggers.iter(),
+    )
+}
+
+thread_local! {
+    static BATCH: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+static NEXT_BATCH: AtomicUsize = AtomicUsize::new(0);
+
+/// Runs `fun`, treating all the changes it notifies as a single change to the store, so that
+/// they are delivered with the same old and new values.
+pub(crate) fn batch<T>(fun: impl FnOnce() -> T) -> T {
+    let outer = BATCH.with(|batch| batch.get());
+    if outer.is_some() {
+        return fun();
+    }
+    // ends the batch even if `fun` panics, so later changes are not treated as part of it
+    struct EndBatch;
+
+    impl Drop for EndBatch {
+        fn drop(&mut self) {
+            BATCH.with(|batch| batch.set(None));
+        }
+    }
+
+    let id = NEXT_BATCH.fetch_add(1, Ordering::Relaxed);
+    BATCH.with(|batch| batch.set(Some(id)));
+    let _end = EndBatch;
+    fun()
+}
+
+/// Subscribes to the changes to the store, capturing its value before and after each change.
+pub(crate) fn subscribe_with_values<T>(
+    triggers: &RwLock<crate::TriggerMap>,
+    value: &Arc<RwLock<T>>,
+    f: impl Fn(&StoreChange<T>) + Send + Sync + 'static,
+) -> StoreSubscription
+where
+    T: Clone + Send + Sync + 'static,
+{
+    // the subscription is owned by the store, so it should not keep the value alive
+    let weak_value = Arc::downgrade(value);
+    let snapshots = Mutex::new(Snapshots {
+        batch: None,
+        old: None,
+        new: Arc::new(value.read().or_poisoned().clone()),
+    });
+    let mut triggers = triggers.write().or_poisoned();
+    let crate::TriggerMap { triggers, watchers } = &mut *triggers;
+    watchers.subscribe(
+        Arc::new(move |path: &StorePath| {
+            let Some(value) = weak_value.upgrade() else {
+                return;
+            };
+            let (old, new) = {
+                let mut snapshots = snapshots.lock().or_poisoned();
+                let batch = BATCH.with(|batch| batch.get());
+                if batch.is_none() || batch != snapshots.batch {
+                    let current = Arc::new(value.read().or_poisoned().clone());
+                    snapshots.old =
+                        Some(std::mem::replace(&mut snapshots.new, current));
+                    snapshots.batch = batch;
+                }
+                (snapshots.old.clone(), Arc::clone(&snapshots.new))
+            };
+            f(&StoreChange {
+                path: path.clone(),
+                old,
+                new: Some(new),
+            })
+        }),
+        triggers.iter(),
+    )
+}
+
+/// The values of the store before and after the latest change.
+struct Snapshots<T> {
+    batch: Option<usize>,
+    old: Option<Arc<T>>,
+    new: Arc<T>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        self as reactive_stores, ArcStore, Patch, Store, StoreFieldMap,
+        StorePath,
+    };
+    use reactive_graph::traits::{Set, Update, Write};
+    use std::{
+        collections::HashMap,
+        panic,
+        sync::{Arc, Mutex},
+    };
+
+    #[derive(Debug, Clone, PartialEq, Store, Patch, Default)]
+    struct Profile {
+        name: String,
+        age: u32,
+        tags: HashMap<String, bool>,
+    }
+
+    fn path(segments: &[usize]) -> StorePath {
+        segments.iter().map(Into::into).collect()
+    }
+
+    #[test]
+    fn subscribers_receive_written_paths() {
+        let store = ArcStore::new(Profile::default());
+        let paths = Arc::new(Mutex::new(Vec::new()));
+        let subscription = store.subscribe({
+            let paths = Arc::clone(&paths);
+            move |change| paths.lock().unwrap().push(change.path.clone())
+        });
+
+        store.clone().name().set("Alice".into());
+        store.clone().age().update(|age| *age += 1);
+        store.clone().tags().insert("admin".into(), true);
+        store.write().age = 30;
+        assert_eq!(paths.lock().unwrap().len(), 4);
+        assert_eq!(paths.lock().unwrap()[..2], [path(&[0]), path(&[1])]);
+        // inserting into a map reports the entry that was inserted
+        assert_eq!(paths.lock().unwrap()[2].len(), 2);
+        assert_eq!(paths.lock().unwrap()[3], path(&[]));
+
+        // patching only reports the fields that changed
+        paths.lock().unwrap().clear();
+        store.patch(Profile {
+            name: "Bob".into(),
+            age: 30,
+            tags: HashMap::from([("admin".into(), true)]),
+        });
+        assert_eq!(*paths.lock().unwrap(), [path(&[0])]);
+
+        subscription.unsubscribe();
+        store.clone().name().set("Carol".into());
+        assert_eq!(paths.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn watchers_are_removed_with_the_last_subscription() {
+        let store = ArcStore::new(Profile::default());
+        let watched = || store.signals.read().unwrap().watchers.watched();
+        store.clone().name().set("Alice".into());
+        store.clone().age().set(30);
+
+        for _ in 0..3 {
+            let first = store.subscribe(|_| {});
+            let second = store.subscribe_with_values(|_| {});
+            assert!(watched() > 0);
+            drop(first);
+            assert!(watched() > 0);
+            drop(second);
+            assert_eq!(watched(), 0);
+        }
+
+        // subscribing again watches the fields again
+        let count = Arc::new(Mutex::new(0));
+        let _subscription = store.subscribe({
+            let count = Arc::clone(&count);
+            move |_| *count.lock().unwrap() += 1
+        });
+        store.clone().name().set("Bob".into());
+        assert_eq!(*count.lock().unwrap(), 1);
+    }
+
+    #[test]
+    fn changes_carry_old_and_new_values() {
+        let store = Store::new(Profile {
+            name: "Alice".into(),
+            age: 30,
+            tags: HashMap::new(),
+        });
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let _subscription = store.subscribe_with_values({
+            let changes = Arc::clone(&changes);
+            move |change| changes.lock().unwrap().push(change.clone())
+        });
+
+        store.age().set(31);
+        store.patch(Profile {
+            name: "Bob".into(),
+            age: 32,
+            tags: HashMap::new(),
+        });
+
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes.len(), 3);
+        let ages = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.old.as_ref().unwrap().age,
+                    change.new.as_ref().unwrap().age,
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(ages, [(30, 31), (31, 32), (31, 32)]);
+        // every field changed by the same patch shares the same values
+        assert!(Arc::ptr_eq(
+            changes[1].new.as_ref().unwrap(),
+            changes[2].new.as_ref().unwrap()
+        ));
+        assert_eq!(changes[2].new.as_ref().unwrap().name, "Bob");
+    }
+
+    #[test]
+    fn batches_end_when_they_panic() {
+        let store = Store::new(Profile::default());
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let _subscription = store.subscribe_with_values({
+            let changes = Arc::clone(&changes);
+            move |change| changes.lock().unwrap().push(change.clone())
+        });
+
+        let result = panic::catch_unwind(|| {
+            super::batch(|| panic!("batch failed"));
+        });
+        assert!(result.is_err());
+
+        // later changes are not treated as part of the batch
+        store.age().set(1);
+        store.age().set(2);
+        let changes = changes.lock().unwrap();
+        assert_eq!(changes[0].new.as_ref().unwrap().age, 1);
+        assert_eq!(changes[1].old.as_ref().unwrap().age, 1);
+        assert_eq!(changes[1].new.as_ref().unwrap().age, 2);
+    }
+}
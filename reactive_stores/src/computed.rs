@@ -0,0 +1,197 @@
+use crate::{ArcField, Field, StoreField};
+use or_poisoned::OrPoisoned;
+use reactive_graph::computed::ArcMemo;
+use rustc_hash::FxHashMap;
+use std::{
+    any::Any,
+    fmt::Debug,
+    sync::{Arc, Mutex, Weak},
+};
+
+/// A memoized value derived from the fields of a store.
+///
+/// Computed fields are declared on a struct with the `#[store(computed(name: Type = function))]`
+/// attribute, where `function` takes the store as a [`Field`] and returns the value. The value
+/// is only recomputed when one of the fields it reads changes, so a computed field that only reads
+/// one subfield is not notified by changes to its siblings.
+///
+/// A computed field can be read, but not written. Each store field shares one memo for each of
+/// its computed fields, however many times it is accessed, for as long as one of them is in use.
+///
+/// ```rust
+/// use reactive_graph::traits::{Get, Read, Write};
+/// use reactive_stores::{Field, Store};
+///
+/// #[derive(Store)]
+/// #[store(computed(total: u32 = order_total))]
+/// struct Order {
+///     customer: String,
+///     items: Vec<Item>,
+/// }
+///
+/// #[derive(Store)]
+/// struct Item {
+///     price: u32,
+/// }
+///
+/// fn order_total(order: Field<Order>) -> u32 {
+///     order.items().read().iter().map(|item| item.price).sum()
+/// }
+///
+/// let order = Store::new(Order {
+///     customer: "Alice".to_string(),
+///     items: vec![Item { price: 3 }],
+/// });
+/// let total = order.total();
+/// assert_eq!(total.get(), 3);
+///
+/// order.items().write().push(Item { price: 4 });
+/// assert_eq!(total.get(), 7);
+/// ```
+pub type ComputedField<T> = Field<T>;
+
+/// The memos for the computed fields of a store field, by name.
+///
+/// These are kept with the field's trigger, and only hold weak references, so that a memo is
+/// shared while it is in use but does not keep the store alive.
+#[derive(Clone, Default)]
+pub(crate) struct ComputedFields(
+    Arc<Mutex<FxHashMap<&'static str, Box<dyn Any + Send + Sync>>>>,
+);
+
+impl Debug for ComputedFields {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ComputedFields").finish_non_exhaustive()
+    }
+}
+
+/// Returns the [`ComputedField`] called `name` that applies `compute` to the given store field.
+///
+/// This is used to implement computed fields declared with the [`Store`](macro@crate::Store)
+/// macro.
+#[track_caller]
+pub fn computed_field<F, T, U>(
+    field: F,
+    name: &'static str,
+    compute: fn(Field<T>) -> U,
+) -> ComputedField<U>
+where
+    F: StoreField<Value = T> + Clone + Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    U: PartialEq + Send + Sync + 'static,
+{
+    let computed = field
+        .get_trigger(field.path().into_iter().collect())
+        .computed;
+    let mut memos = computed.0.lock().or_poisoned();
+    let memo = memos
+        .get(name)
+        .and_then(|memo| memo.downcast_ref::<Weak<ArcMemo<U>>>())
+        .and_then(Weak::upgrade);
+    let memo = match memo {
+        Some(memo) => memo,
+        None => {
+            let source = ArcField::new(field.clone());
+            // the field is created inside the memo, so it is disposed when the memo reruns,
+            // rather than with whichever owner first accessed the computed field
+            let memo = Arc::new(ArcMemo::new(move |_| {
+                compute(Field::from(source.clone()))
+            }));
+            memos.insert(name, Box::new(Arc::downgrade(&memo)));
+            memo
+        }
+    };
+    drop(memos);
+    Field::from(ArcField::from_memo(field, memo))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{self as reactive_stores, Field, Store};
+    use reactive_graph::{
+        effect::Effect,
+        traits::{Get, Read, Set, Write},
+    };
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    pub async fn tick() {
+        tokio::time::sleep(std::time::Duration::from_micros(1)).await;
+    }
+
+    static TOTALS: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Debug, Store)]
+    struct Account {
+        owner: String,
+        order: Order,
+    }
+
+    #[derive(Debug, Store)]
+    #[store(computed(
+        total: u32 = order_total,
+        count: usize = |order| order.items().read().len()
+    ))]
+    struct Order {
+        note: String,
+        items: Vec<Item>,
+    }
+
+    #[derive(Debug, Store)]
+    struct Item {
+        price: u32,
+    }
+
+    fn order_total(order: Field<Order>) -> u32 {
+        TOTALS.fetch_add(1, Ordering::Relaxed);
+        order.items().read().iter().map(|item| item.price).sum()
+    }
+
+    #[tokio::test]
+    async fn computed_fields_only_track_what_they_read() {
+        _ = any_spawner::Executor::init_tokio();
+
+        let store = Store::new(Account {
+            owner: "Alice".into(),
+            order: Order {
+                note: String::new(),
+                items: vec![Item { price: 3 }, Item { price: 4 }],
+            },
+        });
+        let total = store.order().total();
+        let runs = Arc::new(AtomicUsize::new(0));
+        Effect::new_sync({
+            let runs = Arc::clone(&runs);
+            move |_| {
+                total.get();
+                runs.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        tick().await;
+        assert_eq!(total.get(), 7);
+        assert_eq!(store.order().count().get(), 2);
+        assert_eq!(TOTALS.load(Ordering::Relaxed), 1);
+
+        // accessing the computed field again, by any route, shares the same memo
+        assert_eq!(store.order().total().get(), 7);
+        let order: Field<Order> = store.order().into();
+        assert_eq!(order.total().get(), 7);
+        assert_eq!(TOTALS.load(Ordering::Relaxed), 1);
+
+        // siblings of the fields read by the computation do not recompute it
+        store.owner().set("Bob".into());
+        store.order().note().set("deliver quickly".into());
+        tick().await;
+        assert_eq!(total.get(), 7);
+        assert_eq!(TOTALS.load(Ordering::Relaxed), 1);
+        assert_eq!(runs.load(Ordering::Relaxed), 1);
+
+        store.order().items().write().push(Item { price: 5 });
+        tick().await;
+        assert_eq!(total.get(), 12);
+        assert_eq!(TOTALS.load(Ordering::Relaxed), 2);
+        assert_eq!(runs.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{path::StorePath, ArcStore, Patch, PatchField, Store, StoreField};
+use reactive_graph::{
+    graph::{AnySource, ToAnySource},
+    history::Recordable,
+    owner::Storage,
+    traits::ReadUntracked,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+// restoring a value patches the store, so only the fields that differ from the recorded value
+// are notified
+
+impl<T> Recordable for ArcStore<T>
+where
+    T: PatchField + Serialize + DeserializeOwned + 'static,
+{
+    fn source(&self) -> AnySource {
+        // every write to the store or one of its fields notifies the root's children
+        self.get_trigger(StorePath::default())
+            .children
+            .to_any_source()
+    }
+
+    fn snapshot(&self) -> Option<Result<Value, serde_json::Error>> {
+        self.try_read_untracked()
+            .map(|value| serde_json::to_value(&*value))
+    }
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error> {
+        self.patch(serde_json::from_value(value)?);
+        Ok(())
+    }
+}
+
+impl<T, S> Recordable for Store<T, S>
+where
+    T: PatchField + Serialize + DeserializeOwned + 'static,
+    S: Storage<ArcStore<T>>,
+{
+    fn source(&self) -> AnySource {
+        self.get_trigger(StorePath::default())
+            .children
+            .to_any_source()
+    }
+
+    fn snapshot(&self) -> Option<Result<Value, serde_json::Error>> {
+        self.try_read_untracked()
+            .map(|value| serde_json::to_value(&*value))
+    }
+
+    fn restore(&self, value: Value) -> Result<(), serde_json::Error> {
+        self.patch(serde_json::from_value(value)?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{self as reactive_stores, Patch, Store};
+    use reactive_graph::{
+        history::History,
+        owner::Owner,
+        traits::{ReadUntracked, Set, Update},
+    };
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Store, Patch, Serialize, Deserialize)]
+    struct Todos {
+        user: String,
+        todos: Vec<String>,
+    }
+
+    #[test]
+    fn store_writes_are_recorded_and_restored() {
+        let owner = Owner::new();
+        owner.set();
+
+        let history = History::new(10);
+        let store = Store::new(Todos {
+            user: "Bob".into(),
+            todos: Vec::new(),
+        });
+        history.track("todos", store);
+
+        store.user().set("Carol".into());
+        store
+            .todos()
+            .update(|todos| todos.push("Write tests".into()));
+        assert_eq!(history.len(), 2);
+
+        history.go_to(0).unwrap();
+        assert_eq!(store.user().read_untracked().as_str(), "Bob");
+        assert!(store.todos().read_untracked().is_empty());
+        history.forward().unwrap();
+        assert_eq!(store.user().read_untracked().as_str(), "Carol");
+        assert!(store.todos().read_untracked().is_empty());
+        history.forward().unwrap();
+        assert_eq!(*store.todos().read_untracked(), ["Write tests"]);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn stores_are_synced() {
+        use reactive_graph::sync::{LocalChannel, Synchronizer};
+
+        let owner = Owner::new();
+        owner.set();
+
+        let channel = LocalChannel::new();
+        let (first, second) = (
+            Synchronizer::new(channel.endpoint()),
+            Synchronizer::new(channel.endpoint()),
+        );
+        let a = Store::new(Todos {
+            user: "Bob".into(),
+            todos: Vec::new(),
+        });
+        let b = Store::new(Todos {
+            user: "Bob".into(),
+            todos: Vec::new(),
+        });
+        first.sync("todos", a);
+        second.sync("todos", b);
+
+        a.todos().update(|todos| todos.push("Write tests".into()));
+        assert_eq!(*b.todos().read_untracked(), ["Write tests"]);
+        b.user().set("Carol".into());
+        assert_eq!(a.user().read_untracked().as_str(), "Carol");
+    }
+}
@@ -1,6 +1,6 @@
 use std::{
     borrow::Cow,
-    collections::{LinkedList, VecDeque},
+    collections::{BTreeMap, HashMap, LinkedList, VecDeque},
 };
 
 /// A trait for getting the length of a collection.
@@ -62,6 +62,10 @@ delegate_impl_len!(<T,> [T]);
 delegate_impl_len!(<T,> Vec<T>);
 delegate_impl_len!(str);
 delegate_impl_len!(String);
+delegate_impl_len!(<K, V, S,> HashMap<K, V, S>);
+delegate_impl_len!(<K, V,> BTreeMap<K, V>);
+#[cfg(feature = "indexmap")]
+delegate_impl_len!(<K, V, S,> indexmap::IndexMap<K, V, S>);
 
 impl Len for Cow<'_, str> {
     #[inline(always)]
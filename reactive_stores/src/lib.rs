@@ -155,6 +155,28 @@
 //! assert_eq!(store.vec_field().at_unkeyed(1).get(), 2);
 //! assert_eq!(store.vec_field().at_unkeyed(2).get(), 3);
 //! ```
+//! #### HashMap and BTreeMap
+//! Maps are accessed by key with the [StoreFieldMap::at_key()] method. Each key is tracked
+//! separately, so changing the value for one key does not notify fields that read another, and
+//! [StoreFieldMap::insert()], [StoreFieldMap::remove()] and [StoreFieldMap::len()] only notify
+//! readers of the keys they affect. `IndexMap` is supported with the `indexmap` feature.
+//! ```rust
+//! # use reactive_stores::Store;
+//! use reactive_stores::StoreFieldMap;
+//! use reactive_graph::traits::Get;
+//! use std::collections::HashMap;
+//!
+//! #[derive(Store)]
+//! struct StructWithMap {
+//!     map_field: HashMap<String, i32>,
+//! }
+//!
+//! let store = Store::new(StructWithMap { map_field: HashMap::new() });
+//! store.map_field().insert("one".to_string(), 1);
+//!
+//! assert_eq!(store.map_field().at_key("one".to_string()).get(), 1);
+//! assert_eq!(store.map_field().len(), 1);
+//! ```
 //! #### Enum
 //! Enumerated types behave a bit differently as the [`Store`](macro@Store) macro builds underlying traits instead of alternate
 //! enumerated structures.  Each element in an `Enum` generates methods to access it in the store: a
@@ -250,7 +272,7 @@ use reactive_graph::{
         UntrackableGuard, Write,
     },
 };
-pub use reactive_stores_macro::{Patch, Store};
+pub use reactive_stores_macro::{Patch, Store, UndoField};
 use rustc_hash::FxHashMap;
 use std::{
     any::Any,
@@ -262,37 +284,51 @@ use std::{
 };
 
 mod arc_field;
+mod changes;
+mod computed;
 mod deref;
 mod field;
+#[cfg(feature = "history")]
+mod history;
 mod iter;
 mod keyed;
 mod len;
+mod map;
 mod option;
 mod patch;
 mod path;
 mod store_field;
 mod subfield;
+mod undo;
 
 pub use arc_field::ArcField;
+pub use changes::{StoreChange, StoreSubscription};
+pub use computed::*;
 pub use deref::*;
 pub use field::Field;
 pub use iter::*;
 pub use keyed::*;
 pub use len::Len;
+pub use map::*;
 pub use option::*;
 pub use patch::*;
 pub use path::{StorePath, StorePathSegment};
 pub use store_field::StoreField;
 pub use subfield::Subfield;
+pub use undo::{FieldValue, UndoField, UndoStack};
 
 #[derive(Debug, Default)]
-struct TriggerMap(FxHashMap<StorePath, StoreFieldTrigger>);
+struct TriggerMap {
+    triggers: FxHashMap<StorePath, StoreFieldTrigger>,
+    watchers: changes::PathWatchers,
+}
 
 /// The reactive trigger that can be used to track updates to a store field.
 #[derive(Debug, Clone, Default)]
 pub struct StoreFieldTrigger {
     pub(crate) this: ArcTrigger,
     pub(crate) children: ArcTrigger,
+    pub(crate) computed: computed::ComputedFields,
 }
 
 impl StoreFieldTrigger {
@@ -300,22 +336,33 @@ impl StoreFieldTrigger {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// The trigger that is notified when this field itself is written to.
+    pub fn this(&self) -> &ArcTrigger {
+        &self.this
+    }
+
+    /// The trigger that is notified when this field or any of its descendants is written to.
+    pub fn children(&self) -> &ArcTrigger {
+        &self.children
+    }
 }
 
 impl TriggerMap {
     fn get_or_insert(&mut self, key: StorePath) -> StoreFieldTrigger {
-        if let Some(trigger) = self.0.get(&key) {
+        if let Some(trigger) = self.triggers.get(&key) {
             trigger.clone()
         } else {
             let new = StoreFieldTrigger::new();
-            self.0.insert(key, new.clone());
+            self.watchers.watch(&key, &new);
+            self.triggers.insert(key, new.clone());
             new
         }
     }
 
     #[allow(unused)]
     fn remove(&mut self, key: &StorePath) -> Option<StoreFieldTrigger> {
-        self.0.remove(key)
+        self.triggers.remove(key)
     }
 }
 
@@ -485,6 +532,42 @@ impl<T> ArcStore<T> {
     }
 }
 
+impl<T> ArcStore<T> {
+    /// Subscribes to the changes made to this store.
+    ///
+    /// `f` is called synchronously each time a field of the store is written to, with the
+    /// [`StorePath`] of that field. Changes made with [`Patch::patch`] are reported once for each
+    /// field that actually changed. This can be used to send changes to a server, or to log
+    /// them, without wrapping every setter.
+    ///
+    /// The subscription is cancelled when the returned [`StoreSubscription`] is dropped.
+    pub fn subscribe(
+        &self,
+        f: impl Fn(&StoreChange<T>) + Send + Sync + 'static,
+    ) -> StoreSubscription {
+        changes::subscribe(&self.signals, f)
+    }
+
+    /// Subscribes to the changes made to this store, like [`ArcStore::subscribe`], and captures
+    /// the value of the whole store before and after each change.
+    ///
+    /// All the fields changed by a single call to [`Patch::patch`] are delivered with the same
+    /// old and new values.
+    ///
+    /// The old value is the snapshot taken when the previous change was delivered, so it is
+    /// the value the subscriber last saw, not a copy taken at the moment of the write. See
+    /// [`StoreChange::old`].
+    pub fn subscribe_with_values(
+        &self,
+        f: impl Fn(&StoreChange<T>) + Send + Sync + 'static,
+    ) -> StoreSubscription
+    where
+        T: Clone + Send + Sync + 'static,
+    {
+        changes::subscribe_with_values(&self.signals, &self.value, f)
+    }
+}
+
 impl<T: Default> Default for ArcStore<T> {
     fn default() -> Self {
         Self::new(T::default())
@@ -608,6 +691,42 @@ where
     }
 }
 
+impl<T, S> Store<T, S>
+where
+    T: 'static,
+    S: Storage<ArcStore<T>>,
+{
+    /// Subscribes to the changes made to this store.
+    ///
+    /// See [`ArcStore::subscribe`]. If the store has already been disposed, the subscription
+    /// never delivers any changes.
+    pub fn subscribe(
+        &self,
+        f: impl Fn(&StoreChange<T>) + Send + Sync + 'static,
+    ) -> StoreSubscription {
+        self.inner
+            .try_with_value(|inner| inner.subscribe(f))
+            .unwrap_or_default()
+    }
+
+    /// Subscribes to the changes made to this store, capturing the value of the whole store
+    /// before and after each change.
+    ///
+    /// See [`ArcStore::subscribe_with_values`]. If the store has already been disposed, the
+    /// subscription never delivers any changes.
+    pub fn subscribe_with_values(
+        &self,
+        f: impl Fn(&StoreChange<T>) + Send + Sync + 'static,
+    ) -> StoreSubscription
+    where
+        T: Clone + Send + Sync,
+    {
+        self.inner
+            .try_with_value(|inner| inner.subscribe_with_values(f))
+            .unwrap_or_default()
+    }
+}
+
 impl<T, S> PartialEq for Store<T, S> {
     fn eq(&self, other: &Self) -> bool {
         self.inner == other.inner
@@ -0,0 +1,704 @@
+use crate::{
+    len::Len,
+    path::{StorePath, StorePathSegment},
+    store_field::StoreField,
+    KeyMap, StoreFieldTrigger,
+};
+use reactive_graph::{
+    signal::{
+        guards::{MappedMutArc, WriteGuard},
+        ArcTrigger,
+    },
+    traits::{
+        DefinedAt, IsDisposed, Notify, ReadUntracked, Track, UntrackableGuard,
+        Write,
+    },
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, DefaultHasher, Hash, Hasher},
+    iter,
+    ops::DerefMut,
+    panic::Location,
+};
+
+/// The path segment used for the trigger that tracks the set of keys in a map.
+const KEYS_SEGMENT: StorePathSegment = StorePathSegment(usize::MAX);
+
+/// Returns the path segment for the entry with the given key.
+///
+/// Entries are identified by a hash of their key, rather than by their position, so that
+/// the same key always maps to the same trigger, whether it is accessed through a field or
+/// changed by [`PatchField`](crate::PatchField).
+///
+/// Two keys whose hashes collide share a trigger, so a change to one also notifies the
+/// readers of the other. The hash is 64 bits wide on every target (`FxHasher` is only as wide
+/// as `usize`), and is folded into 32 bits on 32-bit targets rather than truncated.
+pub(crate) fn key_segment<K: Hash + ?Sized>(key: &K) -> StorePathSegment {
+    // `DefaultHasher::new` always uses the same keys, so a key always hashes the same way
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    let hash = hasher.finish();
+    #[cfg(target_pointer_width = "64")]
+    let segment = hash as usize;
+    #[cfg(not(target_pointer_width = "64"))]
+    let segment = (hash ^ (hash >> 32)) as usize;
+    // leave room for the segment used to track the keys themselves
+    StorePathSegment(segment.min(usize::MAX - 1))
+}
+
+/// Returns the path of the trigger that tracks the set of keys in the map at `path`.
+pub(crate) fn keys_path(mut path: StorePath) -> StorePath {
+    path.push(KEYS_SEGMENT);
+    path
+}
+
+/// Returns `true` if this is the path of the trigger that tracks the set of keys in a map.
+pub(crate) fn is_keys_path(path: &StorePath) -> bool {
+    path.last() == Some(&KEYS_SEGMENT)
+}
+
+/// A collection that maps keys to values, and can be accessed by key as a store field.
+pub trait MapLike: Len {
+    /// The type of the keys.
+    type Key;
+    /// The type of the values.
+    type Value;
+
+    /// Returns a reference to the value for this key.
+    fn get(&self, key: &Self::Key) -> Option<&Self::Value>;
+
+    /// Returns a mutable reference to the value for this key.
+    fn get_mut(&mut self, key: &Self::Key) -> Option<&mut Self::Value>;
+
+    /// Inserts a value, returning the previous value for this key.
+    fn insert(
+        &mut self,
+        key: Self::Key,
+        value: Self::Value,
+    ) -> Option<Self::Value>;
+
+    /// Removes the value for this key and returns it.
+    fn remove(&mut self, key: &Self::Key) -> Option<Self::Value>;
+
+    /// An iterator over the keys, in the map's own order.
+    fn keys(&self) -> impl Iterator<Item = &Self::Key>;
+}
+
+impl<K, V, S> MapLike for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        HashMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        HashMap::get_mut(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        HashMap::insert(self, key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        HashMap::remove(self, key)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &K> {
+        HashMap::keys(self)
+    }
+}
+
+impl<K, V> MapLike for BTreeMap<K, V>
+where
+    K: Ord,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        BTreeMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        BTreeMap::get_mut(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        BTreeMap::insert(self, key, value)
+    }
+
+    fn remove(&mut self, key: &K) -> Option<V> {
+        BTreeMap::remove(self, key)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &K> {
+        BTreeMap::keys(self)
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V, S> MapLike for indexmap::IndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    S: BuildHasher,
+{
+    type Key = K;
+    type Value = V;
+
+    fn get(&self, key: &K) -> Option<&V> {
+        indexmap::IndexMap::get(self, key)
+    }
+
+    fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+        indexmap::IndexMap::get_mut(self, key)
+    }
+
+    fn insert(&mut self, key: K, value: V) -> Option<V> {
+        indexmap::IndexMap::insert(self, key, value)
+    }
+
+    // removing by shifting keeps the order of the remaining keys
+    fn remove(&mut self, key: &K) -> Option<V> {
+        indexmap::IndexMap::shift_remove(self, key)
+    }
+
+    fn keys(&self) -> impl Iterator<Item = &K> {
+        indexmap::IndexMap::keys(self)
+    }
+}
+
+/// Provides access to the value for some key in a map.
+#[derive(Debug)]
+pub struct AtKey<Inner, Prev>
+where
+    Prev: MapLike,
+{
+    #[cfg(any(debug_assertions, leptos_debuginfo))]
+    defined_at: &'static Location<'static>,
+    inner: Inner,
+    key: Prev::Key,
+}
+
+impl<Inner, Prev> Clone for AtKey<Inner, Prev>
+where
+    Inner: Clone,
+    Prev: MapLike,
+    Prev::Key: Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: self.defined_at,
+            inner: self.inner.clone(),
+            key: self.key.clone(),
+        }
+    }
+}
+
+impl<Inner, Prev> Copy for AtKey<Inner, Prev>
+where
+    Inner: Copy,
+    Prev: MapLike,
+    Prev::Key: Copy,
+{
+}
+
+impl<Inner, Prev> AtKey<Inner, Prev>
+where
+    Prev: MapLike,
+{
+    /// Creates a new accessor for the inner map at the given key.
+    #[track_caller]
+    pub fn new(inner: Inner, key: Prev::Key) -> Self {
+        Self {
+            #[cfg(any(debug_assertions, leptos_debuginfo))]
+            defined_at: Location::caller(),
+            inner,
+            key,
+        }
+    }
+
+    /// The key this field accesses.
+    pub fn key(&self) -> &Prev::Key {
+        &self.key
+    }
+}
+
+impl<Inner, Prev> StoreField for AtKey<Inner, Prev>
+where
+    Inner: StoreField<Value = Prev>,
+    Prev: MapLike + 'static,
+    Prev::Key: Hash + Clone + 'static,
+{
+    type Value = Prev::Value;
+    type Reader = MappedMutArc<Inner::Reader, Prev::Value>;
+    type Writer =
+        WriteGuard<Vec<ArcTrigger>, MappedMutArc<Inner::Writer, Prev::Value>>;
+
+    fn path(&self) -> impl IntoIterator<Item = StorePathSegment> {
+        self.inner
+            .path()
+            .into_iter()
+            .chain(iter::once(key_segment(&self.key)))
+    }
+
+    fn get_trigger(&self, path: StorePath) -> StoreFieldTrigger {
+        self.inner.get_trigger(path)
+    }
+
+    fn reader(&self) -> Option<Self::Reader> {
+        let inner = self.inner.reader()?;
+        inner.get(&self.key)?;
+        let (key, key_mut) = (self.key.clone(), self.key.clone());
+        Some(MappedMutArc::new(
+            inner,
+            move |n| n.get(&key).expect("key was checked above"),
+            move |n| n.get_mut(&key_mut).expect("key was checked above"),
+        ))
+    }
+
+    fn writer(&self) -> Option<Self::Writer> {
+        let mut inner = self.inner.writer()?;
+        inner.get(&self.key)?;
+
+        // writing to one entry should not notify the map itself, which would also notify
+        // every other entry
+        inner.untrack();
+        let triggers = self.triggers_for_current_path();
+        let (key, key_mut) = (self.key.clone(), self.key.clone());
+        Some(WriteGuard::new(
+            triggers,
+            MappedMutArc::new(
+                inner,
+                move |n| n.get(&key).expect("key was checked above"),
+                move |n| n.get_mut(&key_mut).expect("key was checked above"),
+            ),
+        ))
+    }
+
+    #[inline(always)]
+    fn keys(&self) -> Option<KeyMap> {
+        self.inner.keys()
+    }
+
+    #[track_caller]
+    fn track_field(&self) {
+        let mut full_path = self.path().into_iter().collect::<StorePath>();
+        let trigger = self.get_trigger(full_path.clone());
+        trigger.this.track();
+        trigger.children.track();
+
+        // tracks changes made directly to the map or any of its ancestors, such as
+        // replacing the whole map, but not changes to other entries
+        while !full_path.is_empty() {
+            full_path.pop();
+            let inner = self.get_trigger(full_path.clone());
+            inner.this.track();
+        }
+    }
+}
+
+impl<Inner, Prev> DefinedAt for AtKey<Inner, Prev>
+where
+    Prev: MapLike,
+{
+    fn defined_at(&self) -> Option<&'static Location<'static>> {
+        #[cfg(any(debug_assertions, leptos_debuginfo))]
+        {
+            Some(self.defined_at)
+        }
+        #[cfg(not(any(debug_assertions, leptos_debuginfo)))]
+        {
+            None
+        }
+    }
+}
+
+impl<Inner, Prev> IsDisposed for AtKey<Inner, Prev>
+where
+    Inner: IsDisposed,
+    Prev: MapLike,
+{
+    fn is_disposed(&self) -> bool {
+        self.inner.is_disposed()
+    }
+}
+
+impl<Inner, Prev> Notify for AtKey<Inner, Prev>
+where
+    Inner: StoreField<Value = Prev>,
+    Prev: MapLike + 'static,
+    Prev::Key: Hash + Clone + 'static,
+{
+    fn notify(&self) {
+        let trigger = self.get_trigger(self.path().into_iter().collect());
+        trigger.this.notify();
+        trigger.children.notify();
+    }
+}
+
+impl<Inner, Prev> Track for AtKey<Inner, Prev>
+where
+    Inner: StoreField<Value = Prev>,
+    Prev: MapLike + 'static,
+    Prev::Key: Hash + Clone + 'static,
+{
+    fn track(&self) {
+        self.track_field();
+    }
+}
+
+impl<Inner, Prev> ReadUntracked for AtKey<Inner, Prev>
+where
+    Inner: StoreField<Value = Prev>,
+    Prev: MapLike + 'static,
+    Prev::Key: Hash + Clone + 'static,
+{
+    type Value = <Self as StoreField>::Reader;
+
+    fn try_read_untracked(&self) -> Option<Self::Value> {
+        self.reader()
+    }
+}
+
+impl<Inner, Prev> Write for AtKey<Inner, Prev>
+where
+    Inner: StoreField<Value = Prev>,
+    Prev: MapLike + 'static,
+    Prev::Key: Hash + Clone + 'static,
+    Prev::Value: 'static,
+{
+    type Value = Prev::Value;
+
+    fn try_write(&self) -> Option<impl UntrackableGuard<Target = Self::Value>> {
+        self.writer()
+    }
+
+    fn try_write_untracked(
+        &self,
+    ) -> Option<impl DerefMut<Target = Self::Value>> {
+        self.writer().map(|mut writer| {
+            writer.untrack();
+            writer
+        })
+    }
+}
+
+/// Provides keyed reactive access to the entries of a map.
+///
+/// Each entry has its own trigger, so inserting, removing, or updating the value for one key
+/// does not notify fields that only read other keys. Reading the keys or length of the map
+/// reactively only tracks changes to the set of keys, not changes to the values.
+///
+/// Entries are told apart by a 64-bit hash of their key (32 bits on 32-bit targets, such as
+/// `wasm32`). In the unlikely case that two keys of a map collide, they share a trigger, and a
+/// change to either one notifies the readers of both.
+///
+/// The methods are named `at_key` and `map_keys` rather than `get` and `keys`, because every
+/// field already has a [`get`](reactive_graph::traits::Get::get) method that reads its value,
+/// and a [`keys`](StoreField::keys) method from [`StoreField`].
+///
+/// ```rust
+/// use reactive_graph::traits::{Get, Update};
+/// use reactive_stores::{Store, StoreFieldMap};
+/// use std::collections::HashMap;
+///
+/// #[derive(Store)]
+/// struct Scores {
+///     by_player: HashMap<String, u32>,
+/// }
+///
+/// let store = Store::new(Scores {
+///     by_player: HashMap::new(),
+/// });
+/// store.by_player().insert("alice".into(), 1);
+/// store.by_player().at_key("alice".into()).update(|score| *score += 1);
+///
+/// assert_eq!(store.by_player().at_key("alice".into()).get(), 2);
+/// assert_eq!(store.by_player().at_key("bob".into()).try_get(), None);
+/// assert_eq!(store.by_player().len(), 1);
+/// ```
+pub trait StoreFieldMap<Prev>
+where
+    Self: StoreField<Value = Prev>,
+    Prev: MapLike,
+{
+    /// Reactive access to the value for some key.
+    ///
+    /// The field can be created for a key that is not in the map yet: reading it returns `None`,
+    /// and it will be notified when a value is inserted for that key.
+    #[doc(alias = "get")]
+    fn at_key(self, key: Prev::Key) -> AtKey<Self, Prev>;
+
+    /// Inserts a value for the key, notifying only that entry, and returns the previous value.
+    fn insert(&self, key: Prev::Key, value: Prev::Value)
+        -> Option<Prev::Value>;
+
+    /// Removes the value for the key, notifying only that entry, and returns it.
+    fn remove(&self, key: &Prev::Key) -> Option<Prev::Value>;
+
+    /// Reactively returns whether the map contains the key.
+    fn contains_key(&self, key: &Prev::Key) -> bool;
+
+    /// Reactively returns the keys of the map, in the map's own order.
+    #[doc(alias = "keys")]
+    fn map_keys(&self) -> Vec<Prev::Key>;
+
+    /// Reactively returns the number of entries in the map.
+    fn len(&self) -> usize;
+
+    /// Reactively returns `true` if the map has no entries.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<Inner, Prev> StoreFieldMap<Prev> for Inner
+where
+    Inner: StoreField<Value = Prev> + Clone,
+    Prev: MapLike,
+    Prev::Key: Hash + Clone,
+{
+    #[track_caller]
+    fn at_key(self, key: Prev::Key) -> AtKey<Inner, Prev> {
+        AtKey::new(self, key)
+    }
+
+    fn insert(
+        &self,
+        key: Prev::Key,
+        value: Prev::Value,
+    ) -> Option<Prev::Value> {
+        let path = self.path().into_iter().collect::<StorePath>();
+        let mut entry_path = path.clone();
+        entry_path.push(key_segment(&key));
+
+        let mut writer = self.writer()?;
+        // don't notify the whole map
+        writer.untrack();
+        let prev = writer.insert(key, value);
+        drop(writer);
+
+        if prev.is_none() {
+            self.get_trigger(keys_path(path)).this.notify();
+        }
+        self.triggers_for_path(entry_path).notify();
+        prev
+    }
+
+    fn remove(&self, key: &Prev::Key) -> Option<Prev::Value> {
+        let path = self.path().into_iter().collect::<StorePath>();
+        let mut entry_path = path.clone();
+        entry_path.push(key_segment(key));
+
+        let mut writer = self.writer()?;
+        // don't notify the whole map
+        writer.untrack();
+        let prev = writer.remove(key);
+        drop(writer);
+
+        if prev.is_some() {
+            self.get_trigger(keys_path(path)).this.notify();
+            self.triggers_for_path(entry_path).notify();
+        }
+        prev
+    }
+
+    fn contains_key(&self, key: &Prev::Key) -> bool {
+        track_map_keys(self);
+        self.reader().is_some_and(|map| map.get(key).is_some())
+    }
+
+    fn map_keys(&self) -> Vec<Prev::Key> {
+        track_map_keys(self);
+        self.reader()
+            .map(|map| map.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn len(&self) -> usize {
+        track_map_keys(self);
+        self.reader().map(|map| map.len()).unwrap_or(0)
+    }
+}
+
+/// Tracks the set of keys of a map field, as well as direct changes to the map or any of its
+/// ancestors, without tracking changes to individual entries.
+fn track_map_keys(field: &impl StoreField) {
+    let mut path = field.path().into_iter().collect::<StorePath>();
+    field.get_trigger(keys_path(path.clone())).this.track();
+    loop {
+        field.get_trigger(path.clone()).this.track();
+        if path.pop().is_none() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{self as reactive_stores, Patch as _, Store, StoreFieldMap};
+    use reactive_graph::{
+        effect::Effect,
+        traits::{Read, ReadUntracked, Set, Update},
+    };
+    use reactive_stores_macro::Patch;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    pub async fn tick() {
+        tokio::time::sleep(std::time::Duration::from_micros(1)).await;
+    }
+
+    #[derive(Debug, Store, Patch, Default)]
+    struct Inventory {
+        stock: HashMap<String, u32>,
+        prices: BTreeMap<u32, String>,
+    }
+
+    fn data() -> Inventory {
+        Inventory {
+            stock: HashMap::from([("apples".into(), 3), ("pears".into(), 5)]),
+            prices: BTreeMap::from([(1, "cheap".into()), (10, "dear".into())]),
+        }
+    }
+
+    fn count_runs(f: impl Fn() + Send + Sync + 'static) -> Arc<AtomicUsize> {
+        let count = Arc::new(AtomicUsize::new(0));
+        Effect::new_sync({
+            let count = Arc::clone(&count);
+            move |_| {
+                f();
+                count.fetch_add(1, Ordering::Relaxed);
+            }
+        });
+        count
+    }
+
+    #[tokio::test]
+    async fn entries_are_tracked_separately() {
+        _ = any_spawner::Executor::init_tokio();
+
+        let store = Store::new(data());
+        let apples = count_runs(move || {
+            _ = store.stock().at_key("apples".into()).try_read();
+        });
+        let pears = count_runs(move || {
+            _ = store.stock().at_key("pears".into()).try_read();
+        });
+        let plums = count_runs(move || {
+            _ = store.stock().at_key("plums".into()).try_read();
+        });
+        let len = count_runs(move || {
+            _ = store.stock().len();
+        });
+        tick().await;
+
+        store.stock().at_key("apples".into()).update(|n| *n += 1);
+        tick().await;
+        assert_eq!(*store.stock().at_key("apples".into()).read_untracked(), 4);
+        assert_eq!(apples.load(Ordering::Relaxed), 2);
+        assert_eq!(pears.load(Ordering::Relaxed), 1);
+        assert_eq!(len.load(Ordering::Relaxed), 1);
+
+        // inserting a new key notifies that key and the length, but not other entries
+        assert_eq!(store.stock().insert("plums".into(), 7), None);
+        tick().await;
+        assert_eq!(plums.load(Ordering::Relaxed), 2);
+        assert_eq!(len.load(Ordering::Relaxed), 2);
+        assert_eq!(apples.load(Ordering::Relaxed), 2);
+
+        // replacing an existing value does not change the keys
+        assert_eq!(store.stock().insert("plums".into(), 8), Some(7));
+        tick().await;
+        assert_eq!(plums.load(Ordering::Relaxed), 3);
+        assert_eq!(len.load(Ordering::Relaxed), 2);
+
+        assert_eq!(store.stock().remove(&"pears".into()), Some(5));
+        assert_eq!(store.stock().remove(&"pears".into()), None);
+        tick().await;
+        assert_eq!(pears.load(Ordering::Relaxed), 2);
+        assert_eq!(len.load(Ordering::Relaxed), 3);
+        assert_eq!(apples.load(Ordering::Relaxed), 2);
+        assert_eq!(store.stock().at_key("pears".into()).try_read(), None);
+
+        // replacing the whole map notifies every entry
+        store.stock().set(HashMap::new());
+        tick().await;
+        assert_eq!(apples.load(Ordering::Relaxed), 3);
+        assert_eq!(pears.load(Ordering::Relaxed), 3);
+        assert_eq!(len.load(Ordering::Relaxed), 4);
+        assert!(store.stock().is_empty());
+    }
+
+    #[tokio::test]
+    async fn patching_maps_notifies_changed_entries() {
+        _ = any_spawner::Executor::init_tokio();
+
+        let store = Store::new(data());
+        let cheap = count_runs(move || {
+            _ = store.prices().at_key(1).read();
+        });
+        let dear = count_runs(move || {
+            _ = store.prices().at_key(10).try_read();
+        });
+        let keys = count_runs(move || {
+            _ = store.prices().map_keys();
+        });
+        let apples = count_runs(move || {
+            _ = store.stock().at_key("apples".into()).try_read();
+        });
+        tick().await;
+
+        let mut new = data();
+        new.prices.insert(1, "bargain".into());
+        store.patch(new);
+        tick().await;
+        assert_eq!(cheap.load(Ordering::Relaxed), 2);
+        assert_eq!(dear.load(Ordering::Relaxed), 1);
+        assert_eq!(keys.load(Ordering::Relaxed), 1);
+        assert_eq!(apples.load(Ordering::Relaxed), 1);
+
+        let mut new = data();
+        new.prices.insert(1, "bargain".into());
+        new.prices.remove(&10);
+        new.prices.insert(5, "fair".into());
+        store.patch(new);
+        tick().await;
+        assert_eq!(cheap.load(Ordering::Relaxed), 2);
+        assert_eq!(dear.load(Ordering::Relaxed), 2);
+        assert_eq!(keys.load(Ordering::Relaxed), 2);
+        assert_eq!(store.prices().map_keys(), vec![1, 5]);
+        assert!(!store.prices().contains_key(&10));
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[test]
+    fn patching_index_maps_keeps_order() {
+        use crate::PatchField;
+        use indexmap::IndexMap;
+
+        let mut changed = Vec::new();
+        let mut map = IndexMap::from([("a", 1), ("b", 2), ("c", 3)]);
+        map.patch_field(
+            IndexMap::from([("c", 3), ("d", 4), ("a", 1)]),
+            &Default::default(),
+            &mut |path| changed.push(path.clone()),
+        );
+        assert_eq!(map, IndexMap::from([("c", 3), ("d", 4), ("a", 1)]));
+        assert!(map.keys().eq(["c", "d", "a"].iter()));
+        // "b" was removed, "d" was added, and the keys changed
+        assert_eq!(changed.len(), 3);
+    }
+}
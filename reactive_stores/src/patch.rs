@@ -1,8 +1,15 @@
-use crate::{path::StorePath, StoreField};
+use crate::{
+    changes,
+    map::{key_segment, keys_path},
+    path::StorePath,
+    StoreField,
+};
 use itertools::{EitherOrBoth, Itertools};
 use reactive_graph::traits::{Notify, UntrackableGuard};
 use std::{
     borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
     num::{
         NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8,
@@ -34,10 +41,16 @@ where
         if let Some(mut writer) = self.writer() {
             // don't track the writer for the whole store
             writer.untrack();
-            let mut notify = |path: &StorePath| {
-                self.triggers_for_path(path.to_owned()).notify();
-            };
+            let mut changed = Vec::new();
+            let mut notify = |path: &StorePath| changed.push(path.to_owned());
             writer.patch_field(new, &path, &mut notify);
+            // release the lock before notifying, so that subscribers can read the new value
+            drop(writer);
+            changes::batch(|| {
+                for path in changed {
+                    self.triggers_for_path(path).notify();
+                }
+            });
         }
     }
 }
@@ -195,6 +208,121 @@ where
     }
 }
 
+// patches the entries of a map in place: removed and inserted keys notify their own entry and
+// the set of keys, while entries that exist in both maps are patched
+macro_rules! patch_map_entries {
+    ($map:ident, $new:ident, $path:ident, $notify:ident) => {{
+        let mut keys_changed = false;
+        let mut entry_path = $path.to_owned();
+        entry_path.push(0);
+        $map.retain(|key, _| {
+            let keep = $new.contains_key(key);
+            if !keep {
+                entry_path.replace_last(key_segment(key));
+                $notify(&entry_path);
+                keys_changed = true;
+            }
+            keep
+        });
+        for (key, value) in $new {
+            entry_path.replace_last(key_segment(&key));
+            match $map.get_mut(&key) {
+                Some(old) => old.patch_field(value, &entry_path, $notify),
+                None => {
+                    $map.insert(key, value);
+                    $notify(&entry_path);
+                    keys_changed = true;
+                }
+            }
+        }
+        if keys_changed {
+            $notify(&keys_path($path.to_owned()));
+        }
+    }};
+}
+
+impl<K, V, S> PatchField for HashMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: PatchField,
+    S: BuildHasher,
+{
+    fn patch_field(
+        &mut self,
+        new: Self,
+        path: &StorePath,
+        notify: &mut dyn FnMut(&StorePath),
+    ) {
+        patch_map_entries!(self, new, path, notify);
+    }
+}
+
+impl<K, V> PatchField for BTreeMap<K, V>
+where
+    K: Hash + Ord,
+    V: PatchField,
+{
+    fn patch_field(
+        &mut self,
+        new: Self,
+        path: &StorePath,
+        notify: &mut dyn FnMut(&StorePath),
+    ) {
+        patch_map_entries!(self, new, path, notify);
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V, S> PatchField for indexmap::IndexMap<K, V, S>
+where
+    K: Hash + Eq,
+    V: PatchField,
+    S: BuildHasher,
+{
+    fn patch_field(
+        &mut self,
+        new: Self,
+        path: &StorePath,
+        notify: &mut dyn FnMut(&StorePath),
+    ) {
+        let mut keys_changed = false;
+        let mut entry_path = path.to_owned();
+        entry_path.push(0);
+        self.retain(|key, _| {
+            let keep = new.contains_key(key);
+            if !keep {
+                entry_path.replace_last(key_segment(key));
+                notify(&entry_path);
+                keys_changed = true;
+            }
+            keep
+        });
+
+        // only keys that are in the new map are left, so moving each key into its new position
+        // in order leaves the map in the same order as the new one
+        for (idx, (key, value)) in new.into_iter().enumerate() {
+            entry_path.replace_last(key_segment(&key));
+            match self.get_full_mut(&key) {
+                Some((old_idx, _, old)) => {
+                    old.patch_field(value, &entry_path, notify);
+                    if old_idx != idx {
+                        self.move_index(old_idx, idx);
+                        keys_changed = true;
+                    }
+                }
+                None => {
+                    self.shift_insert(idx, key, value);
+                    notify(&entry_path);
+                    keys_changed = true;
+                }
+            }
+        }
+        if keys_changed {
+            notify(&keys_path(path.to_owned()));
+        }
+    }
+}
+
 macro_rules! patch_tuple {
 	($($ty:ident),*) => {
 		impl<$($ty),*> PatchField for ($($ty,)*)
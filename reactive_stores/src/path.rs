@@ -35,6 +35,16 @@ impl StorePath {
         }
     }
 
+    /// Returns the last segment of the path.
+    pub(crate) fn last(&self) -> Option<&StorePathSegment> {
+        self.0.last()
+    }
+
+    /// Returns the segments of the path.
+    pub(crate) fn segments(&self) -> &[StorePathSegment] {
+        &self.0
+    }
+
     /// Returns `true` if the path contains no elements.
     pub fn is_empty(&self) -> bool {
         self.0.is_empty()
@@ -0,0 +1,1091 @@
+use crate::{
+    changes,
+    map::{is_keys_path, key_segment},
+    path::{StorePath, StorePathSegment},
+    PatchField, StoreField,
+};
+use or_poisoned::OrPoisoned;
+use reactive_graph::{
+    graph::{
+        AnySource, AnySubscriber, ReactiveNode, Source, Subscriber, ToAnySource,
+    },
+    signal::{ArcReadSignal, ArcRwSignal},
+    traits::{GetUntracked, Notify, Set, UntrackableGuard},
+};
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, VecDeque},
+    fmt::Debug,
+    hash::{BuildHasher, Hash},
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    num::{
+        NonZeroI128, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroI8,
+        NonZeroIsize, NonZeroU128, NonZeroU16, NonZeroU32, NonZeroU64,
+        NonZeroU8, NonZeroUsize,
+    },
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+/// Records changes to a store, so that they can be undone and redone.
+///
+/// Each undo step holds the previous values of only the fields that changed. Undoing or
+/// redoing a step swaps those values with the ones in the store, and notifies only the fields
+/// that changed.
+///
+/// ```rust
+/// use reactive_graph::traits::{GetUntracked, ReadUntracked, Set};
+/// use reactive_stores::{Patch, Store, UndoField, UndoStack};
+///
+/// #[derive(Debug, Clone, Store, Patch, UndoField)]
+/// struct Document {
+///     title: String,
+///     body: String,
+/// }
+///
+/// let doc = Store::new(Document {
+///     title: "Untitled".into(),
+///     body: String::new(),
+/// });
+/// let history = UndoStack::new(doc);
+/// let can_undo = history.can_undo();
+///
+/// // several writes can be undone as a single step
+/// history.transaction(|| {
+///     doc.title().set("Notes".into());
+///     doc.body().set("Hello".into());
+/// });
+/// assert!(can_undo.get_untracked());
+///
+/// history.undo();
+/// assert_eq!(doc.title().read_untracked().as_str(), "Untitled");
+/// assert!(doc.body().read_untracked().is_empty());
+/// assert!(!can_undo.get_untracked());
+///
+/// history.redo();
+/// assert_eq!(doc.body().read_untracked().as_str(), "Hello");
+/// ```
+///
+/// The stack only sees writes to the field it was created with and that field's descendants.
+/// Setting a parent of the field as a whole is not recorded.
+pub struct UndoStack<T> {
+    state: Arc<UndoState<T>>,
+}
+
+impl<T> Clone for UndoStack<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl<T> Debug for UndoStack<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let inner = self.state.inner.lock().or_poisoned();
+        f.debug_struct("UndoStack")
+            .field("undo", &inner.undo.len())
+            .field("redo", &inner.redo.len())
+            .finish()
+    }
+}
+
+impl<T> UndoStack<T>
+where
+    T: UndoField + Clone,
+{
+    /// Starts recording changes to the given store or field.
+    ///
+    /// By default, every change is its own step and the number of steps is unlimited.
+    pub fn new<F>(store: F) -> Self
+    where
+        F: StoreField<Value = T> + Send + Sync + 'static,
+    {
+        let path = store.path().into_iter().collect::<StorePath>();
+        let source = store.get_trigger(path.clone()).children.to_any_source();
+        let current = store.reader().map(|value| value.clone());
+        let state = Arc::new_cyclic(|this: &Weak<UndoState<T>>| UndoState {
+            store: Box::new(store),
+            path,
+            source,
+            subscriber: AnySubscriber(
+                this.as_ptr() as usize,
+                Weak::clone(this) as Weak<dyn Subscriber + Send + Sync>,
+            ),
+            applying: AtomicBool::new(false),
+            inner: Mutex::new(UndoInner {
+                current,
+                undo: VecDeque::new(),
+                redo: Vec::new(),
+                capacity: None,
+                coalesce: None,
+                last_change: None,
+                open: false,
+                transactions: 0,
+            }),
+            can_undo: ArcRwSignal::new(false),
+            can_redo: ArcRwSignal::new(false),
+        });
+        state.source.add_subscriber(state.subscriber.clone());
+        Self { state }
+    }
+
+    /// Keeps at most `capacity` undo steps, discarding the oldest ones.
+    pub fn with_capacity(self, capacity: usize) -> Self {
+        let mut inner = self.state.inner.lock().or_poisoned();
+        inner.capacity = Some(capacity);
+        inner.truncate();
+        drop(inner);
+        self.state.update_signals();
+        self
+    }
+
+    /// Merges a change into the previous step if it changes the same fields, and happens
+    /// within `window` of the previous change.
+    ///
+    /// This turns a burst of edits, like typing into a text field, into a single step.
+    pub fn with_coalescing(self, window: Duration) -> Self {
+        self.state.inner.lock().or_poisoned().coalesce = Some(window);
+        self
+    }
+
+    /// Runs `f`, recording all the changes it makes as a single step.
+    ///
+    /// Transactions can be nested; the step is recorded when the outermost one ends.
+    ///
+    /// If `f` panics, the changes it made before panicking are recorded as a step.
+    pub fn transaction<U>(&self, f: impl FnOnce() -> U) -> U {
+        struct Transaction<'a, T>(&'a UndoState<T>)
+        where
+            T: UndoField + Clone;
+
+        impl<T> Drop for Transaction<'_, T>
+        where
+            T: UndoField + Clone,
+        {
+            fn drop(&mut self) {
+                let mut inner = self.0.inner.lock().or_poisoned();
+                inner.transactions -= 1;
+                let done = inner.transactions == 0;
+                drop(inner);
+                if done {
+                    self.0.record(false);
+                }
+            }
+        }
+
+        self.state.inner.lock().or_poisoned().transactions += 1;
+        let _transaction = Transaction(&*self.state);
+        f()
+    }
+
+    /// Reverts the most recent step. Returns `false` if there was nothing to undo.
+    pub fn undo(&self) -> bool {
+        let mut inner = self.state.inner.lock().or_poisoned();
+        if inner.current.is_none() {
+            return false;
+        }
+        let Some(step) = inner.undo.pop_back() else {
+            return false;
+        };
+        inner.open = false;
+        drop(inner);
+        // swapping the values back leaves the step holding the values it replaced
+        let step = self.state.apply(step);
+        self.state.inner.lock().or_poisoned().redo.push(step);
+        self.state.update_signals();
+        true
+    }
+
+    /// Reapplies the most recently undone step. Returns `false` if there was nothing to redo.
+    pub fn redo(&self) -> bool {
+        let mut inner = self.state.inner.lock().or_poisoned();
+        if inner.current.is_none() {
+            return false;
+        }
+        let Some(step) = inner.redo.pop() else {
+            return false;
+        };
+        inner.open = false;
+        drop(inner);
+        let step = self.state.apply(step);
+        self.state.inner.lock().or_poisoned().undo.push_back(step);
+        self.state.update_signals();
+        true
+    }
+
+    /// Whether there is a step to undo.
+    ///
+    /// The returned signal updates whenever this changes.
+    pub fn can_undo(&self) -> ArcReadSignal<bool> {
+        self.state.can_undo.read_only()
+    }
+
+    /// Whether there is a step to redo.
+    ///
+    /// The returned signal updates whenever this changes.
+    pub fn can_redo(&self) -> ArcReadSignal<bool> {
+        self.state.can_redo.read_only()
+    }
+
+    /// Discards all undo and redo steps.
+    pub fn clear(&self) {
+        let mut inner = self.state.inner.lock().or_poisoned();
+        inner.undo.clear();
+        inner.redo.clear();
+        inner.open = false;
+        drop(inner);
+        self.state.update_signals();
+    }
+}
+
+/// Allows an [`UndoStack`] to keep only the fields of a value that changed.
+///
+/// This can be derived with [`#[derive(UndoField)]`](macro@crate::UndoField) for a struct that
+/// also derives [`Patch`](macro@crate::Patch). Each field must implement `UndoField`, unless it
+/// is patched with a `#[patch(|this, new| ...)]` closure, in which case it is always kept whole.
+pub trait UndoField: PatchField + Send + Sync + 'static {
+    /// Moves the fields at `paths` out of the value, passing each one to `take` along with the
+    /// index that was given with its path.
+    ///
+    /// Each path is relative to this field, and no path is a prefix of another.
+    fn take_fields(
+        self,
+        paths: &[(usize, &[StorePathSegment])],
+        take: &mut dyn FnMut(usize, FieldValue),
+    );
+
+    /// Swaps the field at `path` with `value`, if it exists.
+    fn swap_field(&mut self, path: &[StorePathSegment], value: &mut FieldValue);
+}
+
+/// The value of a field that has been taken by [`UndoField::take_fields`].
+pub struct FieldValue(Box<dyn Any + Send + Sync>);
+
+impl FieldValue {
+    /// Wraps the value of a field.
+    pub fn new<T>(value: T) -> Self
+    where
+        T: Send + Sync + 'static,
+    {
+        Self(Box::new(value))
+    }
+
+    /// Swaps the wrapped value with `field`. Returns `false` if they have different types.
+    pub fn swap<T>(&mut self, field: &mut T) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        match self.0.downcast_mut::<T>() {
+            Some(value) => {
+                mem::swap(value, field);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Debug for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FieldValue").finish_non_exhaustive()
+    }
+}
+
+/// Takes the whole value if one of `paths` is empty, and otherwise returns it so that its
+/// fields can be taken.
+fn take_whole_field<T>(
+    value: T,
+    paths: &[(usize, &[StorePathSegment])],
+    take: &mut dyn FnMut(usize, FieldValue),
+) -> Option<T>
+where
+    T: Send + Sync + 'static,
+{
+    match paths.iter().find(|(_, path)| path.is_empty()) {
+        Some((idx, _)) => {
+            take(*idx, FieldValue::new(value));
+            None
+        }
+        None => Some(value),
+    }
+}
+
+/// Returns the paths that lead into the child field with this segment, relative to that field.
+fn child_field_paths<'a>(
+    paths: &[(usize, &'a [StorePathSegment])],
+    segment: StorePathSegment,
+) -> Vec<(usize, &'a [StorePathSegment])> {
+    paths
+        .iter()
+        .filter_map(|(idx, path)| match path.split_first() {
+            Some((first, rest)) if *first == segment => Some((*idx, rest)),
+            _ => None,
+        })
+        .collect()
+}
+
+macro_rules! undo_primitives {
+    ($($ty:ty),*) => {
+        $(impl UndoField for $ty {
+            fn take_fields(
+                self,
+                paths: &[(usize, &[StorePathSegment])],
+                take: &mut dyn FnMut(usize, FieldValue),
+            ) {
+                take_whole_field(self, paths, take);
+            }
+
+            fn swap_field(
+                &mut self,
+                path: &[StorePathSegment],
+                value: &mut FieldValue,
+            ) {
+                if path.is_empty() {
+                    value.swap(self);
+                }
+            }
+        })*
+    };
+}
+
+undo_primitives! {
+    (),
+    &'static str,
+    String,
+    Arc<str>,
+    Cow<'static, str>,
+    usize,
+    u8,
+    u16,
+    u32,
+    u64,
+    u128,
+    isize,
+    i8,
+    i16,
+    i32,
+    i64,
+    i128,
+    f32,
+    f64,
+    char,
+    bool,
+    IpAddr,
+    SocketAddr,
+    SocketAddrV4,
+    SocketAddrV6,
+    Ipv4Addr,
+    Ipv6Addr,
+    NonZeroI8,
+    NonZeroU8,
+    NonZeroI16,
+    NonZeroU16,
+    NonZeroI32,
+    NonZeroU32,
+    NonZeroI64,
+    NonZeroU64,
+    NonZeroI128,
+    NonZeroU128,
+    NonZeroIsize,
+    NonZeroUsize
+}
+
+impl<T> UndoField for Option<T>
+where
+    T: UndoField,
+{
+    fn take_fields(
+        self,
+        paths: &[(usize, &[StorePathSegment])],
+        take: &mut dyn FnMut(usize, FieldValue),
+    ) {
+        if let Some(Some(inner)) = take_whole_field(self, paths, take) {
+            inner.take_fields(&child_field_paths(paths, 0.into()), take);
+        }
+    }
+
+    fn swap_field(
+        &mut self,
+        path: &[StorePathSegment],
+        value: &mut FieldValue,
+    ) {
+        match (path.split_first(), self) {
+            (None, this) => {
+                value.swap(this);
+            }
+            (Some((first, rest)), Some(inner)) if first.0 == 0 => {
+                inner.swap_field(rest, value);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<T> UndoField for Vec<T>
+where
+    T: UndoField,
+{
+    fn take_fields(
+        self,
+        paths: &[(usize, &[StorePathSegment])],
+        take: &mut dyn FnMut(usize, FieldValue),
+    ) {
+        if let Some(items) = take_whole_field(self, paths, take) {
+            for (idx, item) in items.into_iter().enumerate() {
+                let child_paths = child_field_paths(paths, idx.into());
+                if !child_paths.is_empty() {
+                    item.take_fields(&child_paths, take);
+                }
+            }
+        }
+    }
+
+    fn swap_field(
+        &mut self,
+        path: &[StorePathSegment],
+        value: &mut FieldValue,
+    ) {
+        match path.split_first() {
+            None => {
+                value.swap(self);
+            }
+            Some((first, rest)) => {
+                if let Some(item) = self.get_mut(first.0) {
+                    item.swap_field(rest, value);
+                }
+            }
+        }
+    }
+}
+
+// entries are identified by the segment for their key, in the same way they are patched
+macro_rules! undo_map_entries {
+    () => {
+        fn take_fields(
+            self,
+            paths: &[(usize, &[StorePathSegment])],
+            take: &mut dyn FnMut(usize, FieldValue),
+        ) {
+            if let Some(map) = take_whole_field(self, paths, take) {
+                for (key, value) in map {
+                    let child_paths =
+                        child_field_paths(paths, key_segment(&key));
+                    if !child_paths.is_empty() {
+                        value.take_fields(&child_paths, take);
+                    }
+                }
+            }
+        }
+
+        fn swap_field(
+            &mut self,
+            path: &[StorePathSegment],
+            value: &mut FieldValue,
+        ) {
+            match path.split_first() {
+                None => {
+                    value.swap(self);
+                }
+                Some((first, rest)) => {
+                    if let Some((_, entry)) = self
+                        .iter_mut()
+                        .find(|(key, _)| key_segment(*key) == *first)
+                    {
+                        entry.swap_field(rest, value);
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl<K, V, S> UndoField for HashMap<K, V, S>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: UndoField,
+    S: BuildHasher + Send + Sync + 'static,
+{
+    undo_map_entries!();
+}
+
+impl<K, V> UndoField for BTreeMap<K, V>
+where
+    K: Hash + Ord + Send + Sync + 'static,
+    V: UndoField,
+{
+    undo_map_entries!();
+}
+
+#[cfg(feature = "indexmap")]
+impl<K, V, S> UndoField for indexmap::IndexMap<K, V, S>
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: UndoField,
+    S: BuildHasher + Send + Sync + 'static,
+{
+    undo_map_entries!();
+}
+
+macro_rules! undo_tuple {
+	($($ty:ident),*) => {
+		impl<$($ty),*> UndoField for ($($ty,)*)
+		where
+			$($ty: UndoField),*,
+		{
+            fn take_fields(
+                self,
+                paths: &[(usize, &[StorePathSegment])],
+                take: &mut dyn FnMut(usize, FieldValue),
+            ) {
+                if let Some(this) = take_whole_field(self, paths, take) {
+                    let mut idx = 0;
+                    paste::paste! {
+                        #[allow(non_snake_case)]
+                        let ($($ty,)*) = this;
+                        $(
+                            let child_paths = child_field_paths(paths, idx.into());
+                            if !child_paths.is_empty() {
+                                $ty.take_fields(&child_paths, take);
+                            }
+                            idx += 1;
+                        )*
+                    }
+                    _ = idx;
+                }
+            }
+
+            fn swap_field(
+                &mut self,
+                path: &[StorePathSegment],
+                value: &mut FieldValue,
+            ) {
+                let Some((first, rest)) = path.split_first() else {
+                    value.swap(self);
+                    return;
+                };
+                let mut idx = 0;
+                paste::paste! {
+                    #[allow(non_snake_case)]
+                    let ($($ty,)*) = self;
+                    $(
+                        if first.0 == idx {
+                            $ty.swap_field(rest, value);
+                        }
+                        idx += 1;
+                    )*
+                }
+                _ = idx;
+            }
+        }
+    }
+}
+
+undo_tuple!(A);
+undo_tuple!(A, B);
+undo_tuple!(A, B, C);
+undo_tuple!(A, B, C, D);
+undo_tuple!(A, B, C, D, E);
+undo_tuple!(A, B, C, D, E, F);
+undo_tuple!(A, B, C, D, E, F, G);
+undo_tuple!(A, B, C, D, E, F, G, H);
+undo_tuple!(A, B, C, D, E, F, G, H, I);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U);
+undo_tuple!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V);
+undo_tuple!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W
+);
+undo_tuple!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X
+);
+undo_tuple!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y
+);
+undo_tuple!(
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y,
+    Z
+);
+
+/// Provides access to the store or field an [`UndoStack`] records.
+trait UndoTarget<T>: Send + Sync {
+    fn current(&self) -> Option<T>;
+
+    /// Swaps the values of the fields with the ones in the store, and notifies the fields at
+    /// `paths`.
+    fn swap(&self, fields: &mut [(StorePath, FieldValue)], paths: &[StorePath]);
+}
+
+impl<F, T> UndoTarget<T> for F
+where
+    F: StoreField<Value = T> + Send + Sync,
+    T: UndoField + Clone,
+{
+    fn current(&self) -> Option<T> {
+        self.reader().map(|value| value.clone())
+    }
+
+    fn swap(
+        &self,
+        fields: &mut [(StorePath, FieldValue)],
+        paths: &[StorePath],
+    ) {
+        if let Some(mut writer) = self.writer() {
+            // don't track the writer for the whole store
+            writer.untrack();
+            for (path, value) in fields {
+                writer.swap_field(path.segments(), value);
+            }
+            // release the lock before notifying, so that subscribers can read the new value
+            drop(writer);
+            changes::batch(|| {
+                for path in paths {
+                    self.triggers_for_path(path.clone()).notify();
+                }
+            });
+        }
+    }
+}
+
+struct Step {
+    /// The values to restore, with the paths of their fields relative to the recorded field.
+    fields: Vec<(StorePath, FieldValue)>,
+    /// The fields that differ between the restored value and the one it replaces.
+    paths: Vec<StorePath>,
+}
+
+struct UndoInner<T> {
+    /// The last known value of the store, which is `None` if it has been disposed.
+    ///
+    /// Changes are only seen after they have been made, so this is where the previous values
+    /// of the fields that changed are taken from.
+    current: Option<T>,
+    undo: VecDeque<Step>,
+    redo: Vec<Step>,
+    capacity: Option<usize>,
+    coalesce: Option<Duration>,
+    last_change: Option<Duration>,
+    /// Whether the most recent undo step can still absorb further changes.
+    open: bool,
+    transactions: usize,
+}
+
+impl<T> UndoInner<T> {
+    fn truncate(&mut self) {
+        if let Some(capacity) = self.capacity {
+            while self.undo.len() > capacity {
+                self.undo.pop_front();
+            }
+        }
+    }
+}
+
+struct UndoState<T> {
+    store: Box<dyn UndoTarget<T>>,
+    path: StorePath,
+    source: AnySource,
+    subscriber: AnySubscriber,
+    applying: AtomicBool,
+    inner: Mutex<UndoInner<T>>,
+    can_undo: ArcRwSignal<bool>,
+    can_redo: ArcRwSignal<bool>,
+}
+
+impl<T> UndoState<T>
+where
+    T: UndoField + Clone,
+{
+    fn record(&self, coalesce: bool) {
+        let mut inner = self.inner.lock().or_poisoned();
+        if inner.transactions > 0 {
+            return;
+        }
+        let Some(new) = self.store.current() else {
+            return;
+        };
+        let Some(current) = inner.current.as_mut() else {
+            inner.current = Some(new);
+            return;
+        };
+
+        // patching a copy of the last known value gives the paths of the fields that changed
+        let mut next = current.clone();
+        let mut paths = Vec::new();
+        next.patch_field(new, &self.path, &mut |path| {
+            paths.push(path.to_owned())
+        });
+        if paths.is_empty() {
+            return;
+        }
+        let previous = mem::replace(current, next);
+
+        // only keep the previous values of the outermost fields that changed
+        let mut changed = paths
+            .iter()
+            .map(|path| {
+                let mut segments = path.segments();
+                // a change to the set of keys in a map restores the whole map
+                if is_keys_path(path) {
+                    segments = &segments[..segments.len() - 1];
+                }
+                &segments[self.path.len()..]
+            })
+            .collect::<Vec<_>>();
+        changed.sort_by_key(|path| path.len());
+        let mut outermost: Vec<&[StorePathSegment]> = Vec::new();
+        for path in changed {
+            if !outermost.iter().any(|outer| path.starts_with(outer)) {
+                outermost.push(path);
+            }
+        }
+        let mut values = outermost.iter().map(|_| None).collect::<Vec<_>>();
+        previous.take_fields(
+            &outermost.iter().copied().enumerate().collect::<Vec<_>>(),
+            &mut |idx, value| values[idx] = Some(value),
+        );
+        let fields = outermost
+            .into_iter()
+            .zip(values)
+            .filter_map(|(path, value)| Some((path.to_vec().into(), value?)))
+            .collect();
+
+        let now = now();
+        let merge = coalesce
+            && inner.open
+            && inner.coalesce.zip(inner.last_change).is_some_and(
+                |(window, last_change)| {
+                    now.saturating_sub(last_change) <= window
+                },
+            )
+            && inner.undo.back().is_some_and(|step| step.paths == paths);
+        if !merge {
+            inner.undo.push_back(Step { fields, paths });
+            inner.truncate();
+        }
+        inner.redo.clear();
+        inner.last_change = Some(now);
+        inner.open = coalesce;
+        drop(inner);
+        self.update_signals();
+    }
+
+    /// Swaps the values of a step into the store, and returns the step with the values they
+    /// replaced.
+    fn apply(&self, mut step: Step) -> Step {
+        self.applying.store(true, Ordering::Relaxed);
+        self.store.swap(&mut step.fields, &step.paths);
+        self.applying.store(false, Ordering::Relaxed);
+        let current = self.store.current();
+        let mut inner = self.inner.lock().or_poisoned();
+        if current.is_some() {
+            inner.current = current;
+        }
+        step
+    }
+
+    fn update_signals(&self) {
+        let inner = self.inner.lock().or_poisoned();
+        let can_undo = !inner.undo.is_empty();
+        let can_redo = !inner.redo.is_empty();
+        drop(inner);
+        if self.can_undo.get_untracked() != can_undo {
+            self.can_undo.set(can_undo);
+        }
+        if self.can_redo.get_untracked() != can_redo {
+            self.can_redo.set(can_redo);
+        }
+    }
+}
+
+impl<T> Drop for UndoState<T> {
+    fn drop(&mut self) {
+        self.source.remove_subscriber(&self.subscriber);
+    }
+}
+
+impl<T> ReactiveNode for UndoState<T>
+where
+    T: UndoField + Clone,
+{
+    fn mark_dirty(&self) {
+        if !self.applying.load(Ordering::Relaxed) {
+            self.record(true);
+        }
+        // store triggers drop their subscribers when they notify them
+        self.source.add_subscriber(self.subscriber.clone());
+    }
+
+    fn mark_check(&self) {}
+
+    fn mark_subscribers_check(&self) {}
+
+    fn update_if_necessary(&self) -> bool {
+        false
+    }
+}
+
+impl<T> Subscriber for UndoState<T>
+where
+    T: UndoField + Clone,
+{
+    fn add_source(&self, _source: AnySource) {}
+
+    fn clear_sources(&self, _subscriber: &AnySubscriber) {}
+}
+
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+fn now() -> Duration {
+    Duration::from_secs_f64(js_sys::Date::now() / 1000.0)
+}
+
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+fn now() -> Duration {
+    use std::{sync::LazyLock, time::Instant};
+
+    static START: LazyLock<Instant> = LazyLock::new(Instant::now);
+    START.elapsed()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{self as reactive_stores, Patch, Store, UndoField, UndoStack};
+    use reactive_graph::traits::{GetUntracked, ReadUntracked, Set, Update};
+    use std::{
+        collections::HashMap,
+        panic::{self, AssertUnwindSafe},
+        time::Duration,
+    };
+
+    #[derive(Debug, Clone, Store, Patch, UndoField)]
+    struct Form {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    fn form() -> Store<Form> {
+        Store::new(Form {
+            name: String::new(),
+            tags: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn undo_and_redo_each_write() {
+        let form = form();
+        let stack = UndoStack::new(form);
+        let (can_undo, can_redo) = (stack.can_undo(), stack.can_redo());
+
+        form.name().set("a".into());
+        form.tags().update(|tags| tags.push("x".into()));
+        // writes that do not change the value are not recorded
+        form.name().set("a".into());
+        assert!(can_undo.get_untracked());
+        assert!(!can_redo.get_untracked());
+
+        assert!(stack.undo());
+        assert!(form.tags().read_untracked().is_empty());
+        assert_eq!(form.name().read_untracked().as_str(), "a");
+        assert!(can_redo.get_untracked());
+        assert!(stack.undo());
+        assert!(form.name().read_untracked().is_empty());
+        assert!(!stack.undo());
+        assert!(!can_undo.get_untracked());
+
+        assert!(stack.redo());
+        assert_eq!(form.name().read_untracked().as_str(), "a");
+
+        // a new change discards the steps that were undone
+        form.name().set("b".into());
+        assert!(!stack.redo());
+        assert!(!can_redo.get_untracked());
+        assert!(stack.undo());
+        assert_eq!(form.name().read_untracked().as_str(), "a");
+    }
+
+    #[test]
+    fn transactions_are_one_step() {
+        let form = form();
+        let stack = UndoStack::new(form);
+
+        stack.transaction(|| {
+            form.name().set("a".into());
+            stack.transaction(|| form.tags().set(vec!["x".into()]));
+            form.name().set("b".into());
+        });
+        form.name().set("c".into());
+
+        stack.undo();
+        assert_eq!(form.name().read_untracked().as_str(), "b");
+        stack.undo();
+        assert!(form.name().read_untracked().is_empty());
+        assert!(form.tags().read_untracked().is_empty());
+        assert!(!stack.can_undo().get_untracked());
+    }
+
+    #[test]
+    fn transactions_end_when_they_panic() {
+        let form = form();
+        let stack = UndoStack::new(form);
+
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            stack.transaction(|| {
+                form.name().set("a".into());
+                form.tags().set(vec!["x".into()]);
+                panic!("transaction failed");
+            })
+        }));
+        assert!(result.is_err());
+
+        // the changes made before the panic are one step, and later changes are recorded again
+        form.name().set("b".into());
+        stack.undo();
+        assert_eq!(form.name().read_untracked().as_str(), "a");
+        stack.undo();
+        assert!(form.name().read_untracked().is_empty());
+        assert!(form.tags().read_untracked().is_empty());
+        assert!(!stack.can_undo().get_untracked());
+    }
+
+    #[derive(Debug, Clone, Store, Patch, UndoField)]
+    struct Inventory {
+        items: Vec<Item>,
+        counts: HashMap<String, usize>,
+    }
+
+    #[derive(Debug, Clone, Store, Patch, UndoField)]
+    struct Item {
+        name: String,
+        price: usize,
+    }
+
+    #[test]
+    fn steps_restore_only_the_fields_that_changed() {
+        let inventory = Store::new(Inventory {
+            items: vec![Item {
+                name: "a".into(),
+                price: 1,
+            }],
+            counts: HashMap::new(),
+        });
+        let stack = UndoStack::new(inventory);
+
+        inventory.items().update(|items| {
+            items[0].price = 2;
+        });
+        inventory.items().update(|items| {
+            items.push(Item {
+                name: "b".into(),
+                price: 3,
+            })
+        });
+        inventory.counts().update(|counts| {
+            counts.insert("a".into(), 1);
+        });
+        inventory.counts().update(|counts| {
+            *counts.get_mut("a").unwrap() = 2;
+        });
+
+        stack.undo();
+        assert_eq!(inventory.counts().read_untracked()["a"], 1);
+        stack.undo();
+        assert!(inventory.counts().read_untracked().is_empty());
+        assert_eq!(inventory.items().read_untracked().len(), 2);
+        stack.undo();
+        assert_eq!(inventory.items().read_untracked().len(), 1);
+        assert_eq!(inventory.items().read_untracked()[0].price, 2);
+        stack.undo();
+        assert_eq!(inventory.items().read_untracked()[0].price, 1);
+        assert_eq!(inventory.items().read_untracked()[0].name, "a");
+
+        while stack.redo() {}
+        assert_eq!(inventory.items().read_untracked().len(), 2);
+        assert_eq!(inventory.items().read_untracked()[0].price, 2);
+        assert_eq!(inventory.counts().read_untracked()["a"], 2);
+    }
+
+    // does not implement `UndoField`
+    #[derive(Debug, Clone, PartialEq)]
+    struct Color(u8, u8, u8);
+
+    #[derive(Debug, Clone, Store, Patch, UndoField)]
+    struct Theme {
+        #[patch(|this, new| *this = new)]
+        accent: Color,
+        name: String,
+    }
+
+    #[test]
+    fn fields_patched_with_a_closure_are_kept_whole() {
+        let theme = Store::new(Theme {
+            accent: Color(0, 0, 0),
+            name: "dark".into(),
+        });
+        let stack = UndoStack::new(theme);
+
+        theme.accent().set(Color(255, 0, 0));
+        theme.name().set("red".into());
+
+        stack.undo();
+        assert_eq!(theme.name().read_untracked().as_str(), "dark");
+        assert_eq!(*theme.accent().read_untracked(), Color(255, 0, 0));
+        stack.undo();
+        assert_eq!(*theme.accent().read_untracked(), Color(0, 0, 0));
+    }
+
+    #[test]
+    fn coalesces_rapid_edits_to_the_same_field() {
+        let form = form();
+        let stack =
+            UndoStack::new(form).with_coalescing(Duration::from_secs(60));
+
+        for name in ["a", "ab", "abc"] {
+            form.name().set(name.into());
+        }
+        form.tags().set(vec!["x".into()]);
+        form.name().set("abcd".into());
+        form.name().set("abcde".into());
+
+        stack.undo();
+        assert_eq!(form.name().read_untracked().as_str(), "abc");
+        stack.undo();
+        assert!(form.tags().read_untracked().is_empty());
+        stack.undo();
+        assert!(form.name().read_untracked().is_empty());
+        assert!(!stack.undo());
+
+        // an undo ends the step being coalesced
+        stack.redo();
+        form.name().set("z".into());
+        stack.undo();
+        assert_eq!(form.name().read_untracked().as_str(), "abc");
+    }
+
+    #[test]
+    fn drops_oldest_steps() {
+        let form = form();
+        let stack = UndoStack::new(form).with_capacity(2);
+
+        for name in ["a", "b", "c"] {
+            form.name().set(name.into());
+        }
+        assert!(stack.undo());
+        assert!(stack.undo());
+        assert!(!stack.undo());
+        assert_eq!(form.name().read_untracked().as_str(), "a");
+    }
+}
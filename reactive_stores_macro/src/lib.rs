@@ -1,13 +1,13 @@
 use convert_case::{Case, Casing};
 use proc_macro2::{Span, TokenStream};
 use proc_macro_error2::{abort, abort_call_site, proc_macro_error, OptionExt};
-use quote::{quote, ToTokens};
+use quote::{format_ident, quote, ToTokens};
 use syn::{
     parse::{Parse, ParseStream, Parser},
     punctuated::Punctuated,
     token::Comma,
-    ExprClosure, Field, Fields, Generics, Ident, Index, Meta, Result, Token,
-    Type, Variant, Visibility, WhereClause,
+    Expr, ExprClosure, Field, Fields, Generics, Ident, Index, Meta, Result,
+    Token, Type, Variant, Visibility, WhereClause,
 };
 
 #[proc_macro_error]
@@ -26,11 +26,22 @@ pub fn derive_patch(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         .into()
 }
 
+#[proc_macro_error]
+#[proc_macro_derive(UndoField, attributes(patch))]
+pub fn derive_undo_field(
+    input: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    syn::parse_macro_input!(input as UndoModel)
+        .into_token_stream()
+        .into()
+}
+
 struct Model {
     vis: Visibility,
     name: Ident,
     generics: Generics,
     ty: ModelTy,
+    computed: Vec<ComputedField>,
 }
 
 enum ModelTy {
@@ -68,15 +79,77 @@ impl Parse for Model {
             }
         };
 
+        let computed = input
+            .attrs
+            .iter()
+            .filter(|attr| attr.meta.path().is_ident("store"))
+            .flat_map(|attr| match &attr.meta {
+                Meta::List(list) => {
+                    match Punctuated::<ModelMode, Comma>::parse_terminated
+                        .parse2(list.tokens.clone())
+                    {
+                        Ok(modes) => modes,
+                        Err(e) => abort!(list, e),
+                    }
+                }
+                _ => abort!(
+                    attr.meta,
+                    "needs to be as `#[store(computed(name: Type = \
+                     function))]`"
+                ),
+            })
+            .flat_map(|ModelMode::Computed(fields)| fields)
+            .collect();
+
         Ok(Self {
             vis: input.vis,
             generics: input.generics,
             name: input.ident,
             ty,
+            computed,
         })
     }
 }
 
+enum ModelMode {
+    Computed(Punctuated<ComputedField, Comma>),
+}
+
+impl Parse for ModelMode {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mode: Ident = input.parse()?;
+        if mode == "computed" {
+            let content;
+            syn::parenthesized!(content in input);
+            Ok(ModelMode::Computed(Punctuated::parse_terminated(&content)?))
+        } else {
+            Err(syn::Error::new(
+                mode.span(),
+                "expected `computed(name: Type = function)`",
+            ))
+        }
+    }
+}
+
+/// A derived field, declared as `name: Type = function`, where the function takes the store
+/// as a `Field` and returns the value of the field.
+struct ComputedField {
+    ident: Ident,
+    ty: Type,
+    compute: Expr,
+}
+
+impl Parse for ComputedField {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let ident = input.parse()?;
+        let _col: Token![:] = input.parse()?;
+        let ty = input.parse()?;
+        let _eq: Token![=] = input.parse()?;
+        let compute = input.parse()?;
+        Ok(Self { ident, ty, compute })
+    }
+}
+
 #[derive(Clone)]
 enum SubfieldMode {
     Keyed(Box<ExprClosure>, Box<Type>),
@@ -108,6 +181,7 @@ impl ToTokens for Model {
             name,
             generics,
             ty,
+            computed,
         } = &self;
         let any_store_field = Ident::new("AnyStoreField", Span::call_site());
         let trait_name = Ident::new(&format!("{name}StoreFields"), name.span());
@@ -133,8 +207,24 @@ impl ToTokens for Model {
 
         // define an extension trait that matches this struct
         // and implement that trait for all StoreFields
-        let (trait_fields, read_fields): (Vec<_>, Vec<_>) =
+        let (mut trait_fields, mut read_fields): (Vec<_>, Vec<_>) =
             ty.to_field_data(&library_path, generics, &any_store_field, name);
+        for field in computed {
+            trait_fields.push(computed_to_tokens(
+                false,
+                &library_path,
+                generics,
+                name,
+                field,
+            ));
+            read_fields.push(computed_to_tokens(
+                true,
+                &library_path,
+                generics,
+                name,
+                field,
+            ));
+        }
 
         // read access
         tokens.extend(quote! {
@@ -334,6 +424,36 @@ fn field_to_tokens(
     }
 }
 
+fn computed_to_tokens(
+    include_body: bool,
+    library_path: &proc_macro2::TokenStream,
+    generics: &Generics,
+    name: &Ident,
+    field: &ComputedField,
+) -> proc_macro2::TokenStream {
+    let ComputedField { ident, ty, compute } = field;
+    let signature = quote! {
+        #[track_caller]
+        fn #ident(self) -> #library_path::ComputedField<#ty>
+        where
+            Self: Clone + Send + Sync + 'static,
+            #name #generics: Send + Sync + 'static
+    };
+    if include_body {
+        quote! {
+            #signature {
+                #library_path::computed_field::<_, #name #generics, #ty>(
+                    self,
+                    stringify!(#ident),
+                    #compute,
+                )
+            }
+        }
+    } else {
+        quote! { #signature; }
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 fn variant_to_tokens(
     include_body: bool,
@@ -684,6 +804,164 @@ impl ToTokens for PatchModel {
     }
 }
 
+/// The input of `#[derive(UndoField)]`, which has the same shape as that of `Patch`.
+struct UndoModel(PatchModel);
+
+impl Parse for UndoModel {
+    fn parse(input: ParseStream) -> Result<Self> {
+        PatchModel::parse(input).map(Self)
+    }
+}
+
+impl ToTokens for UndoModel {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let library_path = quote! { reactive_stores };
+        let PatchModel { name, generics, ty } = &self.0;
+
+        let fields = match ty {
+            PatchModelTy::Struct { fields } => fields
+                .iter()
+                .enumerate()
+                .map(|(idx, field)| UndoFieldModel {
+                    locator: match &field.ident {
+                        Some(ident) => Either::Left(ident.clone()),
+                        None => Either::Right(Index::from(idx)),
+                    },
+                    binding: format_ident!("__field{idx}"),
+                    idx,
+                    ty: field.ty.clone(),
+                    leaf: field
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.meta.path().is_ident("patch")),
+                })
+                .collect::<Vec<_>>(),
+            PatchModelTy::Enum { variants: _ } => {
+                unreachable!("not implemented currently")
+            }
+        };
+
+        let bounds = fields.iter().map(|field| {
+            let ty = &field.ty;
+            if field.leaf {
+                quote! { #ty: Send + Sync + 'static }
+            } else {
+                quote! { #ty: #library_path::UndoField }
+            }
+        });
+        let locators = fields.iter().map(|field| &field.locator);
+        let bindings = fields.iter().map(|field| &field.binding);
+        let take_fields = fields.iter().map(|field| {
+            let UndoFieldModel {
+                binding, idx, leaf, ..
+            } = field;
+            let take = if *leaf {
+                quote! {
+                    if let Some((idx, _)) =
+                        child_paths.iter().find(|(_, path)| path.is_empty())
+                    {
+                        take(*idx, #library_path::FieldValue::new(#binding));
+                    }
+                }
+            } else {
+                quote! {
+                    if !child_paths.is_empty() {
+                        #library_path::UndoField::take_fields(
+                            #binding,
+                            &child_paths,
+                            take
+                        );
+                    }
+                }
+            };
+            quote! {
+                let child_paths = paths
+                    .iter()
+                    .filter_map(|(idx, path)| match path.split_first() {
+                        Some((first, rest))
+                            if *first
+                                == #library_path::StorePathSegment::from(#idx) =>
+                        {
+                            Some((*idx, rest))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                #take
+            }
+        });
+        let swap_fields = fields.iter().map(|field| {
+            let UndoFieldModel {
+                locator, idx, leaf, ..
+            } = field;
+            let swap = if *leaf {
+                quote! {
+                    if rest.is_empty() {
+                        value.swap(&mut self.#locator);
+                    }
+                }
+            } else {
+                quote! {
+                    #library_path::UndoField::swap_field(
+                        &mut self.#locator,
+                        rest,
+                        value
+                    );
+                }
+            };
+            quote! {
+                if *first == #library_path::StorePathSegment::from(#idx) {
+                    #swap
+                }
+            }
+        });
+        tokens.extend(quote! {
+            impl #generics #library_path::UndoField for #name #generics
+            where
+                Self: Send + Sync + 'static,
+                #(#bounds,)*
+            {
+                fn take_fields(
+                    self,
+                    paths: &[(usize, &[#library_path::StorePathSegment])],
+                    take: &mut dyn FnMut(usize, #library_path::FieldValue),
+                ) {
+                    if let Some((idx, _)) =
+                        paths.iter().find(|(_, path)| path.is_empty())
+                    {
+                        take(*idx, #library_path::FieldValue::new(self));
+                        return;
+                    }
+                    let Self { #(#locators: #bindings),* } = self;
+                    #(#take_fields)*
+                }
+
+                fn swap_field(
+                    &mut self,
+                    path: &[#library_path::StorePathSegment],
+                    value: &mut #library_path::FieldValue,
+                ) {
+                    let Some((first, rest)) = path.split_first() else {
+                        value.swap(self);
+                        return;
+                    };
+                    #(#swap_fields)*
+                }
+            }
+        });
+    }
+}
+
+/// A field of a struct that derives `UndoField`, as it is moved out and swapped.
+struct UndoFieldModel {
+    locator: Either<Ident, Index>,
+    binding: Ident,
+    idx: usize,
+    ty: Type,
+    /// Whether the field is patched with a closure, and so is always replaced as a whole.
+    leaf: bool,
+}
+
 enum Either<A, B> {
     Left(A),
     Right(B),
@@ -0,0 +1,736 @@
+//! Batching several server function calls into a single HTTP request.
+//!
+//! A page that loads ten resources, each backed by its own server function, would usually
+//! make ten requests. With the [`Batched`](crate::client::batch::Batched) client, calls
+//! that are made within [`BATCH_WINDOW`](crate::client::batch::BATCH_WINDOW) of each other
+//! are instead queued, and sent together as one `POST` request to the batch endpoint at
+//! [`batch_path`]. The server runs each call through its
+//! registered server function, including its middleware, and responds with the result of
+//! every call. Each caller then receives its own response, so one failing call does not
+//! affect the others.
+//!
+//! The easiest way to opt in is the `batch` argument to the `#[server]` macro, which wraps
+//! the default client:
+//!
+//! ```rust,ignore
+//! #[server(batch = true)]
+//! pub async fn get_post(id: usize) -> Result<Post, ServerFnError> {
+//!     // ...
+//! }
+//! ```
+//!
+//! The server function handlers of the Axum and Actix integrations serve the batch
+//! endpoint. A few things to keep in mind:
+//!
+//! - Calls with a streaming request body, and calls that accept a streaming response
+//!   (like [`Streaming`](crate::codec::Streaming), [`StreamingText`](crate::codec::StreamingText)
+//!   and [`Framed`](crate::codec::Framed)), are never batched. Any other response is
+//!   collected in full before the batch response is sent.
+//! - A batch has at most [`max_batch_size`] calls. Larger batches are rejected with
+//!   `413 Payload Too Large`, and the client splits its queue into batches of this size.
+//!   The calls in a batch run concurrently, each through the middleware of its server
+//!   function, so rate limits and concurrency limits apply to every call.
+//! - Each call sees the headers of the batch request, like `Cookie` and `Authorization`,
+//!   along with its own `Content-Type` and `Accept`. Cookies set by any call are sent
+//!   with the batch response. With Actix, the raw `HttpRequest` of a call is the batch
+//!   request, while [`ActixRequest`](crate::request::actix::ActixRequest) reports the
+//!   method, path and headers of the call.
+//! - If only one call is queued, it is sent as a normal request. Clients that do not
+//!   implement [`Client::sleep`](crate::client::Client::sleep) can't wait for other calls,
+//!   so they send every call as a normal request.
+
+use crate::ContentType;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use bytes::Bytes;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::sync::OnceLock;
+
+/// The path of the batch endpoint, unless it is changed with [`set_batch_path`].
+pub const DEFAULT_BATCH_PATH: &str = "/api/_batch";
+
+static BATCH_PATH: OnceLock<&'static str> = OnceLock::new();
+
+/// Sets the path of the batch endpoint. This needs to be the same on the client and the
+/// server. Returns `Err(_)` if the path has already been set.
+pub fn set_batch_path(path: &'static str) -> Result<(), &'static str> {
+    BATCH_PATH.set(path)
+}
+
+/// The path of the batch endpoint.
+pub fn batch_path() -> &'static str {
+    BATCH_PATH.get().copied().unwrap_or(DEFAULT_BATCH_PATH)
+}
+
+/// The largest number of calls in a batch, unless it is changed with
+/// [`set_max_batch_size`].
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 32;
+
+static MAX_BATCH_SIZE: OnceLock<usize> = OnceLock::new();
+
+/// Sets the largest number of calls in a batch. The server rejects larger batches, so this
+/// needs to be the same on the client and the server. Returns `Err(_)` if the size has
+/// already been set.
+pub fn set_max_batch_size(size: usize) -> Result<(), usize> {
+    MAX_BATCH_SIZE.set(size.max(1))
+}
+
+/// The largest number of calls in a batch.
+pub fn max_batch_size() -> usize {
+    MAX_BATCH_SIZE
+        .get()
+        .copied()
+        .unwrap_or(DEFAULT_MAX_BATCH_SIZE)
+}
+
+/// The `Accept` headers of calls that expect a streaming response.
+const STREAMING_CONTENT_TYPES: [&str; 3] = [
+    crate::codec::Streaming::CONTENT_TYPE,
+    crate::codec::StreamingText::CONTENT_TYPE,
+    crate::codec::StreamingJson::CONTENT_TYPE,
+];
+
+/// One server function call in a batch request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchCall {
+    /// The HTTP method of the call.
+    pub method: String,
+    /// The path of the server function, with the query string of the call, if any.
+    pub path: String,
+    /// The headers of the call, like `Content-Type` and `Accept`.
+    pub headers: Vec<(String, String)>,
+    /// The request body of the call.
+    #[serde(with = "base64_bytes")]
+    pub body: Bytes,
+}
+
+impl BatchCall {
+    /// Whether the call accepts a streaming response, which would have to be collected in
+    /// full before the batch response is sent.
+    pub fn streams_response(&self) -> bool {
+        self.headers.iter().any(|(name, value)| {
+            name.eq_ignore_ascii_case("accept")
+                && STREAMING_CONTENT_TYPES
+                    .iter()
+                    .any(|ty| value.trim().eq_ignore_ascii_case(ty))
+        })
+    }
+}
+
+/// The response to one call in a batch, in the same position as the call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// The HTTP status code of the response.
+    pub status: u16,
+    /// The headers of the response.
+    pub headers: Vec<(String, String)>,
+    /// The response body.
+    #[serde(with = "base64_bytes")]
+    pub body: Bytes,
+}
+
+mod base64_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Bytes,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        STANDARD.encode(bytes).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Bytes, D::Error> {
+        let encoded = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        STANDARD
+            .decode(encoded.as_bytes())
+            .map(Bytes::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Headers of the batch request that are not passed on to its calls, which bring their own.
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+const CALL_HEADERS: [&str; 6] = [
+    "content-type",
+    "content-length",
+    "content-encoding",
+    "transfer-encoding",
+    "accept",
+    "accept-encoding",
+];
+
+/// Headers of a call's response that are not passed on in its [`BatchResult`].
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+const SKIPPED_RESULT_HEADERS: [&str; 6] = [
+    "content-length",
+    "transfer-encoding",
+    "set-cookie",
+    "vary",
+    "accept-encoding",
+    crate::version::BUILD_ID_HEADER,
+];
+
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+fn ser(err: crate::error::ServerFnErrorErr) -> Bytes {
+    use crate::error::FromServerFnError;
+    let err: crate::ServerFnError =
+        FromServerFnError::from_server_fn_error(err);
+    err.ser()
+}
+
+/// Checks that a batch has at most [`max_batch_size`] calls.
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+fn check_size(
+    calls: &[BatchCall],
+) -> Result<(), crate::error::ServerFnErrorErr> {
+    let max = max_batch_size();
+    if calls.len() > max {
+        Err(crate::error::ServerFnErrorErr::PayloadTooLarge(format!(
+            "the batch has {} calls, but at most {max} are allowed",
+            calls.len()
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+fn not_found(path: &str) -> Bytes {
+    format!("Could not find a server function at the route {path}.").into()
+}
+
+/// The headers of a call's response that are passed on in its [`BatchResult`].
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+fn result_headers<'a>(
+    headers: impl Iterator<Item = (&'a str, &'a [u8])>,
+) -> Vec<(String, String)> {
+    headers
+        .filter(|(name, _)| !SKIPPED_RESULT_HEADERS.contains(name))
+        .filter_map(|(name, value)| {
+            Some((name.to_string(), std::str::from_utf8(value).ok()?.into()))
+        })
+        .collect()
+}
+
+#[cfg(feature = "axum-no-default")]
+pub(crate) mod axum {
+    use super::{
+        check_size, not_found, result_headers, ser, BatchCall, BatchResult,
+        CALL_HEADERS,
+    };
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Service},
+        response::Res,
+    };
+    use axum::body::Body;
+    use bytes::Bytes;
+    use futures::future::join_all;
+    use http::{
+        header::{CONTENT_TYPE, SET_COOKIE},
+        request::Parts,
+        HeaderName, HeaderValue, Method, Request, Response, StatusCode,
+    };
+    use http_body_util::BodyExt;
+    use std::{future::Future, pin::Pin};
+
+    /// Responds to a batch request, running each call with its server function
+    /// service by calling `dispatch`.
+    pub async fn handle_batch<F, Fut>(
+        req: Request<Body>,
+        dispatch: F,
+    ) -> Response<Body>
+    where
+        F: Fn(
+                BoxedService<Request<Body>, Response<Body>>,
+                Request<Body>,
+            ) -> Fut
+            + Clone
+            + Send
+            + 'static,
+        Fut: Future<Output = Response<Body>> + Send + 'static,
+    {
+        let service = BoxedService::new(ser, Batch { dispatch });
+        let service = crate::version::axum::advertise_build_id(service);
+        let mut service =
+            crate::compression::axum::negotiate_compression(service);
+        service.run(req).await
+    }
+
+    struct Batch<F> {
+        dispatch: F,
+    }
+
+    impl<F, Fut> Service<Request<Body>, Response<Body>> for Batch<F>
+    where
+        F: Fn(
+                BoxedService<Request<Body>, Response<Body>>,
+                Request<Body>,
+            ) -> Fut
+            + Clone
+            + Send
+            + 'static,
+        Fut: Future<Output = Response<Body>> + Send + 'static,
+    {
+        fn run(
+            &mut self,
+            req: Request<Body>,
+            ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = Response<Body>> + Send>> {
+            let dispatch = self.dispatch.clone();
+            Box::pin(async move {
+                let path = req.uri().path().to_string();
+                let (parts, body) = req.into_parts();
+                let calls = match body.collect().await {
+                    Ok(body) => serde_json::from_slice::<Vec<BatchCall>>(
+                        &body.to_bytes(),
+                    )
+                    .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let calls = match calls {
+                    Ok(calls) => calls,
+                    Err(e) => {
+                        let err = ser(ServerFnErrorErr::Args(e));
+                        let mut res = Response::error_response(&path, err);
+                        *res.status_mut() = StatusCode::BAD_REQUEST;
+                        return res;
+                    }
+                };
+                if let Err(err) = check_size(&calls) {
+                    let mut res = Response::error_response(&path, ser(err));
+                    *res.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+                    return res;
+                }
+
+                let responses = join_all(calls.into_iter().map(|call| {
+                    let path = call.path.clone();
+                    let req = call_request(&parts, call);
+                    let dispatch = dispatch.clone();
+                    async move {
+                        let req = match req {
+                            Ok(req) => req,
+                            Err(e) => return invalid_call(&path, e, ser),
+                        };
+                        let path = req.uri().path();
+                        match crate::axum::get_server_fn_service(
+                            path,
+                            req.method().clone(),
+                        ) {
+                            Some(service) => dispatch(service, req).await,
+                            None => {
+                                let mut res =
+                                    Response::new(Body::from(not_found(path)));
+                                *res.status_mut() = StatusCode::BAD_REQUEST;
+                                res
+                            }
+                        }
+                    }
+                }))
+                .await;
+
+                let mut cookies = Vec::new();
+                let mut results = Vec::with_capacity(responses.len());
+                for res in responses {
+                    let (parts, body) = res.into_parts();
+                    cookies.extend(
+                        parts.headers.get_all(SET_COOKIE).iter().cloned(),
+                    );
+                    let headers = result_headers(parts.headers.iter().map(
+                        |(name, value)| (name.as_str(), value.as_bytes()),
+                    ));
+                    results.push(match body.collect().await {
+                        Ok(body) => BatchResult {
+                            status: parts.status.as_u16(),
+                            headers,
+                            body: body.to_bytes(),
+                        },
+                        Err(e) => BatchResult {
+                            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                            headers: Vec::new(),
+                            body: ser(ServerFnErrorErr::Response(
+                                e.to_string(),
+                            )),
+                        },
+                    });
+                }
+
+                let body = serde_json::to_vec(&results)
+                    .expect("batch results can always be serialized");
+                let mut res = Response::new(Body::from(body));
+                let headers = res.headers_mut();
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/json"),
+                );
+                for cookie in cookies {
+                    headers.append(SET_COOKIE, cookie);
+                }
+                crate::version::axum::insert_build_id(headers);
+                res
+            })
+        }
+    }
+
+    fn invalid_call(
+        path: &str,
+        e: String,
+        ser: fn(ServerFnErrorErr) -> Bytes,
+    ) -> Response<Body> {
+        let err = ser(ServerFnErrorErr::Args(e));
+        let mut res = Response::error_response(path, err);
+        *res.status_mut() = StatusCode::BAD_REQUEST;
+        res
+    }
+
+    /// Builds the request for a call, with the headers and extensions of the batch
+    /// request.
+    fn call_request(
+        parts: &Parts,
+        call: BatchCall,
+    ) -> Result<Request<Body>, String> {
+        let method = Method::from_bytes(call.method.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let mut req = Request::builder()
+            .method(method)
+            .uri(&call.path)
+            .body(Body::from(call.body))
+            .map_err(|e| e.to_string())?;
+        *req.extensions_mut() = parts.extensions.clone();
+        let req_headers = req.headers_mut();
+        *req_headers = parts.headers.clone();
+        for name in CALL_HEADERS {
+            req_headers.remove(name);
+        }
+        for (name, value) in call.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| e.to_string())?;
+            let value =
+                HeaderValue::from_str(&value).map_err(|e| e.to_string())?;
+            req_headers.insert(name, value);
+        }
+        Ok(req)
+    }
+}
+
+#[cfg(feature = "actix-no-default")]
+pub(crate) mod actix {
+    use super::{
+        check_size, not_found, result_headers, ser, BatchCall, BatchResult,
+        CALL_HEADERS,
+    };
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Service},
+        request::actix::{payload, ActixRequest, CallHead},
+        response::{actix::ActixResponse, Res},
+    };
+    use actix_web::{
+        body::to_bytes,
+        http::{
+            header::{HeaderName, HeaderValue, SET_COOKIE},
+            Method, StatusCode, Uri,
+        },
+        web::Payload,
+        HttpRequest, HttpResponse,
+    };
+    use bytes::Bytes;
+    use futures::future::join_all;
+    use send_wrapper::SendWrapper;
+    use std::{future::Future, pin::Pin, rc::Rc};
+
+    type Dispatch = Rc<
+        dyn Fn(
+            BoxedService<ActixRequest, ActixResponse>,
+            ActixRequest,
+        ) -> Pin<Box<dyn Future<Output = ActixResponse>>>,
+    >;
+
+    /// Responds to a batch request, running each call with its server function
+    /// service by calling `dispatch`.
+    pub async fn handle_batch<F, Fut>(
+        req: HttpRequest,
+        payload: Payload,
+        dispatch: F,
+    ) -> HttpResponse
+    where
+        F: Fn(BoxedService<ActixRequest, ActixResponse>, ActixRequest) -> Fut
+            + 'static,
+        Fut: Future<Output = ActixResponse> + 'static,
+    {
+        let dispatch: Dispatch =
+            Rc::new(move |service, req| Box::pin(dispatch(service, req)));
+        // Actix is going to keep this on a single thread anyway so it's fine to wrap it
+        // with SendWrapper, which makes it `Send` but will panic if it moves to another thread
+        let batch = Batch {
+            dispatch: SendWrapper::new(dispatch),
+        };
+        let service = BoxedService::new(ser, batch);
+        let service = crate::version::actix::advertise_build_id(service);
+        let mut service =
+            crate::compression::actix::negotiate_compression(service);
+        service
+            .run(ActixRequest::from((req, payload)))
+            .await
+            .0
+            .take()
+    }
+
+    struct Batch {
+        dispatch: SendWrapper<Dispatch>,
+    }
+
+    impl Service<ActixRequest, ActixResponse> for Batch {
+        fn run(
+            &mut self,
+            req: ActixRequest,
+            ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = ActixResponse> + Send>> {
+            let dispatch = Rc::clone(&self.dispatch);
+            Box::pin(SendWrapper::new(async move {
+                let path = req.path().to_string();
+                let (request, payload) = req.take();
+                let calls = match payload.to_bytes().await {
+                    Ok(body) => serde_json::from_slice::<Vec<BatchCall>>(&body)
+                        .map_err(|e| e.to_string()),
+                    Err(e) => Err(e.to_string()),
+                };
+                let calls = match calls {
+                    Ok(calls) => calls,
+                    Err(e) => {
+                        let err = ser(ServerFnErrorErr::Args(e));
+                        let mut res = ActixResponse::error_response(&path, err);
+                        *res.0.status_mut() = StatusCode::BAD_REQUEST;
+                        return res;
+                    }
+                };
+                if let Err(err) = check_size(&calls) {
+                    let mut res =
+                        ActixResponse::error_response(&path, ser(err));
+                    *res.0.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+                    return res;
+                }
+
+                let responses = join_all(calls.into_iter().map(|call| {
+                    let path = call.path.clone();
+                    let req = call_request(&request, call);
+                    let dispatch = Rc::clone(&dispatch);
+                    async move {
+                        let req = match req {
+                            Ok(req) => req,
+                            Err(e) => return invalid_call(&path, e, ser),
+                        };
+                        match crate::actix::get_server_fn_service(
+                            req.path(),
+                            req.method(),
+                        ) {
+                            Some(service) => dispatch(service, req).await,
+                            None => ActixResponse::from(
+                                HttpResponse::BadRequest()
+                                    .body(not_found(req.path())),
+                            ),
+                        }
+                    }
+                }))
+                .await;
+
+                let mut res = HttpResponse::Ok();
+                res.content_type("application/json");
+                let mut results = Vec::with_capacity(responses.len());
+                for call_res in responses {
+                    let (call_res, body) = call_res.0.take().into_parts();
+                    for cookie in call_res.headers().get_all(SET_COOKIE) {
+                        res.append_header((SET_COOKIE, cookie.clone()));
+                    }
+                    let headers =
+                        result_headers(call_res.headers().iter().map(
+                            |(name, value)| (name.as_str(), value.as_bytes()),
+                        ));
+                    results.push(match to_bytes(body).await {
+                        Ok(body) => BatchResult {
+                            status: call_res.status().as_u16(),
+                            headers,
+                            body,
+                        },
+                        Err(e) => BatchResult {
+                            status: StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                            headers: Vec::new(),
+                            body: ser(ServerFnErrorErr::Response(
+                                e.to_string(),
+                            )),
+                        },
+                    });
+                }
+
+                let body = serde_json::to_vec(&results)
+                    .expect("batch results can always be serialized");
+                let mut res = ActixResponse::from(res.body(body));
+                crate::version::actix::insert_build_id(res.0.headers_mut());
+                res
+            }))
+        }
+    }
+
+    fn invalid_call(
+        path: &str,
+        e: String,
+        ser: fn(ServerFnErrorErr) -> Bytes,
+    ) -> ActixResponse {
+        let err = ser(ServerFnErrorErr::Args(e));
+        let mut res = ActixResponse::error_response(path, err);
+        *res.0.status_mut() = StatusCode::BAD_REQUEST;
+        res
+    }
+
+    /// Builds the request for a call, with the headers of the batch request.
+    fn call_request(
+        request: &HttpRequest,
+        call: BatchCall,
+    ) -> Result<ActixRequest, String> {
+        let method = Method::from_bytes(call.method.as_bytes())
+            .map_err(|e| e.to_string())?;
+        let uri = call.path.parse::<Uri>().map_err(|e| e.to_string())?;
+        let mut headers = request.headers().clone();
+        for name in CALL_HEADERS {
+            headers.remove(name);
+        }
+        for (name, value) in call.headers {
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|e| e.to_string())?;
+            let value =
+                HeaderValue::from_str(&value).map_err(|e| e.to_string())?;
+            headers.insert(name, value);
+        }
+        let body = call.body;
+        let payload =
+            payload(request, futures::stream::once(async move { Ok(body) }));
+        Ok(ActixRequest(
+            SendWrapper::new((request.clone(), payload)),
+            Some(Box::new(CallHead {
+                method,
+                uri,
+                headers,
+            })),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BatchCall, BatchResult};
+    use bytes::Bytes;
+
+    #[test]
+    fn encodes_bodies_as_base64() {
+        let call = BatchCall {
+            method: "POST".into(),
+            path: "/api/add?x=1".into(),
+            headers: vec![("content-type".into(), "application/cbor".into())],
+            body: Bytes::from_static(&[0, 159, 255]),
+        };
+        let json = serde_json::to_value(&call).unwrap();
+        assert_eq!(json["body"], "AJ//");
+        let decoded: BatchCall = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, call);
+
+        let invalid = serde_json::json!({
+            "status": 200,
+            "headers": [],
+            "body": "not base64!",
+        });
+        assert!(serde_json::from_value::<BatchResult>(invalid).is_err());
+    }
+
+    #[test]
+    fn streaming_responses_are_not_batched() {
+        let call = |accept: &str| BatchCall {
+            method: "POST".into(),
+            path: "/api/stream".into(),
+            headers: vec![("Accept".into(), accept.into())],
+            body: Bytes::new(),
+        };
+        assert!(!call("application/json").streams_response());
+        assert!(!call("application/cbor").streams_response());
+        assert!(call("application/octet-stream").streams_response());
+        assert!(call("text/plain").streams_response());
+        assert!(call("application/x-ndjson").streams_response());
+    }
+}
+
+#[cfg(all(test, feature = "axum-no-default"))]
+mod axum_tests {
+    use super::{batch_path, BatchCall, BatchResult};
+    use axum::body::Body;
+    use http::{header::CONTENT_TYPE, Method, Request, StatusCode};
+    use http_body_util::BodyExt;
+
+    fn batch(body: impl Into<Body>) -> Request<Body> {
+        Request::builder()
+            .method(Method::POST)
+            .uri(batch_path())
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.into())
+            .unwrap()
+    }
+
+    #[test]
+    fn answers_each_call_in_order() {
+        let calls = vec![
+            BatchCall {
+                method: "POST".into(),
+                path: "/api/missing".into(),
+                headers: Vec::new(),
+                body: "a=1".into(),
+            },
+            BatchCall {
+                method: "NOT A METHOD".into(),
+                path: "/api/other".into(),
+                headers: Vec::new(),
+                body: Default::default(),
+            },
+        ];
+        let req = batch(serde_json::to_vec(&calls).unwrap());
+        futures::executor::block_on(async {
+            let res = crate::axum::handle_server_fn(req).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            let results: Vec<BatchResult> =
+                serde_json::from_slice(&body).unwrap();
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].status, 400);
+            assert!(String::from_utf8_lossy(&results[0].body)
+                .contains("/api/missing"));
+            assert_eq!(results[1].status, 400);
+            assert!(results[1]
+                .headers
+                .iter()
+                .any(|(name, value)| name == "serverfnerror"
+                    && value == "/api/other"));
+        });
+    }
+
+    #[test]
+    fn rejects_batches_over_the_size_limit() {
+        let call = BatchCall {
+            method: "POST".into(),
+            path: "/api/missing".into(),
+            headers: Vec::new(),
+            body: Default::default(),
+        };
+        let calls = vec![call; super::max_batch_size() + 1];
+        let req = batch(serde_json::to_vec(&calls).unwrap());
+        futures::executor::block_on(async {
+            let res = crate::axum::handle_server_fn(req).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        });
+    }
+
+    #[test]
+    fn rejects_an_invalid_batch() {
+        futures::executor::block_on(async {
+            let res = crate::axum::handle_server_fn(batch("[{")).await;
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        });
+    }
+}
@@ -1,7 +1,14 @@
-use crate::{request::ClientReq, response::ClientRes};
+use crate::{request::ClientReq, response::ClientRes, version};
 use bytes::Bytes;
 use futures::{Sink, Stream};
-use std::{future::Future, sync::OnceLock};
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
 
 static ROOT_URL: OnceLock<&'static str> = OnceLock::new();
 
@@ -17,6 +24,42 @@ pub fn get_server_url() -> &'static str {
     ROOT_URL.get().copied().unwrap_or("")
 }
 
+/// A function that will be called with the server's build ID if it differs from the
+/// client's build ID.
+pub type StaleBuildHook = Box<dyn Fn(&str) + Send + Sync>;
+
+static STALE_BUILD_HOOK: OnceLock<StaleBuildHook> = OnceLock::new();
+static STALE_BUILD_REPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Sets a function that will be called when a server function response shows that the
+/// server is running a different build than this client, for example to prompt the user
+/// to reload the page. Returns `Err(_)` if the hook has already been set.
+///
+/// The hook is called at most once, with the server's build ID. Detection requires a build
+/// ID for both the client and the server; see [`version`](crate::version) for details.
+pub fn set_stale_build_hook(
+    hook: impl Fn(&str) + Send + Sync + 'static,
+) -> Result<(), StaleBuildHook> {
+    STALE_BUILD_HOOK.set(Box::new(hook))
+}
+
+/// Compares the build ID advertised in a server function response with the client's
+/// own, and calls the hook set with [`set_stale_build_hook`] if they differ.
+pub fn check_build_id(server_build_id: Option<&str>) {
+    let (Some(server), Some(client)) = (server_build_id, version::build_id())
+    else {
+        return;
+    };
+    if server == client {
+        return;
+    }
+    if let Some(hook) = STALE_BUILD_HOOK.get() {
+        if !STALE_BUILD_REPORTED.swap(true, Ordering::Relaxed) {
+            hook(server)
+        }
+    }
+}
+
 /// A client defines a pair of request/response types and the logic to send
 /// and receive them.
 ///
@@ -50,26 +93,1032 @@ pub trait Client<Error, InputStreamError = Error, OutputStreamError = Error> {
 
     /// Spawn a future that runs in the background.
     fn spawn(future: impl Future<Output = ()> + Send + 'static);
+
+    /// Waits for the given duration, for example before reconnecting to a stream.
+    ///
+    /// The default implementation does not wait at all. The built-in clients use a timer.
+    /// Server-sent events and websockets do not reconnect with a client that does not wait,
+    /// since reconnecting would retry in a busy loop, so custom clients that use them should
+    /// implement this.
+    fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+        _ = duration;
+        async {}
+    }
+}
+
+/// Waits on a future returned by [`Client::sleep`], and returns `false` if it completed right
+/// away although `duration` is not zero, because the client does not implement a timer.
+pub(crate) async fn waited(
+    duration: Duration,
+    sleep: impl Future<Output = ()>,
+) -> bool {
+    let mut sleep = std::pin::pin!(sleep);
+    if futures::poll!(sleep.as_mut()).is_ready() {
+        return duration.is_zero();
+    }
+    sleep.await;
+    true
+}
+
+/// A local cache of server function responses, used to make conditional requests.
+///
+/// When a `GET` server function responds with an `ETag` (for example, because it uses the
+/// [`ServerFnCache`](crate::middleware::cache::ServerFnCache) middleware), the built-in
+/// clients store the response body in their [`ClientCache`](cache::ClientCache). The next
+/// call to the same URL sends the `ETag` in an `If-None-Match` header, and if the server
+/// answers `304 Not Modified`, the stored body is used instead.
+///
+/// Each client has its own cache. Responses are stored along with the values of the request
+/// headers named in their `Vary` header, and are only reused for requests with the same
+/// values. A shared cache, like the one used by the `reqwest` client (which may make requests
+/// on behalf of many users while rendering on the server), never stores `private` responses.
+pub mod cache {
+    use bytes::Bytes;
+    use std::{
+        collections::VecDeque,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Mutex, PoisonError,
+        },
+    };
+
+    /// A response body stored for conditional requests.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct CachedResponse {
+        /// The `ETag` the server sent with this response.
+        pub etag: String,
+        /// The `Content-Type` of the response, if any.
+        pub content_type: Option<String>,
+        /// The response body.
+        pub body: Bytes,
+    }
+
+    /// A least-recently-used cache of responses, keyed by URL and the request headers the
+    /// responses vary on.
+    #[derive(Debug)]
+    pub struct ClientCache {
+        capacity: AtomicUsize,
+        shared: bool,
+        entries: Mutex<VecDeque<Entry>>,
+    }
+
+    #[derive(Debug)]
+    struct Entry {
+        url: String,
+        /// The request headers named in the `Vary` header, and their values.
+        vary: Vec<(String, Option<String>)>,
+        response: CachedResponse,
+    }
+
+    impl Entry {
+        fn matches(
+            &self,
+            url: &str,
+            request_header: &impl Fn(&str) -> Option<String>,
+        ) -> bool {
+            self.url == url
+                && self
+                    .vary
+                    .iter()
+                    .all(|(name, value)| request_header(name) == *value)
+        }
+    }
+
+    impl ClientCache {
+        /// Creates a cache for a single user, such as a browser, that keeps up to `capacity`
+        /// responses.
+        pub const fn new(capacity: usize) -> Self {
+            Self {
+                capacity: AtomicUsize::new(capacity),
+                shared: false,
+                entries: Mutex::new(VecDeque::new()),
+            }
+        }
+
+        /// Creates a cache that may serve requests made on behalf of many users, which keeps
+        /// up to `capacity` responses. Responses marked `private` are never stored.
+        pub const fn new_shared(capacity: usize) -> Self {
+            Self {
+                capacity: AtomicUsize::new(capacity),
+                shared: true,
+                entries: Mutex::new(VecDeque::new()),
+            }
+        }
+
+        /// Sets the maximum number of responses that are kept.
+        ///
+        /// Setting it to `0` disables the cache.
+        pub fn set_capacity(&self, capacity: usize) {
+            self.capacity.store(capacity, Ordering::Relaxed);
+            self.lock().truncate(capacity);
+        }
+
+        /// Removes all stored responses.
+        pub fn clear(&self) {
+            self.lock().clear();
+        }
+
+        /// Returns the stored response for the given URL, if there is one for a request with
+        /// the same values of the headers it varies on.
+        ///
+        /// `request_header` returns the value of a header of the request that is being made.
+        pub fn get(
+            &self,
+            url: &str,
+            request_header: impl Fn(&str) -> Option<String>,
+        ) -> Option<CachedResponse> {
+            let mut entries = self.lock();
+            let idx = entries
+                .iter()
+                .position(|entry| entry.matches(url, &request_header))?;
+            // move the entry to the front, so the least-recently-used entry is evicted first
+            let entry = entries.remove(idx)?;
+            let value = entry.response.clone();
+            entries.push_front(entry);
+            Some(value)
+        }
+
+        /// Stores a response for the given URL, evicting the least-recently-used entry if
+        /// the cache is full.
+        ///
+        /// `vary` is the `Vary` header of the response, and `request_header` returns the value
+        /// of a header of the request it answers. Responses that vary on `*` are not stored.
+        pub fn insert(
+            &self,
+            url: String,
+            vary: Option<&str>,
+            request_header: impl Fn(&str) -> Option<String>,
+            response: CachedResponse,
+        ) {
+            let capacity = self.capacity.load(Ordering::Relaxed);
+            let mut names = vary
+                .into_iter()
+                .flat_map(|vary| vary.split(','))
+                .map(|name| name.trim().to_ascii_lowercase())
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>();
+            if capacity == 0 || names.iter().any(|name| name == "*") {
+                return;
+            }
+            names.sort();
+            names.dedup();
+            let entry = Entry {
+                url,
+                vary: names
+                    .into_iter()
+                    .map(|name| {
+                        let value = request_header(&name);
+                        (name, value)
+                    })
+                    .collect(),
+                response,
+            };
+            let mut entries = self.lock();
+            entries.retain(|other| {
+                other.url != entry.url || other.vary != entry.vary
+            });
+            entries.push_front(entry);
+            entries.truncate(capacity);
+        }
+
+        /// Removes all the stored responses for the given URL.
+        pub fn remove(&self, url: &str) {
+            self.lock().retain(|entry| entry.url != url);
+        }
+
+        /// Whether a response with the given `Cache-Control` header may be stored.
+        ///
+        /// Responses marked `no-store` are never stored, and responses marked `private`
+        /// are not stored in a shared cache.
+        pub fn is_storable(&self, cache_control: Option<&str>) -> bool {
+            !cache_control
+                .map(|value| {
+                    value.split(',').any(|directive| {
+                        let directive = directive.trim();
+                        directive.eq_ignore_ascii_case("no-store")
+                            || (self.shared
+                                && directive.split('=').next().is_some_and(
+                                    |name| {
+                                        name.trim()
+                                            .eq_ignore_ascii_case("private")
+                                    },
+                                ))
+                    })
+                })
+                .unwrap_or(false)
+        }
+
+        fn lock(&self) -> std::sync::MutexGuard<'_, VecDeque<Entry>> {
+            self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn response(etag: &str) -> CachedResponse {
+            CachedResponse {
+                etag: etag.to_string(),
+                content_type: None,
+                body: Bytes::from_static(b"body"),
+            }
+        }
+
+        fn no_headers(_: &str) -> Option<String> {
+            None
+        }
+
+        #[test]
+        fn stores_and_evicts_responses() {
+            let cache = ClientCache::new(2);
+            cache.insert("/a".into(), None, no_headers, response("a"));
+            cache.insert("/b".into(), None, no_headers, response("b"));
+            // touch `/a`, so `/b` is evicted next
+            assert_eq!(
+                cache.get("/a", no_headers).map(|r| r.etag),
+                Some("a".into())
+            );
+            cache.insert("/c".into(), None, no_headers, response("c"));
+            assert!(cache.get("/b", no_headers).is_none());
+            assert!(cache.get("/a", no_headers).is_some());
+            assert!(cache.get("/c", no_headers).is_some());
+            cache.remove("/a");
+            assert!(cache.get("/a", no_headers).is_none());
+            cache.clear();
+            assert!(cache.get("/c", no_headers).is_none());
+
+            cache.set_capacity(0);
+            cache.insert("/a".into(), None, no_headers, response("a"));
+            assert!(cache.get("/a", no_headers).is_none());
+        }
+
+        #[test]
+        fn shared_caches_do_not_store_private_responses() {
+            let private = ClientCache::new(8);
+            let shared = ClientCache::new_shared(8);
+            for cache in [&private, &shared] {
+                assert!(cache.is_storable(None));
+                assert!(cache.is_storable(Some("public, max-age=60")));
+                assert!(!cache.is_storable(Some("private, no-store")));
+            }
+            assert!(private.is_storable(Some("private, max-age=60")));
+            assert!(!shared.is_storable(Some("private, max-age=60")));
+            assert!(!shared.is_storable(Some("max-age=60, Private")));
+        }
+
+        #[test]
+        fn responses_are_keyed_by_vary_headers() {
+            let cache = ClientCache::new_shared(8);
+            let cookie = |value: &'static str| {
+                move |name: &str| (name == "cookie").then(|| value.to_string())
+            };
+            cache.insert(
+                "/me".into(),
+                Some("Cookie, Accept-Language"),
+                cookie("user=alice"),
+                response("alice"),
+            );
+            cache.insert(
+                "/me".into(),
+                Some("Cookie"),
+                cookie("user=bob"),
+                response("bob"),
+            );
+            assert_eq!(
+                cache.get("/me", cookie("user=alice")).map(|r| r.etag),
+                Some("alice".into())
+            );
+            assert_eq!(
+                cache.get("/me", cookie("user=bob")).map(|r| r.etag),
+                Some("bob".into())
+            );
+            assert!(cache.get("/me", cookie("user=carol")).is_none());
+            assert!(cache.get("/me", no_headers).is_none());
+
+            // responses that vary on anything can't be reused
+            cache.insert("/any".into(), Some("*"), no_headers, response("any"));
+            assert!(cache.get("/any", no_headers).is_none());
+        }
+    }
+}
+
+/// Automatic retries, backoff and timeouts for server function requests.
+///
+/// By default, every request is sent exactly once and without a timeout. A [`RetryPolicy`]
+/// can be set globally with [`set_retry_policy`], per server function with
+/// `#[server(retry = ...)]`, or for a single call with [`with_retry_policy`]. A policy set
+/// for a single call takes precedence over the one set on the server function, which in
+/// turn takes precedence over the global policy.
+///
+/// ```rust,ignore
+/// use server_fn::client::retry::RetryPolicy;
+/// use std::time::Duration;
+///
+/// #[server(retry = RetryPolicy::new(3).with_timeout(Duration::from_secs(5)))]
+/// pub async fn get_posts() -> Result<Vec<Post>, ServerFnError> {
+///     // ...
+/// }
+/// ```
+///
+/// Failed requests are only retried if the HTTP method is idempotent (`GET`, `HEAD`, `PUT`,
+/// `DELETE`, `OPTIONS` and `TRACE`), unless the policy opts in with
+/// [`RetryPolicy::retry_non_idempotent`]. Errors that show the request never reached the
+/// server are retried for any method: with `reqwest`, a failure to connect, and in the
+/// browser, a failure while the browser is offline. Other network errors reported by `fetch`
+/// don't say whether the request was delivered, so they are treated as having reached it.
+///
+/// If the server answers with a `Retry-After` header (in seconds), the next attempt waits at
+/// least that long. If it asks for a longer wait than the maximum backoff, the request is not
+/// retried and the response is returned as it is.
+pub mod retry {
+    use http::Method;
+    use pin_project_lite::pin_project;
+    use std::{
+        cell::Cell,
+        future::Future,
+        pin::Pin,
+        sync::{PoisonError, RwLock},
+        task::{Context, Poll},
+        time::Duration,
+    };
+
+    static GLOBAL_POLICY: RwLock<RetryPolicy> =
+        RwLock::new(RetryPolicy::none());
+
+    thread_local! {
+        static SCOPED_POLICY: Cell<Option<RetryPolicy>> = const { Cell::new(None) };
+    }
+
+    /// How server function requests are retried when they fail.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RetryPolicy {
+        max_attempts: u32,
+        initial_backoff: Duration,
+        max_backoff: Duration,
+        jitter: bool,
+        timeout: Option<Duration>,
+        retry_non_idempotent: bool,
+        retry_statuses: &'static [u16],
+    }
+
+    impl Default for RetryPolicy {
+        fn default() -> Self {
+            Self::none()
+        }
+    }
+
+    impl From<u32> for RetryPolicy {
+        fn from(max_attempts: u32) -> Self {
+            Self::new(max_attempts)
+        }
+    }
+
+    impl RetryPolicy {
+        /// Sends each request once, without a timeout. This is the default.
+        pub const fn none() -> Self {
+            Self {
+                max_attempts: 1,
+                initial_backoff: Duration::from_millis(100),
+                max_backoff: Duration::from_secs(5),
+                jitter: true,
+                timeout: None,
+                retry_non_idempotent: false,
+                retry_statuses: &[429, 502, 503, 504],
+            }
+        }
+
+        /// Sends each request up to `max_attempts` times (including the first attempt).
+        ///
+        /// Retries back off exponentially, starting at 100ms and doubling up to a maximum
+        /// of 5s, with jitter. Responses with the status codes `429`, `502`, `503` and `504`
+        /// are retried, and a `Retry-After` header is respected.
+        pub const fn new(max_attempts: u32) -> Self {
+            let mut policy = Self::none();
+            policy.max_attempts =
+                if max_attempts == 0 { 1 } else { max_attempts };
+            policy
+        }
+
+        /// Sets the delay before the first retry, and the maximum delay between retries.
+        pub const fn with_backoff(
+            mut self,
+            initial: Duration,
+            max: Duration,
+        ) -> Self {
+            self.initial_backoff = initial;
+            self.max_backoff = max;
+            self
+        }
+
+        /// Sets whether the delay between retries is randomized. Defaults to `true`.
+        pub const fn with_jitter(mut self, jitter: bool) -> Self {
+            self.jitter = jitter;
+            self
+        }
+
+        /// Aborts each attempt if no response has arrived after the given duration.
+        pub const fn with_timeout(mut self, timeout: Duration) -> Self {
+            self.timeout = Some(timeout);
+            self
+        }
+
+        /// Retries requests with a non-idempotent method (like `POST`) as well.
+        ///
+        /// Only enable this if the server function is safe to run more than once.
+        pub const fn retry_non_idempotent(mut self) -> Self {
+            self.retry_non_idempotent = true;
+            self
+        }
+
+        /// Sets the response status codes that are retried.
+        pub const fn retry_statuses(
+            mut self,
+            statuses: &'static [u16],
+        ) -> Self {
+            self.retry_statuses = statuses;
+            self
+        }
+
+        /// The maximum number of times a request is sent.
+        pub const fn max_attempts(&self) -> u32 {
+            self.max_attempts
+        }
+
+        /// The timeout for each attempt, if any.
+        pub const fn timeout(&self) -> Option<Duration> {
+            self.timeout
+        }
+
+        /// Whether requests are sent more than once, or with a timeout.
+        pub const fn is_enabled(&self) -> bool {
+            self.max_attempts > 1 || self.timeout.is_some()
+        }
+
+        /// Whether a request with the given method may be sent again after `attempt`
+        /// attempts.
+        ///
+        /// Set `reached_server` to `false` if the request failed before it could be
+        /// delivered (for example, because the connection was refused).
+        pub fn can_retry(
+            &self,
+            method: &Method,
+            attempt: u32,
+            reached_server: bool,
+        ) -> bool {
+            attempt < self.max_attempts
+                && (!reached_server
+                    || self.retry_non_idempotent
+                    || is_idempotent(method))
+        }
+
+        /// Whether a response with the given status code should be retried.
+        pub fn retries_status(&self, status: u16) -> bool {
+            self.retry_statuses.contains(&status)
+        }
+
+        /// The delay before the next attempt, after `attempt` attempts have failed.
+        ///
+        /// If the server sent a `Retry-After` header with a number of seconds, it is used as
+        /// the lower bound for the delay. If it is longer than the maximum backoff, returns
+        /// `None`: the request should not be retried, rather than retried earlier than the
+        /// server asked.
+        pub fn delay(
+            &self,
+            attempt: u32,
+            retry_after: Option<&str>,
+        ) -> Option<Duration> {
+            let exponent = attempt.saturating_sub(1).min(31);
+            let backoff = self
+                .initial_backoff
+                .saturating_mul(1 << exponent)
+                .min(self.max_backoff);
+            // "equal jitter": wait between half and all of the backoff
+            let backoff = if self.jitter {
+                backoff / 2 + backoff.mul_f64(crate::random::fraction() / 2.0)
+            } else {
+                backoff
+            };
+            let retry_after = retry_after
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or_default();
+            (retry_after <= self.max_backoff).then(|| backoff.max(retry_after))
+        }
+    }
+
+    /// Whether requests with this method can safely be sent more than once.
+    pub fn is_idempotent(method: &Method) -> bool {
+        matches!(
+            *method,
+            Method::GET
+                | Method::HEAD
+                | Method::PUT
+                | Method::DELETE
+                | Method::OPTIONS
+                | Method::TRACE
+        )
+    }
+
+    /// Sets the retry policy used for all server functions that do not set their own.
+    pub fn set_retry_policy(policy: RetryPolicy) {
+        *GLOBAL_POLICY
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = policy;
+    }
+
+    /// Returns the retry policy that applies to requests sent from the current context.
+    pub fn retry_policy() -> RetryPolicy {
+        SCOPED_POLICY.with(Cell::get).unwrap_or_else(|| {
+            *GLOBAL_POLICY.read().unwrap_or_else(PoisonError::into_inner)
+        })
+    }
+
+    /// Runs the future (usually a server function call) with the given retry policy.
+    pub fn with_retry_policy<F: Future>(
+        policy: RetryPolicy,
+        fut: F,
+    ) -> WithRetryPolicy<F> {
+        WithRetryPolicy {
+            policy: Some(policy),
+            overrides: true,
+            inner: fut,
+        }
+    }
+
+    /// Runs the future with the given retry policy, unless a policy has already been set
+    /// for the surrounding call.
+    pub(crate) fn with_default_retry_policy<F: Future>(
+        policy: Option<RetryPolicy>,
+        fut: F,
+    ) -> WithRetryPolicy<F> {
+        WithRetryPolicy {
+            policy,
+            overrides: false,
+            inner: fut,
+        }
+    }
+
+    pin_project! {
+        /// A future that runs with a [`RetryPolicy`]. Created by [`with_retry_policy`].
+        pub struct WithRetryPolicy<F> {
+            policy: Option<RetryPolicy>,
+            overrides: bool,
+            #[pin]
+            inner: F,
+        }
+    }
+
+    impl<F: Future> Future for WithRetryPolicy<F> {
+        type Output = F::Output;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+            struct Restore(Option<RetryPolicy>);
+
+            impl Drop for Restore {
+                fn drop(&mut self) {
+                    SCOPED_POLICY.with(|scoped| scoped.set(self.0));
+                }
+            }
+
+            let this = self.project();
+            let prev = SCOPED_POLICY.with(Cell::get);
+            let policy = if *this.overrides {
+                *this.policy
+            } else {
+                prev.or(*this.policy)
+            };
+            SCOPED_POLICY.with(|scoped| scoped.set(policy));
+            let _restore = Restore(prev);
+            this.inner.poll(cx)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn retries_idempotent_methods_only() {
+            let policy = RetryPolicy::new(3);
+            assert!(policy.can_retry(&Method::GET, 1, true));
+            assert!(policy.can_retry(&Method::PUT, 2, true));
+            assert!(!policy.can_retry(&Method::GET, 3, true));
+            assert!(!policy.can_retry(&Method::POST, 1, true));
+            assert!(policy.can_retry(&Method::POST, 1, false));
+            assert!(policy.retry_non_idempotent().can_retry(
+                &Method::POST,
+                1,
+                true
+            ));
+            assert!(!RetryPolicy::none().can_retry(&Method::GET, 1, false));
+            assert!(policy.retries_status(503));
+            assert!(!policy.retries_status(500));
+        }
+
+        #[test]
+        fn backs_off_exponentially() {
+            let policy = RetryPolicy::new(5)
+                .with_backoff(
+                    Duration::from_millis(100),
+                    Duration::from_secs(1),
+                )
+                .with_jitter(false);
+            assert_eq!(policy.delay(1, None), Some(Duration::from_millis(100)));
+            assert_eq!(policy.delay(2, None), Some(Duration::from_millis(200)));
+            assert_eq!(policy.delay(4, None), Some(Duration::from_millis(800)));
+            assert_eq!(policy.delay(5, None), Some(Duration::from_secs(1)));
+            assert_eq!(
+                policy.delay(1, Some("1")),
+                Some(Duration::from_secs(1))
+            );
+            // waiting less than the server asked for would be pointless
+            assert_eq!(policy.delay(1, Some("60")), None);
+
+            let jittered = policy.with_jitter(true).delay(3, None).unwrap();
+            assert!(jittered >= Duration::from_millis(200));
+            assert!(jittered <= Duration::from_millis(400));
+        }
+
+        #[test]
+        fn scoped_policy_takes_precedence() {
+            let call = RetryPolicy::new(5);
+            let function = RetryPolicy::new(2);
+            set_retry_policy(RetryPolicy::new(3));
+            assert_eq!(retry_policy(), RetryPolicy::new(3));
+
+            let current = futures::executor::block_on(
+                with_default_retry_policy(Some(function), async {
+                    retry_policy()
+                }),
+            );
+            assert_eq!(current, function);
+
+            let current = futures::executor::block_on(with_retry_policy(
+                call,
+                with_default_retry_policy(Some(function), async {
+                    retry_policy()
+                }),
+            ));
+            assert_eq!(current, call);
+            assert_eq!(retry_policy(), RetryPolicy::new(3));
+            set_retry_policy(RetryPolicy::none());
+        }
+    }
+}
+
+/// Coalesces the server function calls that are made within a millisecond of each other into
+/// a single request to the batch endpoint.
+///
+/// See [`batch`](crate::batch) for details.
+pub mod batch {
+    use super::Client;
+    use crate::{
+        batch::{batch_path, max_batch_size, BatchCall, BatchResult},
+        error::{FromServerFnError, IntoAppError, ServerFnErrorErr},
+        request::ClientReq,
+        response::ClientRes,
+    };
+    use bytes::Bytes;
+    use futures::channel::oneshot;
+    use http::Method;
+    use std::{
+        any::{Any, TypeId},
+        collections::HashMap,
+        future::Future,
+        marker::PhantomData,
+        sync::{LazyLock, Mutex, PoisonError},
+        time::Duration,
+    };
+
+    /// A [`Client`] whose requests can be sent as calls in a batch.
+    pub trait BatchClient<
+        Error,
+        InputStreamError = Error,
+        OutputStreamError = Error,
+    >: Client<Error, InputStreamError, OutputStreamError>
+    {
+        /// Describes the request as a call in a batch, or returns `None` if it can't be
+        /// batched, for example because it has a streaming body. The request is returned
+        /// as well, so that it can still be sent on its own.
+        fn batch_call(
+            req: Self::Request,
+        ) -> impl Future<Output = (Self::Request, Option<BatchCall>)> + Send;
+
+        /// Creates the response to a call from its result in the batch response.
+        fn batch_response(
+            result: BatchResult,
+        ) -> Result<Self::Response, String>;
+    }
+
+    /// How long the first call in a batch waits for other calls to join it.
+    pub const BATCH_WINDOW: Duration = Duration::from_millis(1);
+
+    /// A [`Client`] that sends the calls made within [`BATCH_WINDOW`] of each other as one
+    /// request to the batch endpoint, using the client `C`.
+    ///
+    /// Calls that can't be batched, calls that turn out to be the only one in their window,
+    /// and every call of a client `C` that does not implement [`Client::sleep`], are sent on
+    /// their own.
+    pub struct Batched<C>(PhantomData<C>);
+
+    enum Outcome<Req, Res> {
+        /// The call was the only one queued, so it should be sent on its own.
+        Alone(Req),
+        Done(Res),
+        Failed(String),
+    }
+
+    struct Pending<Req, Res> {
+        request: Req,
+        call: BatchCall,
+        tx: oneshot::Sender<Outcome<Req, Res>>,
+    }
+
+    type Queue<Req, Res> = Vec<Pending<Req, Res>>;
+
+    /// The queued calls, with one queue for each client, so that calls are always sent
+    /// with the client they were made with.
+    static QUEUES: LazyLock<Mutex<HashMap<TypeId, Box<dyn Any + Send>>>> =
+        LazyLock::new(Default::default);
+
+    /// The key of the queue of client `C`.
+    fn queue_key<C: 'static, Req: 'static, Res: 'static>() -> TypeId {
+        TypeId::of::<(C, Queue<Req, Res>)>()
+    }
+
+    /// Adds a call to the queue of client `C`, and returns whether it is the first call in
+    /// the queue.
+    fn enqueue<C, Req, Res>(pending: Pending<Req, Res>) -> bool
+    where
+        C: 'static,
+        Req: Send + 'static,
+        Res: Send + 'static,
+    {
+        let mut queues = QUEUES.lock().unwrap_or_else(PoisonError::into_inner);
+        let queue = queues
+            .entry(queue_key::<C, Req, Res>())
+            .or_insert_with(|| Box::new(Queue::<Req, Res>::new()))
+            .downcast_mut::<Queue<Req, Res>>()
+            .expect("queues are stored under their own type");
+        queue.push(pending);
+        queue.len() == 1
+    }
+
+    fn take_queue<C, Req, Res>() -> Queue<Req, Res>
+    where
+        C: 'static,
+        Req: Send + 'static,
+        Res: Send + 'static,
+    {
+        QUEUES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get_mut(&queue_key::<C, Req, Res>())
+            .and_then(|queue| queue.downcast_mut::<Queue<Req, Res>>())
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    impl<C, E, IS, OS> Client<E, IS, OS> for Batched<C>
+    where
+        C: BatchClient<E, IS, OS> + 'static,
+        E: FromServerFnError,
+    {
+        type Request = C::Request;
+        type Response = C::Response;
+
+        async fn send(req: Self::Request) -> Result<Self::Response, E> {
+            let (request, call) = C::batch_call(req).await;
+            // a call that is sent alone gets its trace context from the client
+            #[allow(unused_mut)]
+            let Some(mut call) = call.filter(|call| !call.streams_response()) else {
+                return C::send(request).await;
+            };
+            // each call in a batch carries its own trace context
+            #[cfg(feature = "trace-propagation")]
+            call.headers.extend(crate::trace::headers());
+            let (tx, rx) = oneshot::channel();
+            if enqueue::<C, _, _>(Pending { request, call, tx }) {
+                C::spawn(async {
+                    // wait for the other calls made in this window
+                    let waited =
+                        super::waited(BATCH_WINDOW, C::sleep(BATCH_WINDOW))
+                            .await;
+                    flush::<C, E, IS, OS>(waited).await;
+                });
+            }
+            match rx.await {
+                Ok(Outcome::Alone(request)) => C::send(request).await,
+                Ok(Outcome::Done(res)) => Ok(res),
+                Ok(Outcome::Failed(e)) => {
+                    Err(ServerFnErrorErr::Request(e).into_app_error())
+                }
+                Err(_) => Err(ServerFnErrorErr::Request(
+                    "the batch was dropped before it was sent".into(),
+                )
+                .into_app_error()),
+            }
+        }
+
+        fn open_websocket(
+            path: &str,
+        ) -> impl Future<
+            Output = Result<
+                (
+                    impl futures::Stream<Item = Result<Bytes, Bytes>>
+                        + Send
+                        + 'static,
+                    impl futures::Sink<Bytes> + Send + 'static,
+                ),
+                E,
+            >,
+        > + Send {
+            C::open_websocket(path)
+        }
+
+        fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+            C::spawn(future)
+        }
+
+        fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+            C::sleep(duration)
+        }
+    }
+
+    /// Sends the queued calls, and passes each caller its response. If the client did not
+    /// wait for other calls, each call is sent on its own.
+    async fn flush<C, E, IS, OS>(waited: bool)
+    where
+        C: BatchClient<E, IS, OS> + 'static,
+        E: FromServerFnError,
+    {
+        let mut queue = take_queue::<C, C::Request, C::Response>();
+        if !waited || queue.len() == 1 {
+            for Pending { request, tx, .. } in queue {
+                _ = tx.send(Outcome::Alone(request));
+            }
+            return;
+        }
+        let mut batches = Vec::new();
+        while queue.len() > max_batch_size() {
+            let rest = queue.split_off(max_batch_size());
+            batches.push(std::mem::replace(&mut queue, rest));
+        }
+        batches.push(queue);
+        futures::future::join_all(
+            batches.into_iter().map(send_queue::<C, E, IS, OS>),
+        )
+        .await;
+    }
+
+    /// Sends one batch of queued calls, and passes each caller its response.
+    async fn send_queue<C, E, IS, OS>(queue: Queue<C::Request, C::Response>)
+    where
+        C: BatchClient<E, IS, OS>,
+        E: FromServerFnError,
+    {
+        let (calls, senders): (Vec<_>, Vec<_>) = queue
+            .into_iter()
+            .map(|pending| (pending.call, pending.tx))
+            .unzip();
+        match send_batch::<C, E, IS, OS>(&calls).await {
+            Ok(results) if results.len() == senders.len() => {
+                for (result, tx) in results.into_iter().zip(senders) {
+                    _ = tx.send(match C::batch_response(result) {
+                        Ok(res) => Outcome::Done(res),
+                        Err(e) => Outcome::Failed(e),
+                    });
+                }
+            }
+            Ok(results) => {
+                let e = format!(
+                    "the batch response has {} results for {} calls",
+                    results.len(),
+                    senders.len()
+                );
+                for tx in senders {
+                    _ = tx.send(Outcome::Failed(e.clone()));
+                }
+            }
+            Err(e) => {
+                for tx in senders {
+                    _ = tx.send(Outcome::Failed(e.clone()));
+                }
+            }
+        }
+    }
+
+    async fn send_batch<C, E, IS, OS>(
+        calls: &[BatchCall],
+    ) -> Result<Vec<BatchResult>, String>
+    where
+        C: BatchClient<E, IS, OS>,
+        E: FromServerFnError,
+    {
+        let body = serde_json::to_vec(calls).map_err(|e| e.to_string())?;
+        let req = <C::Request as ClientReq<E>>::try_new_req_bytes(
+            batch_path(),
+            "application/json",
+            "application/json",
+            body.into(),
+            Method::POST,
+        )
+        .map_err(|e| format!("{e:?}"))?;
+        let res = C::send(req).await.map_err(|e| format!("{e:?}"))?;
+        let status = res.status();
+        let body = ClientRes::<E>::try_into_bytes(res)
+            .await
+            .map_err(|e| format!("{e:?}"))?;
+        if status != 200 {
+            return Err(format!(
+                "the batch request failed with status {status}: {}",
+                String::from_utf8_lossy(&body)
+            ));
+        }
+        serde_json::from_slice(&body).map_err(|e| e.to_string())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        struct ClientA;
+        struct ClientB;
+
+        fn pending() -> Pending<(), ()> {
+            Pending {
+                request: (),
+                call: BatchCall {
+                    method: "POST".into(),
+                    path: "/api/queued".into(),
+                    headers: Vec::new(),
+                    body: Bytes::new(),
+                },
+                tx: oneshot::channel().0,
+            }
+        }
+
+        #[test]
+        fn queues_are_kept_per_client() {
+            assert!(enqueue::<ClientA, _, _>(pending()));
+            assert!(enqueue::<ClientB, _, _>(pending()));
+            assert!(!enqueue::<ClientA, _, _>(pending()));
+            assert_eq!(take_queue::<ClientA, (), ()>().len(), 2);
+            assert_eq!(take_queue::<ClientB, (), ()>().len(), 1);
+            assert!(take_queue::<ClientA, (), ()>().is_empty());
+        }
+    }
 }
 
 #[cfg(feature = "browser")]
 /// Implements [`Client`] for a `fetch` request in the browser.
 pub mod browser {
-    use super::Client;
+    use super::{
+        batch::BatchClient,
+        cache::{CachedResponse, ClientCache},
+        retry, Client,
+    };
     use crate::{
+        batch::{BatchCall, BatchResult},
+        compression::{self, ContentEncoding},
         error::{FromServerFnError, IntoAppError, ServerFnErrorErr},
-        request::browser::{BrowserRequest, RequestInner},
+        request::browser::{
+            abort_signal, AbortOnDrop, BrowserRequest, RequestInner,
+        },
         response::browser::BrowserResponse,
+        version::BUILD_ID_HEADER,
+        websocket,
     };
     use bytes::Bytes;
-    use futures::{Sink, SinkExt, StreamExt};
-    use gloo_net::websocket::{Message, WebSocketError};
+    use futures::{
+        future::{select, Either},
+        Sink, SinkExt, StreamExt,
+    };
+    use gloo_net::{
+        http::{Headers, Request, Response},
+        websocket::{Message, WebSocketError},
+    };
+    use http::Method;
+    use js_sys::{Array, Function, Reflect, Uint8Array};
     use send_wrapper::SendWrapper;
-    use std::future::Future;
+    use std::{future::Future, pin::pin, time::Duration};
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::RequestInit;
 
     /// Implements [`Client`] for a `fetch` request in the browser.
     pub struct BrowserClient;
 
+    static CACHE: ClientCache = ClientCache::new(64);
+
+    impl BrowserClient {
+        /// The cache of responses used to make conditional requests.
+        pub fn cache() -> &'static ClientCache {
+            &CACHE
+        }
+    }
+
     impl<
             Error: FromServerFnError,
             InputStreamError: FromServerFnError,
@@ -83,27 +1132,68 @@ pub mod browser {
             req: Self::Request,
         ) -> impl Future<Output = Result<Self::Response, Error>> + Send
         {
+            #[cfg(feature = "trace-propagation")]
+            let req = {
+                let mut req = req;
+                crate::trace::inject::<Error>(&mut req);
+                req
+            };
+
             SendWrapper::new(async move {
                 let req = req.0.take();
                 let RequestInner {
                     request,
-                    mut abort_ctrl,
+                    abort_ctrl,
+                    compress,
+                    ..
                 } = req;
-                let res = request
-                    .send()
+                let request = if compress
+                    && compression::server_accepts(
+                        &request.url(),
+                        ContentEncoding::Gzip,
+                    ) {
+                    gzip(request).await
+                } else {
+                    request
+                };
+
+                // conditional requests are only made for GET requests
+                let cache_key =
+                    (request.method() == Method::GET).then(|| request.url());
+                let request_headers =
+                    request.headers().entries().collect::<Vec<_>>();
+                let cached = cache_key.as_deref().and_then(|url| {
+                    CACHE.get(url, header_value(&request_headers))
+                });
+                if let Some(cached) = &cached {
+                    request.headers().set("If-None-Match", &cached.etag);
+                }
+
+                let (res, abort_ctrl) =
+                    fetch(request, abort_ctrl, retry::retry_policy())
+                        .await
+                        .map_err(|e| {
+                            ServerFnErrorErr::Request(e).into_app_error()
+                        })?;
+                super::check_build_id(
+                    res.headers().get(BUILD_ID_HEADER).as_deref(),
+                );
+                if let Some(accepts) = res.headers().get("Accept-Encoding") {
+                    compression::set_server_accepts(&res.url(), &accepts);
+                }
+                let res = revalidate(res, cache_key, cached, &request_headers)
                     .await
-                    .map(|res| BrowserResponse(SendWrapper::new(res)))
                     .map_err(|e| {
                         ServerFnErrorErr::Request(e.to_string())
                             .into_app_error()
-                    });
+                    })?;
 
-                // at this point, the future has successfully resolved without being dropped, so we
-                // can prevent the `AbortController` from firing
-                if let Some(ctrl) = abort_ctrl.as_mut() {
-                    ctrl.prevent_cancellation();
-                }
-                res
+                // the `AbortController` moves into the response, so that the request is still
+                // aborted if the response is dropped before its body has been read
+                Ok(BrowserResponse(
+                    SendWrapper::new(res),
+                    SendWrapper::new(abort_ctrl),
+                ))
             })
         }
 
@@ -131,18 +1221,33 @@ pub mod browser {
                         })?;
                 let (sink, stream) = websocket.split();
 
-                let stream = stream.map(|message| match message {
-                    Ok(message) => Ok(match message {
-                        Message::Text(text) => Bytes::from(text),
-                        Message::Bytes(bytes) => Bytes::from(bytes),
-                    }),
-                    Err(err) => {
-                        web_sys::console::error_1(&err.to_string().into());
-                        Err(OutputStreamError::from_server_fn_error(
-                            ServerFnErrorErr::Request(err.to_string()),
-                        )
-                        .ser())
-                    }
+                let stream = stream.filter_map(|message| {
+                    std::future::ready(match message {
+                        Ok(Message::Text(text)) => Some(Ok(Bytes::from(text))),
+                        Ok(Message::Bytes(bytes)) => {
+                            Some(Ok(Bytes::from(bytes)))
+                        }
+                        // a normal closure ends the stream
+                        Err(WebSocketError::ConnectionClose(event))
+                            if event.was_clean && event.code == 1000 =>
+                        {
+                            None
+                        }
+                        Err(WebSocketError::ConnectionClose(event)) => {
+                            Some(Err(websocket::closed_error::<
+                                OutputStreamError,
+                            >(
+                                event.code, event.reason
+                            )))
+                        }
+                        Err(err) => {
+                            web_sys::console::error_1(&err.to_string().into());
+                            Some(Err(OutputStreamError::from_server_fn_error(
+                                ServerFnErrorErr::Request(err.to_string()),
+                            )
+                            .ser()))
+                        }
+                    })
                 });
                 let stream = SendWrapper::new(stream);
 
@@ -210,25 +1315,343 @@ pub mod browser {
         fn spawn(future: impl Future<Output = ()> + Send + 'static) {
             wasm_bindgen_futures::spawn_local(future);
         }
+
+        fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+            SendWrapper::new(sleep(duration))
+        }
+    }
+
+    impl<
+            Error: FromServerFnError,
+            InputStreamError: FromServerFnError,
+            OutputStreamError: FromServerFnError,
+        > BatchClient<Error, InputStreamError, OutputStreamError>
+        for BrowserClient
+    {
+        fn batch_call(
+            req: BrowserRequest,
+        ) -> impl Future<Output = (BrowserRequest, Option<BatchCall>)> + Send
+        {
+            SendWrapper::new(async move {
+                let inner = req.0.take();
+                // streaming bodies can't be batched
+                if inner.streaming {
+                    return (BrowserRequest(SendWrapper::new(inner)), None);
+                }
+                let request = web_sys::Request::from(inner.request);
+                let call = batch_call(&request).await.ok();
+                let inner = RequestInner {
+                    request: Request::from(request),
+                    ..inner
+                };
+                (BrowserRequest(SendWrapper::new(inner)), call)
+            })
+        }
+
+        fn batch_response(
+            result: BatchResult,
+        ) -> Result<BrowserResponse, String> {
+            let headers = Headers::new();
+            for (name, value) in &result.headers {
+                headers.append(name, value);
+            }
+            let mut body = result.body.to_vec();
+            // responses with these statuses can't have a body
+            let body = (!matches!(result.status, 101 | 204 | 205 | 304))
+                .then_some(body.as_mut_slice());
+            let res = Response::builder()
+                .status(result.status)
+                .headers(headers)
+                .body(body)
+                .map_err(|e| e.to_string())?;
+            Ok(BrowserResponse(
+                SendWrapper::new(res),
+                SendWrapper::new(None),
+            ))
+        }
+    }
+
+    /// Describes the request as a call in a batch, reading the body from a copy so that
+    /// the request can still be sent on its own.
+    async fn batch_call(
+        request: &web_sys::Request,
+    ) -> Result<BatchCall, JsValue> {
+        let url = url::Url::parse(&request.url())
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let body =
+            JsFuture::from(web_sys::Request::clone(request)?.array_buffer()?)
+                .await?;
+        Ok(BatchCall {
+            method: request.method(),
+            path: match url.query() {
+                Some(query) => format!("{}?{query}", url.path()),
+                None => url.path().to_string(),
+            },
+            headers: Headers::from_raw(request.headers()).entries().collect(),
+            body: Uint8Array::new(&body).to_vec().into(),
+        })
+    }
+
+    /// Compresses the request body with gzip, if the browser supports `CompressionStream`.
+    /// Otherwise, or if compressing fails, returns the request as it is.
+    async fn gzip(request: Request) -> Request {
+        let request = web_sys::Request::from(request);
+        match try_gzip(&request).await {
+            Ok(Some(compressed)) => Request::from(compressed),
+            _ => Request::from(request),
+        }
+    }
+
+    async fn try_gzip(
+        request: &web_sys::Request,
+    ) -> Result<Option<web_sys::Request>, JsValue> {
+        let Ok(compression_stream) =
+            Reflect::get(&js_sys::global(), &"CompressionStream".into())
+                .and_then(|constructor| constructor.dyn_into::<Function>())
+        else {
+            return Ok(None);
+        };
+        // read the body from a copy, so the original can still be sent if this fails
+        let Some(body) = request.clone()?.body() else {
+            return Ok(None);
+        };
+        let compression_stream = Reflect::construct(
+            &compression_stream,
+            &Array::of1(&ContentEncoding::Gzip.as_str().into()),
+        )?;
+        let pipe_through = Reflect::get(&body, &"pipeThrough".into())?
+            .dyn_into::<Function>()?;
+        let compressed = pipe_through
+            .call1(&body, &compression_stream)?
+            .dyn_into::<web_sys::ReadableStream>()?;
+        let compressed = JsFuture::from(
+            web_sys::Response::new_with_opt_readable_stream(Some(&compressed))?
+                .array_buffer()?,
+        )
+        .await?;
+
+        let init = RequestInit::new();
+        init.set_body(&compressed);
+        let compressed =
+            web_sys::Request::new_with_request_and_init(request, &init)?;
+        compressed
+            .headers()
+            .set("Content-Encoding", ContentEncoding::Gzip.as_str())?;
+        Ok(Some(compressed))
+    }
+
+    /// Sends the request, retrying it as allowed by the policy, and returns the response
+    /// along with the `AbortController` for the attempt that produced it.
+    ///
+    /// Each attempt is sent as a copy of the original request with its own
+    /// `AbortController`, so that an attempt that times out can be aborted without
+    /// affecting the next one.
+    async fn fetch(
+        request: Request,
+        abort_ctrl: Option<AbortOnDrop>,
+        policy: retry::RetryPolicy,
+    ) -> Result<(Response, Option<AbortOnDrop>), String> {
+        if !policy.is_enabled() {
+            return request
+                .send()
+                .await
+                .map(|res| (res, abort_ctrl))
+                .map_err(|e| e.to_string());
+        }
+
+        let method = request.method();
+        let template = web_sys::Request::from(request);
+        let mut attempt = 1;
+        loop {
+            let (abort_ctrl, abort_signal) = abort_signal();
+            let init = RequestInit::new();
+            init.set_signal(abort_signal.as_ref());
+            let request = web_sys::Request::clone(&template)
+                .and_then(|request| {
+                    web_sys::Request::new_with_request_and_init(&request, &init)
+                })
+                .map_err(|e| format!("{e:?}"))?;
+            let send = Request::from(request).send();
+            let res = match policy.timeout() {
+                Some(timeout) => {
+                    match select(pin!(send), pin!(sleep(timeout))).await {
+                        Either::Left((res, _)) => {
+                            res.map_err(|e| e.to_string())
+                        }
+                        // dropping `abort_ctrl` aborts the request
+                        Either::Right(_) => Err(format!(
+                            "request timed out after {}ms",
+                            timeout.as_millis()
+                        )),
+                    }
+                }
+                None => send.await.map_err(|e| e.to_string()),
+            };
+            let delay = match &res {
+                Ok(res)
+                    if policy.retries_status(res.status())
+                        && policy.can_retry(&method, attempt, true) =>
+                {
+                    policy.delay(
+                        attempt,
+                        res.headers().get("Retry-After").as_deref(),
+                    )
+                }
+                Err(_) if policy.can_retry(&method, attempt, is_online()) => {
+                    policy.delay(attempt, None)
+                }
+                _ => None,
+            };
+            let Some(delay) = delay else {
+                return res.map(|res| (res, abort_ctrl));
+            };
+            drop(abort_ctrl);
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Whether the browser thinks it is online. A request that fails while it is offline
+    /// can't have reached the server.
+    fn is_online() -> bool {
+        Reflect::get(&js_sys::global(), &"navigator".into())
+            .and_then(|navigator| Reflect::get(&navigator, &"onLine".into()))
+            .ok()
+            .and_then(|online| online.as_bool())
+            .unwrap_or(true)
+    }
+
+    /// Waits for the given duration, using `setTimeout`.
+    async fn sleep(duration: Duration) {
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            let global = js_sys::global();
+            let set_timeout =
+                Reflect::get(&global, &"setTimeout".into()).ok().and_then(
+                    |set_timeout| set_timeout.dyn_into::<Function>().ok(),
+                );
+            let millis = duration.as_millis().min(i32::MAX as u128) as i32;
+            if let Some(set_timeout) = set_timeout {
+                _ = set_timeout.call2(&global, &resolve, &millis.into());
+            }
+        });
+        _ = JsFuture::from(promise).await;
+    }
+
+    /// Returns the value of a request header, for matching the `Vary` header of a cached
+    /// response.
+    fn header_value(
+        headers: &[(String, String)],
+    ) -> impl Fn(&str) -> Option<String> + '_ {
+        |name| {
+            let values = headers
+                .iter()
+                .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+                .collect::<Vec<_>>();
+            (!values.is_empty()).then(|| values.join(", "))
+        }
+    }
+
+    /// Answers a `304 Not Modified` from the client-side cache, and stores
+    /// cacheable responses that carry an `ETag`.
+    async fn revalidate(
+        res: Response,
+        cache_key: Option<String>,
+        cached: Option<CachedResponse>,
+        request_headers: &[(String, String)],
+    ) -> Result<Response, gloo_net::Error> {
+        let Some(cache_key) = cache_key else {
+            return Ok(res);
+        };
+        let headers = res.headers();
+        match (res.status(), cached) {
+            (304, Some(cached)) => {
+                if let Some(content_type) = &cached.content_type {
+                    headers.set("Content-Type", content_type);
+                }
+                let mut body = cached.body.to_vec();
+                Response::builder()
+                    .status(200)
+                    .headers(headers)
+                    .body(Some(body.as_mut_slice()))
+            }
+            (200, _) => {
+                let etag = headers.get("ETag");
+                match etag.filter(|_| {
+                    CACHE.is_storable(headers.get("Cache-Control").as_deref())
+                }) {
+                    Some(etag) => {
+                        let mut body = res.binary().await?;
+                        CACHE.insert(
+                            cache_key,
+                            headers.get("Vary").as_deref(),
+                            header_value(request_headers),
+                            CachedResponse {
+                                etag,
+                                content_type: headers.get("Content-Type"),
+                                body: Bytes::copy_from_slice(&body),
+                            },
+                        );
+                        Response::builder()
+                            .status(200)
+                            .headers(headers)
+                            .body(Some(body.as_mut_slice()))
+                    }
+                    None => {
+                        CACHE.remove(&cache_key);
+                        Ok(res)
+                    }
+                }
+            }
+            _ => Ok(res),
+        }
     }
 }
 
 #[cfg(feature = "reqwest")]
 /// Implements [`Client`] for a request made by [`reqwest`].
 pub mod reqwest {
-    use super::{get_server_url, Client};
+    use super::{
+        batch::BatchClient,
+        cache::{CachedResponse, ClientCache},
+        get_server_url, retry, Client,
+    };
     use crate::{
+        batch::{BatchCall, BatchResult},
+        compression::{self, ContentEncoding, COMPRESSION_THRESHOLD},
         error::{FromServerFnError, IntoAppError, ServerFnErrorErr},
         request::reqwest::CLIENT,
+        version::BUILD_ID_HEADER,
+        websocket,
     };
     use bytes::Bytes;
-    use futures::{SinkExt, StreamExt, TryFutureExt};
-    use reqwest::{Request, Response};
-    use std::future::Future;
+    use futures::{SinkExt, StreamExt, TryStreamExt};
+    use http::{
+        header::{
+            ACCEPT_ENCODING, CACHE_CONTROL, CONTENT_ENCODING, CONTENT_LENGTH,
+            CONTENT_TYPE, ETAG, IF_NONE_MATCH, RETRY_AFTER, VARY,
+        },
+        HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+    };
+    use http_body_util::BodyExt;
+    use reqwest::{Body, Request, Response};
+    use std::{future::Future, io, time::Duration};
 
     /// Implements [`Client`] for a request made by [`reqwest`].
     pub struct ReqwestClient;
 
+    // this client may make requests on behalf of many users while rendering on the server
+    static CACHE: ClientCache = ClientCache::new_shared(64);
+
+    impl ReqwestClient {
+        /// The cache of responses used to make conditional requests.
+        ///
+        /// This is a shared cache, so responses marked `private` are never stored.
+        pub fn cache() -> &'static ClientCache {
+            &CACHE
+        }
+    }
+
     impl<
             Error: FromServerFnError,
             InputStreamError: FromServerFnError,
@@ -239,12 +1662,57 @@ pub mod reqwest {
         type Response = Response;
 
         fn send(
-            req: Self::Request,
+            mut req: Self::Request,
         ) -> impl Future<Output = Result<Self::Response, Error>> + Send
         {
-            CLIENT.execute(req).map_err(|e| {
-                ServerFnErrorErr::Request(e.to_string()).into_app_error()
-            })
+            #[cfg(feature = "trace-propagation")]
+            crate::trace::inject::<Error>(&mut req);
+
+            compress(&mut req);
+
+            // conditional requests are only made for GET requests
+            let cache_key =
+                (req.method() == Method::GET).then(|| req.url().to_string());
+            let request_headers = req.headers().clone();
+            let cached = cache_key
+                .as_deref()
+                .and_then(|url| CACHE.get(url, header_value(&request_headers)));
+            if let Some(etag) = cached
+                .as_ref()
+                .and_then(|cached| HeaderValue::from_str(&cached.etag).ok())
+            {
+                req.headers_mut().insert(IF_NONE_MATCH, etag);
+            }
+
+            async move {
+                let res =
+                    execute(req, retry::retry_policy()).await.map_err(|e| {
+                        ServerFnErrorErr::Request(e.to_string())
+                            .into_app_error()
+                    })?;
+                super::check_build_id(
+                    res.headers()
+                        .get(BUILD_ID_HEADER)
+                        .and_then(|value| value.to_str().ok()),
+                );
+                if let Some(accepts) = res
+                    .headers()
+                    .get(ACCEPT_ENCODING)
+                    .and_then(|value| value.to_str().ok())
+                {
+                    compression::set_server_accepts(
+                        res.url().as_str(),
+                        accepts,
+                    );
+                }
+                let res = decompress(res);
+                revalidate(res, cache_key, cached, &request_headers)
+                    .await
+                    .map_err(|e| {
+                        ServerFnErrorErr::Request(e.to_string())
+                            .into_app_error()
+                    })
+            }
         }
 
         async fn open_websocket(
@@ -276,12 +1744,33 @@ pub mod reqwest {
             let (write, read) = ws_stream.split();
 
             Ok((
-                read.map(|msg| match msg {
-                    Ok(msg) => Ok(msg.into_data()),
-                    Err(e) => Err(OutputStreamError::from_server_fn_error(
-                        ServerFnErrorErr::Request(e.to_string()),
-                    )
-                    .ser()),
+                read.filter_map(|msg| {
+                    use tokio_tungstenite::tungstenite::{
+                        protocol::frame::coding::CloseCode, Message,
+                    };
+
+                    std::future::ready(match msg {
+                        Ok(Message::Binary(bytes)) => Some(Ok(bytes)),
+                        Ok(Message::Text(text)) => Some(Ok(text.into())),
+                        // a normal closure ends the stream
+                        Ok(Message::Close(Some(frame)))
+                            if frame.code != CloseCode::Normal =>
+                        {
+                            Some(Err(websocket::closed_error::<
+                                OutputStreamError,
+                            >(
+                                frame.code.into(),
+                                frame.reason.as_str(),
+                            )))
+                        }
+                        Ok(_) => None,
+                        Err(e) => Some(Err(websocket::closed_error::<
+                            OutputStreamError,
+                        >(
+                            websocket::ABNORMAL_CLOSURE,
+                            e.to_string(),
+                        ))),
+                    })
                 }),
                 write.with(|msg: Bytes| async move {
                     Ok::<
@@ -297,5 +1786,261 @@ pub mod reqwest {
         fn spawn(future: impl Future<Output = ()> + Send + 'static) {
             tokio::spawn(future);
         }
+
+        fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+            tokio::time::sleep(duration)
+        }
+    }
+
+    impl<
+            Error: FromServerFnError,
+            InputStreamError: FromServerFnError,
+            OutputStreamError: FromServerFnError,
+        > BatchClient<Error, InputStreamError, OutputStreamError>
+        for ReqwestClient
+    {
+        async fn batch_call(req: Request) -> (Request, Option<BatchCall>) {
+            // streaming bodies can't be batched
+            let body = match req.body() {
+                Some(body) => body.as_bytes().map(Bytes::copy_from_slice),
+                None => Some(Bytes::new()),
+            };
+            let call = body.map(|body| {
+                let url = req.url();
+                BatchCall {
+                    method: req.method().to_string(),
+                    path: match url.query() {
+                        Some(query) => format!("{}?{query}", url.path()),
+                        None => url.path().to_string(),
+                    },
+                    headers: req
+                        .headers()
+                        .iter()
+                        .filter_map(|(name, value)| {
+                            Some((
+                                name.to_string(),
+                                value.to_str().ok()?.to_string(),
+                            ))
+                        })
+                        .collect(),
+                    body,
+                }
+            });
+            (req, call)
+        }
+
+        fn batch_response(result: BatchResult) -> Result<Response, String> {
+            let mut res = http::Response::new(result.body);
+            *res.status_mut() = StatusCode::from_u16(result.status)
+                .map_err(|e| e.to_string())?;
+            for (name, value) in result.headers {
+                let name = HeaderName::from_bytes(name.as_bytes())
+                    .map_err(|e| e.to_string())?;
+                let value =
+                    HeaderValue::from_str(&value).map_err(|e| e.to_string())?;
+                res.headers_mut().append(name, value);
+            }
+            Ok(Response::from(res))
+        }
+    }
+
+    /// Asks for a compressed response, and compresses the request body if the server
+    /// accepts one of the enabled formats and the body is large enough or streaming.
+    fn compress(req: &mut Request) {
+        if let Some(accepts) = compression::accept_encoding()
+            .and_then(|value| HeaderValue::from_str(&value).ok())
+        {
+            req.headers_mut().entry(ACCEPT_ENCODING).or_insert(accepts);
+        }
+        let Some(encoding) = compression::request_encoding(req.url().as_str())
+        else {
+            return;
+        };
+        let compressible = match req.body() {
+            Some(body) => body
+                .as_bytes()
+                .is_none_or(|bytes| bytes.len() >= COMPRESSION_THRESHOLD),
+            None => false,
+        };
+        if !compressible || req.headers().contains_key(CONTENT_ENCODING) {
+            return;
+        }
+        let Some(body) = req.body_mut().take() else {
+            return;
+        };
+        let body =
+            match body.as_bytes() {
+                Some(bytes) => match compression::compress(encoding, bytes) {
+                    Ok(compressed) => Body::from(compressed),
+                    Err(_) => {
+                        *req.body_mut() = Some(body);
+                        return;
+                    }
+                },
+                None => match compression::Compressor::new(encoding) {
+                    Ok(compressor) => Body::wrap_stream(compressor.stream(
+                        body.into_data_stream().map_err(io::Error::other),
+                    )),
+                    Err(_) => {
+                        *req.body_mut() = Some(body);
+                        return;
+                    }
+                },
+            };
+        *req.body_mut() = Some(body);
+        req.headers_mut().remove(CONTENT_LENGTH);
+        req.headers_mut().insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+    }
+
+    /// Decompresses the response body, if it is in one of the enabled formats.
+    fn decompress(res: Response) -> Response {
+        let decompressor = res
+            .headers()
+            .get(CONTENT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(ContentEncoding::parse)
+            .and_then(|encoding| compression::Decompressor::new(encoding).ok());
+        let Some(decompressor) = decompressor else {
+            return res;
+        };
+        let (mut parts, body) = http::Response::from(res).into_parts();
+        parts.headers.remove(CONTENT_ENCODING);
+        parts.headers.remove(CONTENT_LENGTH);
+        let body = decompressor
+            .stream(body.into_data_stream().map_err(io::Error::other));
+        Response::from(http::Response::from_parts(
+            parts,
+            Body::wrap_stream(body),
+        ))
+    }
+
+    /// Sends the request, retrying it as allowed by the policy.
+    async fn execute(
+        mut req: Request,
+        policy: retry::RetryPolicy,
+    ) -> Result<Response, reqwest::Error> {
+        if let Some(timeout) = policy.timeout() {
+            *req.timeout_mut() = Some(timeout);
+        }
+        let method = req.method().clone();
+        let mut attempt = 1;
+        loop {
+            // requests with a streaming body can't be cloned, so they are only sent once
+            let next = if attempt < policy.max_attempts() {
+                req.try_clone()
+            } else {
+                None
+            };
+            let res = CLIENT.execute(req).await;
+            let retry = match (&res, next) {
+                (Ok(res), Some(next))
+                    if policy.retries_status(res.status().as_u16())
+                        && policy.can_retry(&method, attempt, true) =>
+                {
+                    let retry_after = res
+                        .headers()
+                        .get(RETRY_AFTER)
+                        .and_then(|value| value.to_str().ok());
+                    policy.delay(attempt, retry_after).zip(Some(next))
+                }
+                (Err(e), Some(next))
+                    if policy.can_retry(&method, attempt, !e.is_connect()) =>
+                {
+                    policy.delay(attempt, None).zip(Some(next))
+                }
+                _ => None,
+            };
+            let Some((delay, next)) = retry else {
+                return res;
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+            req = next;
+        }
+    }
+
+    /// Returns the value of a request header, for matching the `Vary` header of a cached
+    /// response.
+    fn header_value(
+        headers: &HeaderMap,
+    ) -> impl Fn(&str) -> Option<String> + '_ {
+        |name| {
+            let values = headers
+                .get_all(name)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .collect::<Vec<_>>();
+            (!values.is_empty()).then(|| values.join(", "))
+        }
+    }
+
+    /// Answers a `304 Not Modified` from the client-side cache, and stores
+    /// cacheable responses that carry an `ETag`.
+    async fn revalidate(
+        res: Response,
+        cache_key: Option<String>,
+        cached: Option<CachedResponse>,
+        request_headers: &HeaderMap,
+    ) -> Result<Response, reqwest::Error> {
+        let Some(cache_key) = cache_key else {
+            return Ok(res);
+        };
+        match (res.status(), cached) {
+            (StatusCode::NOT_MODIFIED, Some(cached)) => {
+                let mut headers = res.headers().clone();
+                if let Some(content_type) = cached
+                    .content_type
+                    .as_deref()
+                    .and_then(|value| HeaderValue::from_str(value).ok())
+                {
+                    headers.insert(CONTENT_TYPE, content_type);
+                }
+                Ok(rebuild(headers, cached.body))
+            }
+            (StatusCode::OK, _) => {
+                let header = |name| {
+                    res.headers()
+                        .get(name)
+                        .and_then(|value: &HeaderValue| value.to_str().ok())
+                        .map(ToOwned::to_owned)
+                };
+                let etag = header(ETAG).filter(|_| {
+                    CACHE.is_storable(header(CACHE_CONTROL).as_deref())
+                });
+                match etag {
+                    Some(etag) => {
+                        let content_type = header(CONTENT_TYPE);
+                        let vary = header_value(res.headers())(VARY.as_str());
+                        let headers = res.headers().clone();
+                        let body = res.bytes().await?;
+                        CACHE.insert(
+                            cache_key,
+                            vary.as_deref(),
+                            header_value(request_headers),
+                            CachedResponse {
+                                etag,
+                                content_type,
+                                body: body.clone(),
+                            },
+                        );
+                        Ok(rebuild(headers, body))
+                    }
+                    None => {
+                        CACHE.remove(&cache_key);
+                        Ok(res)
+                    }
+                }
+            }
+            _ => Ok(res),
+        }
+    }
+
+    fn rebuild(headers: http::HeaderMap, body: Bytes) -> Response {
+        let mut res = http::Response::new(body);
+        *res.headers_mut() = headers;
+        Response::from(res)
     }
 }
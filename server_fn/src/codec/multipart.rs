@@ -1,6 +1,8 @@
 use super::{Encoding, FromReq};
 use crate::{
-    error::{FromServerFnError, ServerFnErrorWrapper},
+    error::{
+        FromServerFnError, IntoAppError, ServerFnErrorErr, ServerFnErrorWrapper,
+    },
     request::{browser::BrowserFormData, ClientReq, Req},
     ContentType, IntoReq,
 };
@@ -59,19 +61,32 @@ impl From<FormData> for MultipartData {
     }
 }
 
+impl TryFrom<MultipartData> for BrowserFormData {
+    type Error = MultipartData;
+
+    fn try_from(data: MultipartData) -> Result<Self, Self::Error> {
+        match data {
+            MultipartData::Client(data) => Ok(data),
+            data => Err(data),
+        }
+    }
+}
+
 impl<E: FromServerFnError, T, Request> IntoReq<MultipartFormData, Request, E>
     for T
 where
-    Request: ClientReq<E, FormData = BrowserFormData>,
+    Request: ClientReq<E>,
+    Request::FormData: TryFrom<MultipartData>,
     T: Into<MultipartData>,
 {
     fn into_req(self, path: &str, accepts: &str) -> Result<Request, E> {
-        let multi = self.into();
-        Request::try_new_post_multipart(
-            path,
-            accepts,
-            multi.into_client_data().unwrap(),
-        )
+        let data = Request::FormData::try_from(self.into()).map_err(|_| {
+            ServerFnErrorErr::Serialization(
+                "this client can't send this kind of multipart data".into(),
+            )
+            .into_app_error()
+        })?;
+        Request::try_new_post_multipart(path, accepts, data)
     }
 }
 
@@ -1,14 +1,14 @@
-use super::{Encoding, FromReq, FromRes, IntoReq};
+use super::{Encoding, FromReq, FromRes, IntoReq, JsonEncoding};
 use crate::{
     error::{FromServerFnError, ServerFnErrorErr},
     request::{ClientReq, Req},
     response::{ClientRes, TryRes},
-    ContentType, IntoRes, ServerFnError,
+    ContentType, Decodes, Encodes, Format, FormatType, IntoRes, ServerFnError,
 };
-use bytes::Bytes;
-use futures::{Stream, StreamExt, TryStreamExt};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{future, Stream, StreamExt, TryStreamExt};
 use http::Method;
-use std::{fmt::Debug, pin::Pin};
+use std::{fmt::Debug, marker::PhantomData, ops::Deref, pin::Pin};
 
 /// An encoding that represents a stream of bytes.
 ///
@@ -280,3 +280,501 @@ where
         }))))
     }
 }
+
+/// An encoding that represents a stream of values, each encoded with `Codec`.
+///
+/// A server function that uses this as its output encoding should return a [`FramedStream`],
+/// such as a [`JsonStream`]. Values in a text format (like JSON) are separated by newlines, and
+/// values in a binary format are prefixed with their length as a 32-bit big-endian integer.
+///
+/// ## Browser Support for Streaming Input
+///
+/// Browser fetch requests do not currently support full request duplexing, which
+/// means that that they do begin handling responses until the full request has been sent.
+/// This means that if you use a streaming input encoding, the input stream needs to
+/// end before the output will begin.
+///
+/// Streaming requests are only allowed over HTTP2 or HTTP3.
+pub struct Framed<Codec>(PhantomData<Codec>);
+
+/// An encoding that represents a stream of newline-delimited JSON values.
+pub type StreamingJson = Framed<JsonEncoding>;
+
+/// An encoding that represents a stream of length-prefixed CBOR values.
+#[cfg(feature = "cbor")]
+pub type StreamingCbor = Framed<super::CborEncoding>;
+
+/// An encoding that represents a stream of length-prefixed postcard values.
+#[cfg(feature = "postcard")]
+pub type StreamingPostcard = Framed<super::PostcardEncoding>;
+
+impl<Codec: FormatType> ContentType for Framed<Codec> {
+    const CONTENT_TYPE: &'static str = match Codec::FORMAT_TYPE {
+        Format::Text => "application/x-ndjson",
+        Format::Binary => "application/octet-stream",
+    };
+}
+
+impl<Codec: FormatType> Encoding for Framed<Codec> {
+    const METHOD: Method = Method::POST;
+}
+
+/// A stream of values, each encoded with `Codec`.
+///
+/// A server function can return this type if its output encoding is [`Framed`], and the client
+/// receives each value as soon as it arrives.
+///
+/// ```rust, no_run
+/// # use server_fn_macro_default::server;
+/// # #[cfg(feature = "browser")] {
+/// use futures::StreamExt;
+/// use serde::{Deserialize, Serialize};
+/// use server_fn::{
+///     codec::{JsonStream, StreamingJson},
+///     ServerFnError,
+/// };
+///
+/// #[derive(Serialize, Deserialize)]
+/// pub struct Row {
+///     id: u32,
+/// }
+///
+/// #[server(output = StreamingJson)]
+/// async fn rows() -> Result<JsonStream<Row>, ServerFnError> {
+///     Ok(futures::stream::iter(0..10).map(|id| Row { id }).into())
+/// }
+///
+/// // a stream that can fail is created with `FramedStream::new`
+/// #[server(output = StreamingJson)]
+/// async fn checked_rows() -> Result<JsonStream<Row>, ServerFnError> {
+///     Ok(JsonStream::new(futures::stream::iter(0..10).map(|id| {
+///         if id < 5 {
+///             Ok(Row { id })
+///         } else {
+///             Err(ServerFnError::new("too many rows"))
+///         }
+///     })))
+/// }
+/// # }
+/// ```
+///
+/// Each value can be at most [`MAX_FRAME_SIZE`] bytes long when it is decoded.
+///
+/// ## Browser Support for Streaming Input
+///
+/// Browser fetch requests do not currently support full request duplexing, which
+/// means that that they do begin handling responses until the full request has been sent.
+/// This means that if you use a streaming input encoding, the input stream needs to
+/// end before the output will begin.
+///
+/// Streaming requests are only allowed over HTTP2 or HTTP3.
+pub struct FramedStream<Codec, T, E = ServerFnError>(
+    Pin<Box<dyn Stream<Item = Result<T, E>> + Send>>,
+    PhantomData<fn() -> Codec>,
+);
+
+/// A stream of newline-delimited JSON values.
+pub type JsonStream<T, E = ServerFnError> = FramedStream<JsonEncoding, T, E>;
+
+/// A stream of length-prefixed CBOR values.
+#[cfg(feature = "cbor")]
+pub type CborStream<T, E = ServerFnError> =
+    FramedStream<super::CborEncoding, T, E>;
+
+/// A stream of length-prefixed postcard values.
+#[cfg(feature = "postcard")]
+pub type PostcardStream<T, E = ServerFnError> =
+    FramedStream<super::PostcardEncoding, T, E>;
+
+impl<Codec, T, E> Debug for FramedStream<Codec, T, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("FramedStream").finish()
+    }
+}
+
+impl<Codec, T, E> FramedStream<Codec, T, E> {
+    /// Creates a new `FramedStream` from a stream of results.
+    ///
+    /// Streams of values that can't fail can be converted with `into()` instead. When the stream
+    /// is sent, an error ends it.
+    pub fn new(
+        value: impl Stream<Item = Result<T, E>> + Send + 'static,
+    ) -> Self {
+        Self(Box::pin(value), PhantomData)
+    }
+
+    /// Consumes the wrapper, returning a stream of values.
+    pub fn into_inner(self) -> impl Stream<Item = Result<T, E>> + Send {
+        self.0
+    }
+}
+
+impl<Codec, T, E, S> From<S> for FramedStream<Codec, T, E>
+where
+    S: Stream<Item = T> + Send + 'static,
+    T: 'static,
+    E: 'static,
+{
+    fn from(value: S) -> Self {
+        Self(Box::pin(value.map(Ok)), PhantomData)
+    }
+}
+
+impl<E, S, T, Codec, Request> IntoReq<Framed<Codec>, Request, E> for S
+where
+    Request: ClientReq<E>,
+    S: Deref<Target = FramedStream<Codec, T, E>>
+        + Into<FramedStream<Codec, T, E>>,
+    Codec: Encodes<T> + 'static,
+    T: 'static,
+    E: FromServerFnError + Send + 'static,
+{
+    fn into_req(self, path: &str, accepts: &str) -> Result<Request, E> {
+        // a request body can't carry errors, so the stream ends at the first one
+        let data = self
+            .into()
+            .into_inner()
+            .map(|item| {
+                item.and_then(|item| encode_frame::<Codec, T, E>(&item))
+            })
+            .take_while(|frame| future::ready(frame.is_ok()))
+            .filter_map(|frame| future::ready(frame.ok()));
+        Request::try_new_post_streaming(
+            path,
+            accepts,
+            Framed::<Codec>::CONTENT_TYPE,
+            data,
+        )
+    }
+}
+
+impl<E, S, T, Codec, Request> FromReq<Framed<Codec>, Request, E> for S
+where
+    Request: Req<E> + Send + 'static,
+    S: Deref<Target = FramedStream<Codec, T, E>>
+        + From<FramedStream<Codec, T, E>>
+        + 'static,
+    Codec: Decodes<T> + FormatType,
+    T: Send + 'static,
+    E: FromServerFnError + Send,
+{
+    async fn from_req(req: Request) -> Result<Self, E> {
+        let data = req.try_into_stream()?;
+        Ok(decode_frames(data).into())
+    }
+}
+
+impl<E, T, Codec, Response> IntoRes<Framed<Codec>, Response, E>
+    for FramedStream<Codec, T, E>
+where
+    Response: TryRes<E>,
+    Codec: Encodes<T> + 'static,
+    T: 'static,
+    E: FromServerFnError,
+{
+    async fn into_res(self) -> Result<Response, E> {
+        // the response ends with the first error
+        let mut failed = false;
+        let data = self
+            .into_inner()
+            .map(|item| {
+                item.and_then(|item| encode_frame::<Codec, T, E>(&item))
+                    .map_err(|e| e.ser())
+            })
+            .take_while(move |frame| {
+                let more = !failed;
+                failed |= frame.is_err();
+                future::ready(more)
+            });
+        Response::try_from_stream(Framed::<Codec>::CONTENT_TYPE, data)
+    }
+}
+
+impl<E, T, Codec, Response> FromRes<Framed<Codec>, Response, E>
+    for FramedStream<Codec, T, E>
+where
+    Response: ClientRes<E> + Send,
+    Codec: Decodes<T> + FormatType,
+    T: Send + 'static,
+    E: FromServerFnError + Send,
+{
+    async fn from_res(res: Response) -> Result<Self, E> {
+        let stream = res.try_into_stream()?;
+        Ok(decode_frames(stream))
+    }
+}
+
+/// Encodes a single value, with a trailing newline or a length prefix.
+fn encode_frame<Codec, T, E>(item: &T) -> Result<Bytes, E>
+where
+    Codec: Encodes<T>,
+    E: FromServerFnError,
+{
+    let data = Codec::encode(item).map_err(|e| {
+        E::from_server_fn_error(ServerFnErrorErr::Serialization(e.to_string()))
+    })?;
+    let mut frame = BytesMut::with_capacity(data.len() + 4);
+    match Codec::FORMAT_TYPE {
+        Format::Text => {
+            frame.extend_from_slice(&data);
+            frame.put_u8(b'\n');
+        }
+        Format::Binary => {
+            let len = u32::try_from(data.len()).map_err(|e| {
+                E::from_server_fn_error(ServerFnErrorErr::Serialization(
+                    e.to_string(),
+                ))
+            })?;
+            frame.put_u32(len);
+            frame.extend_from_slice(&data);
+        }
+    }
+    Ok(frame.freeze())
+}
+
+/// The largest value that is decoded from a [`FramedStream`], in bytes.
+///
+/// The length of each value is sent by the other side, so a larger value ends the stream with
+/// an error instead of being buffered.
+pub const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Splits the next complete frame off the front of the buffer, if there is one.
+fn split_frame(
+    format: &Format,
+    buf: &mut BytesMut,
+) -> Result<Option<Bytes>, String> {
+    let too_large = || format!("a value is larger than {MAX_FRAME_SIZE} bytes");
+    match format {
+        Format::Text => {
+            let Some(end) = buf.iter().position(|b| *b == b'\n') else {
+                return if buf.len() > MAX_FRAME_SIZE {
+                    Err(too_large())
+                } else {
+                    Ok(None)
+                };
+            };
+            if end > MAX_FRAME_SIZE {
+                return Err(too_large());
+            }
+            let mut line = buf.split_to(end + 1);
+            line.truncate(end);
+            Ok(Some(line.freeze()))
+        }
+        Format::Binary => {
+            let Some(len) = buf.get(..4) else {
+                return Ok(None);
+            };
+            let len = u32::from_be_bytes(len.try_into().unwrap()) as usize;
+            if len > MAX_FRAME_SIZE {
+                return Err(too_large());
+            }
+            if buf.len() < 4 + len {
+                return Ok(None);
+            }
+            buf.advance(4);
+            Ok(Some(buf.split_to(len).freeze()))
+        }
+    }
+}
+
+/// Decodes a stream of frames, as they arrive in chunks.
+fn decode_frames<Codec, T, E>(
+    stream: impl Stream<Item = Result<Bytes, Bytes>> + Send + 'static,
+) -> FramedStream<Codec, T, E>
+where
+    Codec: Decodes<T> + FormatType,
+    T: Send + 'static,
+    E: FromServerFnError + Send,
+{
+    let decode = |frame: Bytes| {
+        Codec::decode(frame).map_err(|e| {
+            E::from_server_fn_error(ServerFnErrorErr::Deserialization(
+                e.to_string(),
+            ))
+        })
+    };
+    let state = (Box::pin(stream), BytesMut::new(), false);
+    FramedStream::new(futures::stream::unfold(
+        state,
+        move |(mut stream, mut buf, mut done)| async move {
+            loop {
+                match split_frame(&Codec::FORMAT_TYPE, &mut buf) {
+                    // tolerate blank lines between values
+                    Ok(Some(frame))
+                        if frame.is_empty()
+                            && matches!(Codec::FORMAT_TYPE, Format::Text) =>
+                    {
+                        continue;
+                    }
+                    Ok(Some(frame)) => {
+                        return Some((decode(frame), (stream, buf, done)));
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        done = true;
+                        buf.clear();
+                        let err = E::from_server_fn_error(
+                            ServerFnErrorErr::Deserialization(e),
+                        );
+                        return Some((Err(err), (stream, buf, done)));
+                    }
+                }
+                if done {
+                    return None;
+                }
+                match stream.next().await {
+                    Some(Ok(chunk)) => buf.extend_from_slice(&chunk),
+                    Some(Err(e)) => {
+                        done = true;
+                        buf.clear();
+                        return Some((Err(E::de(e)), (stream, buf, done)));
+                    }
+                    None => {
+                        done = true;
+                        if buf.is_empty() {
+                            return None;
+                        }
+                        let rest = buf.split().freeze();
+                        let item = match Codec::FORMAT_TYPE {
+                            // the last value may not be followed by a newline
+                            Format::Text => decode(rest),
+                            Format::Binary => Err(E::from_server_fn_error(
+                                ServerFnErrorErr::Deserialization(
+                                    "the stream ended in the middle of a \
+                                     value"
+                                        .into(),
+                                ),
+                            )),
+                        };
+                        return Some((item, (stream, buf, done)));
+                    }
+                }
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    fn roundtrip<Codec>(
+        chunk_size: usize,
+    ) -> Vec<Result<Vec<u32>, ServerFnError>>
+    where
+        Codec: Encodes<Vec<u32>> + Decodes<Vec<u32>>,
+    {
+        let items = [vec![1, 2], vec![], vec![3]];
+        let bytes = items
+            .iter()
+            .flat_map(|item| {
+                encode_frame::<Codec, _, ServerFnError>(item)
+                    .unwrap()
+                    .to_vec()
+            })
+            .collect::<Vec<_>>();
+        // split the frames across arbitrary chunks
+        let chunks = bytes
+            .chunks(chunk_size)
+            .map(|chunk| Ok(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+        let stream: FramedStream<Codec, Vec<u32>> =
+            decode_frames(futures::stream::iter(chunks));
+        block_on(stream.into_inner().collect())
+    }
+
+    #[test]
+    fn decodes_frames_split_across_chunks() {
+        let expected = vec![Ok(vec![1, 2]), Ok(vec![]), Ok(vec![3])];
+        for chunk_size in [1, 3, 64] {
+            assert_eq!(roundtrip::<JsonEncoding>(chunk_size), expected);
+            #[cfg(feature = "cbor")]
+            assert_eq!(
+                roundtrip::<super::super::CborEncoding>(chunk_size),
+                expected
+            );
+        }
+    }
+
+    fn decode_json(
+        chunks: Vec<Result<Bytes, Bytes>>,
+    ) -> Vec<Result<Vec<u32>, ServerFnError>> {
+        let stream: JsonStream<Vec<u32>> =
+            decode_frames(futures::stream::iter(chunks));
+        block_on(stream.into_inner().collect())
+    }
+
+    #[test]
+    fn tolerates_blank_lines_and_a_missing_last_newline() {
+        let chunks = vec![Ok(Bytes::from_static(b"[1]\n\n\n[2]"))];
+        assert_eq!(decode_json(chunks), vec![Ok(vec![1]), Ok(vec![2])]);
+    }
+
+    #[test]
+    fn ends_at_the_first_transport_error() {
+        let err = ServerFnError::new("connection lost").ser();
+        let chunks = vec![
+            Ok(Bytes::from_static(b"[1]\n[2")),
+            Err(err),
+            Ok(Bytes::from_static(b"]\n[3]\n")),
+        ];
+        let items = decode_json(chunks);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0], Ok(vec![1]));
+        assert_eq!(items[1], Err(ServerFnError::new("connection lost")));
+    }
+
+    #[test]
+    fn rejects_frames_over_the_limit() {
+        // an unterminated line is not buffered past the limit
+        let chunks = vec![
+            Ok(Bytes::from_static(b"[1]\n")),
+            Ok(Bytes::from(vec![b' '; MAX_FRAME_SIZE + 1])),
+            Ok(Bytes::from_static(b"[2]\n")),
+        ];
+        let items = decode_json(chunks);
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0], Ok(vec![1]));
+        assert!(matches!(items[1], Err(ServerFnError::Deserialization(_))));
+
+        // a length prefix is checked before the value arrives
+        let mut buf = BytesMut::new();
+        buf.put_u32(MAX_FRAME_SIZE as u32 + 1);
+        assert!(split_frame(&Format::Binary, &mut buf).is_err());
+
+        let mut buf = BytesMut::new();
+        buf.put_u32(3);
+        buf.extend_from_slice(b"ab");
+        assert_eq!(split_frame(&Format::Binary, &mut buf), Ok(None));
+        buf.extend_from_slice(b"c");
+        assert_eq!(
+            split_frame(&Format::Binary, &mut buf),
+            Ok(Some(Bytes::from_static(b"abc")))
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[cfg(feature = "axum")]
+    #[test]
+    fn fallible_streams_end_at_the_first_error() {
+        use axum::body::Body;
+        use http::Response;
+
+        let stream: JsonStream<u32> = JsonStream::new(futures::stream::iter([
+            Ok(1),
+            Err(ServerFnError::new("failed")),
+            Ok(2),
+        ]));
+        let res: Response<Body> =
+            block_on(IntoRes::<StreamingJson, _, ServerFnError>::into_res(
+                stream,
+            ))
+            .unwrap();
+        let chunks =
+            block_on(res.into_body().into_data_stream().collect::<Vec<_>>());
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].as_ref().unwrap(), "1\n");
+        assert!(chunks[1].is_err());
+    }
+}
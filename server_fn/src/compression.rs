@@ -0,0 +1,1073 @@
+//! Compression of server function requests and responses.
+//!
+//! With one or more of the `gzip`, `brotli` and `zstd` features enabled, the server
+//! integrations negotiate a compression format with the client:
+//!
+//! - Responses are compressed in the best format the client lists in its
+//!   `Accept-Encoding` header. Streaming responses (like [`ByteStream`](crate::codec::ByteStream)
+//!   and [`TextStream`](crate::codec::TextStream)) are compressed chunk by chunk, and each
+//!   chunk is flushed, so the client receives it as soon as it is sent.
+//! - Request bodies with a `Content-Encoding` header are decompressed before they are
+//!   decoded. A request in a format this server does not support is rejected with
+//!   `415 Unsupported Media Type`, and one that is larger than
+//!   [`max_decompressed_size`] once decompressed with `413 Payload Too Large`.
+//! - Every response lists the formats the server can decompress in its own
+//!   `Accept-Encoding` header, so that clients know which formats they may use for
+//!   request bodies.
+//!
+//! On the client side, the [`ReqwestClient`](crate::client::reqwest::ReqwestClient)
+//! decompresses responses, and compresses request bodies of at least
+//! [`COMPRESSION_THRESHOLD`] bytes (and all streaming request bodies) once the server has
+//! said that it accepts one of the enabled formats. What each server accepts is remembered
+//! by origin, so a client that talks to several servers only compresses requests to the
+//! ones that can decompress them. The
+//! [`BrowserClient`](crate::client::browser::BrowserClient) leaves responses to the
+//! browser, and compresses request bodies with gzip if the browser supports
+//! [`CompressionStream`](https://developer.mozilla.org/en-US/docs/Web/API/CompressionStream)
+//! and the server accepts gzip. This does not need any of the features to be enabled on
+//! the client.
+//!
+//! Responses that are smaller than [`COMPRESSION_THRESHOLD`], or that already have a
+//! `Content-Encoding`, are sent as they are.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        LazyLock, Mutex, PoisonError,
+    },
+};
+
+/// Bodies smaller than this number of bytes are not compressed.
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// The default for [`max_decompressed_size`]: 16 MiB.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
+
+static MAX_DECOMPRESSED_SIZE: AtomicUsize =
+    AtomicUsize::new(DEFAULT_MAX_DECOMPRESSED_SIZE);
+
+/// Sets the largest number of bytes a compressed request body may decompress to.
+///
+/// This protects the server from small bodies that decompress to huge ones. Larger bodies
+/// are rejected with `413 Payload Too Large`. Defaults to
+/// [`DEFAULT_MAX_DECOMPRESSED_SIZE`].
+pub fn set_max_decompressed_size(bytes: usize) {
+    MAX_DECOMPRESSED_SIZE.store(bytes, Ordering::Relaxed);
+}
+
+/// The largest number of bytes a compressed request body may decompress to.
+pub fn max_decompressed_size() -> usize {
+    MAX_DECOMPRESSED_SIZE.load(Ordering::Relaxed)
+}
+
+/// A compression format for request and response bodies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentEncoding {
+    /// `gzip`, available with the `gzip` feature.
+    Gzip,
+    /// `br`, available with the `brotli` feature.
+    Brotli,
+    /// `zstd`, available with the `zstd` feature.
+    Zstd,
+}
+
+impl ContentEncoding {
+    /// All formats, in the order in which they are preferred.
+    const ALL: [ContentEncoding; 3] = [
+        ContentEncoding::Brotli,
+        ContentEncoding::Zstd,
+        ContentEncoding::Gzip,
+    ];
+
+    /// The name of the format in `Accept-Encoding` and `Content-Encoding` headers.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Zstd => "zstd",
+        }
+    }
+
+    /// Parses the name of a format, as used in `Content-Encoding` headers.
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim();
+        if name.eq_ignore_ascii_case("gzip")
+            || name.eq_ignore_ascii_case("x-gzip")
+        {
+            Some(ContentEncoding::Gzip)
+        } else if name.eq_ignore_ascii_case("br") {
+            Some(ContentEncoding::Brotli)
+        } else if name.eq_ignore_ascii_case("zstd") {
+            Some(ContentEncoding::Zstd)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the feature for this format is enabled.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            ContentEncoding::Gzip => cfg!(feature = "gzip"),
+            ContentEncoding::Brotli => cfg!(feature = "brotli"),
+            ContentEncoding::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    fn bit(self) -> u8 {
+        match self {
+            ContentEncoding::Gzip => 1,
+            ContentEncoding::Brotli => 2,
+            ContentEncoding::Zstd => 4,
+        }
+    }
+}
+
+impl Display for ContentEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The formats for which a feature is enabled, in the order in which they are preferred.
+pub fn enabled() -> impl Iterator<Item = ContentEncoding> {
+    ContentEncoding::ALL
+        .into_iter()
+        .filter(|encoding| encoding.is_enabled())
+}
+
+/// The value of an `Accept-Encoding` header listing all enabled formats, if there are any.
+pub fn accept_encoding() -> Option<String> {
+    let encodings = enabled().map(ContentEncoding::as_str).collect::<Vec<_>>();
+    (!encodings.is_empty()).then(|| encodings.join(", "))
+}
+
+/// Chooses the enabled format that the client prefers, given its `Accept-Encoding` header.
+///
+/// Formats are ranked by their quality value, and formats with the same quality value by
+/// the order of [`enabled`]. A wildcard `*` applies to the formats the header does not list,
+/// and a quality value of `0` rules a format out.
+pub fn negotiate(accept_encoding: &str) -> Option<ContentEncoding> {
+    let mut qualities = [None; 3];
+    let mut wildcard = None;
+    for entry in accept_encoding.split(',') {
+        let mut parts = entry.split(';');
+        let name = parts.next().unwrap_or_default().trim();
+        let quality = parts
+            .find_map(|param| {
+                let (key, value) = param.split_once('=')?;
+                key.trim()
+                    .eq_ignore_ascii_case("q")
+                    .then(|| value.trim().parse::<f32>().ok())
+                    .flatten()
+            })
+            .unwrap_or(1.0);
+        if name == "*" {
+            wildcard = Some(quality);
+        } else if let Some(encoding) = ContentEncoding::parse(name) {
+            let index = ContentEncoding::ALL
+                .iter()
+                .position(|candidate| *candidate == encoding)
+                .expect("every encoding is listed");
+            qualities[index] = Some(quality);
+        }
+    }
+
+    let mut best: Option<(ContentEncoding, f32)> = None;
+    for (encoding, quality) in ContentEncoding::ALL.into_iter().zip(qualities) {
+        let Some(quality) = quality.or(wildcard) else {
+            continue;
+        };
+        if encoding.is_enabled()
+            && quality > 0.0
+            && best.is_none_or(|(_, best)| quality > best)
+        {
+            best = Some((encoding, quality));
+        }
+    }
+    best.map(|(encoding, _)| encoding)
+}
+
+/// Compresses or decompresses data that arrives in chunks.
+trait Coder: Send {
+    /// Processes the next chunk, returning the output that is ready.
+    fn process(&mut self, chunk: &[u8]) -> io::Result<Bytes>;
+
+    /// Finishes the stream, returning the rest of the output.
+    fn finish(self: Box<Self>) -> io::Result<Bytes>;
+}
+
+fn unsupported(encoding: ContentEncoding) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("the `{encoding}` content encoding is not enabled"),
+    )
+}
+
+/// Compresses a body chunk by chunk.
+///
+/// The output for each chunk is flushed, so that it can be decompressed as soon as it
+/// arrives.
+pub struct Compressor(Box<dyn Coder>);
+
+impl Compressor {
+    /// Creates a compressor for the given format. Fails if its feature is not enabled.
+    pub fn new(encoding: ContentEncoding) -> io::Result<Self> {
+        match encoding {
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => {
+                Ok(Self(Box::new(flate2::write::GzEncoder::new(
+                    Vec::new(),
+                    flate2::Compression::default(),
+                ))))
+            }
+            #[cfg(feature = "brotli")]
+            ContentEncoding::Brotli => Ok(Self(Box::new(
+                brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22),
+            ))),
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => Ok(Self(Box::new(
+                zstd::stream::write::Encoder::new(Vec::new(), 0)?,
+            ))),
+            #[allow(unreachable_patterns)]
+            encoding => Err(unsupported(encoding)),
+        }
+    }
+
+    /// Compresses the next chunk, returning the compressed output.
+    pub fn compress(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        self.0.process(chunk)
+    }
+
+    /// Finishes the compressed stream, returning the rest of the output.
+    pub fn finish(self) -> io::Result<Bytes> {
+        self.0.finish()
+    }
+
+    /// Compresses a stream of chunks, flushing the output for each chunk.
+    pub fn stream<S>(self, stream: S) -> impl Stream<Item = io::Result<Bytes>>
+    where
+        S: Stream<Item = io::Result<Bytes>>,
+    {
+        transcode(self.0, stream)
+    }
+}
+
+/// Decompresses a body chunk by chunk.
+pub struct Decompressor(Box<dyn Coder>);
+
+impl Decompressor {
+    /// Creates a decompressor for the given format. Fails if its feature is not enabled.
+    pub fn new(encoding: ContentEncoding) -> io::Result<Self> {
+        match encoding {
+            #[cfg(feature = "gzip")]
+            ContentEncoding::Gzip => {
+                Ok(Self(Box::new(flate2::write::GzDecoder::new(Vec::new()))))
+            }
+            #[cfg(feature = "brotli")]
+            ContentEncoding::Brotli => Ok(Self(Box::new(
+                brotli::DecompressorWriter::new(Vec::new(), 4096),
+            ))),
+            #[cfg(feature = "zstd")]
+            ContentEncoding::Zstd => Ok(Self(Box::new(ZstdDecoder::new(
+                Vec::new(),
+                zstd::stream::raw::Decoder::new()?,
+            )))),
+            #[allow(unreachable_patterns)]
+            encoding => Err(unsupported(encoding)),
+        }
+    }
+
+    /// Decompresses the next chunk, returning the decompressed output.
+    pub fn decompress(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        self.0.process(chunk)
+    }
+
+    /// Checks that the compressed stream is complete, returning the rest of the output.
+    pub fn finish(self) -> io::Result<Bytes> {
+        self.0.finish()
+    }
+
+    /// Decompresses a stream of chunks.
+    pub fn stream<S>(self, stream: S) -> impl Stream<Item = io::Result<Bytes>>
+    where
+        S: Stream<Item = io::Result<Bytes>>,
+    {
+        transcode(self.0, stream)
+    }
+}
+
+/// Compresses a complete body.
+pub fn compress(encoding: ContentEncoding, data: &[u8]) -> io::Result<Bytes> {
+    let mut compressor = Compressor::new(encoding)?;
+    let mut output = compressor.compress(data)?.to_vec();
+    output.extend_from_slice(&compressor.finish()?);
+    Ok(output.into())
+}
+
+/// Decompresses a complete body.
+pub fn decompress(encoding: ContentEncoding, data: &[u8]) -> io::Result<Bytes> {
+    let mut decompressor = Decompressor::new(encoding)?;
+    let mut output = decompressor.decompress(data)?.to_vec();
+    output.extend_from_slice(&decompressor.finish()?);
+    Ok(output.into())
+}
+
+fn transcode<S>(
+    coder: Box<dyn Coder>,
+    stream: S,
+) -> impl Stream<Item = io::Result<Bytes>>
+where
+    S: Stream<Item = io::Result<Bytes>>,
+{
+    futures::stream::unfold(
+        (Some(coder), Box::pin(stream)),
+        |(mut coder, mut stream)| async move {
+            let current = coder.as_mut()?;
+            loop {
+                match stream.next().await {
+                    Some(Ok(chunk)) => match current.process(&chunk) {
+                        // the coder may hold on to input until it has enough to output
+                        Ok(output) if output.is_empty() => continue,
+                        Ok(output) => {
+                            return Some((Ok(output), (coder, stream)))
+                        }
+                        Err(e) => return Some((Err(e), (None, stream))),
+                    },
+                    Some(Err(e)) => return Some((Err(e), (None, stream))),
+                    None => {
+                        let output = coder.take()?.finish();
+                        return match output {
+                            Ok(output) if output.is_empty() => None,
+                            output => Some((output, (None, stream))),
+                        };
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// Ends a decompressed stream with an error once it grows larger than `max` bytes, and
+/// calls `exceeded` when it does.
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+fn limit<S>(
+    stream: S,
+    max: usize,
+    exceeded: impl FnOnce() + Send,
+) -> impl Stream<Item = io::Result<Bytes>>
+where
+    S: Stream<Item = io::Result<Bytes>>,
+{
+    let mut exceeded = Some(exceeded);
+    stream
+        .scan(0, move |size, chunk| {
+            let chunk = chunk.and_then(|chunk| {
+                *size += chunk.len();
+                if *size > max {
+                    if let Some(exceeded) = exceeded.take() {
+                        exceeded();
+                    }
+                    Err(too_large(max))
+                } else {
+                    Ok(chunk)
+                }
+            });
+            futures::future::ready(Some(chunk))
+        })
+        .scan(false, |failed, chunk| {
+            // nothing follows the first error
+            let next = (!*failed).then_some(chunk);
+            *failed = next.as_ref().is_some_and(Result::is_err);
+            futures::future::ready(next)
+        })
+}
+
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+fn too_large(max: usize) -> io::Error {
+    io::Error::other(format!(
+        "the request body is larger than {max} bytes once decompressed"
+    ))
+}
+
+macro_rules! write_coder {
+    ($feature:literal, $ty:ty, |$this:ident| $finish:expr) => {
+        #[cfg(feature = $feature)]
+        impl Coder for $ty {
+            fn process(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+                use std::io::Write;
+                self.write_all(chunk)?;
+                self.flush()?;
+                Ok(std::mem::take(self.get_mut()).into())
+            }
+
+            fn finish(self: Box<Self>) -> io::Result<Bytes> {
+                let $this = *self;
+                $finish.map(Bytes::from)
+            }
+        }
+    };
+}
+
+write_coder!("gzip", flate2::write::GzEncoder<Vec<u8>>, |this| this
+    .finish());
+write_coder!("gzip", flate2::write::GzDecoder<Vec<u8>>, |this| this
+    .finish());
+write_coder!("brotli", brotli::CompressorWriter<Vec<u8>>, |this| {
+    io::Result::Ok(this.into_inner())
+});
+write_coder!("brotli", brotli::DecompressorWriter<Vec<u8>>, |this| {
+    let mut this = this;
+    this.close().and_then(|_| {
+        this.into_inner().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the brotli stream is incomplete",
+            )
+        })
+    })
+});
+write_coder!(
+    "zstd",
+    zstd::stream::write::Encoder<'static, Vec<u8>>,
+    |this| { this.finish() }
+);
+
+/// The zstd decoder, which unlike `zstd::stream::write::Decoder` reports incomplete frames
+/// when it is finished.
+#[cfg(feature = "zstd")]
+type ZstdDecoder =
+    zstd::stream::zio::Writer<Vec<u8>, zstd::stream::raw::Decoder<'static>>;
+
+#[cfg(feature = "zstd")]
+impl Coder for ZstdDecoder {
+    fn process(&mut self, chunk: &[u8]) -> io::Result<Bytes> {
+        use std::io::Write;
+        self.write_all(chunk)?;
+        self.flush()?;
+        Ok(std::mem::take(self.writer_mut()).into())
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<Bytes> {
+        zstd::stream::zio::Writer::finish(&mut self)?;
+        Ok(self.into_inner().0.into())
+    }
+}
+
+/// The formats each server has said it accepts for request bodies, as sets of bits keyed
+/// by origin.
+static SERVER_ACCEPTS: LazyLock<Mutex<HashMap<String, u8>>> =
+    LazyLock::new(Default::default);
+
+/// The `scheme://host:port` part of a URL.
+fn origin(url: &str) -> &str {
+    let start = url.find("://").map_or(0, |index| index + 3);
+    let end = url[start..]
+        .find(['/', '?', '#'])
+        .map_or(url.len(), |index| start + index);
+    &url[..end]
+}
+
+/// Remembers the formats listed in an `Accept-Encoding` header of a response to a request
+/// to `url`.
+#[cfg_attr(
+    not(any(feature = "browser", feature = "reqwest")),
+    allow(dead_code)
+)]
+pub(crate) fn set_server_accepts(url: &str, accept_encoding: &str) {
+    let accepted = accept_encoding
+        .split(',')
+        .filter_map(|entry| ContentEncoding::parse(entry.split(';').next()?))
+        .fold(0, |bits, encoding| bits | encoding.bit());
+    SERVER_ACCEPTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert(origin(url).to_ascii_lowercase(), accepted);
+}
+
+/// Whether the server at the origin of `url` has said that it accepts request bodies in
+/// this format.
+#[cfg_attr(
+    not(any(feature = "browser", feature = "reqwest")),
+    allow(dead_code)
+)]
+pub(crate) fn server_accepts(url: &str, encoding: ContentEncoding) -> bool {
+    SERVER_ACCEPTS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .get(&origin(url).to_ascii_lowercase())
+        .is_some_and(|accepted| accepted & encoding.bit() != 0)
+}
+
+/// The enabled format in which to compress request bodies to `url`, if its server accepts
+/// one.
+#[cfg_attr(not(feature = "reqwest"), allow(dead_code))]
+pub(crate) fn request_encoding(url: &str) -> Option<ContentEncoding> {
+    enabled().find(|encoding| server_accepts(url, *encoding))
+}
+
+#[cfg(feature = "axum-no-default")]
+pub(crate) mod axum {
+    use super::{
+        accept_encoding, limit, max_decompressed_size, negotiate, too_large,
+        Compressor, ContentEncoding, Decompressor, COMPRESSION_THRESHOLD,
+    };
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Service},
+        response::Res,
+    };
+    use axum::body::{Body, HttpBody};
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    use http::{
+        header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY},
+        HeaderValue, Request, Response, StatusCode,
+    };
+    use std::{
+        future::Future,
+        io,
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    /// Wraps the service so that it decompresses request bodies and compresses responses,
+    /// if any compression feature is enabled.
+    pub(crate) fn negotiate_compression(
+        inner: BoxedService<Request<Body>, Response<Body>>,
+    ) -> BoxedService<Request<Body>, Response<Body>> {
+        match accept_encoding().and_then(|value| value.parse().ok()) {
+            Some(accepts) => {
+                BoxedService::new(inner.ser, Compression { inner, accepts })
+            }
+            None => inner,
+        }
+    }
+
+    struct Compression {
+        inner: BoxedService<Request<Body>, Response<Body>>,
+        accepts: HeaderValue,
+    }
+
+    impl Service<Request<Body>, Response<Body>> for Compression {
+        fn run(
+            &mut self,
+            req: Request<Body>,
+            ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = Response<Body>> + Send>> {
+            let accepts = self.accepts.clone();
+            let path = req.uri().path().to_string();
+            let max = max_decompressed_size();
+            let too_large = Arc::new(AtomicBool::new(false));
+            let req = match decompress_request(req, max, too_large.clone()) {
+                Ok(req) => req,
+                Err(err) => {
+                    let status = err.status_code();
+                    let mut res = Response::error_response(&path, ser(err));
+                    res.set_status(status);
+                    res.headers_mut().insert(ACCEPT_ENCODING, accepts);
+                    return Box::pin(async move { res });
+                }
+            };
+            let encoding = req
+                .headers()
+                .get(ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .and_then(negotiate);
+            let inner = self.inner.run(req);
+            Box::pin(async move {
+                let mut res = inner.await;
+                if too_large.load(Ordering::Relaxed) {
+                    let err = ServerFnErrorErr::PayloadTooLarge(
+                        self::too_large(max).to_string(),
+                    );
+                    res = Response::error_response(&path, ser(err));
+                    *res.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+                } else if let Some(encoding) = encoding {
+                    res = compress_response(res, encoding);
+                }
+                res.headers_mut().insert(ACCEPT_ENCODING, accepts);
+                res
+            })
+        }
+    }
+
+    fn decompress_request(
+        mut req: Request<Body>,
+        max: usize,
+        too_large: Arc<AtomicBool>,
+    ) -> Result<Request<Body>, ServerFnErrorErr> {
+        let Some(value) = req.headers_mut().remove(CONTENT_ENCODING) else {
+            return Ok(req);
+        };
+        let name = value.to_str().unwrap_or_default().trim();
+        if name.eq_ignore_ascii_case("identity") {
+            return Ok(req);
+        }
+        let decompressor = ContentEncoding::parse(name)
+            .and_then(|encoding| Decompressor::new(encoding).ok())
+            .ok_or_else(|| {
+                ServerFnErrorErr::UnsupportedMediaType(format!(
+                    "unsupported content encoding `{name}`"
+                ))
+            })?;
+        let (mut parts, body) = req.into_parts();
+        let body = decompressor
+            .stream(body.into_data_stream().map_err(io::Error::other));
+        let body =
+            limit(body, max, move || too_large.store(true, Ordering::Relaxed));
+        parts.headers.remove(CONTENT_LENGTH);
+        Ok(Request::from_parts(parts, Body::from_stream(body)))
+    }
+
+    fn compress_response(
+        res: Response<Body>,
+        encoding: ContentEncoding,
+    ) -> Response<Body> {
+        let skip = matches!(
+            res.status(),
+            StatusCode::SWITCHING_PROTOCOLS
+                | StatusCode::NO_CONTENT
+                | StatusCode::NOT_MODIFIED
+        ) || res.headers().contains_key(CONTENT_ENCODING)
+            || res
+                .body()
+                .size_hint()
+                .exact()
+                .is_some_and(|len| len < COMPRESSION_THRESHOLD as u64);
+        if skip {
+            return res;
+        }
+        let Ok(compressor) = Compressor::new(encoding) else {
+            return res;
+        };
+        let (mut parts, body) = res.into_parts();
+        let body = compressor
+            .stream(body.into_data_stream().map_err(io::Error::other));
+        parts.headers.remove(CONTENT_LENGTH);
+        parts.headers.insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+        parts
+            .headers
+            .append(VARY, HeaderValue::from_static("accept-encoding"));
+        Response::from_parts(parts, Body::from_stream(body))
+    }
+}
+
+#[cfg(feature = "actix-no-default")]
+pub(crate) mod actix {
+    use super::{
+        accept_encoding, limit, max_decompressed_size, negotiate, too_large,
+        Compressor, ContentEncoding, Decompressor, COMPRESSION_THRESHOLD,
+    };
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Service},
+        request::actix::ActixRequest,
+        response::{actix::ActixResponse, Res},
+    };
+    use actix_web::{
+        body::{BodySize, BodyStream, BoxBody, MessageBody},
+        error::PayloadError,
+        http::{
+            header::{
+                HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH,
+                VARY,
+            },
+            StatusCode,
+        },
+    };
+    use bytes::Bytes;
+    use futures::TryStreamExt;
+    use send_wrapper::SendWrapper;
+    use std::{
+        future::Future,
+        io,
+        pin::Pin,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    /// Wraps the service so that it decompresses request bodies and compresses responses,
+    /// if any compression feature is enabled.
+    pub(crate) fn negotiate_compression(
+        inner: BoxedService<ActixRequest, ActixResponse>,
+    ) -> BoxedService<ActixRequest, ActixResponse> {
+        match accept_encoding()
+            .and_then(|value| HeaderValue::from_str(&value).ok())
+        {
+            Some(accepts) => {
+                BoxedService::new(inner.ser, Compression { inner, accepts })
+            }
+            None => inner,
+        }
+    }
+
+    struct Compression {
+        inner: BoxedService<ActixRequest, ActixResponse>,
+        accepts: HeaderValue,
+    }
+
+    impl Service<ActixRequest, ActixResponse> for Compression {
+        fn run(
+            &mut self,
+            req: ActixRequest,
+            ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = ActixResponse> + Send>> {
+            let accepts = self.accepts.clone();
+            let path = req.path().to_string();
+            let max = max_decompressed_size();
+            let too_large = Arc::new(AtomicBool::new(false));
+            let req = match decompress_request(req, max, too_large.clone()) {
+                Ok(req) => req,
+                Err(err) => {
+                    let status = err.status_code();
+                    let mut res =
+                        ActixResponse::error_response(&path, ser(err));
+                    res.set_status(status);
+                    res.0.headers_mut().insert(ACCEPT_ENCODING, accepts);
+                    return Box::pin(async move { res });
+                }
+            };
+            let encoding = req
+                .headers()
+                .get(ACCEPT_ENCODING)
+                .and_then(|value| value.to_str().ok())
+                .and_then(negotiate);
+            let inner = self.inner.run(req);
+            Box::pin(async move {
+                let mut res = inner.await;
+                if too_large.load(Ordering::Relaxed) {
+                    let err = ServerFnErrorErr::PayloadTooLarge(
+                        self::too_large(max).to_string(),
+                    );
+                    res = ActixResponse::error_response(&path, ser(err));
+                    *res.0.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+                } else if let Some(encoding) = encoding {
+                    res = compress_response(res, encoding);
+                }
+                res.0.headers_mut().insert(ACCEPT_ENCODING, accepts);
+                res
+            })
+        }
+    }
+
+    fn decompress_request(
+        req: ActixRequest,
+        max: usize,
+        too_large: Arc<AtomicBool>,
+    ) -> Result<ActixRequest, ServerFnErrorErr> {
+        let Some(value) = req.headers().get(CONTENT_ENCODING) else {
+            return Ok(req);
+        };
+        let name = value.to_str().unwrap_or_default().trim();
+        if name.eq_ignore_ascii_case("identity") {
+            return Ok(req);
+        }
+        let decompressor = ContentEncoding::parse(name)
+            .and_then(|encoding| Decompressor::new(encoding).ok())
+            .ok_or_else(|| {
+                ServerFnErrorErr::UnsupportedMediaType(format!(
+                    "unsupported content encoding `{name}`"
+                ))
+            })?;
+        Ok(req.map_body(|payload| {
+            let body = decompressor.stream(payload.map_err(io::Error::other));
+            limit(body, max, move || too_large.store(true, Ordering::Relaxed))
+                .map_err(PayloadError::from)
+        }))
+    }
+
+    fn compress_response(
+        res: ActixResponse,
+        encoding: ContentEncoding,
+    ) -> ActixResponse {
+        let skip = matches!(
+            res.0.status(),
+            StatusCode::SWITCHING_PROTOCOLS
+                | StatusCode::NO_CONTENT
+                | StatusCode::NOT_MODIFIED
+        ) || res.0.headers().contains_key(CONTENT_ENCODING)
+            || match res.0.body().size() {
+                BodySize::None => true,
+                BodySize::Sized(len) => len < COMPRESSION_THRESHOLD as u64,
+                BodySize::Stream => false,
+            };
+        if skip {
+            return res;
+        }
+        let Ok(compressor) = Compressor::new(encoding) else {
+            return res;
+        };
+        let (mut res, mut body) = res.0.take().into_parts();
+        let body = futures::stream::poll_fn(move |cx| {
+            Pin::new(&mut body).poll_next(cx)
+        })
+        .map_err(|e| io::Error::other(e.to_string()));
+        let body = compressor.stream(body);
+        let headers = res.headers_mut();
+        headers.remove(CONTENT_LENGTH);
+        headers.insert(
+            CONTENT_ENCODING,
+            HeaderValue::from_static(encoding.as_str()),
+        );
+        headers.append(VARY, HeaderValue::from_static("accept-encoding"));
+        ActixResponse(SendWrapper::new(
+            res.set_body(BoxBody::new(BodyStream::new(body))),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, stream, TryStreamExt};
+
+    #[test]
+    fn parses_encodings() {
+        assert_eq!(ContentEncoding::parse("gzip"), Some(ContentEncoding::Gzip));
+        assert_eq!(
+            ContentEncoding::parse(" X-GZIP "),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(ContentEncoding::parse("br"), Some(ContentEncoding::Brotli));
+        assert_eq!(ContentEncoding::parse("zstd"), Some(ContentEncoding::Zstd));
+        assert_eq!(ContentEncoding::parse("deflate"), None);
+    }
+
+    #[test]
+    fn negotiates_the_preferred_encoding() {
+        let enabled = enabled().collect::<Vec<_>>();
+        let best = enabled.first().copied();
+        assert_eq!(negotiate("gzip, br, zstd"), best);
+        assert_eq!(negotiate("*"), best);
+        assert_eq!(negotiate("identity"), None);
+        assert_eq!(negotiate(""), None);
+        assert_eq!(negotiate("gzip;q=0, br;q=0, zstd;q=0"), None);
+        assert_eq!(negotiate("*;q=0"), None);
+        if ContentEncoding::Gzip.is_enabled() {
+            assert_eq!(
+                negotiate("br;q=0.5, zstd;q=0.5, gzip;q=0.8"),
+                Some(ContentEncoding::Gzip)
+            );
+            assert_eq!(negotiate("gzip, *;q=0"), Some(ContentEncoding::Gzip));
+        }
+        if let Some(other) = enabled
+            .iter()
+            .find(|encoding| **encoding != ContentEncoding::Gzip)
+        {
+            assert_eq!(negotiate("gzip;q=0.1, *"), Some(*other));
+        }
+    }
+
+    #[test]
+    fn remembers_what_the_server_accepts() {
+        let url = "https://accepts.example/api/a";
+        set_server_accepts(url, "br, gzip;q=0.5, identity");
+        assert!(server_accepts(url, ContentEncoding::Brotli));
+        assert!(server_accepts(url, ContentEncoding::Gzip));
+        assert!(!server_accepts(url, ContentEncoding::Zstd));
+        set_server_accepts(url, "zstd");
+        assert!(!server_accepts(url, ContentEncoding::Gzip));
+        assert!(server_accepts(url, ContentEncoding::Zstd));
+    }
+
+    #[test]
+    fn what_servers_accept_is_kept_by_origin() {
+        assert_eq!(
+            origin("https://a.example:8080/api/b?c#d"),
+            "https://a.example:8080"
+        );
+        assert_eq!(origin("http://a.example?c"), "http://a.example");
+
+        set_server_accepts("https://origin.example/api/a", "gzip");
+        assert!(server_accepts(
+            "https://ORIGIN.example/api/b",
+            ContentEncoding::Gzip
+        ));
+        assert!(!server_accepts(
+            "https://origin.example:8443/api/a",
+            ContentEncoding::Gzip
+        ));
+        assert!(!server_accepts(
+            "https://other.example/api/a",
+            ContentEncoding::Gzip
+        ));
+    }
+
+    #[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+    #[test]
+    fn limits_the_decompressed_size() {
+        let chunks =
+            || stream::iter([Ok(Bytes::from("abcd")), Ok(Bytes::from("efgh"))]);
+        let output = block_on(
+            limit(chunks(), 8, || panic!("not exceeded"))
+                .try_collect::<Vec<_>>(),
+        );
+        assert_eq!(output.unwrap().concat(), b"abcdefgh");
+
+        let mut exceeded = false;
+        let output = block_on(
+            limit(chunks().chain(chunks()), 7, || exceeded = true)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(output.len(), 2);
+        assert!(output[1].is_err());
+        assert!(exceeded);
+    }
+
+    #[test]
+    fn disabled_encodings_are_unsupported() {
+        for encoding in ContentEncoding::ALL {
+            assert_eq!(
+                Compressor::new(encoding).is_ok(),
+                encoding.is_enabled()
+            );
+            assert_eq!(
+                Decompressor::new(encoding).is_ok(),
+                encoding.is_enabled()
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_chunk_by_chunk() {
+        let chunks = (0..20)
+            .map(|i| Bytes::from(format!("chunk {i} ").repeat(100)))
+            .collect::<Vec<_>>();
+        let expected = chunks.concat();
+        for encoding in enabled() {
+            let data = compress(encoding, &expected).unwrap();
+            assert!(data.len() < expected.len());
+            assert_eq!(decompress(encoding, &data).unwrap(), expected);
+
+            let mut compressor = Compressor::new(encoding).unwrap();
+            let mut decompressor = Decompressor::new(encoding).unwrap();
+            for chunk in &chunks {
+                // every chunk can be decompressed as soon as it arrives
+                let compressed = compressor.compress(chunk).unwrap();
+                assert_eq!(
+                    decompressor.decompress(&compressed).unwrap(),
+                    chunk
+                );
+            }
+            let rest = compressor.finish().unwrap();
+            assert!(decompressor.decompress(&rest).unwrap().is_empty());
+            decompressor.finish().unwrap();
+
+            let compressed = Compressor::new(encoding)
+                .unwrap()
+                .stream(stream::iter(chunks.clone().into_iter().map(Ok)));
+            let decompressed =
+                Decompressor::new(encoding).unwrap().stream(compressed);
+            let output = block_on(decompressed.try_collect::<Vec<_>>())
+                .unwrap()
+                .concat();
+            assert_eq!(output, expected);
+        }
+    }
+
+    #[test]
+    fn incomplete_streams_are_errors() {
+        for encoding in enabled() {
+            let data = compress(encoding, &[b'a'; 4096]).unwrap();
+            let truncated = &data[..data.len() / 2];
+            let mut decompressor = Decompressor::new(encoding).unwrap();
+            let result = decompressor
+                .decompress(truncated)
+                .and_then(|_| decompressor.finish());
+            assert!(result.is_err(), "{encoding} accepted a truncated body");
+        }
+    }
+}
+
+#[cfg(all(test, feature = "axum-no-default", feature = "gzip"))]
+mod axum_tests {
+    use super::{axum::negotiate_compression, compress, ContentEncoding};
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Service},
+        ServerFnError,
+    };
+    use axum::body::Body;
+    use bytes::Bytes;
+    use http::{header::CONTENT_ENCODING, Request, Response, StatusCode};
+    use http_body_util::BodyExt;
+    use std::{future::Future, pin::Pin};
+
+    /// Reads the request body, and answers with its length.
+    struct Len;
+
+    impl Service<Request<Body>, Response<Body>> for Len {
+        fn run(
+            &mut self,
+            req: Request<Body>,
+            _ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = Response<Body>> + Send>> {
+            Box::pin(async move {
+                match req.into_body().collect().await {
+                    Ok(body) => Response::new(Body::from(
+                        body.to_bytes().len().to_string(),
+                    )),
+                    Err(e) => Response::builder()
+                        .status(StatusCode::BAD_REQUEST)
+                        .body(Body::from(e.to_string()))
+                        .unwrap(),
+                }
+            })
+        }
+    }
+
+    fn ser(err: ServerFnErrorErr) -> Bytes {
+        use crate::{error::ServerFnErrorEncoding, Encodes};
+        let err: ServerFnError =
+            crate::FromServerFnError::from_server_fn_error(err);
+        ServerFnErrorEncoding::encode(&err).unwrap()
+    }
+
+    fn request(encoding: &str, body: impl Into<Body>) -> Request<Body> {
+        Request::post("/api/compressed")
+            .header(CONTENT_ENCODING, encoding)
+            .body(body.into())
+            .unwrap()
+    }
+
+    async fn text(res: Response<Body>) -> String {
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn rejects_unsupported_and_oversized_bodies() {
+        let mut service = negotiate_compression(BoxedService::new(ser, Len));
+        futures::executor::block_on(async {
+            let res = service.run(request("deflate", "data")).await;
+            assert_eq!(res.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+            assert!(text(res).await.starts_with("UnsupportedMediaType|"));
+
+            let max = super::max_decompressed_size();
+            let body = compress(ContentEncoding::Gzip, &vec![0; max]).unwrap();
+            let res = service.run(request("gzip", body)).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(text(res).await, max.to_string());
+
+            let body =
+                compress(ContentEncoding::Gzip, &vec![0; max + 1]).unwrap();
+            assert!(body.len() < 64 * 1024);
+            let res = service.run(request("gzip", body)).await;
+            assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            assert!(text(res).await.starts_with("PayloadTooLarge|"));
+        });
+    }
+}
@@ -0,0 +1,979 @@
+//! Protocols that speak the [Connect protocol](https://connectrpc.com/docs/protocol), so that
+//! Connect clients in other languages can call server functions.
+//!
+//! Only the Connect protocol itself is spoken. Clients that use the gRPC or gRPC-web
+//! protocols can't call these server functions.
+//!
+//! [`Connect`] is for unary calls, which send one message and receive one message.
+//! [`ConnectStream`] is for server-streaming calls, which send one message and receive a
+//! stream of messages.
+//!
+//! Both are generic over the encoding of the messages. [`JsonEncoding`](crate::codec::JsonEncoding)
+//! is the Connect `json` codec. The Connect `proto` codec is any encoding whose content type is
+//! `application/proto`, for example one that encodes `prost` messages, and other binary
+//! encodings are sent with their own codec name (for example, `cbor` for `application/cbor`).
+//!
+//! The path of a server function is its Connect procedure, so it should usually be set with
+//! the `prefix` and `endpoint` arguments of the `#[server]` macro, to something like
+//! `/acme.greet.v1.GreetService/Greet`.
+//!
+//! Errors are sent as Connect errors, with a code from [`ToConnectError`]. The error itself
+//! is also attached as an error detail, so that a Rust client gets back exactly the error the
+//! server function returned. Errors that are returned before the protocol runs, for example
+//! by a guard or a middleware, are sent as usual, and Connect clients take the code from the
+//! HTTP status.
+//!
+//! Unary `GET` requests, timeouts and the compression of single messages in a stream are not
+//! supported. Unary requests and responses are compressed like any other server function.
+
+use crate::{
+    error::{FromServerFnError, ServerFnErrorErr},
+    request::{ClientReq, Req},
+    response::{ClientRes, Res, TryRes},
+    BoxedStream, Decodes, Encodes, Protocol, ServerFnError,
+};
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use futures::{future, stream, Stream, StreamExt};
+use http::Method;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt::Display, future::Future, marker::PhantomData, pin::Pin};
+
+/// The header that carries the version of the Connect protocol.
+pub const PROTOCOL_VERSION_HEADER: &str = "connect-protocol-version";
+
+/// The type of the error detail that carries the error returned by the server function.
+pub const ERROR_DETAIL_TYPE: &str = "server_fn.Error";
+
+// Connect sends base64 without padding, but accepts it either way
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new()
+        .with_encode_padding(false)
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+// the flags of a message in a stream
+const COMPRESSED_FLAG: u8 = 0b01;
+const END_STREAM_FLAG: u8 = 0b10;
+
+/// The Connect protocol for unary calls.
+///
+/// The arguments are sent as one message, and the server function returns one message. Both
+/// are encoded with `Encoding`.
+///
+/// # Example
+///
+/// ```rust, no_run
+/// # use server_fn_macro_default::server;
+/// # #[cfg(feature = "browser")] {
+/// use server_fn::{codec::JsonEncoding, Connect, ServerFnError};
+///
+/// #[server(
+///     prefix = "/acme.greet.v1.GreetService",
+///     endpoint = "Greet",
+///     protocol = Connect<JsonEncoding>
+/// )]
+/// async fn greet(name: String) -> Result<String, ServerFnError> {
+///     Ok(format!("Hello, {name}!"))
+/// }
+/// # }
+/// ```
+pub struct Connect<Encoding>(PhantomData<Encoding>);
+
+impl<Input, Output, Encoding, Client, Server, Error>
+    Protocol<Input, Output, Client, Server, Error> for Connect<Encoding>
+where
+    Input: Send,
+    Output: Send,
+    Encoding:
+        Encodes<Input> + Decodes<Input> + Encodes<Output> + Decodes<Output>,
+    Error: FromServerFnError + ToConnectError + Send,
+    Server: crate::Server<Error>,
+    Client: crate::Client<Error>,
+{
+    const METHOD: Method = Method::POST;
+    const CONTENT_TYPES: Option<(&'static str, &'static str)> =
+        Some((Encoding::CONTENT_TYPE, Encoding::CONTENT_TYPE));
+
+    async fn run_server<F, Fut>(
+        request: Server::Request,
+        server_fn: F,
+    ) -> Result<Server::Response, Error>
+    where
+        F: Fn(Input) -> Fut + Send,
+        Fut: Future<Output = Result<Output, Error>> + Send,
+    {
+        let output = async move {
+            let body = request.try_into_bytes().await?;
+            let input = <Encoding as Decodes<Input>>::decode(body)
+                .map_err(|e| args_error(e.to_string()))?;
+            let output = server_fn(input).await?;
+            <Encoding as Encodes<Output>>::encode(&output).map_err(|e| {
+                Error::from_server_fn_error(ServerFnErrorErr::Serialization(
+                    e.to_string(),
+                ))
+            })
+        }
+        .await;
+
+        match output {
+            Ok(body) => {
+                Server::Response::try_from_bytes(Encoding::CONTENT_TYPE, body)
+            }
+            Err(err) => {
+                let error = wire_error(&err);
+                let status = error.code.http_status();
+                let body = serde_json::to_vec(&error).map_err(|e| {
+                    Error::from_server_fn_error(
+                        ServerFnErrorErr::Serialization(e.to_string()),
+                    )
+                })?;
+                let mut res = Server::Response::try_from_bytes(
+                    "application/json",
+                    body.into(),
+                )?;
+                res.set_status(status);
+                Ok(res)
+            }
+        }
+    }
+
+    async fn run_client(path: &str, input: Input) -> Result<Output, Error> {
+        let body =
+            <Encoding as Encodes<Input>>::encode(&input).map_err(|e| {
+                Error::from_server_fn_error(ServerFnErrorErr::Serialization(
+                    e.to_string(),
+                ))
+            })?;
+        let mut req = Client::Request::try_new_req_bytes(
+            path,
+            Encoding::CONTENT_TYPE,
+            Encoding::CONTENT_TYPE,
+            body,
+            Method::POST,
+        )?;
+        req.try_set_header(PROTOCOL_VERSION_HEADER, "1")?;
+        let res = Client::send(req).await?;
+
+        let status = res.status();
+        let body = res.try_into_bytes().await?;
+        if status == 200 {
+            <Encoding as Decodes<Output>>::decode(body).map_err(|e| {
+                Error::from_server_fn_error(ServerFnErrorErr::Deserialization(
+                    e.to_string(),
+                ))
+            })
+        } else {
+            Err(ConnectError::from_response(status, &body).into_error())
+        }
+    }
+}
+
+/// The Connect protocol for server-streaming calls.
+///
+/// The arguments are sent as one message. The server function returns a [`BoxedStream`], and
+/// each of its items is sent as one message. Both are encoded with `Encoding`.
+///
+/// An error in the output stream ends the stream, because a Connect stream can only end with
+/// an error.
+///
+/// # Example
+///
+/// ```rust, no_run
+/// # use server_fn_macro_default::server;
+/// # #[cfg(feature = "browser")] {
+/// use server_fn::{
+///     codec::JsonEncoding, BoxedStream, ConnectStream, ServerFnError,
+/// };
+///
+/// #[server(
+///     prefix = "/acme.count.v1.CountService",
+///     endpoint = "Count",
+///     protocol = ConnectStream<JsonEncoding>
+/// )]
+/// async fn count(
+///     to: u32,
+/// ) -> Result<BoxedStream<u32, ServerFnError>, ServerFnError> {
+///     Ok(futures::stream::iter((1..=to).map(Ok)).into())
+/// }
+/// # }
+/// ```
+pub struct ConnectStream<Encoding>(PhantomData<Encoding>);
+
+impl<
+        Input,
+        OutputItem,
+        Encoding,
+        Client,
+        Server,
+        Error,
+        InputStreamError,
+        OutputStreamError,
+    >
+    Protocol<
+        Input,
+        BoxedStream<OutputItem, OutputStreamError>,
+        Client,
+        Server,
+        Error,
+        InputStreamError,
+        OutputStreamError,
+    > for ConnectStream<Encoding>
+where
+    Input: Send,
+    OutputItem: Send + 'static,
+    Encoding: Encodes<Input>
+        + Decodes<Input>
+        + Encodes<OutputItem>
+        + Decodes<OutputItem>
+        + 'static,
+    Error: FromServerFnError + ToConnectError + Send,
+    OutputStreamError: FromServerFnError + ToConnectError + Send,
+    Server: crate::Server<Error, InputStreamError, OutputStreamError>,
+    Client: crate::Client<Error, InputStreamError, OutputStreamError>,
+{
+    const METHOD: Method = Method::POST;
+
+    async fn run_server<F, Fut>(
+        request: Server::Request,
+        server_fn: F,
+    ) -> Result<Server::Response, Error>
+    where
+        F: Fn(Input) -> Fut + Send,
+        Fut: Future<
+                Output = Result<
+                    BoxedStream<OutputItem, OutputStreamError>,
+                    Error,
+                >,
+            > + Send,
+    {
+        let output = async move {
+            let body = request.try_into_bytes().await?;
+            let message = single_message(body).map_err(args_error)?;
+            let input = <Encoding as Decodes<Input>>::decode(message)
+                .map_err(|e| args_error(e.to_string()))?;
+            server_fn(input).await
+        }
+        .await;
+
+        // errors are sent at the end of the stream, with a `200 OK` status
+        let body = match output {
+            Ok(output) => {
+                let mut done = false;
+                output
+                    .stream
+                    .map(Some)
+                    .chain(stream::once(async { None }))
+                    .filter_map(move |item| {
+                        let frame = match item {
+                            _ if done => None,
+                            Some(Ok(item)) => Some(
+                                match <Encoding as Encodes<OutputItem>>::encode(
+                                    &item,
+                                ) {
+                                    Ok(message) => envelope(0, &message),
+                                    Err(e) => {
+                                        done = true;
+                                        end_stream(Some(
+                                            &OutputStreamError::from_server_fn_error(
+                                                ServerFnErrorErr::Serialization(
+                                                    e.to_string(),
+                                                ),
+                                            ),
+                                        ))
+                                    }
+                                },
+                            ),
+                            Some(Err(err)) => {
+                                done = true;
+                                Some(end_stream(Some(&err)))
+                            }
+                            None => Some(end_stream::<OutputStreamError>(None)),
+                        };
+                        future::ready(frame.map(Ok))
+                    })
+                    .boxed()
+            }
+            Err(err) => stream::iter([Ok(end_stream(Some(&err)))]).boxed(),
+        };
+
+        Server::Response::try_from_stream(
+            &stream_content_type(Encoding::CONTENT_TYPE),
+            body,
+        )
+    }
+
+    async fn run_client(
+        path: &str,
+        input: Input,
+    ) -> Result<BoxedStream<OutputItem, OutputStreamError>, Error> {
+        let message =
+            <Encoding as Encodes<Input>>::encode(&input).map_err(|e| {
+                Error::from_server_fn_error(ServerFnErrorErr::Serialization(
+                    e.to_string(),
+                ))
+            })?;
+        let content_type = stream_content_type(Encoding::CONTENT_TYPE);
+        let mut req = Client::Request::try_new_req_bytes(
+            path,
+            &content_type,
+            &content_type,
+            envelope(0, &message),
+            Method::POST,
+        )?;
+        req.try_set_header(PROTOCOL_VERSION_HEADER, "1")?;
+        let res = Client::send(req).await?;
+
+        let status = res.status();
+        if status != 200 {
+            let body = res.try_into_bytes().await?;
+            return Err(ConnectError::from_response(status, &body).into_error());
+        }
+        let state = StreamState {
+            body: Box::pin(res.try_into_stream()?),
+            buf: BytesMut::new(),
+            done: false,
+        };
+        let items = stream::unfold(state, |mut state| async move {
+            let item = next_message::<OutputItem, Encoding, OutputStreamError>(
+                &mut state,
+            )
+            .await?;
+            Some((item, state))
+        });
+        Ok(BoxedStream::from(items))
+    }
+}
+
+/// A Connect error code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Code {
+    /// The call was cancelled, usually by the caller.
+    Canceled,
+    /// An unknown error. This is also used for codes that are not known to this client.
+    Unknown,
+    /// The arguments are invalid.
+    InvalidArgument,
+    /// The call did not complete before its deadline.
+    DeadlineExceeded,
+    /// The requested entity was not found.
+    NotFound,
+    /// The entity the call tried to create already exists.
+    AlreadyExists,
+    /// The caller is not allowed to make the call.
+    PermissionDenied,
+    /// A resource, such as a rate limit, has been exhausted.
+    ResourceExhausted,
+    /// The system is not in a state in which the call can run.
+    FailedPrecondition,
+    /// The call was aborted, usually because of a conflict.
+    Aborted,
+    /// The call was attempted past the valid range.
+    OutOfRange,
+    /// The call is not implemented or supported by the server.
+    Unimplemented,
+    /// An internal error.
+    Internal,
+    /// The service is currently unavailable, and the call may be retried.
+    Unavailable,
+    /// Data has been lost or corrupted.
+    DataLoss,
+    /// The caller is not authenticated.
+    Unauthenticated,
+}
+
+impl Code {
+    /// The name of the code, as it is sent.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Code::Canceled => "canceled",
+            Code::Unknown => "unknown",
+            Code::InvalidArgument => "invalid_argument",
+            Code::DeadlineExceeded => "deadline_exceeded",
+            Code::NotFound => "not_found",
+            Code::AlreadyExists => "already_exists",
+            Code::PermissionDenied => "permission_denied",
+            Code::ResourceExhausted => "resource_exhausted",
+            Code::FailedPrecondition => "failed_precondition",
+            Code::Aborted => "aborted",
+            Code::OutOfRange => "out_of_range",
+            Code::Unimplemented => "unimplemented",
+            Code::Internal => "internal",
+            Code::Unavailable => "unavailable",
+            Code::DataLoss => "data_loss",
+            Code::Unauthenticated => "unauthenticated",
+        }
+    }
+
+    /// The HTTP status code of a unary response with this error code.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            Code::Canceled => 499,
+            Code::InvalidArgument
+            | Code::FailedPrecondition
+            | Code::OutOfRange => 400,
+            Code::DeadlineExceeded => 504,
+            Code::NotFound => 404,
+            Code::AlreadyExists | Code::Aborted => 409,
+            Code::PermissionDenied => 403,
+            Code::ResourceExhausted => 429,
+            Code::Unimplemented => 501,
+            Code::Unavailable => 503,
+            Code::Unauthenticated => 401,
+            Code::Unknown | Code::Internal | Code::DataLoss => 500,
+        }
+    }
+
+    /// The error code of a response that does not carry a Connect error, from its HTTP status.
+    pub fn from_http_status(status: u16) -> Self {
+        match status {
+            400 => Code::Internal,
+            401 => Code::Unauthenticated,
+            403 => Code::PermissionDenied,
+            404 => Code::Unimplemented,
+            429 | 502 | 503 | 504 => Code::Unavailable,
+            _ => Code::Unknown,
+        }
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for Code {
+    fn serialize<S: Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        self.as_str().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Code {
+    fn deserialize<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        const CODES: [Code; 16] = [
+            Code::Canceled,
+            Code::Unknown,
+            Code::InvalidArgument,
+            Code::DeadlineExceeded,
+            Code::NotFound,
+            Code::AlreadyExists,
+            Code::PermissionDenied,
+            Code::ResourceExhausted,
+            Code::FailedPrecondition,
+            Code::Aborted,
+            Code::OutOfRange,
+            Code::Unimplemented,
+            Code::Internal,
+            Code::Unavailable,
+            Code::DataLoss,
+            Code::Unauthenticated,
+        ];
+
+        let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Ok(CODES
+            .into_iter()
+            .find(|code| code.as_str() == name)
+            .unwrap_or(Code::Unknown))
+    }
+}
+
+/// An error as it is sent by the Connect protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectError {
+    /// The error code.
+    pub code: Code,
+    /// A message for developers.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+    /// Additional details of the error.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<ErrorDetail>,
+}
+
+impl ConnectError {
+    /// Creates an error with the given code and message.
+    pub fn new(code: Code, message: impl ToString) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+            details: Vec::new(),
+        }
+    }
+
+    /// Reads the error from a response that does not have a `200 OK` status.
+    fn from_response(status: u16, body: &[u8]) -> Self {
+        serde_json::from_slice(body).unwrap_or_else(|_| {
+            Self::new(
+                Code::from_http_status(status),
+                String::from_utf8_lossy(body),
+            )
+        })
+    }
+
+    /// Converts the error into the error type of a server function, preferring the error that
+    /// is attached as a detail.
+    fn into_error<E: FromServerFnError>(self) -> E {
+        match self
+            .details
+            .iter()
+            .find(|detail| detail.type_name == ERROR_DETAIL_TYPE)
+        {
+            Some(detail) => E::de(detail.value.clone()),
+            None => E::from_server_fn_error(self.into()),
+        }
+    }
+}
+
+impl From<ConnectError> for ServerFnErrorErr {
+    fn from(value: ConnectError) -> Self {
+        let message = if value.message.is_empty() {
+            value.code.to_string()
+        } else {
+            value.message
+        };
+        match value.code {
+            Code::InvalidArgument => ServerFnErrorErr::Args(message),
+            Code::Unauthenticated => ServerFnErrorErr::Unauthorized(message),
+            Code::PermissionDenied => ServerFnErrorErr::Forbidden(message),
+            Code::ResourceExhausted => {
+                ServerFnErrorErr::TooManyRequests { retry_after: None }
+            }
+            Code::Unavailable | Code::Unimplemented => {
+                ServerFnErrorErr::Request(message)
+            }
+            _ => ServerFnErrorErr::ServerError(message),
+        }
+    }
+}
+
+/// A detail of a [`ConnectError`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorDetail {
+    /// The fully qualified type name of the detail.
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// The encoded detail.
+    #[serde(with = "base64_bytes")]
+    pub value: Bytes,
+}
+
+mod base64_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        bytes: &Bytes,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        BASE64.encode(bytes).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Bytes, D::Error> {
+        let encoded = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        BASE64
+            .decode(encoded.as_bytes())
+            .map(Bytes::from)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// Describes an error as a [`ConnectError`], so that Connect clients can handle it.
+///
+/// This is implemented for [`ServerFnError`] and [`ServerFnErrorErr`]. To use a custom error
+/// type with the Connect protocols, implement it to choose the code of each error.
+pub trait ToConnectError {
+    /// Describes the error as a Connect error.
+    fn to_connect_error(&self) -> ConnectError;
+}
+
+impl ToConnectError for ServerFnErrorErr {
+    fn to_connect_error(&self) -> ConnectError {
+        let code = match self {
+            ServerFnErrorErr::ServerError(_) => Code::Unknown,
+            ServerFnErrorErr::Args(_) | ServerFnErrorErr::MissingArg(_) => {
+                Code::InvalidArgument
+            }
+            ServerFnErrorErr::Unauthorized(_) => Code::Unauthenticated,
+            ServerFnErrorErr::Forbidden(_) => Code::PermissionDenied,
+            ServerFnErrorErr::TooManyRequests { .. }
+            | ServerFnErrorErr::PayloadTooLarge(_) => Code::ResourceExhausted,
+            ServerFnErrorErr::UnsupportedMediaType(_) => Code::InvalidArgument,
+            ServerFnErrorErr::UnsupportedRequestMethod(_) => {
+                Code::Unimplemented
+            }
+            ServerFnErrorErr::Request(_)
+            | ServerFnErrorErr::WebsocketClosed { .. } => Code::Unavailable,
+            ServerFnErrorErr::Registration(_)
+            | ServerFnErrorErr::MiddlewareError(_)
+            | ServerFnErrorErr::Deserialization(_)
+            | ServerFnErrorErr::Serialization(_)
+            | ServerFnErrorErr::Response(_) => Code::Internal,
+        };
+        ConnectError::new(code, self)
+    }
+}
+
+impl<CustErr> ToConnectError for ServerFnError<CustErr>
+where
+    CustErr: Display + Clone,
+{
+    fn to_connect_error(&self) -> ConnectError {
+        let code = ServerFnErrorErr::from(self.clone()).to_connect_error().code;
+        ConnectError::new(code, self)
+    }
+}
+
+/// The error as it is sent, with the error itself attached as a detail.
+fn wire_error<E: FromServerFnError + ToConnectError>(err: &E) -> ConnectError {
+    let mut error = err.to_connect_error();
+    error.details.push(ErrorDetail {
+        type_name: ERROR_DETAIL_TYPE.into(),
+        value: err.ser(),
+    });
+    error
+}
+
+fn args_error<E: FromServerFnError>(message: String) -> E {
+    E::from_server_fn_error(ServerFnErrorErr::Args(message))
+}
+
+/// The content type of a stream, such as `application/connect+json` for `application/json`.
+fn stream_content_type(content_type: &str) -> String {
+    let codec = content_type
+        .strip_prefix("application/")
+        .unwrap_or(content_type);
+    format!("application/connect+{codec}")
+}
+
+/// Prefixes a message in a stream with its flags and length.
+fn envelope(flags: u8, message: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(message.len() + 5);
+    buf.put_u8(flags);
+    buf.put_u32(message.len() as u32);
+    buf.put_slice(message);
+    buf.freeze()
+}
+
+/// The message that ends a stream, with the error that ended it, if any.
+fn end_stream<E: FromServerFnError + ToConnectError>(
+    error: Option<&E>,
+) -> Bytes {
+    #[derive(Serialize)]
+    struct EndStream {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<ConnectError>,
+    }
+
+    let end = EndStream {
+        error: error.map(wire_error),
+    };
+    let message = serde_json::to_vec(&end)
+        .expect("the end of a stream can always be serialized");
+    envelope(END_STREAM_FLAG, &message)
+}
+
+/// Splits the next message off the buffer, if it has been received completely.
+fn split_message(buf: &mut BytesMut) -> Option<(u8, Bytes)> {
+    let len = u32::from_be_bytes(buf.get(1..5)?.try_into().ok()?) as usize;
+    if buf.len() < len + 5 {
+        return None;
+    }
+    let mut message = buf.split_to(len + 5);
+    let flags = message.get_u8();
+    message.advance(4);
+    Some((flags, message.freeze()))
+}
+
+/// Reads the single message of the request of a server-streaming call.
+fn single_message(body: Bytes) -> Result<Bytes, String> {
+    let mut buf = BytesMut::from(body);
+    match split_message(&mut buf) {
+        Some((flags, _)) if flags & COMPRESSED_FLAG != 0 => {
+            Err("compressed messages are not supported".into())
+        }
+        Some((_, message)) if buf.is_empty() => Ok(message),
+        _ => Err("the request must contain exactly one message".into()),
+    }
+}
+
+struct StreamState {
+    body: Pin<Box<dyn Stream<Item = Result<Bytes, Bytes>> + Send>>,
+    buf: BytesMut,
+    done: bool,
+}
+
+/// Reads the next message of a server-streaming response.
+async fn next_message<Item, Encoding, E>(
+    state: &mut StreamState,
+) -> Option<Result<Item, E>>
+where
+    Encoding: Decodes<Item>,
+    E: FromServerFnError,
+{
+    #[derive(Deserialize)]
+    struct EndStream {
+        #[serde(default)]
+        error: Option<ConnectError>,
+    }
+
+    while !state.done {
+        let Some((flags, message)) = split_message(&mut state.buf) else {
+            match state.body.next().await {
+                Some(Ok(chunk)) => state.buf.extend_from_slice(&chunk),
+                Some(Err(err)) => {
+                    state.done = true;
+                    return Some(Err(E::de(err)));
+                }
+                None => {
+                    state.done = true;
+                    return Some(Err(E::from_server_fn_error(
+                        ServerFnErrorErr::Request(
+                            "the stream closed before it ended".into(),
+                        ),
+                    )));
+                }
+            }
+            continue;
+        };
+
+        if flags & COMPRESSED_FLAG != 0 {
+            state.done = true;
+            return Some(Err(E::from_server_fn_error(
+                ServerFnErrorErr::Deserialization(
+                    "compressed messages are not supported".into(),
+                ),
+            )));
+        }
+        if flags & END_STREAM_FLAG != 0 {
+            state.done = true;
+            return match serde_json::from_slice::<EndStream>(&message) {
+                Ok(end) => end.error.map(|error| Err(error.into_error())),
+                Err(e) => Some(Err(E::from_server_fn_error(
+                    ServerFnErrorErr::Deserialization(e.to_string()),
+                ))),
+            };
+        }
+        return Some(Encoding::decode(message).map_err(|e| {
+            E::from_server_fn_error(ServerFnErrorErr::Deserialization(
+                e.to_string(),
+            ))
+        }));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_messages_across_chunks() {
+        let mut stream = envelope(0, b"{\"a\":1}").to_vec();
+        stream.extend_from_slice(&end_stream::<ServerFnError>(None));
+
+        let mut buf = BytesMut::new();
+        let mut messages = Vec::new();
+        for chunk in stream.chunks(3) {
+            buf.extend_from_slice(chunk);
+            while let Some(message) = split_message(&mut buf) {
+                messages.push(message);
+            }
+        }
+        assert_eq!(
+            messages,
+            [
+                (0, Bytes::from_static(b"{\"a\":1}")),
+                (END_STREAM_FLAG, Bytes::from_static(b"{}")),
+            ]
+        );
+        assert!(buf.is_empty());
+
+        assert_eq!(
+            single_message(envelope(0, b"hi")),
+            Ok(Bytes::from_static(b"hi"))
+        );
+        assert!(single_message(Bytes::from_static(b"\0\0\0")).is_err());
+        assert!(single_message(envelope(COMPRESSED_FLAG, b"hi")).is_err());
+    }
+
+    #[test]
+    fn sends_errors_with_codes() {
+        let err = ServerFnError::Args("missing name".into());
+        let json = serde_json::to_value(wire_error(&err)).unwrap();
+        assert_eq!(json["code"], "invalid_argument");
+        assert_eq!(json["details"][0]["type"], ERROR_DETAIL_TYPE);
+
+        let error: ConnectError = serde_json::from_value(json).unwrap();
+        assert_eq!(error.code.http_status(), 400);
+        assert_eq!(error.into_error::<ServerFnError>(), err);
+
+        let foreign = ConnectError::from_response(
+            403,
+            br#"{"code":"permission_denied","message":"no"}"#,
+        );
+        assert_eq!(
+            foreign.into_error::<ServerFnError>(),
+            ServerFnError::Forbidden("no".into())
+        );
+        let unknown =
+            ConnectError::from_response(200, br#"{"code":"something_new"}"#);
+        assert_eq!(unknown.code, Code::Unknown);
+        let proxy = ConnectError::from_response(502, b"Bad Gateway");
+        assert_eq!(proxy, ConnectError::new(Code::Unavailable, "Bad Gateway"));
+    }
+}
+
+#[cfg(all(test, feature = "axum-no-default"))]
+mod axum_tests {
+    use super::*;
+    use crate::{codec::JsonEncoding, testing::axum::TestServer, ServerFn};
+    use axum::body::Body;
+    use http::{header::CONTENT_TYPE, Request, StatusCode};
+    use http_body_util::BodyExt;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Greet {
+        name: String,
+    }
+
+    impl ServerFn for Greet {
+        const PATH: &'static str = "/acme.greet.v1.GreetService/Greet";
+
+        type Client = crate::testing::TestClient;
+        type Server = crate::axum::AxumServerFnBackend;
+        type Protocol = Connect<JsonEncoding>;
+        type Output = String;
+        type Error = ServerFnError;
+        type InputStreamError = ServerFnError;
+        type OutputStreamError = ServerFnError;
+
+        async fn run_body(self) -> Result<String, ServerFnError> {
+            if self.name.is_empty() {
+                return Err(ServerFnError::Args("name is empty".into()));
+            }
+            Ok(format!("Hello, {}!", self.name))
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Count {
+        to: u32,
+    }
+
+    impl ServerFn for Count {
+        const PATH: &'static str = "/acme.count.v1.CountService/Count";
+
+        type Client = crate::testing::TestClient;
+        type Server = crate::axum::AxumServerFnBackend;
+        type Protocol = ConnectStream<JsonEncoding>;
+        type Output = BoxedStream<u32, ServerFnError>;
+        type Error = ServerFnError;
+        type InputStreamError = ServerFnError;
+        type OutputStreamError = ServerFnError;
+
+        async fn run_body(
+            self,
+        ) -> Result<BoxedStream<u32, ServerFnError>, ServerFnError> {
+            let items = (1..=self.to)
+                .map(Ok)
+                .chain([Err(ServerFnError::ServerError("too far".into()))]);
+            Ok(stream::iter(items).into())
+        }
+    }
+
+    async fn send(req: Request<Body>) -> (StatusCode, String, Bytes) {
+        let res = crate::axum::handle_server_fn(req).await;
+        let content_type = res.headers()[CONTENT_TYPE].to_str().unwrap().into();
+        let status = res.status();
+        let body = res.into_body().collect().await.unwrap().to_bytes();
+        (status, content_type, body)
+    }
+
+    #[test]
+    fn answers_unary_calls() {
+        crate::axum::register_explicit::<Greet>();
+        futures::executor::block_on(async {
+            let req = Request::post(Greet::PATH)
+                .header(CONTENT_TYPE, "application/json")
+                .header(PROTOCOL_VERSION_HEADER, "1")
+                .body(Body::from(r#"{"name":"Ada"}"#))
+                .unwrap();
+            let (status, content_type, body) = send(req).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(content_type, "application/json");
+            assert_eq!(body, r#""Hello, Ada!""#);
+
+            let req = Request::post(Greet::PATH)
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(r#"{"name":""}"#))
+                .unwrap();
+            let (status, content_type, body) = send(req).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert_eq!(content_type, "application/json");
+            let error: ConnectError = serde_json::from_slice(&body).unwrap();
+            assert_eq!(error.code, Code::InvalidArgument);
+
+            let server = TestServer::new();
+            assert_eq!(
+                server.call(Greet { name: "Ada".into() }).await,
+                Ok("Hello, Ada!".into())
+            );
+            assert_eq!(
+                server.call(Greet { name: "".into() }).await,
+                Err(ServerFnError::Args("name is empty".into()))
+            );
+        });
+    }
+
+    #[test]
+    fn answers_server_streaming_calls() {
+        crate::axum::register_explicit::<Count>();
+        futures::executor::block_on(async {
+            let req = Request::post(Count::PATH)
+                .header(CONTENT_TYPE, "application/connect+json")
+                .body(Body::from(envelope(0, br#"{"to":2}"#)))
+                .unwrap();
+            let (status, content_type, body) = send(req).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(content_type, "application/connect+json");
+            let mut buf = BytesMut::from(body);
+            assert_eq!(split_message(&mut buf).unwrap().1, "1");
+            assert_eq!(split_message(&mut buf).unwrap().1, "2");
+            let (flags, end) = split_message(&mut buf).unwrap();
+            assert_eq!(flags, END_STREAM_FLAG);
+            let end: serde_json::Value = serde_json::from_slice(&end).unwrap();
+            assert_eq!(end["error"]["code"], "unknown");
+            assert!(buf.is_empty());
+
+            let items = TestServer::new()
+                .call(Count { to: 3 })
+                .await
+                .unwrap()
+                .stream
+                .collect::<Vec<_>>()
+                .await;
+            assert_eq!(
+                items,
+                [
+                    Ok(1),
+                    Ok(2),
+                    Ok(3),
+                    Err(ServerFnError::ServerError("too far".into()))
+                ]
+            );
+        });
+    }
+}
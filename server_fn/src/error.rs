@@ -194,6 +194,31 @@ pub enum ServerFnError<E = NoCustomError> {
     Args(String),
     /// Occurs on the server if there's a missing argument.
     MissingArg(String),
+    /// Occurs when a server function's guard rejected the request because the client is not
+    /// authenticated (`401 Unauthorized`).
+    Unauthorized(String),
+    /// Occurs when a server function's guard rejected the request because the client is not
+    /// allowed to call it (`403 Forbidden`).
+    Forbidden(String),
+    /// Occurs when the server rejected the request because a rate limit or concurrency
+    /// limit was exceeded (`429 Too Many Requests`).
+    TooManyRequests {
+        /// The number of seconds after which the request may be retried, if known.
+        retry_after: Option<u64>,
+    },
+    /// Occurs when the server rejected a request body in a content encoding it does not
+    /// support (`415 Unsupported Media Type`).
+    UnsupportedMediaType(String),
+    /// Occurs when the server rejected a request body that was too large once decompressed
+    /// (`413 Payload Too Large`).
+    PayloadTooLarge(String),
+    /// Occurs on the client when a websocket connection was closed abnormally, or was lost.
+    WebsocketClosed {
+        /// The close code sent by the server, or `1006` if the connection was lost.
+        code: u16,
+        /// The reason for closing the connection, if any.
+        reason: String,
+    },
 }
 
 impl ServerFnError<NoCustomError> {
@@ -203,6 +228,31 @@ impl ServerFnError<NoCustomError> {
     }
 }
 
+impl<CustErr> ServerFnError<CustErr> {
+    /// The name of this error's variant, for example `ServerError`.
+    #[allow(deprecated)]
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            ServerFnError::WrappedServerError(_) => "WrappedServerError",
+            ServerFnError::Registration(_) => "Registration",
+            ServerFnError::Request(_) => "Request",
+            ServerFnError::Response(_) => "Response",
+            ServerFnError::ServerError(_) => "ServerError",
+            ServerFnError::MiddlewareError(_) => "MiddlewareError",
+            ServerFnError::Deserialization(_) => "Deserialization",
+            ServerFnError::Serialization(_) => "Serialization",
+            ServerFnError::Args(_) => "Args",
+            ServerFnError::MissingArg(_) => "MissingArg",
+            ServerFnError::Unauthorized(_) => "Unauthorized",
+            ServerFnError::Forbidden(_) => "Forbidden",
+            ServerFnError::TooManyRequests { .. } => "TooManyRequests",
+            ServerFnError::UnsupportedMediaType(_) => "UnsupportedMediaType",
+            ServerFnError::PayloadTooLarge(_) => "PayloadTooLarge",
+            ServerFnError::WebsocketClosed { .. } => "WebsocketClosed",
+        }
+    }
+}
+
 impl<CustErr> From<CustErr> for ServerFnError<CustErr> {
     fn from(value: CustErr) -> Self {
         ServerFnError::WrappedServerError(value)
@@ -244,6 +294,16 @@ where
                 ServerFnError::MissingArg(s) => format!("missing argument {s}"),
                 ServerFnError::Response(s) =>
                     format!("error generating HTTP response: {s}"),
+                ServerFnError::Unauthorized(s) => format!("unauthorized: {s}"),
+                ServerFnError::Forbidden(s) => format!("forbidden: {s}"),
+                ServerFnError::TooManyRequests { retry_after } =>
+                    too_many_requests(*retry_after),
+                ServerFnError::UnsupportedMediaType(s) =>
+                    format!("unsupported media type: {s}"),
+                ServerFnError::PayloadTooLarge(s) =>
+                    format!("payload too large: {s}"),
+                ServerFnError::WebsocketClosed { code, reason } =>
+                    websocket_closed(*code, reason),
                 ServerFnError::WrappedServerError(e) => format!("{e}"),
             }
         )
@@ -294,6 +354,27 @@ where
             ServerFnError::MissingArg(e) => {
                 write!(&mut buf, "MissingArg|{e}")
             }
+            ServerFnError::Unauthorized(e) => {
+                write!(&mut buf, "Unauthorized|{e}")
+            }
+            ServerFnError::Forbidden(e) => write!(&mut buf, "Forbidden|{e}"),
+            ServerFnError::TooManyRequests { retry_after } => {
+                write!(&mut buf, "TooManyRequests|").and_then(|_| {
+                    match retry_after {
+                        Some(seconds) => write!(&mut buf, "{seconds}"),
+                        None => Ok(()),
+                    }
+                })
+            }
+            ServerFnError::UnsupportedMediaType(e) => {
+                write!(&mut buf, "UnsupportedMediaType|{e}")
+            }
+            ServerFnError::PayloadTooLarge(e) => {
+                write!(&mut buf, "PayloadTooLarge|{e}")
+            }
+            ServerFnError::WebsocketClosed { code, reason } => {
+                write!(&mut buf, "WebsocketClosed|{code}|{reason}")
+            }
         };
 
         match result {
@@ -342,6 +423,30 @@ where
                 }
                 "Args" => Ok(ServerFnError::Args(data.to_string())),
                 "MissingArg" => Ok(ServerFnError::MissingArg(data.to_string())),
+                "Unauthorized" => {
+                    Ok(ServerFnError::Unauthorized(data.to_string()))
+                }
+                "Forbidden" => Ok(ServerFnError::Forbidden(data.to_string())),
+                "TooManyRequests" => Ok(ServerFnError::TooManyRequests {
+                    retry_after: data.parse().ok(),
+                }),
+                "UnsupportedMediaType" => {
+                    Ok(ServerFnError::UnsupportedMediaType(data.to_string()))
+                }
+                "PayloadTooLarge" => {
+                    Ok(ServerFnError::PayloadTooLarge(data.to_string()))
+                }
+                "WebsocketClosed" => data
+                    .split_once('|')
+                    .and_then(|(code, reason)| {
+                        Some(ServerFnError::WebsocketClosed {
+                            code: code.parse().ok()?,
+                            reason: reason.to_string(),
+                        })
+                    })
+                    .ok_or_else(|| {
+                        format!("Invalid websocket close in {data:?}")
+                    }),
                 _ => Err(format!("Unknown error type: {ty}")),
             })
     }
@@ -379,6 +484,80 @@ where
             ServerFnErrorErr::UnsupportedRequestMethod(value) => {
                 ServerFnError::Request(value)
             }
+            ServerFnErrorErr::Unauthorized(value) => {
+                ServerFnError::Unauthorized(value)
+            }
+            ServerFnErrorErr::Forbidden(value) => {
+                ServerFnError::Forbidden(value)
+            }
+            ServerFnErrorErr::TooManyRequests { retry_after } => {
+                ServerFnError::TooManyRequests { retry_after }
+            }
+            ServerFnErrorErr::UnsupportedMediaType(value) => {
+                ServerFnError::UnsupportedMediaType(value)
+            }
+            ServerFnErrorErr::PayloadTooLarge(value) => {
+                ServerFnError::PayloadTooLarge(value)
+            }
+            ServerFnErrorErr::WebsocketClosed { code, reason } => {
+                ServerFnError::WebsocketClosed { code, reason }
+            }
+        }
+    }
+
+    fn error_type(&self) -> &'static str {
+        self.variant_name()
+    }
+}
+
+impl<CustErr> From<ServerFnError<CustErr>> for ServerFnErrorErr
+where
+    CustErr: Display,
+{
+    fn from(value: ServerFnError<CustErr>) -> Self {
+        match value {
+            ServerFnError::WrappedServerError(value) => {
+                ServerFnErrorErr::ServerError(value.to_string())
+            }
+            ServerFnError::Registration(value) => {
+                ServerFnErrorErr::Registration(value)
+            }
+            ServerFnError::Request(value) => ServerFnErrorErr::Request(value),
+            ServerFnError::Response(value) => ServerFnErrorErr::Response(value),
+            ServerFnError::ServerError(value) => {
+                ServerFnErrorErr::ServerError(value)
+            }
+            ServerFnError::MiddlewareError(value) => {
+                ServerFnErrorErr::MiddlewareError(value)
+            }
+            ServerFnError::Deserialization(value) => {
+                ServerFnErrorErr::Deserialization(value)
+            }
+            ServerFnError::Serialization(value) => {
+                ServerFnErrorErr::Serialization(value)
+            }
+            ServerFnError::Args(value) => ServerFnErrorErr::Args(value),
+            ServerFnError::MissingArg(value) => {
+                ServerFnErrorErr::MissingArg(value)
+            }
+            ServerFnError::Unauthorized(value) => {
+                ServerFnErrorErr::Unauthorized(value)
+            }
+            ServerFnError::Forbidden(value) => {
+                ServerFnErrorErr::Forbidden(value)
+            }
+            ServerFnError::TooManyRequests { retry_after } => {
+                ServerFnErrorErr::TooManyRequests { retry_after }
+            }
+            ServerFnError::UnsupportedMediaType(value) => {
+                ServerFnErrorErr::UnsupportedMediaType(value)
+            }
+            ServerFnError::PayloadTooLarge(value) => {
+                ServerFnErrorErr::PayloadTooLarge(value)
+            }
+            ServerFnError::WebsocketClosed { code, reason } => {
+                ServerFnErrorErr::WebsocketClosed { code, reason }
+            }
         }
     }
 }
@@ -435,6 +614,92 @@ pub enum ServerFnErrorErr {
     /// Occurs on the server if there is an error creating an HTTP response.
     #[error("error creating response {0}")]
     Response(String),
+    /// Occurs when a server function's guard rejected the request because the client is not
+    /// authenticated (`401 Unauthorized`).
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    /// Occurs when a server function's guard rejected the request because the client is not
+    /// allowed to call it (`403 Forbidden`).
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+    /// Occurs when the server rejected the request because a rate limit or concurrency
+    /// limit was exceeded (`429 Too Many Requests`).
+    #[error("{}", too_many_requests(*.retry_after))]
+    TooManyRequests {
+        /// The number of seconds after which the request may be retried, if known.
+        retry_after: Option<u64>,
+    },
+    /// Occurs when the server rejected a request body in a content encoding it does not
+    /// support (`415 Unsupported Media Type`).
+    #[error("unsupported media type: {0}")]
+    UnsupportedMediaType(String),
+    /// Occurs when the server rejected a request body that was too large once decompressed
+    /// (`413 Payload Too Large`).
+    #[error("payload too large: {0}")]
+    PayloadTooLarge(String),
+    /// Occurs on the client when a websocket connection was closed abnormally, or was lost.
+    #[error("{}", websocket_closed(*.code, .reason))]
+    WebsocketClosed {
+        /// The close code sent by the server, or `1006` if the connection was lost.
+        code: u16,
+        /// The reason for closing the connection, if any.
+        reason: String,
+    },
+}
+
+impl ServerFnErrorErr {
+    /// The name of this error's variant, for example `ServerError`.
+    pub fn variant_name(&self) -> &'static str {
+        match self {
+            ServerFnErrorErr::Registration(_) => "Registration",
+            ServerFnErrorErr::UnsupportedRequestMethod(_) => {
+                "UnsupportedRequestMethod"
+            }
+            ServerFnErrorErr::Request(_) => "Request",
+            ServerFnErrorErr::ServerError(_) => "ServerError",
+            ServerFnErrorErr::MiddlewareError(_) => "MiddlewareError",
+            ServerFnErrorErr::Deserialization(_) => "Deserialization",
+            ServerFnErrorErr::Serialization(_) => "Serialization",
+            ServerFnErrorErr::Args(_) => "Args",
+            ServerFnErrorErr::MissingArg(_) => "MissingArg",
+            ServerFnErrorErr::Response(_) => "Response",
+            ServerFnErrorErr::Unauthorized(_) => "Unauthorized",
+            ServerFnErrorErr::Forbidden(_) => "Forbidden",
+            ServerFnErrorErr::TooManyRequests { .. } => "TooManyRequests",
+            ServerFnErrorErr::UnsupportedMediaType(_) => "UnsupportedMediaType",
+            ServerFnErrorErr::PayloadTooLarge(_) => "PayloadTooLarge",
+            ServerFnErrorErr::WebsocketClosed { .. } => "WebsocketClosed",
+        }
+    }
+
+    /// The HTTP status code of a response carrying this error.
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ServerFnErrorErr::Unauthorized(_) => 401,
+            ServerFnErrorErr::Forbidden(_) => 403,
+            ServerFnErrorErr::PayloadTooLarge(_) => 413,
+            ServerFnErrorErr::UnsupportedMediaType(_) => 415,
+            ServerFnErrorErr::TooManyRequests { .. } => 429,
+            _ => 500,
+        }
+    }
+}
+
+fn too_many_requests(retry_after: Option<u64>) -> String {
+    match retry_after {
+        Some(seconds) => {
+            format!("too many requests; retry after {seconds} seconds")
+        }
+        None => "too many requests".to_string(),
+    }
+}
+
+fn websocket_closed(code: u16, reason: &str) -> String {
+    if reason.is_empty() {
+        format!("websocket closed with code {code}")
+    } else {
+        format!("websocket closed with code {code}: {reason}")
+    }
 }
 
 /// Associates a particular server function error with the server function
@@ -568,6 +833,14 @@ pub trait FromServerFnError: std::fmt::Debug + Sized + 'static {
     /// Converts a [`ServerFnErrorErr`] into the application-specific custom error type.
     fn from_server_fn_error(value: ServerFnErrorErr) -> Self;
 
+    /// The kind of error this is, recorded as `error.type` on the span of a server function
+    /// call with the `tracing` feature. Defaults to the name of the type, without its path.
+    fn error_type(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
     /// Converts the custom error type to a [`String`].
     fn ser(&self) -> Bytes {
         Self::Encoder::encode(self).unwrap_or_else(|e| {
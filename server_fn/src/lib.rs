@@ -105,20 +105,42 @@ pub mod client;
 /// Implementations of the server side of the server function call.
 pub mod server;
 
+/// Batching of several server function calls into a single request.
+pub mod batch;
 /// Encodings for arguments and results.
 pub mod codec;
+/// Compression of request and response bodies.
+pub mod compression;
+/// Protocols that let Connect clients call server functions.
+pub mod connect;
+mod random;
 
 #[macro_use]
 /// Error types and utilities.
 pub mod error;
 /// Types to add server middleware to a server function.
 pub mod middleware;
+/// Describes server functions in an OpenAPI document.
+#[cfg(feature = "openapi")]
+pub mod openapi;
 /// Utilities to allow client-side redirects.
 pub mod redirect;
 /// Types and traits for  for HTTP requests.
 pub mod request;
 /// Types and traits for HTTP responses.
 pub mod response;
+/// A protocol that streams server function output as server-sent events.
+pub mod sse;
+/// Calling server functions in-process, for tests.
+#[cfg(feature = "ssr")]
+pub mod testing;
+/// Propagating trace context between the client and the server.
+#[cfg(feature = "tracing")]
+pub mod trace;
+/// Versioning of server functions, and detecting clients from an older build.
+pub mod version;
+/// Heartbeats, reconnection and send buffering for websocket server functions.
+pub mod websocket;
 
 #[cfg(feature = "actix-no-default")]
 #[doc(hidden)]
@@ -139,6 +161,7 @@ pub use bytes::Bytes;
 use bytes::{BufMut, BytesMut};
 use client::Client;
 use codec::{Encoding, FromReq, FromRes, IntoReq, IntoRes};
+pub use connect::{Connect, ConnectStream};
 #[doc(hidden)]
 pub use const_format;
 #[doc(hidden)]
@@ -162,6 +185,7 @@ pub use serde;
 #[cfg(feature = "serde-lite")]
 pub use serde_lite;
 use server::Server;
+pub use sse::Sse;
 use std::{
     fmt::{Debug, Display},
     future::Future,
@@ -279,6 +303,49 @@ pub trait ServerFn: Send + Sized {
         Vec::new()
     }
 
+    /// The retry policy for calls to this server function, if it overrides the global one.
+    ///
+    /// See [`client::retry`] for details.
+    fn retry_policy() -> Option<client::retry::RetryPolicy> {
+        None
+    }
+
+    /// The websocket configuration for calls to this server function, if it overrides the
+    /// global one.
+    ///
+    /// This only applies to server functions that use the [`Websocket`] protocol. See
+    /// [`websocket`] for details.
+    fn websocket_config() -> Option<websocket::WebsocketConfig> {
+        None
+    }
+
+    /// Whether this server function has a [`guard`](ServerFn::guard).
+    ///
+    /// This is used to audit which server functions can be called without any check; see
+    /// `unguarded_server_fns` in the Axum and Actix integrations.
+    const GUARDED: bool = false;
+
+    /// Checks whether the current request may call this server function.
+    ///
+    /// This runs on the server before the arguments are deserialized. It can access the
+    /// request through the server integration's context (for example, with the `extract`
+    /// helpers in `leptos_axum` and `leptos_actix`). An [`Unauthorized`](ServerFnErrorErr::Unauthorized)
+    /// or [`Forbidden`](ServerFnErrorErr::Forbidden) error is sent with a `401` or `403`
+    /// status code.
+    ///
+    /// This is usually set with the `guard` argument to the `#[server]` macro.
+    fn guard() -> impl Future<Output = Result<(), ServerFnErrorErr>> + Send {
+        async { Ok(()) }
+    }
+
+    /// Describes the arguments, return value and error of this server function.
+    #[cfg(feature = "openapi")]
+    fn openapi_schema(
+        _generator: &mut openapi::SchemaGenerator,
+    ) -> openapi::ServerFnSchema {
+        openapi::ServerFnSchema::default()
+    }
+
     /// The body of the server function. This will only run on the server.
     fn run_body(
         self,
@@ -302,8 +369,8 @@ pub trait ServerFn: Send + Sized {
         async move {
             #[allow(unused_variables, unused_mut)]
             // used in form redirects feature
-            let (mut res, err) =
-                Self::Protocol::run_server(req, Self::run_body)
+            let (mut res, err) = match Self::guard().await {
+                Ok(()) => Self::Protocol::run_server(req, Self::run_body)
                     .await
                     .map(|res| (res, None))
                     .unwrap_or_else(|e| {
@@ -317,7 +384,27 @@ pub trait ServerFn: Send + Sized {
                             ),
                             Some(e),
                         )
-                    });
+                    }),
+                Err(rejection) => {
+                    let status = rejection.status_code();
+                    let e = Self::Error::from_server_fn_error(rejection);
+                    let mut res =
+                        <<Self as ServerFn>::Server as crate::Server<
+                            Self::Error,
+                            Self::InputStreamError,
+                            Self::OutputStreamError,
+                        >>::Response::error_response(
+                            Self::PATH, e.ser()
+                        );
+                    res.set_status(status);
+                    (res, Some(e))
+                }
+            };
+
+            #[cfg(feature = "tracing")]
+            if let Some(err) = &err {
+                trace::record_error(err);
+            }
 
             // if it accepts HTML, we'll redirect to the Referer
             #[cfg(feature = "form-redirects")]
@@ -348,7 +435,13 @@ pub trait ServerFn: Send + Sized {
     fn run_on_client(
         self,
     ) -> impl Future<Output = Result<Self::Output, Self::Error>> + Send {
-        async move { Self::Protocol::run_client(Self::PATH, self).await }
+        client::retry::with_default_retry_policy(
+            Self::retry_policy(),
+            websocket::with_default_websocket_config(
+                Self::websocket_config(),
+                Self::Protocol::run_client(Self::PATH, self),
+            ),
+        )
     }
 }
 
@@ -370,6 +463,10 @@ pub trait Protocol<
     /// The HTTP method used for requests.
     const METHOD: Method;
 
+    /// The content types of the request and response bodies, if the protocol uses a single
+    /// request and response. This is used to describe the server function.
+    const CONTENT_TYPES: Option<(&'static str, &'static str)> = None;
+
     /// Run the server function on the server. The implementation should handle deserializing the
     /// input, running the server function, and serializing the output.
     fn run_server<F, Fut>(
@@ -444,6 +541,8 @@ where
     Server: crate::Server<E>,
 {
     const METHOD: Method = InputProtocol::METHOD;
+    const CONTENT_TYPES: Option<(&'static str, &'static str)> =
+        Some((InputProtocol::CONTENT_TYPE, OutputProtocol::CONTENT_TYPE));
 
     async fn run_server<F, Fut>(
         request: Server::Request,
@@ -497,6 +596,9 @@ where
 /// formats. For example, [`Websocket<CborEncoding, JsonEncoding>`] would accept a stream of Cbor-encoded messages
 /// and return a stream of JSON-encoded messages.
 ///
+/// On the client, the connection can send heartbeats, reconnect when it is lost, and buffer
+/// messages that can't be sent yet. See [`websocket`] for details.
+///
 /// # Example
 ///
 /// ```rust, no_run
@@ -614,7 +716,7 @@ where
     OutputStreamError: FromServerFnError + Send,
     Error: FromServerFnError + Send,
     Server: crate::Server<Error, InputStreamError, OutputStreamError>,
-    Client: crate::Client<Error, InputStreamError, OutputStreamError>,
+    Client: crate::Client<Error, InputStreamError, OutputStreamError> + 'static,
     OutputItem: Send + 'static,
     InputItem: Send + 'static,
 {
@@ -635,7 +737,27 @@ where
     {
         let (request_bytes, response_stream, response) =
             request.try_into_websocket().await?;
-        let input = request_bytes.map(|request_bytes| {
+
+        // heartbeats are answered as they arrive, so that they don't depend on the server
+        // function reading its input
+        let (pong_tx, mut pongs) = futures::channel::mpsc::unbounded();
+        let (mut input_tx, input_rx) = futures::channel::mpsc::channel(16);
+        Server::spawn(async move {
+            pin_mut!(request_bytes);
+            while let Some(request_bytes) = request_bytes.next().await {
+                match request_bytes {
+                    Ok(bytes) if bytes.first() == Some(&websocket::PING) => {
+                        _ = pong_tx.unbounded_send(Bytes::from_static(&[
+                            websocket::PONG,
+                        ]));
+                    }
+                    // keep answering heartbeats if the input has been dropped
+                    request_bytes => _ = input_tx.send(request_bytes).await,
+                }
+            }
+        })?;
+
+        let input = input_rx.map(|request_bytes| {
             let request_bytes = request_bytes
                 .map(|bytes| deserialize_result::<InputStreamError>(bytes))
                 .unwrap_or_else(Err);
@@ -675,9 +797,19 @@ where
 
         Server::spawn(async move {
             pin_mut!(response_stream);
-            pin_mut!(output);
-            while let Some(output) = output.next().await {
-                if response_stream.send(output).await.is_err() {
+            let mut output = output.fuse();
+            loop {
+                let frame = futures::select! {
+                    output = output.next() => match output {
+                        Some(output) => output,
+                        None => break,
+                    },
+                    pong = pongs.next() => match pong {
+                        Some(pong) => pong,
+                        None => continue,
+                    },
+                };
+                if response_stream.send(frame).await.is_err() {
                     break;
                 }
             }
@@ -695,32 +827,25 @@ where
         let input = input.into();
 
         async move {
-            let (stream, sink) = Client::open_websocket(path).await?;
-
-            // Forward the input stream to the websocket
-            Client::spawn(async move {
-                pin_mut!(input);
-                pin_mut!(sink);
-                while let Some(input) = input.stream.next().await {
-                    let result = match input {
-                        Ok(input) => {
-                            InputEncoding::encode(&input).map_err(|e| {
-                                InputStreamError::from_server_fn_error(
-                                    ServerFnErrorErr::Serialization(
-                                        e.to_string(),
-                                    ),
-                                )
-                                .ser()
-                            })
-                        }
-                        Err(err) => Err(err.ser()),
-                    };
-                    let result = serialize_result(result);
-                    if sink.send(result).await.is_err() {
-                        break;
-                    }
-                }
+            let input = input.stream.map(|input| {
+                let result = match input {
+                    Ok(input) => InputEncoding::encode(&input).map_err(|e| {
+                        InputStreamError::from_server_fn_error(
+                            ServerFnErrorErr::Serialization(e.to_string()),
+                        )
+                        .ser()
+                    }),
+                    Err(err) => Err(err.ser()),
+                };
+                serialize_result(result)
             });
+            let stream = websocket::connect::<
+                Client,
+                Error,
+                InputStreamError,
+                OutputStreamError,
+            >(path, input)
+            .await?;
 
             // Return the output stream
             let stream = stream.map(|request_bytes| {
@@ -756,7 +881,7 @@ where
 // Format: [tag: u8][content: Bytes]
 // - Tag 0: Ok variant
 // - Tag 1: Err variant
-fn serialize_result(result: Result<Bytes, Bytes>) -> Bytes {
+pub(crate) fn serialize_result(result: Result<Bytes, Bytes>) -> Bytes {
     match result {
         Ok(bytes) => {
             let mut buf = BytesMut::with_capacity(1 + bytes.len());
@@ -884,6 +1009,9 @@ pub struct ServerFnTraitObj<Req, Res> {
     handler: fn(Req) -> Pin<Box<dyn Future<Output = Res> + Send>>,
     middleware: fn() -> MiddlewareSet<Req, Res>,
     ser: fn(ServerFnErrorErr) -> Bytes,
+    guarded: bool,
+    #[cfg(feature = "openapi")]
+    openapi: fn(&mut openapi::SchemaGenerator) -> openapi::Operation,
 }
 
 impl<Req, Res> ServerFnTraitObj<Req, Res> {
@@ -917,6 +1045,9 @@ impl<Req, Res> ServerFnTraitObj<Req, Res> {
             handler,
             middleware: S::middlewares,
             ser: |e| S::Error::from_server_fn_error(e).ser(),
+            guarded: S::GUARDED,
+            #[cfg(feature = "openapi")]
+            openapi: openapi::Operation::of::<S>,
         }
     }
 
@@ -940,6 +1071,20 @@ impl<Req, Res> ServerFnTraitObj<Req, Res> {
         (self.middleware)()
     }
 
+    /// Whether the server function checks requests with a [guard](ServerFn::guard).
+    pub fn guarded(&self) -> bool {
+        self.guarded
+    }
+
+    /// Describes this server function as an OpenAPI operation.
+    #[cfg(feature = "openapi")]
+    pub fn openapi_operation(
+        &self,
+        generator: &mut openapi::SchemaGenerator,
+    ) -> openapi::Operation {
+        (self.openapi)(generator)
+    }
+
     /// Converts the server function into a boxed service.
     pub fn boxed(self) -> BoxedService<Req, Res>
     where
@@ -974,6 +1119,9 @@ impl<Req, Res> Clone for ServerFnTraitObj<Req, Res> {
             handler: self.handler,
             middleware: self.middleware,
             ser: self.ser,
+            guarded: self.guarded,
+            #[cfg(feature = "openapi")]
+            openapi: self.openapi,
         }
     }
 }
@@ -1004,6 +1152,8 @@ pub mod axum {
     use http::{Method, Request, Response, StatusCode};
     use std::future::Future;
 
+    pub use crate::batch::axum::handle_batch;
+
     static REGISTERED_SERVER_FUNCTIONS: LazyServerFnMap<
         Request<Body>,
         Response<Body>,
@@ -1075,19 +1225,53 @@ pub mod axum {
             .map(|item| (item.path(), item.method()))
     }
 
+    /// The paths of all registered server functions that do not have a guard.
+    ///
+    /// This can be used to audit which server functions can be called without any
+    /// authorization check.
+    pub fn unguarded_server_fns() -> impl Iterator<Item = (&'static str, Method)>
+    {
+        REGISTERED_SERVER_FUNCTIONS
+            .iter()
+            .filter(|item| !item.guarded())
+            .map(|item| (item.path(), item.method()))
+    }
+
+    /// Builds an OpenAPI document that describes all registered server functions.
+    #[cfg(feature = "openapi")]
+    pub fn openapi_document(
+        api: &crate::openapi::OpenApi,
+    ) -> serde_json::Value {
+        let mut generator = crate::openapi::schema_generator();
+        let operations = REGISTERED_SERVER_FUNCTIONS
+            .iter()
+            .map(|item| item.openapi_operation(&mut generator))
+            .collect::<Vec<_>>();
+        api.document(operations, generator)
+    }
+
     /// An Axum handler that responds to a server function request.
+    ///
+    /// This also responds to batch requests at [`batch_path`](crate::batch::batch_path).
     pub async fn handle_server_fn(req: Request<Body>) -> Response<Body> {
         let path = req.uri().path();
 
-        if let Some(mut service) =
+        if path == crate::batch::batch_path() {
+            handle_batch(req, |mut service, req| async move {
+                service.run(req).await
+            })
+            .await
+        } else if let Some(mut service) =
             get_server_fn_service(path, req.method().clone())
         {
             service.run(req).await
         } else {
-            Response::builder()
-                .status(StatusCode::BAD_REQUEST)
-                .body(Body::from(format!(
-                    "Could not find a server function at the route {path}. \
+            let mut res = Response::builder().status(StatusCode::BAD_REQUEST);
+            if let Some(headers) = res.headers_mut() {
+                crate::version::axum::insert_build_id(headers);
+            }
+            res.body(Body::from(format!(
+                "Could not find a server function at the route {path}. \
                      \n\nIt's likely that either\n 1. The API prefix you \
                      specify in the `#[server]` macro doesn't match the \
                      prefix at which your server function handler is mounted, \
@@ -1095,8 +1279,8 @@ pub mod axum {
                      automatic server function registration and you need to \
                      call ServerFn::register_explicit() on the server \
                      function type, somewhere in your `main` function.",
-                )))
-                .unwrap()
+            )))
+            .unwrap()
         }
     }
 
@@ -1112,7 +1296,8 @@ pub mod axum {
             for middleware in middleware {
                 service = middleware.layer(service);
             }
-            service
+            let service = crate::version::axum::advertise_build_id(service);
+            crate::compression::axum::negotiate_compression(service)
         })
     }
 }
@@ -1131,6 +1316,8 @@ pub mod actix {
     pub use send_wrapper::SendWrapper;
     use std::future::Future;
 
+    pub use crate::batch::actix::handle_batch;
+
     static REGISTERED_SERVER_FUNCTIONS: LazyServerFnMap<
         ActixRequest,
         ActixResponse,
@@ -1186,21 +1373,57 @@ pub mod actix {
             .map(|item| (item.path(), item.method()))
     }
 
+    /// The paths of all registered server functions that do not have a guard.
+    ///
+    /// This can be used to audit which server functions can be called without any
+    /// authorization check.
+    pub fn unguarded_server_fns() -> impl Iterator<Item = (&'static str, Method)>
+    {
+        REGISTERED_SERVER_FUNCTIONS
+            .iter()
+            .filter(|item| !item.guarded())
+            .map(|item| (item.path(), item.method()))
+    }
+
+    /// Builds an OpenAPI document that describes all registered server functions.
+    #[cfg(feature = "openapi")]
+    pub fn openapi_document(
+        api: &crate::openapi::OpenApi,
+    ) -> serde_json::Value {
+        let mut generator = crate::openapi::schema_generator();
+        let operations = REGISTERED_SERVER_FUNCTIONS
+            .iter()
+            .map(|item| item.openapi_operation(&mut generator))
+            .collect::<Vec<_>>();
+        api.document(operations, generator)
+    }
+
     /// An Actix handler that responds to a server function request.
+    ///
+    /// This also responds to batch requests at [`batch_path`](crate::batch::batch_path).
     pub async fn handle_server_fn(
         req: HttpRequest,
         payload: Payload,
     ) -> HttpResponse {
         let path = req.uri().path();
         let method = req.method();
-        if let Some(mut service) = get_server_fn_service(path, method) {
+        if path == crate::batch::batch_path() {
+            handle_batch(req, payload, |mut service, req| async move {
+                service.run(req).await
+            })
+            .await
+        } else if let Some(mut service) = get_server_fn_service(path, method) {
             service
                 .run(ActixRequest::from((req, payload)))
                 .await
                 .0
                 .take()
         } else {
-            HttpResponse::BadRequest().body(format!(
+            let mut res = HttpResponse::BadRequest();
+            if let Some(id) = crate::version::build_id() {
+                res.insert_header((crate::version::BUILD_ID_HEADER, id));
+            }
+            res.body(format!(
                 "Could not find a server function at the route {path}. \
                  \n\nIt's likely that either\n 1. The API prefix you specify \
                  in the `#[server]` macro doesn't match the prefix at which \
@@ -1239,7 +1462,9 @@ pub mod actix {
                 for middleware in middleware {
                     service = middleware.layer(service);
                 }
-                service
+                let service =
+                    crate::version::actix::advertise_build_id(service);
+                crate::compression::actix::negotiate_compression(service)
             },
         )
     }
@@ -0,0 +1,507 @@
+//! HTTP caching for server functions.
+//!
+//! [`ServerFnCache`] is a middleware layer that computes an `ETag` over the encoded
+//! response of a server function, answers conditional requests (`If-None-Match`) with
+//! `304 Not Modified`, and adds `Cache-Control` and `Vary` headers according to a
+//! [`CachePolicy`].
+//!
+//! Only `GET` requests with a successful, fully-buffered response are cached. This means
+//! caching is useful for server functions that use the [`GetUrl`](crate::codec::GetUrl)
+//! input encoding, and never applies to streaming responses.
+//!
+//! The easiest way to use it is the `cache` argument to the `#[server]` macro:
+//!
+//! ```rust,ignore
+//! #[server(input = GetUrl, cache = CachePolicy::max_age(60).private())]
+//! pub async fn dashboard_stats() -> Result<Stats, ServerFnError> {
+//!     // ...
+//! }
+//!
+//! // an integer is shorthand for `CachePolicy::max_age(n)`
+//! #[server(input = GetUrl, cache = 30)]
+//! pub async fn list_projects() -> Result<Vec<Project>, ServerFnError> {
+//!     // ...
+//! }
+//! ```
+
+use std::{borrow::Cow, time::Duration};
+
+/// Describes how the response of a server function may be cached by clients and proxies.
+///
+/// The default policy ([`CachePolicy::revalidate`]) allows responses to be stored, but
+/// requires them to be revalidated with the server on every use, so that only the
+/// `ETag` comparison decides whether the body is sent again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachePolicy {
+    max_age: Option<Duration>,
+    stale_while_revalidate: Option<Duration>,
+    private: bool,
+    vary: Vec<Cow<'static, str>>,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self::revalidate()
+    }
+}
+
+impl CachePolicy {
+    /// Responses may be stored, but must be revalidated before each use (`no-cache`).
+    pub fn revalidate() -> Self {
+        Self {
+            max_age: None,
+            stale_while_revalidate: None,
+            private: false,
+            vary: vec![Cow::Borrowed("Accept")],
+        }
+    }
+
+    /// Responses may be reused without revalidation for the given number of seconds.
+    pub fn max_age(seconds: u64) -> Self {
+        Self {
+            max_age: Some(Duration::from_secs(seconds)),
+            ..Self::revalidate()
+        }
+    }
+
+    /// Marks the response as specific to a single user, so that shared caches
+    /// (like a CDN or proxy) will not store it.
+    pub fn private(mut self) -> Self {
+        self.private = true;
+        self
+    }
+
+    /// Allows a stale response to be served for the given number of seconds while it is
+    /// revalidated in the background.
+    pub fn stale_while_revalidate(mut self, seconds: u64) -> Self {
+        self.stale_while_revalidate = Some(Duration::from_secs(seconds));
+        self
+    }
+
+    /// Adds a request header that the response depends on to the `Vary` header.
+    ///
+    /// `Accept` is always included. If the server function reads cookies or
+    /// other headers to produce its result, they should be added here.
+    pub fn vary(mut self, header: impl Into<Cow<'static, str>>) -> Self {
+        let header = header.into();
+        if !self
+            .vary
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(&header))
+        {
+            self.vary.push(header);
+        }
+        self
+    }
+
+    /// The value of the `Cache-Control` header for this policy.
+    pub fn cache_control(&self) -> String {
+        let mut directives =
+            vec![if self.private { "private" } else { "public" }.to_string()];
+        match self.max_age {
+            Some(max_age) => {
+                directives.push(format!("max-age={}", max_age.as_secs()))
+            }
+            None => directives.push("no-cache".to_string()),
+        }
+        if let Some(swr) = self.stale_while_revalidate {
+            directives
+                .push(format!("stale-while-revalidate={}", swr.as_secs()));
+        }
+        directives.join(", ")
+    }
+
+    /// The value of the `Vary` header for this policy.
+    pub fn vary_header(&self) -> String {
+        self.vary.join(", ")
+    }
+}
+
+impl From<u64> for CachePolicy {
+    fn from(seconds: u64) -> Self {
+        CachePolicy::max_age(seconds)
+    }
+}
+
+/// Computes a strong `ETag` for the given encoded response body.
+pub fn etag(body: &[u8]) -> String {
+    let hash = xxhash_rust::const_xxh64::xxh64(body, 0);
+    format!("\"{hash:016x}\"")
+}
+
+/// Returns `true` if the value of an `If-None-Match` header matches the given `ETag`.
+///
+/// This uses the weak comparison required for `If-None-Match`, so `W/"abc"` matches `"abc"`.
+pub fn if_none_match(header: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    header.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
+}
+
+/// A middleware layer that adds `ETag`-based conditional responses and `Cache-Control`
+/// headers to a server function.
+///
+/// See the [module-level documentation](self) for more details.
+#[derive(Debug, Clone, Default)]
+pub struct ServerFnCache {
+    policy: CachePolicy,
+}
+
+impl ServerFnCache {
+    /// Creates a new caching layer with the given policy.
+    pub fn new(policy: impl Into<CachePolicy>) -> Self {
+        Self {
+            policy: policy.into(),
+        }
+    }
+
+    /// The caching policy applied by this layer.
+    pub fn policy(&self) -> &CachePolicy {
+        &self.policy
+    }
+}
+
+#[cfg(feature = "axum-no-default")]
+mod axum {
+    use super::{etag, if_none_match, CachePolicy, ServerFnCache};
+    use crate::{
+        error::ServerFnErrorErr, middleware::BoxedService, response::Res,
+        ServerFnError,
+    };
+    use axum::body::Body;
+    use http::{
+        header::{CACHE_CONTROL, ETAG, IF_NONE_MATCH, VARY},
+        HeaderValue, Method, Request, Response, StatusCode,
+    };
+    use http_body_util::BodyExt;
+    use std::{future::Future, pin::Pin, sync::Arc};
+
+    impl tower_layer::Layer<BoxedService<Request<Body>, Response<Body>>>
+        for ServerFnCache
+    {
+        type Service = CacheService;
+
+        fn layer(
+            &self,
+            inner: BoxedService<Request<Body>, Response<Body>>,
+        ) -> Self::Service {
+            CacheService {
+                policy: Arc::new(self.policy.clone()),
+                inner,
+            }
+        }
+    }
+
+    /// The service created by [`ServerFnCache`] for Axum.
+    pub struct CacheService {
+        policy: Arc<CachePolicy>,
+        inner: BoxedService<Request<Body>, Response<Body>>,
+    }
+
+    impl tower::Service<Request<Body>> for CacheService {
+        type Response = Response<Body>;
+        type Error = ServerFnError;
+        type Future = Pin<
+            Box<
+                dyn Future<Output = Result<Self::Response, Self::Error>> + Send,
+            >,
+        >;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            Ok(()).into()
+        }
+
+        fn call(&mut self, req: Request<Body>) -> Self::Future {
+            let is_get = req.method() == Method::GET;
+            let path = req.uri().path().to_string();
+            let condition = req
+                .headers()
+                .get(IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned);
+            let policy = Arc::clone(&self.policy);
+            let ser = self.inner.ser;
+            let inner = self.inner.run(req);
+            Box::pin(async move {
+                let res = inner.await;
+                if !is_get
+                    || res.status() != StatusCode::OK
+                    || axum::body::HttpBody::size_hint(res.body())
+                        .exact()
+                        .is_none()
+                {
+                    return Ok(res);
+                }
+
+                let (mut parts, body) = res.into_parts();
+                let body = match body.collect().await {
+                    Ok(body) => body.to_bytes(),
+                    Err(e) => {
+                        return Ok(Response::error_response(
+                            &path,
+                            ser(ServerFnErrorErr::Response(e.to_string())),
+                        ))
+                    }
+                };
+
+                let tag = match parts.headers.get(ETAG) {
+                    Some(existing) => {
+                        existing.to_str().unwrap_or_default().to_owned()
+                    }
+                    None => etag(&body),
+                };
+                if let Ok(value) = HeaderValue::from_str(&tag) {
+                    parts.headers.insert(ETAG, value);
+                }
+                if !parts.headers.contains_key(CACHE_CONTROL) {
+                    if let Ok(value) =
+                        HeaderValue::from_str(&policy.cache_control())
+                    {
+                        parts.headers.insert(CACHE_CONTROL, value);
+                    }
+                }
+                if let Ok(value) = HeaderValue::from_str(&policy.vary_header())
+                {
+                    parts.headers.append(VARY, value);
+                }
+
+                let not_modified = condition
+                    .as_deref()
+                    .map(|header| if_none_match(header, &tag))
+                    .unwrap_or(false);
+                if not_modified {
+                    parts.status = StatusCode::NOT_MODIFIED;
+                    parts.headers.remove(http::header::CONTENT_LENGTH);
+                    Ok(Response::from_parts(parts, Body::empty()))
+                } else {
+                    Ok(Response::from_parts(parts, Body::from(body)))
+                }
+            })
+        }
+    }
+}
+
+#[cfg(all(test, feature = "axum-no-default"))]
+mod axum_tests {
+    use super::{CachePolicy, ServerFnCache};
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Layer, Service},
+    };
+    use axum::body::Body;
+    use bytes::Bytes;
+    use http::{Request, Response, StatusCode};
+    use http_body_util::BodyExt;
+    use std::{future::Future, pin::Pin};
+
+    struct Echo;
+
+    impl Service<Request<Body>, Response<Body>> for Echo {
+        fn run(
+            &mut self,
+            _req: Request<Body>,
+            _ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = Response<Body>> + Send>> {
+            Box::pin(async { Response::new(Body::from("hello")) })
+        }
+    }
+
+    fn service() -> BoxedService<Request<Body>, Response<Body>> {
+        let inner = BoxedService::new(|e| Bytes::from(e.to_string()), Echo);
+        Layer::layer(&ServerFnCache::new(CachePolicy::max_age(60)), inner)
+    }
+
+    #[test]
+    fn answers_conditional_requests() {
+        futures::executor::block_on(async {
+            let res = service()
+                .run(Request::get("/api/fn").body(Body::empty()).unwrap())
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+            assert_eq!(res.headers()["cache-control"], "public, max-age=60");
+            assert_eq!(res.headers()["vary"], "Accept");
+            let etag = res.headers()["etag"].clone();
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "hello");
+
+            let res = service()
+                .run(
+                    Request::get("/api/fn")
+                        .header("If-None-Match", etag)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await;
+            assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert!(body.is_empty());
+
+            let res = service()
+                .run(Request::post("/api/fn").body(Body::empty()).unwrap())
+                .await;
+            assert!(res.headers().get("etag").is_none());
+        });
+    }
+}
+
+#[cfg(feature = "actix-no-default")]
+mod actix {
+    use super::{etag, if_none_match, CachePolicy, ServerFnCache};
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Layer, Service},
+        request::actix::ActixRequest,
+        response::{actix::ActixResponse, Res},
+    };
+    use actix_web::{
+        body::{to_bytes, BodySize, MessageBody},
+        http::{
+            header::{
+                HeaderValue, CACHE_CONTROL, CONTENT_LENGTH, ETAG,
+                IF_NONE_MATCH, VARY,
+            },
+            Method, StatusCode,
+        },
+        HttpResponse,
+    };
+    use bytes::Bytes;
+    use send_wrapper::SendWrapper;
+    use std::{future::Future, pin::Pin, sync::Arc};
+
+    impl Layer<ActixRequest, ActixResponse> for ServerFnCache {
+        fn layer(
+            &self,
+            inner: BoxedService<ActixRequest, ActixResponse>,
+        ) -> BoxedService<ActixRequest, ActixResponse> {
+            BoxedService::new(
+                inner.ser,
+                ActixCacheService {
+                    policy: Arc::new(self.policy.clone()),
+                    inner,
+                },
+            )
+        }
+    }
+
+    struct ActixCacheService {
+        policy: Arc<CachePolicy>,
+        inner: BoxedService<ActixRequest, ActixResponse>,
+    }
+
+    impl Service<ActixRequest, ActixResponse> for ActixCacheService {
+        fn run(
+            &mut self,
+            req: ActixRequest,
+            ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = ActixResponse> + Send>> {
+            let is_get = req.method() == Method::GET;
+            let path = req.path().to_string();
+            let condition = req
+                .headers()
+                .get(IF_NONE_MATCH)
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned);
+            let policy = Arc::clone(&self.policy);
+            let inner = self.inner.run(req);
+            // Actix keeps the response on a single thread, so it's fine to wrap it
+            // with SendWrapper, which makes it `Send` but will panic if it moves to another thread
+            Box::pin(SendWrapper::new(async move {
+                let res = inner.await.take();
+                if !is_get
+                    || res.status() != StatusCode::OK
+                    || !matches!(res.body().size(), BodySize::Sized(_))
+                {
+                    return ActixResponse::from(res);
+                }
+
+                let (mut res, body) = res.into_parts();
+                let body = match to_bytes(body).await {
+                    Ok(body) => body,
+                    Err(e) => {
+                        return ActixResponse::error_response(
+                            &path,
+                            ser(ServerFnErrorErr::Response(e.to_string())),
+                        )
+                    }
+                };
+
+                let tag = match res.headers().get(ETAG) {
+                    Some(existing) => {
+                        existing.to_str().unwrap_or_default().to_owned()
+                    }
+                    None => etag(&body),
+                };
+                let headers = res.headers_mut();
+                if let Ok(value) = HeaderValue::from_str(&tag) {
+                    headers.insert(ETAG, value);
+                }
+                if !headers.contains_key(CACHE_CONTROL) {
+                    if let Ok(value) =
+                        HeaderValue::from_str(&policy.cache_control())
+                    {
+                        headers.insert(CACHE_CONTROL, value);
+                    }
+                }
+                if let Ok(value) = HeaderValue::from_str(&policy.vary_header())
+                {
+                    headers.append(VARY, value);
+                }
+
+                let not_modified = condition
+                    .as_deref()
+                    .map(|header| if_none_match(header, &tag))
+                    .unwrap_or(false);
+                let res: HttpResponse = if not_modified {
+                    *res.status_mut() = StatusCode::NOT_MODIFIED;
+                    res.headers_mut().remove(CONTENT_LENGTH);
+                    res.set_body(().boxed())
+                } else {
+                    res.set_body(body.boxed())
+                };
+                ActixResponse::from(res)
+            }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_control_header() {
+        assert_eq!(
+            CachePolicy::revalidate().cache_control(),
+            "public, no-cache"
+        );
+        assert_eq!(
+            CachePolicy::max_age(60)
+                .private()
+                .stale_while_revalidate(30)
+                .cache_control(),
+            "private, max-age=60, stale-while-revalidate=30"
+        );
+        assert_eq!(
+            CachePolicy::max_age(10)
+                .vary("Cookie")
+                .vary("accept")
+                .vary_header(),
+            "Accept, Cookie"
+        );
+    }
+
+    #[test]
+    fn etag_matching() {
+        let tag = etag(b"hello");
+        assert_eq!(tag, etag(b"hello"));
+        assert_ne!(tag, etag(b"world"));
+        assert!(if_none_match(&tag, &tag));
+        assert!(if_none_match(&format!("\"other\", W/{tag}"), &tag));
+        assert!(if_none_match("*", &tag));
+        assert!(!if_none_match("\"other\"", &tag));
+    }
+}
@@ -0,0 +1,745 @@
+//! Rate limiting and concurrency limiting for server functions.
+//!
+//! [`ServerFnRateLimit`] is a middleware layer that keeps a token bucket for each client of
+//! a server function, and rejects requests once that client's bucket is empty.
+//! [`ServerFnConcurrencyLimit`] caps the number of requests to a server function that are
+//! handled at the same time.
+//!
+//! A rejected request receives a `429 Too Many Requests` response whose body is
+//! [`ServerFnErrorErr::TooManyRequests`], encoded with the server function's error type, so
+//! the client receives it as a typed error (for example,
+//! [`ServerFnError::TooManyRequests`](crate::ServerFnError::TooManyRequests)). Responses
+//! from the rate limit also carry a `Retry-After` header, which is respected by the
+//! client's [`RetryPolicy`](crate::client::retry::RetryPolicy).
+//!
+//! Limits are tracked in memory for the current process, separately for each server
+//! function path. The easiest way to use them is the `rate_limit` and `max_concurrency`
+//! arguments to the `#[server]` macro:
+//!
+//! ```rust,ignore
+//! #[server(rate_limit = RateLimit::per_minute(20).key(ClientKey::cookie("session")))]
+//! pub async fn post_comment(text: String) -> Result<(), ServerFnError> {
+//!     // ...
+//! }
+//!
+//! // an integer is shorthand for `RateLimit::per_second(n)`, keyed by IP address
+//! #[server(rate_limit = 5, max_concurrency = 2)]
+//! pub async fn export_report() -> Result<Report, ServerFnError> {
+//!     // ...
+//! }
+//! ```
+
+// the limits are only checked by the server integrations
+#![cfg_attr(
+    not(any(feature = "axum-no-default", feature = "actix-no-default")),
+    allow(dead_code)
+)]
+
+use crate::error::ServerFnErrorErr;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, LazyLock, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
+};
+
+/// Once a server function has this many token buckets, idle (full) buckets are dropped
+/// before a new one is added.
+const PRUNE_THRESHOLD: usize = 1024;
+
+static BUCKETS: LazyLock<Mutex<HashMap<String, HashMap<String, Bucket>>>> =
+    LazyLock::new(Default::default);
+static IN_FLIGHT: LazyLock<Mutex<HashMap<String, Arc<AtomicUsize>>>> =
+    LazyLock::new(Default::default);
+
+/// Identifies the client a request counts against for a [`RateLimit`].
+///
+/// Requests that do not carry the key (for example, a missing header or cookie) all share
+/// a single bucket.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ClientKey {
+    /// The IP address of the connected peer.
+    ///
+    /// With Axum, the peer address is only known if the app is served with
+    /// `into_make_service_with_connect_info`:
+    ///
+    /// ```rust,ignore
+    /// let listener = tokio::net::TcpListener::bind(&addr).await?;
+    /// axum::serve(
+    ///     listener,
+    ///     app.into_make_service_with_connect_info::<SocketAddr>(),
+    /// )
+    /// .await?;
+    /// ```
+    ///
+    /// Otherwise, requests to a server function limited by IP address fail with a
+    /// [`ServerFnErrorErr::MiddlewareError`] and a `500` status, rather than letting all
+    /// clients share a single limit. Behind a reverse proxy, the peer is the proxy, so use a
+    /// header set by the proxy (like `X-Real-IP`) instead.
+    #[default]
+    Ip,
+    /// The value of a request header.
+    Header(Cow<'static, str>),
+    /// The value of a cookie, like a session ID.
+    Cookie(Cow<'static, str>),
+    /// All clients share a single limit.
+    Global,
+}
+
+impl ClientKey {
+    /// Identifies clients by the value of the given request header.
+    pub fn header(name: impl Into<Cow<'static, str>>) -> Self {
+        ClientKey::Header(name.into())
+    }
+
+    /// Identifies clients by the value of the given cookie.
+    pub fn cookie(name: impl Into<Cow<'static, str>>) -> Self {
+        ClientKey::Cookie(name.into())
+    }
+
+    fn extract(
+        &self,
+        peer: Option<IpAddr>,
+        header: impl Fn(&str) -> Option<String>,
+    ) -> String {
+        let key = match self {
+            ClientKey::Ip => peer.map(|ip| ip.to_string()),
+            ClientKey::Header(name) => header(name),
+            ClientKey::Cookie(name) => header("cookie").and_then(|cookies| {
+                cookies.split(';').find_map(|cookie| {
+                    let (key, value) = cookie.trim().split_once('=')?;
+                    (key == name).then(|| value.to_string())
+                })
+            }),
+            ClientKey::Global => None,
+        };
+        key.unwrap_or_default()
+    }
+}
+
+/// Describes how many requests each client may make to a server function.
+///
+/// The limit is a token bucket: a client may send up to [`burst`](RateLimit::burst)
+/// requests at once, after which tokens are refilled at a steady rate of `requests` per
+/// `period`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimit {
+    requests: u32,
+    period: Duration,
+    burst: u32,
+    key: ClientKey,
+}
+
+impl RateLimit {
+    /// Allows `requests` requests per `period` for each client, identified by IP address.
+    pub fn new(requests: u32, period: Duration) -> Self {
+        let requests = requests.max(1);
+        Self {
+            requests,
+            period,
+            burst: requests,
+            key: ClientKey::Ip,
+        }
+    }
+
+    /// Allows the given number of requests per second for each client.
+    pub fn per_second(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(1))
+    }
+
+    /// Allows the given number of requests per minute for each client.
+    pub fn per_minute(requests: u32) -> Self {
+        Self::new(requests, Duration::from_secs(60))
+    }
+
+    /// Sets the number of requests a client may send at once before being limited to the
+    /// refill rate. Defaults to the number of requests per period.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Sets how clients are identified.
+    pub fn key(mut self, key: ClientKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    fn tokens_per_second(&self) -> f64 {
+        f64::from(self.requests) / self.period.as_secs_f64()
+    }
+
+    /// Takes a token from the bucket for the given client, or returns the number of seconds
+    /// until one is available.
+    fn acquire(&self, path: &str, client: String) -> Result<(), u64> {
+        let now = Instant::now();
+        let mut buckets =
+            BUCKETS.lock().unwrap_or_else(PoisonError::into_inner);
+        let buckets = buckets.entry(path.to_string()).or_default();
+        if buckets.len() >= PRUNE_THRESHOLD && !buckets.contains_key(&client) {
+            buckets.retain(|_, bucket| !bucket.is_full(self, now));
+        }
+        buckets
+            .entry(client)
+            .or_insert_with(|| Bucket::full(self, now))
+            .try_acquire(self, now)
+            .map_err(|wait| wait.as_secs_f64().ceil().max(1.0) as u64)
+    }
+}
+
+impl From<u32> for RateLimit {
+    fn from(requests: u32) -> Self {
+        RateLimit::per_second(requests)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn full(limit: &RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(limit.burst),
+            updated: now,
+        }
+    }
+
+    fn refill(&mut self, limit: &RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated);
+        self.tokens = (self.tokens
+            + elapsed.as_secs_f64() * limit.tokens_per_second())
+        .min(f64::from(limit.burst));
+        self.updated = now;
+    }
+
+    fn is_full(&self, limit: &RateLimit, now: Instant) -> bool {
+        let mut bucket = *self;
+        bucket.refill(limit, now);
+        bucket.tokens >= f64::from(limit.burst)
+    }
+
+    /// Takes a token, or returns how long it will take until one is available.
+    fn try_acquire(
+        &mut self,
+        limit: &RateLimit,
+        now: Instant,
+    ) -> Result<(), Duration> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - self.tokens) / limit.tokens_per_second(),
+            ))
+        }
+    }
+}
+
+/// Marks a request as in flight until it is dropped.
+struct Permit(Arc<AtomicUsize>);
+
+impl Permit {
+    fn acquire(path: &str, max: usize) -> Option<Self> {
+        let counter = Arc::clone(
+            IN_FLIGHT
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(path.to_string())
+                .or_default(),
+        );
+        counter
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                (current < max).then_some(current + 1)
+            })
+            .ok()
+            .map(|_| Permit(counter))
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// A middleware layer that applies a [`RateLimit`] to a server function.
+///
+/// See the [module-level documentation](self) for more details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerFnRateLimit {
+    limit: RateLimit,
+}
+
+impl ServerFnRateLimit {
+    /// Creates a new rate limiting layer with the given limit.
+    pub fn new(limit: impl Into<RateLimit>) -> Self {
+        Self {
+            limit: limit.into(),
+        }
+    }
+
+    /// The rate limit applied by this layer.
+    pub fn limit(&self) -> &RateLimit {
+        &self.limit
+    }
+}
+
+/// A middleware layer that limits how many requests to a server function are handled at
+/// the same time.
+///
+/// A request counts as in flight until the server function has produced its response; the
+/// body of a streaming response is not included.
+///
+/// See the [module-level documentation](self) for more details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ServerFnConcurrencyLimit {
+    max: usize,
+}
+
+impl ServerFnConcurrencyLimit {
+    /// Creates a new layer that allows at most `max` concurrent requests.
+    pub fn new(max: usize) -> Self {
+        Self { max }
+    }
+
+    /// The maximum number of concurrent requests.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+/// The limit checked by a limiting service.
+#[derive(Debug)]
+enum Limit {
+    Rate(RateLimit),
+    Concurrency(usize),
+}
+
+impl From<&ServerFnRateLimit> for Limit {
+    fn from(layer: &ServerFnRateLimit) -> Self {
+        Limit::Rate(layer.limit.clone())
+    }
+}
+
+impl From<&ServerFnConcurrencyLimit> for Limit {
+    fn from(layer: &ServerFnConcurrencyLimit) -> Self {
+        Limit::Concurrency(layer.max)
+    }
+}
+
+impl Limit {
+    /// Checks the limit for a request, returning the error to respond with if it is
+    /// exceeded.
+    fn check(
+        &self,
+        path: &str,
+        peer: Option<IpAddr>,
+        header: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Permit>, ServerFnErrorErr> {
+        match self {
+            Limit::Rate(RateLimit {
+                key: ClientKey::Ip, ..
+            }) if peer.is_none() => Err(ServerFnErrorErr::MiddlewareError(
+                "the rate limit is keyed by IP address, but the peer address \
+                 of the request is unknown; with Axum, serve the app with \
+                 `into_make_service_with_connect_info::<SocketAddr>()`"
+                    .into(),
+            )),
+            Limit::Rate(limit) => limit
+                .acquire(path, limit.key.extract(peer, header))
+                .map(|_| None)
+                .map_err(|seconds| ServerFnErrorErr::TooManyRequests {
+                    retry_after: Some(seconds),
+                }),
+            Limit::Concurrency(max) => Permit::acquire(path, *max)
+                .map(Some)
+                .ok_or(ServerFnErrorErr::TooManyRequests { retry_after: None }),
+        }
+    }
+}
+
+#[cfg(feature = "axum-no-default")]
+mod axum {
+    use super::{Limit, ServerFnConcurrencyLimit, ServerFnRateLimit};
+    use crate::{
+        error::ServerFnErrorErr, middleware::BoxedService, response::Res,
+        ServerFnError,
+    };
+    use axum::{body::Body, extract::ConnectInfo};
+    use http::{header::RETRY_AFTER, Request, Response, StatusCode};
+    use std::{future::Future, net::SocketAddr, pin::Pin, sync::Arc};
+
+    impl tower_layer::Layer<BoxedService<Request<Body>, Response<Body>>>
+        for ServerFnRateLimit
+    {
+        type Service = LimitService;
+
+        fn layer(
+            &self,
+            inner: BoxedService<Request<Body>, Response<Body>>,
+        ) -> Self::Service {
+            LimitService {
+                limit: Arc::new(self.into()),
+                inner,
+            }
+        }
+    }
+
+    impl tower_layer::Layer<BoxedService<Request<Body>, Response<Body>>>
+        for ServerFnConcurrencyLimit
+    {
+        type Service = LimitService;
+
+        fn layer(
+            &self,
+            inner: BoxedService<Request<Body>, Response<Body>>,
+        ) -> Self::Service {
+            LimitService {
+                limit: Arc::new(self.into()),
+                inner,
+            }
+        }
+    }
+
+    /// The service created by [`ServerFnRateLimit`] and [`ServerFnConcurrencyLimit`] for
+    /// Axum.
+    pub struct LimitService {
+        limit: Arc<Limit>,
+        inner: BoxedService<Request<Body>, Response<Body>>,
+    }
+
+    impl tower::Service<Request<Body>> for LimitService {
+        type Response = Response<Body>;
+        type Error = ServerFnError;
+        type Future = Pin<
+            Box<
+                dyn Future<Output = Result<Self::Response, Self::Error>> + Send,
+            >,
+        >;
+
+        fn poll_ready(
+            &mut self,
+            _cx: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<Result<(), Self::Error>> {
+            Ok(()).into()
+        }
+
+        fn call(&mut self, req: Request<Body>) -> Self::Future {
+            let path = req.uri().path().to_string();
+            let peer = req
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip());
+            let checked = self.limit.check(&path, peer, |name| {
+                req.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(ToOwned::to_owned)
+            });
+            match checked {
+                Ok(permit) => {
+                    let inner = self.inner.run(req);
+                    Box::pin(async move {
+                        let res = inner.await;
+                        drop(permit);
+                        Ok(res)
+                    })
+                }
+                Err(err) => {
+                    let limited = match &err {
+                        ServerFnErrorErr::TooManyRequests { retry_after } => {
+                            Some(*retry_after)
+                        }
+                        _ => None,
+                    };
+                    let mut res =
+                        Response::error_response(&path, (self.inner.ser)(err));
+                    if let Some(retry_after) = limited {
+                        *res.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+                        if let Some(seconds) = retry_after {
+                            res.headers_mut()
+                                .insert(RETRY_AFTER, seconds.into());
+                        }
+                    }
+                    Box::pin(async move { Ok(res) })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "axum-no-default"))]
+mod axum_tests {
+    use super::{
+        ClientKey, RateLimit, ServerFnConcurrencyLimit, ServerFnRateLimit,
+    };
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Layer, Service},
+        ServerFnError,
+    };
+    use axum::{body::Body, extract::ConnectInfo};
+    use bytes::Bytes;
+    use http::{Request, Response, StatusCode};
+    use http_body_util::BodyExt;
+    use std::{future::Future, net::SocketAddr, pin::Pin};
+
+    struct Echo;
+
+    impl Service<Request<Body>, Response<Body>> for Echo {
+        fn run(
+            &mut self,
+            _req: Request<Body>,
+            _ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = Response<Body>> + Send>> {
+            Box::pin(async { Response::new(Body::from("hello")) })
+        }
+    }
+
+    fn ser(err: ServerFnErrorErr) -> Bytes {
+        use crate::{error::ServerFnErrorEncoding, Encodes};
+        let err: ServerFnError =
+            crate::FromServerFnError::from_server_fn_error(err);
+        ServerFnErrorEncoding::encode(&err).unwrap()
+    }
+
+    fn request(client: &str) -> Request<Body> {
+        Request::post("/api/rate_limited")
+            .header("x-client", client)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn rejects_clients_over_the_limit() {
+        let layer = ServerFnRateLimit::new(
+            RateLimit::per_minute(2).key(ClientKey::header("x-client")),
+        );
+        futures::executor::block_on(async {
+            for _ in 0..2 {
+                let mut service =
+                    Layer::layer(&layer, BoxedService::new(ser, Echo));
+                let res = service.run(request("a")).await;
+                assert_eq!(res.status(), StatusCode::OK);
+            }
+
+            let mut service =
+                Layer::layer(&layer, BoxedService::new(ser, Echo));
+            let res = service.run(request("a")).await;
+            assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert_eq!(res.headers()["retry-after"], "30");
+            let body = res.into_body().collect().await.unwrap().to_bytes();
+            assert_eq!(body, "TooManyRequests|30");
+
+            let mut service =
+                Layer::layer(&layer, BoxedService::new(ser, Echo));
+            let res = service.run(request("b")).await;
+            assert_eq!(res.status(), StatusCode::OK);
+        });
+    }
+
+    #[test]
+    fn ip_limits_require_the_peer_address() {
+        let layer = ServerFnRateLimit::new(RateLimit::per_minute(1));
+        futures::executor::block_on(async {
+            let mut service =
+                Layer::layer(&layer, BoxedService::new(ser, Echo));
+            let path = "/api/ip_limited";
+            for _ in 0..2 {
+                let res = service
+                    .run(Request::post(path).body(Body::empty()).unwrap())
+                    .await;
+                assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            }
+
+            let connected = || {
+                let mut req = Request::post(path).body(Body::empty()).unwrap();
+                req.extensions_mut().insert(ConnectInfo(SocketAddr::from((
+                    [127, 0, 0, 1],
+                    3000,
+                ))));
+                req
+            };
+            let res = service.run(connected()).await;
+            assert_eq!(res.status(), StatusCode::OK);
+            let res = service.run(connected()).await;
+            assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+        });
+    }
+
+    #[test]
+    fn concurrency_permits_are_released() {
+        let layer = ServerFnConcurrencyLimit::new(1);
+        futures::executor::block_on(async {
+            let mut first = Layer::layer(&layer, BoxedService::new(ser, Echo));
+            let mut second = Layer::layer(&layer, BoxedService::new(ser, Echo));
+            let path = "/api/concurrency_limited";
+            let pending =
+                first.run(Request::post(path).body(Body::empty()).unwrap());
+            let res = second
+                .run(Request::post(path).body(Body::empty()).unwrap())
+                .await;
+            assert_eq!(res.status(), StatusCode::TOO_MANY_REQUESTS);
+            assert!(res.headers().get("retry-after").is_none());
+
+            assert_eq!(pending.await.status(), StatusCode::OK);
+            let res = second
+                .run(Request::post(path).body(Body::empty()).unwrap())
+                .await;
+            assert_eq!(res.status(), StatusCode::OK);
+        });
+    }
+}
+
+#[cfg(feature = "actix-no-default")]
+mod actix {
+    use super::{Limit, ServerFnConcurrencyLimit, ServerFnRateLimit};
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Layer, Service},
+        request::actix::ActixRequest,
+        response::{actix::ActixResponse, Res},
+    };
+    use actix_web::http::{
+        header::{HeaderValue, RETRY_AFTER},
+        StatusCode,
+    };
+    use bytes::Bytes;
+    use std::{future::Future, pin::Pin};
+
+    impl Layer<ActixRequest, ActixResponse> for ServerFnRateLimit {
+        fn layer(
+            &self,
+            inner: BoxedService<ActixRequest, ActixResponse>,
+        ) -> BoxedService<ActixRequest, ActixResponse> {
+            BoxedService::new(
+                inner.ser,
+                ActixLimitService {
+                    limit: self.into(),
+                    inner,
+                },
+            )
+        }
+    }
+
+    impl Layer<ActixRequest, ActixResponse> for ServerFnConcurrencyLimit {
+        fn layer(
+            &self,
+            inner: BoxedService<ActixRequest, ActixResponse>,
+        ) -> BoxedService<ActixRequest, ActixResponse> {
+            BoxedService::new(
+                inner.ser,
+                ActixLimitService {
+                    limit: self.into(),
+                    inner,
+                },
+            )
+        }
+    }
+
+    struct ActixLimitService {
+        limit: Limit,
+        inner: BoxedService<ActixRequest, ActixResponse>,
+    }
+
+    impl Service<ActixRequest, ActixResponse> for ActixLimitService {
+        fn run(
+            &mut self,
+            req: ActixRequest,
+            ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = ActixResponse> + Send>> {
+            let path = req.path().to_string();
+            let peer = req.0 .0.peer_addr().map(|addr| addr.ip());
+            let checked = self.limit.check(&path, peer, |name| {
+                req.headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(ToOwned::to_owned)
+            });
+            match checked {
+                Ok(permit) => {
+                    let inner = self.inner.run(req);
+                    Box::pin(async move {
+                        let res = inner.await;
+                        drop(permit);
+                        res
+                    })
+                }
+                Err(err) => {
+                    let limited = match &err {
+                        ServerFnErrorErr::TooManyRequests { retry_after } => {
+                            Some(*retry_after)
+                        }
+                        _ => None,
+                    };
+                    let mut res =
+                        ActixResponse::error_response(&path, ser(err));
+                    if let Some(retry_after) = limited {
+                        *res.0.status_mut() = StatusCode::TOO_MANY_REQUESTS;
+                        if let Some(seconds) = retry_after {
+                            res.0.headers_mut().insert(
+                                RETRY_AFTER,
+                                HeaderValue::from(seconds),
+                            );
+                        }
+                    }
+                    Box::pin(async move { res })
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_bucket_refills() {
+        let limit = RateLimit::per_second(2).burst(3);
+        let start = Instant::now();
+        let mut bucket = Bucket::full(&limit, start);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire(&limit, start).is_ok());
+        }
+        assert_eq!(
+            bucket.try_acquire(&limit, start),
+            Err(Duration::from_millis(500))
+        );
+
+        let later = start + Duration::from_millis(500);
+        assert!(bucket.try_acquire(&limit, later).is_ok());
+        assert!(bucket.try_acquire(&limit, later).is_err());
+        assert!(!bucket.is_full(&limit, later + Duration::from_secs(1)));
+        assert!(bucket.is_full(&limit, later + Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn client_keys() {
+        let peer = Some(IpAddr::from([127, 0, 0, 1]));
+        let headers = |name: &str| match name {
+            "cookie" => Some("theme=dark; session=abc123".to_string()),
+            "x-api-key" => Some("key".to_string()),
+            _ => None,
+        };
+        assert_eq!(ClientKey::Ip.extract(peer, headers), "127.0.0.1");
+        assert_eq!(
+            ClientKey::header("x-api-key").extract(None, headers),
+            "key"
+        );
+        assert_eq!(
+            ClientKey::cookie("session").extract(peer, headers),
+            "abc123"
+        );
+        assert_eq!(ClientKey::cookie("missing").extract(peer, headers), "");
+        assert_eq!(ClientKey::Global.extract(peer, headers), "");
+    }
+}
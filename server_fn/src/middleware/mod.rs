@@ -2,6 +2,9 @@ use crate::error::ServerFnErrorErr;
 use bytes::Bytes;
 use std::{future::Future, pin::Pin};
 
+pub mod cache;
+pub mod limit;
+
 /// An abstraction over a middleware layer, which can be used to add additional
 /// middleware layer to a [`Service`].
 pub trait Layer<Req, Res>: Send + Sync + 'static {
@@ -168,7 +171,7 @@ mod actix {
             req: ActixRequest,
             ser: fn(ServerFnErrorErr) -> Bytes,
         ) -> Pin<Box<dyn Future<Output = ActixResponse> + Send>> {
-            let path = req.0 .0.uri().path().to_string();
+            let path = req.path().to_string();
             let inner = self.call(req.0.take().0);
             Box::pin(async move {
                 ActixResponse::from(inner.await.unwrap_or_else(|e| {
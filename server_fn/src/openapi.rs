@@ -0,0 +1,460 @@
+//! Generates an [OpenAPI 3.1](https://spec.openapis.org/oas/v3.1.0) document that describes the
+//! registered server functions, so that they can be called from clients that are not written in
+//! Rust.
+//!
+//! Each server function is described with its path, HTTP method, input and output encodings, and
+//! error type. The schemas of the arguments, the return value and the error come from the
+//! [`JsonSchema`] trait of [`schemars`], which is re-exported here. It can be derived for your own
+//! types, and follows their `serde` attributes:
+//!
+//! ```rust,ignore
+//! use server_fn::openapi::JsonSchema;
+//!
+//! #[derive(Serialize, Deserialize, JsonSchema)]
+//! #[serde(rename_all = "camelCase")]
+//! pub struct Todo {
+//!     id: u32,
+//!     title: String,
+//!     completed_at: Option<String>,
+//! }
+//! ```
+//!
+//! The derive macro refers to the `schemars` crate by name. If it is not a dependency of your
+//! crate, point the derive at the re-export with `#[schemars(crate = "server_fn::openapi::schemars")]`.
+//!
+//! Types that do not implement [`JsonSchema`] are described with an empty schema, which accepts
+//! any value.
+//!
+//! The document can be generated with `server_fn::axum::openapi_document` or
+//! `server_fn::actix::openapi_document`, and is served by the Leptos integrations.
+
+use crate::{
+    error::{FromServerFnError, SERVER_FN_ERROR_HEADER},
+    ContentType, Protocol, ServerFn,
+};
+use http::Method;
+use schemars::generate::SchemaSettings;
+use serde_json::{json, Map};
+use std::marker::PhantomData;
+
+pub use schemars::{self, JsonSchema, SchemaGenerator};
+pub use serde_json::Value;
+
+/// The path of the named schemas in the document, as a JSON pointer.
+const DEFINITIONS_PATH: &str = "/components/schemas";
+
+/// Creates a generator for JSON Schema 2020-12, the dialect used by OpenAPI 3.1, that stores named
+/// schemas in `components/schemas`.
+pub fn schema_generator() -> SchemaGenerator {
+    SchemaSettings::draft2020_12()
+        .with(|settings| {
+            settings.definitions_path = DEFINITIONS_PATH.into();
+            settings.meta_schema = None;
+        })
+        .into_generator()
+}
+
+/// Follows a `$ref` to one of the generator's named schemas.
+fn dereference<'a>(
+    generator: &'a SchemaGenerator,
+    schema: &'a Value,
+) -> &'a Value {
+    schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|path| path.strip_prefix("#/components/schemas/"))
+        .and_then(|name| generator.definitions().get(name))
+        .unwrap_or(schema)
+}
+
+/// Describes the Rust types of a server function. This is implemented by the `#[server]` macro.
+#[derive(Debug, Clone, Default)]
+pub struct ServerFnSchema {
+    /// The name of the server function.
+    pub name: &'static str,
+    /// The documentation comments on the server function.
+    pub docs: &'static str,
+    /// The schema of the arguments, as an object with one property per argument.
+    pub input: Value,
+    /// The schema of the value returned on success.
+    pub output: Value,
+    /// The schema of the error type.
+    pub error: Value,
+}
+
+/// A single operation in the OpenAPI document.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// The path of the server function.
+    pub path: &'static str,
+    /// The HTTP method of the server function.
+    pub method: Method,
+    /// The [operation object](https://spec.openapis.org/oas/v3.1.0#operation-object).
+    pub operation: Value,
+}
+
+impl Operation {
+    /// Describes the server function `S`.
+    pub fn of<S: ServerFn>(generator: &mut SchemaGenerator) -> Self {
+        let schema = S::openapi_schema(generator);
+        let method = S::Protocol::METHOD;
+        let or_any = |schema: Value| {
+            if schema.is_null() {
+                json!({})
+            } else {
+                schema
+            }
+        };
+
+        let mut operation = Map::new();
+        // function names are not unique across modules, but paths are
+        operation.insert("operationId".into(), operation_id(S::PATH).into());
+        let docs = schema.docs.trim();
+        if let Some(summary) = docs.lines().next().filter(|s| !s.is_empty()) {
+            operation.insert("summary".into(), summary.trim().into());
+            operation.insert("description".into(), docs.into());
+        } else if !schema.name.is_empty() {
+            operation.insert("summary".into(), schema.name.into());
+        }
+
+        let mut responses = Map::new();
+        match S::Protocol::CONTENT_TYPES {
+            Some((input_type, output_type)) => {
+                let input = or_any(schema.input);
+                if method == Method::GET || method == Method::DELETE {
+                    operation.insert(
+                        "parameters".into(),
+                        query_parameters(generator, &input).into(),
+                    );
+                } else {
+                    operation.insert(
+                        "requestBody".into(),
+                        json!({
+                            "required": true,
+                            "content": { input_type: { "schema": input } },
+                        }),
+                    );
+                }
+                responses.insert(
+                    "200".into(),
+                    json!({
+                        "description": "The server function succeeded.",
+                        "content": {
+                            output_type: { "schema": or_any(schema.output) },
+                        },
+                    }),
+                );
+            }
+            None => {
+                responses.insert(
+                    "101".into(),
+                    json!({ "description": "Switching to a WebSocket connection." }),
+                );
+            }
+        }
+        let error_type =
+            <<S::Error as FromServerFnError>::Encoder as ContentType>::CONTENT_TYPE;
+        let error_header = SERVER_FN_ERROR_HEADER;
+        responses.insert(
+            "500".into(),
+            json!({
+                "description": "The server function returned an error.",
+                "headers": {
+                    error_header: {
+                        "description": "The path of the server function that failed.",
+                        "schema": { "type": "string" },
+                    },
+                },
+                "content": { error_type: { "schema": or_any(schema.error) } },
+            }),
+        );
+        operation.insert("responses".into(), responses.into());
+
+        Operation {
+            path: S::PATH,
+            method,
+            operation: operation.into(),
+        }
+    }
+}
+
+/// Derives a unique operation ID from the path of a server function, replacing the characters
+/// that are not allowed in identifiers of most generated clients.
+fn operation_id(path: &str) -> String {
+    path.trim_start_matches('/')
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Describes each property of the input as a query parameter.
+fn query_parameters(generator: &SchemaGenerator, input: &Value) -> Vec<Value> {
+    let input = dereference(generator, input);
+    let required = input
+        .get("required")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let Some(properties) = input.get("properties").and_then(Value::as_object)
+    else {
+        return Vec::new();
+    };
+    properties
+        .iter()
+        .map(|(name, schema)| {
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&Value::from(name.as_str())),
+                "schema": schema,
+            });
+            // nested values are encoded as `field[key]=value`
+            let nested = schema.get("$ref").is_some()
+                || schema.get("type") == Some(&Value::from("object"));
+            if nested {
+                parameter["style"] = "deepObject".into();
+                parameter["explode"] = true.into();
+            }
+            parameter
+        })
+        .collect()
+}
+
+/// Information about the API, used to build the OpenAPI document.
+#[derive(Debug, Clone)]
+pub struct OpenApi {
+    title: String,
+    version: String,
+    description: Option<String>,
+    servers: Vec<String>,
+}
+
+impl OpenApi {
+    /// Creates an API description with the given title and version.
+    pub fn new(title: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            version: version.into(),
+            description: None,
+            servers: Vec::new(),
+        }
+    }
+
+    /// Sets the description of the API.
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Adds a server URL that the paths are relative to.
+    pub fn server(mut self, url: impl Into<String>) -> Self {
+        self.servers.push(url.into());
+        self
+    }
+
+    /// Builds the OpenAPI document from the given operations, and the schemas they reference.
+    pub fn document(
+        &self,
+        operations: impl IntoIterator<Item = Operation>,
+        mut generator: SchemaGenerator,
+    ) -> Value {
+        let mut info = json!({ "title": self.title, "version": self.version });
+        if let Some(description) = &self.description {
+            info["description"] = description.as_str().into();
+        }
+
+        let mut paths = Map::new();
+        for Operation {
+            path,
+            method,
+            operation,
+        } in operations
+        {
+            let item = paths
+                .entry(path)
+                .or_insert_with(|| Value::Object(Map::new()));
+            item[method.as_str().to_ascii_lowercase()] = operation;
+        }
+
+        let mut document = json!({
+            "openapi": "3.1.0",
+            "info": info,
+            "paths": paths,
+        });
+        if !self.servers.is_empty() {
+            document["servers"] = self
+                .servers
+                .iter()
+                .map(|url| json!({ "url": url }))
+                .collect();
+        }
+        let definitions = generator.take_definitions(true);
+        if !definitions.is_empty() {
+            document["components"] = json!({ "schemas": definitions });
+        }
+        document
+    }
+}
+
+/// Creates an object schema from its properties, given as `(name, schema, required)`.
+pub fn object_schema(
+    properties: impl IntoIterator<Item = (&'static str, Value, bool)>,
+) -> Value {
+    let mut schema = Map::new();
+    let mut required = Vec::new();
+    for (name, property, is_required) in properties {
+        if is_required {
+            required.push(Value::from(name));
+        }
+        schema.insert(name.to_string(), property);
+    }
+    json!({
+        "type": "object",
+        "properties": schema,
+        "required": required,
+    })
+}
+
+/// Helpers for the code generated by the `#[server]` macro.
+#[doc(hidden)]
+pub mod __private {
+    use super::{dereference, SchemaGenerator};
+    use serde_json::Value;
+
+    pub fn with_description(
+        mut schema: Value,
+        description: &'static str,
+    ) -> Value {
+        if let Some(object) = schema.as_object_mut() {
+            object.insert("description".into(), description.into());
+        }
+        schema
+    }
+
+    /// Adds the properties of a `#[serde(flatten)]` field to an object schema.
+    pub fn flatten_into(
+        generator: &SchemaGenerator,
+        schema: &mut Value,
+        flattened: Value,
+    ) {
+        let flattened = dereference(generator, &flattened).clone();
+        let (Some(properties), Some(required)) = (
+            flattened.get("properties").and_then(Value::as_object),
+            flattened.get("required").and_then(Value::as_array),
+        ) else {
+            return;
+        };
+        if let Some(target) =
+            schema.get_mut("properties").and_then(Value::as_object_mut)
+        {
+            target.extend(properties.clone());
+        }
+        if let Some(target) =
+            schema.get_mut("required").and_then(Value::as_array_mut)
+        {
+            target.extend(required.iter().cloned());
+        }
+    }
+}
+
+#[doc(hidden)]
+pub struct SchemaOf<T: ?Sized>(PhantomData<T>);
+
+impl<T: ?Sized> SchemaOf<T> {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: ?Sized> Default for SchemaOf<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Used by the `#[server]` macro to describe types that implement [`JsonSchema`].
+#[doc(hidden)]
+pub trait ViaJsonSchema {
+    fn schema(&self, generator: &mut SchemaGenerator) -> Value;
+}
+
+impl<T: JsonSchema + ?Sized> ViaJsonSchema for SchemaOf<T> {
+    fn schema(&self, generator: &mut SchemaGenerator) -> Value {
+        generator.subschema_for::<T>().to_value()
+    }
+}
+
+/// Used by the `#[server]` macro to describe types that do not implement [`JsonSchema`], with
+/// an empty schema.
+#[doc(hidden)]
+pub trait ViaAnySchema {
+    fn schema(&self, generator: &mut SchemaGenerator) -> Value;
+}
+
+impl<T: ?Sized> ViaAnySchema for &SchemaOf<T> {
+    fn schema(&self, _generator: &mut SchemaGenerator) -> Value {
+        json!({})
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Node {
+        value: u32,
+        children: Vec<Node>,
+    }
+
+    #[test]
+    // the macro always takes a reference, so the tests mirror that
+    #[allow(clippy::needless_borrow)]
+    fn describes_std_and_recursive_types() {
+        let mut generator = schema_generator();
+        assert_eq!(
+            (&SchemaOf::<Option<Vec<String>>>::new()).schema(&mut generator),
+            json!({ "type": ["array", "null"], "items": { "type": "string" } })
+        );
+        let node = (&SchemaOf::<Node>::new()).schema(&mut generator);
+        assert_eq!(node, json!({ "$ref": "#/components/schemas/Node" }));
+        assert_eq!(
+            dereference(&generator, &node)["properties"]["children"]["items"],
+            node
+        );
+
+        // types without a `JsonSchema` impl fall back to an empty schema
+        struct Opaque;
+        assert_eq!(
+            (&SchemaOf::<Opaque>::new()).schema(&mut generator),
+            json!({})
+        );
+        assert_eq!(
+            (&SchemaOf::<bool>::new()).schema(&mut generator),
+            json!({ "type": "boolean" })
+        );
+    }
+
+    #[test]
+    fn builds_query_parameters() {
+        let generator = schema_generator();
+        let input = object_schema([
+            ("id", json!({ "type": "integer" }), true),
+            ("filter", json!({ "type": "object" }), false),
+        ]);
+        let parameters = query_parameters(&generator, &input);
+        assert_eq!(parameters.len(), 2);
+        assert_eq!(parameters[0]["name"], "filter");
+        assert_eq!(parameters[0]["style"], "deepObject");
+        assert_eq!(parameters[1]["name"], "id");
+        assert_eq!(parameters[1]["required"], true);
+    }
+
+    #[test]
+    fn operation_ids_come_from_paths() {
+        assert_eq!(operation_id("/api/todos/add_todo"), "api_todos_add_todo");
+        assert_eq!(operation_id("/api/v2/get-user"), "api_v2_get_user");
+    }
+}
@@ -0,0 +1,41 @@
+//! Random numbers for retry jitter and trace ids.
+//!
+//! In the browser, `RandomState` has no source of entropy and hands out the same keys on every
+//! page load, so `Math.random()` is used instead.
+
+/// A random `u64`.
+#[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
+pub(crate) fn next_u64() -> u64 {
+    // `Math.random()` has at most 53 bits of randomness, so build the number from two halves
+    let half = || (js_sys::Math::random() * (1u64 << 32) as f64) as u64;
+    (half() << 32) | half()
+}
+
+/// A random `u64`.
+#[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+pub(crate) fn next_u64() -> u64 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+    };
+
+    RandomState::new().build_hasher().finish()
+}
+
+/// A random number in `[0, 1)`.
+pub(crate) fn fraction() -> f64 {
+    (next_u64() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_differ_between_calls() {
+        assert_ne!(next_u64(), next_u64());
+        let fractions = [fraction(), fraction()];
+        assert_ne!(fractions[0], fractions[1]);
+        assert!(fractions.iter().all(|f| (0.0..1.0).contains(f)));
+    }
+}
@@ -3,19 +3,39 @@ use crate::{
     request::Req,
     response::actix::ActixResponse,
 };
-use actix_web::{web::Payload, HttpRequest};
+use actix_web::{
+    dev::Payload as DevPayload,
+    error::PayloadError,
+    http::{header::HeaderMap, Method, Uri},
+    web::Payload,
+    FromRequest, HttpRequest,
+};
 use actix_ws::Message;
 use bytes::Bytes;
 use futures::{FutureExt, Stream, StreamExt};
 use send_wrapper::SendWrapper;
-use std::{borrow::Cow, future::Future};
+use std::{borrow::Cow, future::Future, pin::Pin};
 
 /// A wrapped Actix request.
 ///
 /// This uses a [`SendWrapper`] that allows the Actix `HttpRequest` type to be `Send`, but panics
 /// if it it is ever sent to another thread. Actix pins request handling to a single thread, so this
 /// is necessary to be compatible with traits that require `Send` but should never panic in actual use.
-pub struct ActixRequest(pub(crate) SendWrapper<(HttpRequest, Payload)>);
+///
+/// For a call in a batch, the raw request is the batch request, and the method, URI and
+/// headers of the call are kept alongside it.
+pub struct ActixRequest(
+    pub(crate) SendWrapper<(HttpRequest, Payload)>,
+    pub(crate) Option<Box<CallHead>>,
+);
+
+/// The method, URI and headers of a call in a batch, which take the place of those of the
+/// batch request.
+pub(crate) struct CallHead {
+    pub(crate) method: Method,
+    pub(crate) uri: Uri,
+    pub(crate) headers: HeaderMap,
+}
 
 impl ActixRequest {
     /// Returns the raw Actix request, and its body.
@@ -23,18 +43,75 @@ impl ActixRequest {
         self.0.take()
     }
 
+    /// Returns the raw Actix request. For a call in a batch, this is the batch request.
+    pub fn request(&self) -> &HttpRequest {
+        &self.0 .0
+    }
+
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        match &self.1 {
+            Some(head) => &head.method,
+            None => self.0 .0.method(),
+        }
+    }
+
+    /// The URI of the request.
+    pub fn uri(&self) -> &Uri {
+        match &self.1 {
+            Some(head) => &head.uri,
+            None => self.0 .0.uri(),
+        }
+    }
+
+    /// The path of the request.
+    pub fn path(&self) -> &str {
+        self.uri().path()
+    }
+
+    /// The headers of the request.
+    pub fn headers(&self) -> &HeaderMap {
+        match &self.1 {
+            Some(head) => &head.headers,
+            None => self.0 .0.headers(),
+        }
+    }
+
+    /// Replaces the body of the request with a stream made from the current body.
+    pub(crate) fn map_body<S>(self, f: impl FnOnce(Payload) -> S) -> Self
+    where
+        S: Stream<Item = Result<Bytes, PayloadError>> + 'static,
+    {
+        let ActixRequest(inner, head) = self;
+        let (request, body) = inner.take();
+        let payload = payload(&request, f(body));
+        ActixRequest(SendWrapper::new((request, payload)), head)
+    }
+
     fn header(&self, name: &str) -> Option<Cow<'_, str>> {
-        self.0
-             .0
-            .headers()
+        self.headers()
             .get(name)
             .map(|h| String::from_utf8_lossy(h.as_bytes()))
     }
 }
 
+/// Creates a request body from a stream.
+pub(crate) fn payload(
+    request: &HttpRequest,
+    body: impl Stream<Item = Result<Bytes, PayloadError>> + 'static,
+) -> Payload {
+    let body: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+        Box::pin(body);
+    let mut body = DevPayload::from(body);
+    Payload::from_request(request, &mut body)
+        .now_or_never()
+        .and_then(Result::ok)
+        .expect("extracting the payload is infallible")
+}
+
 impl From<(HttpRequest, Payload)> for ActixRequest {
     fn from(value: (HttpRequest, Payload)) -> Self {
-        ActixRequest(SendWrapper::new(value))
+        ActixRequest(SendWrapper::new(value), None)
     }
 }
 
@@ -48,7 +125,7 @@ where
     type WebsocketResponse = ActixResponse;
 
     fn as_query(&self) -> Option<&str> {
-        self.0 .0.uri().query()
+        self.uri().query()
     }
 
     fn to_content_type(&self) -> Option<Cow<'_, str>> {
@@ -63,6 +140,10 @@ where
         self.header("Referer")
     }
 
+    fn header(&self, name: &str) -> Option<Cow<'_, str>> {
+        ActixRequest::header(self, name)
+    }
+
     fn try_into_bytes(
         self,
     ) -> impl Future<Output = Result<Bytes, Error>> + Send {
@@ -45,6 +45,12 @@ where
             .map(|h| String::from_utf8_lossy(h.as_bytes()))
     }
 
+    fn header(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.headers()
+            .get(name)
+            .map(|h| String::from_utf8_lossy(h.as_bytes()))
+    }
+
     async fn try_into_bytes(self) -> Result<Bytes, Error> {
         let (_parts, body) = self.into_parts();
 
@@ -1,6 +1,7 @@
 use super::ClientReq;
 use crate::{
     client::get_server_url,
+    compression::COMPRESSION_THRESHOLD,
     error::{FromServerFnError, ServerFnErrorErr},
 };
 use bytes::Bytes;
@@ -25,18 +26,15 @@ pub struct BrowserRequest(pub(crate) SendWrapper<RequestInner>);
 pub(crate) struct RequestInner {
     pub(crate) request: Request,
     pub(crate) abort_ctrl: Option<AbortOnDrop>,
+    /// Whether the body is large enough to be compressed before sending.
+    pub(crate) compress: bool,
+    /// Whether the body is a stream, which means the request can't be batched.
+    pub(crate) streaming: bool,
 }
 
 #[derive(Debug)]
 pub(crate) struct AbortOnDrop(Option<AbortController>);
 
-impl AbortOnDrop {
-    /// Prevents the request from being aborted on drop.
-    pub fn prevent_cancellation(&mut self) {
-        self.0.take();
-    }
-}
-
 impl Drop for AbortOnDrop {
     fn drop(&mut self) {
         if let Some(inner) = self.0.take() {
@@ -88,7 +86,7 @@ impl From<FormData> for BrowserFormData {
     }
 }
 
-fn abort_signal() -> (Option<AbortOnDrop>, Option<AbortSignal>) {
+pub(crate) fn abort_signal() -> (Option<AbortOnDrop>, Option<AbortSignal>) {
     let ctrl = AbortController::new().ok();
     let signal = ctrl.as_ref().map(|ctrl| ctrl.signal());
     (ctrl.map(|ctrl| AbortOnDrop(Some(ctrl))), signal)
@@ -141,6 +139,8 @@ where
                 ))
             })?,
             abort_ctrl,
+            compress: false,
+            streaming: false,
         })))
     }
 
@@ -152,6 +152,7 @@ where
         method: Method,
     ) -> Result<Self, E> {
         let (abort_ctrl, abort_signal) = abort_signal();
+        let compress = body.len() >= COMPRESSION_THRESHOLD;
         let server_url = get_server_url();
         let mut url = String::with_capacity(server_url.len() + path.len());
         url.push_str(server_url);
@@ -179,6 +180,8 @@ where
                 ))
             })?,
             abort_ctrl,
+            compress,
+            streaming: false,
         })))
     }
 
@@ -190,6 +193,7 @@ where
         method: Method,
     ) -> Result<Self, E> {
         let (abort_ctrl, abort_signal) = abort_signal();
+        let compress = body.len() >= COMPRESSION_THRESHOLD;
         let server_url = get_server_url();
         let mut url = String::with_capacity(server_url.len() + path.len());
         url.push_str(server_url);
@@ -219,6 +223,8 @@ where
                 ))
             })?,
             abort_ctrl,
+            compress,
+            streaming: false,
         })))
     }
 
@@ -255,6 +261,8 @@ where
                 ))
             })?,
             abort_ctrl,
+            compress: false,
+            streaming: false,
         })))
     }
 
@@ -300,6 +308,8 @@ where
                 ))
             })?,
             abort_ctrl,
+            compress: false,
+            streaming: false,
         })))
     }
 
@@ -319,7 +329,6 @@ where
                 ))
             }
         }
-        // TODO abort signal
         let (request, abort_ctrl) =
             streaming_request(path, accepts, content_type, body, method)
                 .map_err(|e| {
@@ -330,8 +339,15 @@ where
         Ok(Self(SendWrapper::new(RequestInner {
             request,
             abort_ctrl,
+            compress: false,
+            streaming: true,
         })))
     }
+
+    fn try_set_header(&mut self, name: &str, value: &str) -> Result<(), E> {
+        self.headers().set(name, value);
+        Ok(())
+    }
 }
 
 fn streaming_request(
@@ -70,6 +70,12 @@ where
             .map(|val| String::from_utf8_lossy(val.as_bytes()))
     }
 
+    fn header(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.headers()
+            .get(name)
+            .map(|val| String::from_utf8_lossy(val.as_bytes()))
+    }
+
     fn as_query(&self) -> Option<&str> {
         self.uri().query()
     }
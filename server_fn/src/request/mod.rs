@@ -79,6 +79,15 @@ where
         method: Method,
     ) -> Result<Self, E>;
 
+    /// Attempts to set a header on the request, replacing any existing value.
+    ///
+    /// By default, the header is not set. Features that rely on request headers, like
+    /// compression, cache revalidation and trace propagation, are then skipped for this client.
+    fn try_set_header(&mut self, name: &str, value: &str) -> Result<(), E> {
+        _ = (name, value);
+        Ok(())
+    }
+
     /// Attempts to construct a new `GET` request.
     fn try_new_get(
         path: &str,
@@ -337,6 +346,14 @@ where
     /// Returns the `Referer` header, if any.
     fn referer(&self) -> Option<Cow<'_, str>>;
 
+    /// Returns the value of the given header, if any.
+    ///
+    /// By default, no headers are available.
+    fn header(&self, name: &str) -> Option<Cow<'_, str>> {
+        _ = name;
+        None
+    }
+
     /// Attempts to extract the body of the request into [`Bytes`].
     fn try_into_bytes(
         self,
@@ -396,6 +413,10 @@ where
     fn referer(&self) -> Option<Cow<'_, str>> {
         unreachable!()
     }
+
+    fn header(&self, _name: &str) -> Option<Cow<'_, str>> {
+        unreachable!()
+    }
     async fn try_into_bytes(self) -> Result<Bytes, Error> {
         unreachable!()
     }
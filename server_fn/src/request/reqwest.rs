@@ -6,7 +6,7 @@ use crate::{
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
 use reqwest::{
-    header::{ACCEPT, CONTENT_TYPE},
+    header::{HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE},
     Body,
 };
 pub use reqwest::{multipart::Form, Client, Method, Request, Url};
@@ -177,4 +177,15 @@ where
         .build()
         .map_err(|e| ServerFnErrorErr::Request(e.to_string()).into_app_error())
     }
+
+    fn try_set_header(&mut self, name: &str, value: &str) -> Result<(), E> {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+            ServerFnErrorErr::Request(e.to_string()).into_app_error()
+        })?;
+        let value = HeaderValue::from_str(value).map_err(|e| {
+            ServerFnErrorErr::Request(e.to_string()).into_app_error()
+        })?;
+        self.headers_mut().insert(name, value);
+        Ok(())
+    }
 }
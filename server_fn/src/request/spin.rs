@@ -34,6 +34,12 @@ where
             .map(|h| String::from_utf8_lossy(h.as_bytes()))
     }
 
+    fn header(&self, name: &str) -> Option<Cow<'_, str>> {
+        self.headers()
+            .get(name)
+            .map(|h| String::from_utf8_lossy(h.as_bytes()))
+    }
+
     async fn try_into_bytes(self) -> Result<Bytes, E> {
         let (_parts, body) = self.into_parts();
 
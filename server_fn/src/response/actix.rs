@@ -86,4 +86,10 @@ impl Res for ActixResponse {
             self.0.headers_mut().insert(LOCATION, path);
         }
     }
+
+    fn set_status(&mut self, status: u16) {
+        if let Ok(status) = StatusCode::from_u16(status) {
+            *self.0.status_mut() = status;
+        }
+    }
 }
@@ -2,6 +2,7 @@ use super::ClientRes;
 use crate::{
     error::{FromServerFnError, IntoAppError, ServerFnErrorErr},
     redirect::REDIRECT_HEADER,
+    request::browser::AbortOnDrop,
 };
 use bytes::Bytes;
 use futures::{Stream, StreamExt};
@@ -14,7 +15,12 @@ use wasm_bindgen::JsCast;
 use wasm_streams::ReadableStream;
 
 /// The response to a `fetch` request made in the browser.
-pub struct BrowserResponse(pub(crate) SendWrapper<Response>);
+///
+/// If the response is dropped before its body has been read, the request is aborted.
+pub struct BrowserResponse(
+    pub(crate) SendWrapper<Response>,
+    pub(crate) SendWrapper<Option<AbortOnDrop>>,
+);
 
 impl BrowserResponse {
     /// Generate the headers from the internal [`Response`] object.
@@ -61,9 +67,11 @@ impl<E: FromServerFnError> ClientRes<E> for BrowserResponse {
         self,
     ) -> Result<impl Stream<Item = Result<Bytes, Bytes>> + Send + 'static, E>
     {
+        // keep the request alive for as long as the stream is being read
+        let abort_ctrl = self.1;
         let stream = ReadableStream::from_raw(self.0.body().unwrap())
             .into_stream()
-            .map(|data| match data {
+            .map(move |data| match data {
                 Err(e) => {
                     web_sys::console::error_1(&e);
                     Err(E::from_server_fn_error(ServerFnErrorErr::Request(
@@ -72,6 +80,7 @@ impl<E: FromServerFnError> ClientRes<E> for BrowserResponse {
                     .ser())
                 }
                 Ok(data) => {
+                    let _ = &abort_ctrl;
                     let data = data.unchecked_into::<Uint8Array>();
                     let mut buf = Vec::new();
                     let length = data.length();
@@ -66,4 +66,10 @@ impl Res for Response<Body> {
             *self.status_mut() = StatusCode::FOUND;
         }
     }
+
+    fn set_status(&mut self, status: u16) {
+        if let Ok(status) = StatusCode::from_u16(status) {
+            *self.status_mut() = status;
+        }
+    }
 }
@@ -42,6 +42,14 @@ pub trait Res {
 
     /// Redirect the response by setting a 302 code and Location header.
     fn redirect(&mut self, path: &str);
+
+    /// Sets the status code of the response.
+    ///
+    /// By default, the status code is not changed, so responses for rejected requests keep the
+    /// `500` status code of [`error_response`](Res::error_response).
+    fn set_status(&mut self, status: u16) {
+        _ = status;
+    }
 }
 
 /// Represents the response as received by the client.
@@ -106,4 +114,8 @@ impl Res for BrowserMockRes {
     fn redirect(&mut self, _path: &str) {
         unreachable!()
     }
+
+    fn set_status(&mut self, _status: u16) {
+        unreachable!()
+    }
 }
@@ -2,7 +2,15 @@ use crate::{
     request::Req,
     response::{Res, TryRes},
 };
-use std::future::Future;
+use std::{
+    future::{poll_fn, Future},
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Poll, Waker},
+};
 
 /// A server defines a pair of request/response types and the logic to spawn
 /// an async task.
@@ -28,3 +36,126 @@ pub trait Server<Error, InputStreamError = Error, OutputStreamError = Error> {
         future: impl Future<Output = ()> + Send + 'static,
     ) -> Result<(), Error>;
 }
+
+/// A token that is cancelled when the client that called a server function goes away.
+///
+/// The server integrations provide a `CancellationToken` for each server function call, and
+/// cancel it if the connection is closed before the server function has returned. Work that
+/// outlives the server function's own future (for example, a spawned task) can use this to
+/// stop early.
+///
+/// ```rust,ignore
+/// #[server]
+/// pub async fn long_running_job() -> Result<(), ServerFnError> {
+///     let token = expect_context::<CancellationToken>();
+///     tokio::spawn(async move {
+///         while !token.is_cancelled() {
+///             // ...
+///         }
+///     });
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<CancellationState>);
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    /// Creates a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, waking up everyone who is waiting for it.
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Release);
+        let wakers = mem::take(
+            &mut *self.0.wakers.lock().unwrap_or_else(PoisonError::into_inner),
+        );
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Resolves once the token has been cancelled.
+    pub fn cancelled(&self) -> impl Future<Output = ()> + Send + 'static {
+        let token = self.clone();
+        poll_fn(move |cx| {
+            if token.is_cancelled() {
+                return Poll::Ready(());
+            }
+            let mut wakers = token
+                .0
+                .wakers
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+            }
+            drop(wakers);
+            // check again, in case the token was cancelled while registering
+            if token.is_cancelled() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Returns a guard that cancels the token when it is dropped, unless it is
+    /// [disarmed](CancelOnDrop::disarm) first.
+    pub fn drop_guard(&self) -> CancelOnDrop {
+        CancelOnDrop(Some(self.clone()))
+    }
+}
+
+/// Cancels a [`CancellationToken`] when it is dropped. Created by
+/// [`CancellationToken::drop_guard`].
+#[derive(Debug)]
+pub struct CancelOnDrop(Option<CancellationToken>);
+
+impl CancelOnDrop {
+    /// Drops the guard without cancelling the token.
+    pub fn disarm(mut self) {
+        self.0.take();
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.0.take() {
+            token.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancels_on_drop_unless_disarmed() {
+        let token = CancellationToken::new();
+        token.drop_guard().disarm();
+        assert!(!token.is_cancelled());
+
+        let guard = token.drop_guard();
+        let waiting = std::thread::spawn({
+            let token = token.clone();
+            move || futures::executor::block_on(token.cancelled())
+        });
+        drop(guard);
+        waiting.join().unwrap();
+        assert!(token.is_cancelled());
+    }
+}
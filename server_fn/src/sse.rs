@@ -0,0 +1,596 @@
+//! A protocol that streams the output of a server function as
+//! [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html).
+//!
+//! Server-sent events are a one-way stream of text events over a regular HTTP response. Unlike
+//! websockets, they do not need a connection upgrade, so they work behind proxies and load
+//! balancers that only allow plain HTTP.
+//!
+//! Each item of the output stream is sent as one event with a sequential `id`, starting at `1`.
+//! If the connection drops before the stream has ended, the client reconnects and sends the
+//! id of the last event it received in a `Last-Event-ID` header. The server function can read it
+//! with [`last_event_id`] to resume the stream where it left off.
+
+use crate::{
+    client::waited,
+    codec::{Encoding, FromReq, IntoReq},
+    error::{FromServerFnError, ServerFnErrorErr},
+    request::{ClientReq, Req},
+    response::{ClientRes, TryRes},
+    BoxedStream, Decodes, Encodes, FormatType, Protocol,
+};
+use bytes::Bytes;
+use futures::{stream, Stream, StreamExt};
+use http::Method;
+use pin_project_lite::pin_project;
+use std::{
+    cell::RefCell,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// The content type of a stream of server-sent events.
+pub const SSE_CONTENT_TYPE: &str = "text/event-stream";
+
+/// The header a client sends to resume a stream of server-sent events.
+pub const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+
+// the event that marks the end of the stream, so the client does not reconnect
+const END_EVENT: &str = "end";
+// the event that carries an error from the output stream
+const ERROR_EVENT: &str = "error";
+
+// how long the client waits before reconnecting, unless the server sends a retry hint
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+// how many times in a row the client reconnects without receiving an event before giving up
+const MAX_RECONNECTS: u32 = 5;
+
+/// The server-sent events protocol, which streams the output of a server function as
+/// `text/event-stream` events.
+///
+/// The input is sent like a normal [`Http`](crate::Http) request, using `InputEncoding`. The
+/// server function returns a [`BoxedStream`], and each of its items is encoded with
+/// `OutputEncoding` and sent as one event. Binary encodings are sent as base64.
+///
+/// The client reconnects if the connection drops, sending the id of the last event it received.
+/// See the [module documentation](crate::sse) for details.
+///
+/// # Example
+///
+/// ```rust, no_run
+/// # use server_fn_macro_default::server;
+/// # #[cfg(feature = "browser")] {
+/// use server_fn::{
+///     codec::{GetUrl, JsonEncoding},
+///     BoxedStream, ServerFnError, Sse,
+/// };
+///
+/// #[server(protocol = Sse<GetUrl, JsonEncoding>)]
+/// async fn countdown(
+///     from: u32,
+/// ) -> Result<BoxedStream<u32, ServerFnError>, ServerFnError> {
+///     // skip the items the client has already received
+///     let skip = server_fn::sse::last_event_id().unwrap_or(0);
+///     let items = (0..from).rev().skip(skip as usize).map(Ok);
+///     Ok(futures::stream::iter(items).into())
+/// }
+/// # }
+/// ```
+pub struct Sse<InputEncoding, OutputEncoding>(
+    PhantomData<(InputEncoding, OutputEncoding)>,
+);
+
+impl<
+        Input,
+        OutputItem,
+        InputEncoding,
+        OutputEncoding,
+        Client,
+        Server,
+        Error,
+        InputStreamError,
+        OutputStreamError,
+    >
+    Protocol<
+        Input,
+        BoxedStream<OutputItem, OutputStreamError>,
+        Client,
+        Server,
+        Error,
+        InputStreamError,
+        OutputStreamError,
+    > for Sse<InputEncoding, OutputEncoding>
+where
+    Input: IntoReq<InputEncoding, Client::Request, Error>
+        + FromReq<InputEncoding, Server::Request, Error>
+        + Clone
+        + Send
+        + 'static,
+    InputEncoding: Encoding,
+    OutputEncoding: Encodes<OutputItem> + Decodes<OutputItem>,
+    Error: FromServerFnError + Send,
+    OutputStreamError: FromServerFnError + Send,
+    Server: crate::Server<Error, InputStreamError, OutputStreamError>,
+    Client: crate::Client<Error, InputStreamError, OutputStreamError>,
+    OutputItem: Send + 'static,
+{
+    const METHOD: Method = InputEncoding::METHOD;
+    const CONTENT_TYPES: Option<(&'static str, &'static str)> =
+        Some((InputEncoding::CONTENT_TYPE, SSE_CONTENT_TYPE));
+
+    async fn run_server<F, Fut>(
+        request: Server::Request,
+        server_fn: F,
+    ) -> Result<Server::Response, Error>
+    where
+        F: Fn(Input) -> Fut + Send,
+        Fut: Future<
+                Output = Result<
+                    BoxedStream<OutputItem, OutputStreamError>,
+                    Error,
+                >,
+            > + Send,
+    {
+        let last_event_id = request
+            .header(LAST_EVENT_ID_HEADER)
+            .and_then(|id| id.trim().parse::<u64>().ok());
+        let input = Input::from_req(request).await?;
+
+        let scope = SseScope {
+            last_event_id,
+            retry: None,
+        };
+        let (output, scope) = InSseScope::new(scope, server_fn(input)).await;
+        let output = output?;
+
+        let retry = scope.retry.map(|retry| {
+            Bytes::from(format!("retry: {}\n\n", retry.as_millis()))
+        });
+        let first_id = last_event_id.map_or(1, |id| id.saturating_add(1));
+        let events = output.stream.zip(stream::iter(first_id..)).map(
+            |(item, id)| {
+                let encoded = item.and_then(|item| {
+                    OutputEncoding::encode(&item).map_err(|e| {
+                        OutputStreamError::from_server_fn_error(
+                            ServerFnErrorErr::Serialization(e.to_string()),
+                        )
+                    })
+                });
+                match encoded {
+                    Ok(bytes) => encode_event(
+                        Some(id),
+                        None,
+                        &OutputEncoding::into_encoded_string(bytes),
+                    ),
+                    Err(err) => encode_event(
+                        Some(id),
+                        Some(ERROR_EVENT),
+                        &<OutputStreamError::Encoder as FormatType>::into_encoded_string(
+                            err.ser(),
+                        ),
+                    ),
+                }
+            },
+        );
+        let events = stream::iter(retry)
+            .chain(events)
+            .chain(stream::once(async {
+                encode_event(None, Some(END_EVENT), "")
+            }))
+            .map(Ok);
+
+        Server::Response::try_from_stream(SSE_CONTENT_TYPE, events)
+    }
+
+    fn run_client(
+        path: &str,
+        input: Input,
+    ) -> impl Future<
+        Output = Result<BoxedStream<OutputItem, OutputStreamError>, Error>,
+    > + Send {
+        let path = path.to_string();
+
+        async move {
+            let body =
+                connect::<Client, _, _, _, _, _>(&path, input.clone(), None)
+                    .await?;
+            let state = ClientState {
+                path,
+                input,
+                body: Some(body),
+                parser: EventParser::default(),
+                reconnects: 0,
+                done: false,
+            };
+            let events = stream::unfold(state, |mut state| async move {
+                let item = next_item::<
+                    Client,
+                    Input,
+                    InputEncoding,
+                    OutputItem,
+                    OutputEncoding,
+                    Error,
+                    InputStreamError,
+                    OutputStreamError,
+                >(&mut state)
+                .await?;
+                Some((item, state))
+            });
+            Ok(BoxedStream::from(events))
+        }
+    }
+}
+
+/// Returns the id of the last event the client received, if it is reconnecting to a stream of
+/// server-sent events.
+///
+/// This is only available while the body of a server function that uses the [`Sse`] protocol
+/// runs, and returns `None` everywhere else. Event ids count the items of the output stream,
+/// starting at `1`, so a server function can skip that many items to resume the stream.
+pub fn last_event_id() -> Option<u64> {
+    SCOPE.with(|scope| scope.borrow().as_ref()?.last_event_id)
+}
+
+/// Sets how long the client should wait before reconnecting, if the connection drops.
+///
+/// This only has an effect while the body of a server function that uses the [`Sse`] protocol
+/// runs. Otherwise, the client waits for three seconds.
+pub fn set_retry_hint(retry: Duration) {
+    SCOPE.with(|scope| {
+        if let Some(scope) = scope.borrow_mut().as_mut() {
+            scope.retry = Some(retry);
+        }
+    });
+}
+
+thread_local! {
+    static SCOPE: RefCell<Option<SseScope>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Default)]
+struct SseScope {
+    last_event_id: Option<u64>,
+    retry: Option<Duration>,
+}
+
+pin_project! {
+    /// Makes the [`SseScope`] available while the inner future is polled, and returns it
+    /// with the output, so that changes made by the server function can be read back.
+    struct InSseScope<F> {
+        scope: Option<SseScope>,
+        #[pin]
+        inner: F,
+    }
+}
+
+impl<F> InSseScope<F> {
+    fn new(scope: SseScope, inner: F) -> Self {
+        Self {
+            scope: Some(scope),
+            inner,
+        }
+    }
+}
+
+impl<F: Future> Future for InSseScope<F> {
+    type Output = (F::Output, SseScope);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let prev = SCOPE.with(|scope| scope.replace(this.scope.take()));
+        let res = this.inner.poll(cx);
+        let scope = SCOPE.with(|scope| scope.replace(prev));
+        match res {
+            Poll::Ready(output) => {
+                Poll::Ready((output, scope.unwrap_or_default()))
+            }
+            Poll::Pending => {
+                *this.scope = scope;
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Encodes one event. Lines of the data are sent as separate `data` fields.
+fn encode_event(id: Option<u64>, event: Option<&str>, data: &str) -> Bytes {
+    let mut buf = String::with_capacity(data.len() + 32);
+    if let Some(event) = event {
+        buf.push_str("event: ");
+        buf.push_str(event);
+        buf.push('\n');
+    }
+    if let Some(id) = id {
+        buf.push_str("id: ");
+        buf.push_str(&id.to_string());
+        buf.push('\n');
+    }
+    for line in data.split('\n') {
+        buf.push_str("data: ");
+        buf.push_str(line.strip_suffix('\r').unwrap_or(line));
+        buf.push('\n');
+    }
+    buf.push('\n');
+    Bytes::from(buf)
+}
+
+/// An event that has been parsed from the stream.
+#[derive(Debug, PartialEq, Eq)]
+struct Event {
+    event: Option<String>,
+    data: String,
+}
+
+/// Parses a `text/event-stream` body that arrives in chunks.
+#[derive(Debug, Default)]
+struct EventParser {
+    buf: Vec<u8>,
+    event: Option<String>,
+    data: Option<String>,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl EventParser {
+    fn feed(&mut self, chunk: &[u8]) {
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Returns the next complete event, if the buffer contains one.
+    fn next_event(&mut self) -> Option<Event> {
+        while let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+            let line = self.buf.drain(..=end).collect::<Vec<_>>();
+            let line = String::from_utf8_lossy(&line[..end]);
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+
+            if line.is_empty() {
+                let event = self.event.take();
+                let data = self.data.take();
+                if event.is_some() || data.is_some() {
+                    return Some(Event {
+                        event,
+                        data: data.unwrap_or_default(),
+                    });
+                }
+                continue;
+            }
+
+            let (field, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.event = Some(value.to_string()),
+                "data" => match &mut self.data {
+                    Some(data) => {
+                        data.push('\n');
+                        data.push_str(value);
+                    }
+                    None => self.data = Some(value.to_string()),
+                },
+                "id" if !value.contains('\0') => {
+                    self.last_event_id = Some(value.to_string())
+                }
+                "retry" => {
+                    if let Ok(millis) = value.parse() {
+                        self.retry = Some(Duration::from_millis(millis));
+                    }
+                }
+                // comments (which start with `:`) and unknown fields are ignored
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Discards a partially received event, when the connection drops.
+    fn reset(&mut self) {
+        self.buf.clear();
+        self.event = None;
+        self.data = None;
+    }
+}
+
+type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, Bytes>> + Send>>;
+
+struct ClientState<Input> {
+    path: String,
+    input: Input,
+    body: Option<ByteStream>,
+    parser: EventParser,
+    reconnects: u32,
+    done: bool,
+}
+
+/// Sends the request, and returns the response body as a stream.
+async fn connect<
+    Client,
+    Input,
+    InputEncoding,
+    Error,
+    InputStreamError,
+    OutputStreamError,
+>(
+    path: &str,
+    input: Input,
+    last_event_id: Option<&str>,
+) -> Result<ByteStream, Error>
+where
+    Client: crate::Client<Error, InputStreamError, OutputStreamError>,
+    Input: IntoReq<InputEncoding, Client::Request, Error>,
+    Error: FromServerFnError,
+{
+    let mut req = input.into_req(path, SSE_CONTENT_TYPE)?;
+    if let Some(id) = last_event_id {
+        req.try_set_header(LAST_EVENT_ID_HEADER, id)?;
+    }
+    let res = Client::send(req).await?;
+    if (400..=599).contains(&res.status()) {
+        return Err(Error::de(res.try_into_bytes().await?));
+    }
+    Ok(Box::pin(res.try_into_stream()?))
+}
+
+/// Reads the next item from the stream, reconnecting if the connection has dropped.
+#[allow(clippy::type_complexity)]
+async fn next_item<
+    Client,
+    Input,
+    InputEncoding,
+    OutputItem,
+    OutputEncoding,
+    Error,
+    InputStreamError,
+    OutputStreamError,
+>(
+    state: &mut ClientState<Input>,
+) -> Option<Result<OutputItem, OutputStreamError>>
+where
+    Client: crate::Client<Error, InputStreamError, OutputStreamError>,
+    Input: IntoReq<InputEncoding, Client::Request, Error> + Clone,
+    OutputEncoding: Decodes<OutputItem> + FormatType,
+    Error: FromServerFnError,
+    OutputStreamError: FromServerFnError,
+{
+    loop {
+        if state.done {
+            return None;
+        }
+
+        if let Some(event) = state.parser.next_event() {
+            state.reconnects = 0;
+            return match event.event.as_deref() {
+                Some(END_EVENT) => {
+                    state.done = true;
+                    None
+                }
+                Some(ERROR_EVENT) => Some(Err(
+                    <OutputStreamError::Encoder as FormatType>::from_encoded_string(
+                        &event.data,
+                    )
+                    .map(OutputStreamError::de)
+                    .unwrap_or_else(|e| {
+                        OutputStreamError::from_server_fn_error(
+                            ServerFnErrorErr::Deserialization(e.to_string()),
+                        )
+                    }),
+                )),
+                _ => Some(
+                    OutputEncoding::from_encoded_string(&event.data)
+                        .map_err(|e| e.to_string())
+                        .and_then(|bytes| {
+                            OutputEncoding::decode(bytes)
+                                .map_err(|e| e.to_string())
+                        })
+                        .map_err(|e| {
+                            OutputStreamError::from_server_fn_error(
+                                ServerFnErrorErr::Deserialization(e),
+                            )
+                        }),
+                ),
+            };
+        }
+
+        if let Some(body) = &mut state.body {
+            if let Some(Ok(chunk)) = body.next().await {
+                state.parser.feed(&chunk);
+                continue;
+            }
+        }
+
+        // the connection has dropped before the end of the stream, so reconnect
+        state.body = None;
+        state.parser.reset();
+        if state.reconnects >= MAX_RECONNECTS {
+            state.done = true;
+            return Some(Err(OutputStreamError::from_server_fn_error(
+                ServerFnErrorErr::Request(
+                    "the event stream closed before it ended".into(),
+                ),
+            )));
+        }
+        state.reconnects += 1;
+        let delay = state.parser.retry.unwrap_or(DEFAULT_RETRY);
+        if !waited(delay, Client::sleep(delay)).await {
+            state.done = true;
+            return Some(Err(OutputStreamError::from_server_fn_error(
+                ServerFnErrorErr::Request(
+                    "the event stream closed before it ended, and the client \
+                     can't wait to reconnect"
+                        .into(),
+                ),
+            )));
+        }
+        match connect::<Client, _, _, _, _, _>(
+            &state.path,
+            state.input.clone(),
+            state.parser.last_event_id.as_deref(),
+        )
+        .await
+        {
+            Ok(body) => state.body = Some(body),
+            Err(err) => {
+                state.done = true;
+                return Some(Err(OutputStreamError::de(err.ser())));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_multiline_events() {
+        assert_eq!(
+            encode_event(Some(3), None, "a\nb"),
+            Bytes::from_static(b"id: 3\ndata: a\ndata: b\n\n")
+        );
+        assert_eq!(
+            encode_event(None, Some(END_EVENT), ""),
+            Bytes::from_static(b"event: end\ndata: \n\n")
+        );
+    }
+
+    #[test]
+    fn parses_events_across_chunks() {
+        let mut parser = EventParser::default();
+        parser.feed(b": comment\nretry: 1500\n\nid: 1\nda");
+        assert_eq!(parser.next_event(), None);
+        assert_eq!(parser.retry, Some(Duration::from_millis(1500)));
+        parser.feed(b"ta: a\r\ndata: b\r\n\r\nevent: end\ndata:\n\n");
+        assert_eq!(
+            parser.next_event(),
+            Some(Event {
+                event: None,
+                data: "a\nb".into()
+            })
+        );
+        assert_eq!(parser.last_event_id.as_deref(), Some("1"));
+        assert_eq!(
+            parser.next_event(),
+            Some(Event {
+                event: Some(END_EVENT.into()),
+                data: String::new()
+            })
+        );
+        assert_eq!(parser.next_event(), None);
+    }
+
+    #[test]
+    fn scope_is_available_while_polled() {
+        let scope = SseScope {
+            last_event_id: Some(4),
+            retry: None,
+        };
+        let fut = InSseScope::new(scope, async {
+            set_retry_hint(Duration::from_secs(1));
+            last_event_id()
+        });
+        let (id, scope) = futures::executor::block_on(fut);
+        assert_eq!(id, Some(4));
+        assert_eq!(scope.retry, Some(Duration::from_secs(1)));
+        assert_eq!(last_event_id(), None);
+    }
+}
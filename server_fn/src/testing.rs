@@ -0,0 +1,1346 @@
+//! Calls server functions in-process, without an HTTP server.
+//!
+//! A `TestServer` from the [`axum`] or [`actix`] module calls a server function the way a
+//! client would: the arguments are encoded with its input encoding, the request runs through
+//! the registered server function and its middleware, and the response is decoded with its
+//! output encoding.
+//!
+//! ```rust,ignore
+//! use server_fn::testing::axum::TestServer;
+//!
+//! #[tokio::test]
+//! async fn adds_numbers() {
+//!     let server = TestServer::new();
+//!     assert_eq!(server.call(Add { a: 1, b: 2 }).await.unwrap(), 3);
+//! }
+//! ```
+//!
+//! By default, each call runs the server function service directly. Use `with_dispatch` to
+//! run it in some other way, for example to provide context to the server function.
+//! `leptos_axum` and `leptos_actix` provide test servers that run each call with the same
+//! context as their server function handlers.
+//!
+//! Tasks that the client spawns, for example to send a batch, run on the same executor as
+//! the call, and are dropped when it finishes. Calls are not retried, and protocols that open
+//! a websocket are not supported. Server functions that take
+//! [`MultipartData`](crate::codec::MultipartData) can be called with a [`TestFormData`].
+
+use crate::{
+    client::Client,
+    error::{FromServerFnError, IntoAppError, ServerFnErrorErr},
+    redirect::REDIRECT_HEADER,
+    request::ClientReq,
+    response::ClientRes,
+    Protocol, ServerFn,
+};
+use bytes::{Bytes, BytesMut};
+use futures::{
+    stream::FuturesUnordered, Sink, Stream, StreamExt, TryStreamExt,
+};
+use http::{
+    header::{ACCEPT, CONTENT_TYPE, LOCATION},
+    HeaderMap, HeaderName, HeaderValue, Method, Response, StatusCode, Uri,
+};
+use pin_project_lite::pin_project;
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex, PoisonError},
+    task::{Context, Poll},
+};
+
+type BoxStream<T> = Pin<Box<dyn Stream<Item = T> + Send>>;
+type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// The peer address of the requests made by a test server.
+#[cfg(any(feature = "axum-no-default", feature = "actix-no-default"))]
+const LOOPBACK: std::net::SocketAddr = std::net::SocketAddr::new(
+    std::net::IpAddr::V4(std::net::Ipv4Addr::LOCALHOST),
+    0,
+);
+
+/// Runs a request with the registered server function, or returns `None` if there is none.
+type Dispatcher = Arc<
+    dyn Fn(
+            TestRequest,
+        )
+            -> Pin<Box<dyn Future<Output = Option<TestResponse>> + Send>>
+        + Send
+        + Sync,
+>;
+
+thread_local! {
+    static DISPATCHER: RefCell<Option<Dispatcher>> = const { RefCell::new(None) };
+    /// The tasks spawned by the client while a call is being polled.
+    static SPAWNED: RefCell<Option<Vec<BoxFuture>>> = const { RefCell::new(None) };
+}
+
+/// A request sent by the [`TestClient`].
+pub struct TestRequest {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: TestBody,
+}
+
+enum TestBody {
+    Bytes(Bytes),
+    Stream(BoxStream<Bytes>),
+}
+
+impl TestRequest {
+    fn new<E: FromServerFnError>(
+        method: Method,
+        uri: &str,
+        content_type: &str,
+        accepts: &str,
+        body: TestBody,
+    ) -> Result<Self, E> {
+        let uri = uri.parse::<Uri>().map_err(|e| {
+            ServerFnErrorErr::Request(e.to_string()).into_app_error()
+        })?;
+        let mut req = Self {
+            method,
+            uri,
+            headers: HeaderMap::new(),
+            body,
+        };
+        if !content_type.is_empty() {
+            req.try_set_header(CONTENT_TYPE.as_str(), content_type)?;
+        }
+        req.try_set_header(ACCEPT.as_str(), accepts)?;
+        Ok(req)
+    }
+
+    /// Adds the default headers of the test server, unless the request sets them itself.
+    fn with_default_headers(mut self, defaults: &HeaderMap) -> Self {
+        for (name, value) in defaults {
+            if !self.headers.contains_key(name) {
+                self.headers.append(name, value.clone());
+            }
+        }
+        self
+    }
+}
+
+impl<E> ClientReq<E> for TestRequest
+where
+    E: FromServerFnError,
+{
+    type FormData = TestFormData;
+
+    fn try_new_req_query(
+        path: &str,
+        content_type: &str,
+        accepts: &str,
+        query: &str,
+        method: Method,
+    ) -> Result<Self, E> {
+        let uri = format!("{path}?{query}");
+        Self::new(
+            method,
+            &uri,
+            content_type,
+            accepts,
+            TestBody::Bytes(Bytes::new()),
+        )
+    }
+
+    fn try_new_req_text(
+        path: &str,
+        content_type: &str,
+        accepts: &str,
+        body: String,
+        method: Method,
+    ) -> Result<Self, E> {
+        Self::new(
+            method,
+            path,
+            content_type,
+            accepts,
+            TestBody::Bytes(body.into()),
+        )
+    }
+
+    fn try_new_req_bytes(
+        path: &str,
+        content_type: &str,
+        accepts: &str,
+        body: Bytes,
+        method: Method,
+    ) -> Result<Self, E> {
+        Self::new(method, path, content_type, accepts, TestBody::Bytes(body))
+    }
+
+    fn try_new_req_form_data(
+        path: &str,
+        accepts: &str,
+        content_type: &str,
+        body: Self::FormData,
+        method: Method,
+    ) -> Result<Self, E> {
+        #[cfg_attr(
+            not(feature = "multipart"),
+            allow(clippy::infallible_destructuring_match)
+        )]
+        let fields = match body.0 {
+            FormBody::Fields(fields) => fields,
+            #[cfg(feature = "multipart")]
+            FormBody::Multipart(_) => {
+                return Err(ServerFnErrorErr::Request(
+                    "Multipart data can't be sent as a URL-encoded form."
+                        .into(),
+                )
+                .into_app_error())
+            }
+        };
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(fields.iter().map(|field| {
+                (&field.name, String::from_utf8_lossy(&field.value))
+            }))
+            .finish();
+        Self::new(
+            method,
+            path,
+            content_type,
+            accepts,
+            TestBody::Bytes(body.into()),
+        )
+    }
+
+    fn try_new_req_multipart(
+        path: &str,
+        accepts: &str,
+        body: Self::FormData,
+        method: Method,
+    ) -> Result<Self, E> {
+        let content_type = format!("multipart/form-data; boundary={BOUNDARY}");
+        Self::new(method, path, &content_type, accepts, body.into_body())
+    }
+
+    fn try_new_req_streaming(
+        path: &str,
+        accepts: &str,
+        content_type: &str,
+        body: impl Stream<Item = Bytes> + Send + 'static,
+        method: Method,
+    ) -> Result<Self, E> {
+        Self::new(
+            method,
+            path,
+            content_type,
+            accepts,
+            TestBody::Stream(Box::pin(body)),
+        )
+    }
+
+    fn try_set_header(&mut self, name: &str, value: &str) -> Result<(), E> {
+        let name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| {
+            ServerFnErrorErr::Request(e.to_string()).into_app_error()
+        })?;
+        let value = HeaderValue::from_str(value).map_err(|e| {
+            ServerFnErrorErr::Request(e.to_string()).into_app_error()
+        })?;
+        self.headers.insert(name, value);
+        Ok(())
+    }
+}
+
+/// The boundary between the fields of a multipart body sent by the [`TestClient`].
+const BOUNDARY: &str = "server-fn-test-boundary";
+
+/// Form data sent by the [`TestClient`].
+///
+/// With the `multipart` feature, this converts into
+/// [`MultipartData`](crate::codec::MultipartData), to call a server function that takes it:
+///
+/// ```rust,ignore
+/// let data = TestFormData::new()
+///     .text("title", "Notes")
+///     .file("file", "notes.txt", "text/plain", "hello");
+/// server.call(UploadFile { data: data.into() }).await?;
+/// ```
+pub struct TestFormData(FormBody);
+
+enum FormBody {
+    Fields(Vec<FormField>),
+    /// Multipart data that is sent again as it is read.
+    #[cfg(feature = "multipart")]
+    Multipart(multer::Multipart<'static>),
+}
+
+struct FormField {
+    name: String,
+    /// The file name and content type of a file.
+    file: Option<(String, String)>,
+    value: Bytes,
+}
+
+impl Default for TestFormData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TestFormData {
+    /// Creates form data without any fields.
+    pub fn new() -> Self {
+        Self(FormBody::Fields(Vec::new()))
+    }
+
+    /// Adds a text field.
+    pub fn text(
+        self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.field(FormField {
+            name: name.into(),
+            file: None,
+            value: value.into().into(),
+        })
+    }
+
+    /// Adds a file with the given file name and content type.
+    pub fn file(
+        self,
+        name: impl Into<String>,
+        file_name: impl Into<String>,
+        content_type: impl Into<String>,
+        data: impl Into<Bytes>,
+    ) -> Self {
+        self.field(FormField {
+            name: name.into(),
+            file: Some((file_name.into(), content_type.into())),
+            value: data.into(),
+        })
+    }
+
+    fn field(mut self, field: FormField) -> Self {
+        match &mut self.0 {
+            FormBody::Fields(fields) => fields.push(field),
+            #[cfg(feature = "multipart")]
+            FormBody::Multipart(_) => {
+                panic!("fields can't be added to received multipart data")
+            }
+        }
+        self
+    }
+
+    /// The form data as a multipart body.
+    fn into_body(self) -> TestBody {
+        match self.0 {
+            FormBody::Fields(fields) => {
+                let mut body = BytesMut::new();
+                for field in &fields {
+                    body.extend_from_slice(&encode_field(field));
+                }
+                body.extend_from_slice(
+                    format!("--{BOUNDARY}--\r\n").as_bytes(),
+                );
+                TestBody::Bytes(body.freeze())
+            }
+            #[cfg(feature = "multipart")]
+            FormBody::Multipart(multipart) => {
+                TestBody::Stream(Box::pin(futures::stream::unfold(
+                    Some(multipart),
+                    |multipart| async move {
+                        let mut multipart = multipart?;
+                        let Ok(field) = multipart.next_field().await else {
+                            // the body ends without its closing boundary
+                            return None;
+                        };
+                        let Some(field) = field else {
+                            let end = format!("--{BOUNDARY}--\r\n");
+                            return Some((Bytes::from(end), None));
+                        };
+                        let name = field.name().unwrap_or_default().to_string();
+                        let file = field.file_name().map(|file_name| {
+                            let content_type =
+                                field.content_type().map_or_else(
+                                    || "application/octet-stream".to_string(),
+                                    ToString::to_string,
+                                );
+                            (file_name.to_string(), content_type)
+                        });
+                        let value = field.bytes().await.ok()?;
+                        let field = FormField { name, file, value };
+                        Some((encode_field(&field), Some(multipart)))
+                    },
+                )))
+            }
+        }
+    }
+}
+
+/// Encodes a field of a multipart body, with the boundary before it.
+fn encode_field(field: &FormField) -> Bytes {
+    let mut head = format!(
+        "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"{}\"",
+        field.name
+    );
+    if let Some((file_name, content_type)) = &field.file {
+        head.push_str(&format!(
+            "; filename=\"{file_name}\"\r\nContent-Type: {content_type}"
+        ));
+    }
+    head.push_str("\r\n\r\n");
+    let mut encoded = BytesMut::from(head.as_bytes());
+    encoded.extend_from_slice(&field.value);
+    encoded.extend_from_slice(b"\r\n");
+    encoded.freeze()
+}
+
+#[cfg(feature = "multipart")]
+impl From<TestFormData> for crate::codec::MultipartData {
+    fn from(data: TestFormData) -> Self {
+        match data.0 {
+            FormBody::Multipart(multipart) => Self::Server(multipart),
+            fields => {
+                let body = match TestFormData(fields).into_body() {
+                    TestBody::Bytes(bytes) => bytes,
+                    TestBody::Stream(_) => {
+                        unreachable!("fields are encoded at once")
+                    }
+                };
+                let body = futures::stream::once(async move {
+                    Ok::<_, std::convert::Infallible>(body)
+                });
+                Self::Server(multer::Multipart::new(body, BOUNDARY))
+            }
+        }
+    }
+}
+
+#[cfg(feature = "multipart")]
+impl TryFrom<crate::codec::MultipartData> for TestFormData {
+    type Error = crate::codec::MultipartData;
+
+    fn try_from(
+        data: crate::codec::MultipartData,
+    ) -> Result<Self, Self::Error> {
+        match data {
+            crate::codec::MultipartData::Server(multipart) => {
+                Ok(Self(FormBody::Multipart(multipart)))
+            }
+            data => Err(data),
+        }
+    }
+}
+
+/// A response received by the [`TestClient`].
+pub struct TestResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    // the response bodies of the servers are not `Sync`, but the stream of a `ClientRes` is
+    body: Mutex<BoxStream<Result<Bytes, String>>>,
+}
+
+impl TestResponse {
+    fn new(
+        status: StatusCode,
+        headers: HeaderMap,
+        body: impl Stream<Item = Result<Bytes, String>> + Send + 'static,
+    ) -> Self {
+        Self {
+            status,
+            headers,
+            body: Mutex::new(Box::pin(body)),
+        }
+    }
+
+    fn head(&self) -> Response<()> {
+        let mut res = Response::new(());
+        *res.status_mut() = self.status;
+        *res.headers_mut() = self.headers.clone();
+        res
+    }
+
+    fn into_body(
+        self,
+    ) -> impl Stream<Item = Result<Bytes, String>> + Send + Sync + 'static {
+        let mut body = self.body;
+        futures::stream::poll_fn(move |cx| {
+            body.get_mut()
+                .unwrap_or_else(PoisonError::into_inner)
+                .as_mut()
+                .poll_next(cx)
+        })
+    }
+}
+
+impl<E: FromServerFnError> ClientRes<E> for TestResponse {
+    async fn try_into_string(self) -> Result<String, E> {
+        let bytes: Bytes = ClientRes::<E>::try_into_bytes(self).await?;
+        String::from_utf8(bytes.into()).map_err(|e| {
+            ServerFnErrorErr::Deserialization(e.to_string()).into_app_error()
+        })
+    }
+
+    async fn try_into_bytes(self) -> Result<Bytes, E> {
+        let mut bytes = BytesMut::new();
+        let mut body = Box::pin(self.into_body());
+        while let Some(chunk) = body.next().await {
+            let chunk = chunk.map_err(|e| {
+                ServerFnErrorErr::Deserialization(e).into_app_error()
+            })?;
+            bytes.extend_from_slice(&chunk);
+        }
+        Ok(bytes.freeze())
+    }
+
+    fn try_into_stream(
+        self,
+    ) -> Result<
+        impl Stream<Item = Result<Bytes, Bytes>> + Send + Sync + 'static,
+        E,
+    > {
+        Ok(self.into_body().map_err(|e| {
+            E::from_server_fn_error(ServerFnErrorErr::Response(e)).ser()
+        }))
+    }
+
+    fn status(&self) -> u16 {
+        self.status.as_u16()
+    }
+
+    fn status_text(&self) -> String {
+        self.status.to_string()
+    }
+
+    fn location(&self) -> String {
+        self.headers
+            .get(LOCATION)
+            .map(|value| String::from_utf8_lossy(value.as_bytes()).to_string())
+            .unwrap_or_default()
+    }
+
+    fn has_redirect(&self) -> bool {
+        self.headers.contains_key(REDIRECT_HEADER)
+    }
+}
+
+/// A [`Client`] that sends requests to the server functions of a `TestServer`, in-process.
+///
+/// It can only send requests while a call from a test server is running.
+pub struct TestClient;
+
+impl<
+        Error: FromServerFnError,
+        InputStreamError: FromServerFnError,
+        OutputStreamError: FromServerFnError,
+    > Client<Error, InputStreamError, OutputStreamError> for TestClient
+{
+    type Request = TestRequest;
+    type Response = TestResponse;
+
+    fn send(
+        req: Self::Request,
+    ) -> impl Future<Output = Result<Self::Response, Error>> + Send {
+        let path = req.uri.path().to_string();
+        let res = DISPATCHER
+            .with_borrow(Clone::clone)
+            .map(|dispatcher| dispatcher(req));
+        async move {
+            let Some(res) = res else {
+                return Err(ServerFnErrorErr::Request(
+                    "The test client can only be used by a test server.".into(),
+                )
+                .into_app_error());
+            };
+            res.await.ok_or_else(|| {
+                ServerFnErrorErr::Request(format!(
+                    "Could not find a server function at the route {path}."
+                ))
+                .into_app_error()
+            })
+        }
+    }
+
+    async fn open_websocket(
+        _path: &str,
+    ) -> Result<
+        (
+            impl Stream<Item = Result<Bytes, Bytes>> + Send + 'static,
+            impl Sink<Bytes> + Send + 'static,
+        ),
+        Error,
+    > {
+        Err::<(futures::stream::Empty<_>, futures::sink::Drain<_>), _>(
+            ServerFnErrorErr::Request(
+                "Websockets are not supported by the test client.".into(),
+            )
+            .into_app_error(),
+        )
+    }
+
+    fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+        // during a call, the task runs along with it, so that it can send requests too
+        let future = SPAWNED.with_borrow_mut(|spawned| match spawned {
+            Some(spawned) => {
+                spawned.push(Box::pin(future));
+                None
+            }
+            None => Some(future),
+        });
+        if let Some(future) = future {
+            std::thread::spawn(move || futures::executor::block_on(future));
+        }
+    }
+}
+
+pin_project! {
+    /// Runs a call with its dispatcher, along with the tasks the client spawns.
+    struct WithDispatcher<F> {
+        dispatcher: Dispatcher,
+        tasks: FuturesUnordered<BoxFuture>,
+        #[pin]
+        inner: F,
+    }
+}
+
+impl<F> WithDispatcher<F> {
+    fn new(dispatcher: Dispatcher, inner: F) -> Self {
+        Self {
+            dispatcher,
+            tasks: FuturesUnordered::new(),
+            inner,
+        }
+    }
+}
+
+impl<F: Future> Future for WithDispatcher<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        struct Restore(Option<Dispatcher>, Option<Vec<BoxFuture>>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                DISPATCHER.set(self.0.take());
+                SPAWNED.set(self.1.take());
+            }
+        }
+
+        let this = self.project();
+        let _restore = Restore(
+            DISPATCHER.replace(Some(Arc::clone(this.dispatcher))),
+            SPAWNED.replace(Some(Vec::new())),
+        );
+        let output = this.inner.poll(cx);
+        if output.is_pending() {
+            // run the spawned tasks, and the tasks they spawn in turn
+            loop {
+                let spawned = SPAWNED.with_borrow_mut(|spawned| {
+                    spawned.as_mut().map(std::mem::take).unwrap_or_default()
+                });
+                this.tasks.extend(spawned);
+                while let Poll::Ready(Some(())) = this.tasks.poll_next_unpin(cx)
+                {
+                }
+                if SPAWNED.with_borrow(|spawned| {
+                    spawned.as_ref().is_none_or(Vec::is_empty)
+                }) {
+                    break;
+                }
+            }
+        }
+        output
+    }
+}
+
+/// Calls the server function with the [`TestClient`], which sends its request to `dispatcher`.
+///
+/// Also returns the status and headers of the response, if the server function was found.
+async fn call<T>(
+    dispatcher: Dispatcher,
+    input: T,
+) -> (Result<T::Output, T::Error>, Option<Response<()>>)
+where
+    T: ServerFn,
+    T::Protocol: Protocol<
+        T,
+        T::Output,
+        TestClient,
+        T::Server,
+        T::Error,
+        T::InputStreamError,
+        T::OutputStreamError,
+    >,
+{
+    let head = Arc::new(Mutex::new(None));
+    let dispatcher: Dispatcher = {
+        let head = Arc::clone(&head);
+        Arc::new(move |req| {
+            let res = dispatcher(req);
+            let head = Arc::clone(&head);
+            Box::pin(async move {
+                let res = res.await?;
+                *head.lock().unwrap_or_else(PoisonError::into_inner) =
+                    Some(res.head());
+                Some(res)
+            })
+        })
+    };
+    let output = WithDispatcher::new(
+        dispatcher,
+        <T::Protocol as Protocol<
+            T,
+            T::Output,
+            TestClient,
+            T::Server,
+            T::Error,
+            T::InputStreamError,
+            T::OutputStreamError,
+        >>::run_client(T::PATH, input),
+    )
+    .await;
+    let head = head.lock().unwrap_or_else(PoisonError::into_inner).take();
+    (output, head)
+}
+
+/// A test server for server functions registered with the Axum backend.
+#[cfg(feature = "axum-no-default")]
+pub mod axum {
+    use super::{TestBody, TestClient, TestRequest, TestResponse};
+    use crate::{middleware::BoxedService, Protocol, ServerFn};
+    use axum::body::Body;
+    use futures::{StreamExt, TryStreamExt};
+    use http::{HeaderMap, HeaderName, HeaderValue, Request, Response};
+    use std::{convert::Infallible, future::Future, pin::Pin, sync::Arc};
+
+    type Dispatch = Arc<
+        dyn Fn(
+                BoxedService<Request<Body>, Response<Body>>,
+                Request<Body>,
+            )
+                -> Pin<Box<dyn Future<Output = Response<Body>> + Send>>
+            + Send
+            + Sync,
+    >;
+
+    /// Calls server functions registered with the Axum backend, without an HTTP server.
+    ///
+    /// See the [module docs](super) for details.
+    #[derive(Clone)]
+    pub struct TestServer {
+        headers: HeaderMap,
+        dispatch: Dispatch,
+    }
+
+    impl Default for TestServer {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl TestServer {
+        /// Creates a test server that runs each call with its server function service.
+        pub fn new() -> Self {
+            Self {
+                headers: HeaderMap::new(),
+                dispatch: Arc::new(|mut service, req| {
+                    Box::pin(async move { service.run(req).await })
+                }),
+            }
+        }
+
+        /// Runs each call by calling `dispatch` with its server function service, for
+        /// example to provide context or request extensions to the server function.
+        pub fn with_dispatch<F, Fut>(mut self, dispatch: F) -> Self
+        where
+            F: Fn(
+                    BoxedService<Request<Body>, Response<Body>>,
+                    Request<Body>,
+                ) -> Fut
+                + Send
+                + Sync
+                + 'static,
+            Fut: Future<Output = Response<Body>> + Send + 'static,
+        {
+            self.dispatch =
+                Arc::new(move |service, req| Box::pin(dispatch(service, req)));
+            self
+        }
+
+        /// Sends the header with every call, unless the call sets it itself.
+        pub fn with_header(
+            mut self,
+            name: HeaderName,
+            value: HeaderValue,
+        ) -> Self {
+            self.headers.append(name, value);
+            self
+        }
+
+        /// Calls the server function.
+        pub async fn call<T>(&self, input: T) -> Result<T::Output, T::Error>
+        where
+            T: ServerFn,
+            T::Protocol: Protocol<
+                T,
+                T::Output,
+                TestClient,
+                T::Server,
+                T::Error,
+                T::InputStreamError,
+                T::OutputStreamError,
+            >,
+        {
+            self.call_with_response(input).await.0
+        }
+
+        /// Calls the server function, and also returns the status and headers of its
+        /// response.
+        ///
+        /// The response is `None` if the request could not be sent, or no server function
+        /// is registered at its path.
+        pub async fn call_with_response<T>(
+            &self,
+            input: T,
+        ) -> (Result<T::Output, T::Error>, Option<Response<()>>)
+        where
+            T: ServerFn,
+            T::Protocol: Protocol<
+                T,
+                T::Output,
+                TestClient,
+                T::Server,
+                T::Error,
+                T::InputStreamError,
+                T::OutputStreamError,
+            >,
+        {
+            let headers = self.headers.clone();
+            let dispatch = Arc::clone(&self.dispatch);
+            let dispatcher = Arc::new(move |req: TestRequest| {
+                let req = into_request(req.with_default_headers(&headers));
+                let service = crate::axum::get_server_fn_service(
+                    req.uri().path(),
+                    req.method().clone(),
+                );
+                let res = service.map(|service| dispatch(service, req));
+                Box::pin(async move { Some(from_response(res?.await)) })
+                    as Pin<Box<dyn Future<Output = _> + Send>>
+            });
+            super::call(dispatcher, input).await
+        }
+    }
+
+    fn into_request(req: TestRequest) -> Request<Body> {
+        let body = match req.body {
+            TestBody::Bytes(bytes) => Body::from(bytes),
+            TestBody::Stream(stream) => {
+                Body::from_stream(stream.map(Ok::<_, Infallible>))
+            }
+        };
+        let mut request = Request::new(body);
+        *request.method_mut() = req.method;
+        *request.uri_mut() = req.uri;
+        *request.headers_mut() = req.headers;
+        // calls come from this process, like a client connected over loopback
+        request
+            .extensions_mut()
+            .insert(axum::extract::ConnectInfo(super::LOOPBACK));
+        request
+    }
+
+    fn from_response(res: Response<Body>) -> TestResponse {
+        let (parts, body) = res.into_parts();
+        TestResponse::new(
+            parts.status,
+            parts.headers,
+            body.into_data_stream().map_err(|e| e.to_string()),
+        )
+    }
+}
+
+/// A test server for server functions registered with the Actix backend.
+#[cfg(feature = "actix-no-default")]
+pub mod actix {
+    use super::{TestBody, TestClient, TestRequest, TestResponse};
+    use crate::{
+        middleware::BoxedService,
+        request::actix::{payload, ActixRequest},
+        response::actix::ActixResponse,
+        Protocol, ServerFn,
+    };
+    use actix_web::body::MessageBody;
+    use bytes::Bytes;
+    use futures::{StreamExt, TryStreamExt};
+    use http::{HeaderMap, HeaderName, HeaderValue, Response, StatusCode};
+    use send_wrapper::SendWrapper;
+    use std::{future::Future, pin::Pin, sync::Arc};
+
+    type Dispatch = Arc<
+        dyn Fn(
+                BoxedService<ActixRequest, ActixResponse>,
+                ActixRequest,
+            ) -> Pin<Box<dyn Future<Output = ActixResponse>>>
+            + Send
+            + Sync,
+    >;
+
+    /// Calls server functions registered with the Actix backend, without an HTTP server.
+    ///
+    /// Like Actix itself, this has to run on a single-threaded runtime, for example in
+    /// a `#[actix_web::test]`. See the [module docs](super) for details.
+    #[derive(Clone)]
+    pub struct TestServer {
+        headers: HeaderMap,
+        dispatch: Dispatch,
+    }
+
+    impl Default for TestServer {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl TestServer {
+        /// Creates a test server that runs each call with its server function service.
+        pub fn new() -> Self {
+            Self {
+                headers: HeaderMap::new(),
+                dispatch: Arc::new(|mut service, req| {
+                    Box::pin(async move { service.run(req).await })
+                }),
+            }
+        }
+
+        /// Runs each call by calling `dispatch` with its server function service, for
+        /// example to provide context to the server function.
+        pub fn with_dispatch<F, Fut>(mut self, dispatch: F) -> Self
+        where
+            F: Fn(
+                    BoxedService<ActixRequest, ActixResponse>,
+                    ActixRequest,
+                ) -> Fut
+                + Send
+                + Sync
+                + 'static,
+            Fut: Future<Output = ActixResponse> + 'static,
+        {
+            self.dispatch =
+                Arc::new(move |service, req| Box::pin(dispatch(service, req)));
+            self
+        }
+
+        /// Sends the header with every call, unless the call sets it itself.
+        pub fn with_header(
+            mut self,
+            name: HeaderName,
+            value: HeaderValue,
+        ) -> Self {
+            self.headers.append(name, value);
+            self
+        }
+
+        /// Calls the server function.
+        pub async fn call<T>(&self, input: T) -> Result<T::Output, T::Error>
+        where
+            T: ServerFn,
+            T::Protocol: Protocol<
+                T,
+                T::Output,
+                TestClient,
+                T::Server,
+                T::Error,
+                T::InputStreamError,
+                T::OutputStreamError,
+            >,
+        {
+            self.call_with_response(input).await.0
+        }
+
+        /// Calls the server function, and also returns the status and headers of its
+        /// response.
+        ///
+        /// The response is `None` if the request could not be sent, or no server function
+        /// is registered at its path.
+        pub async fn call_with_response<T>(
+            &self,
+            input: T,
+        ) -> (Result<T::Output, T::Error>, Option<Response<()>>)
+        where
+            T: ServerFn,
+            T::Protocol: Protocol<
+                T,
+                T::Output,
+                TestClient,
+                T::Server,
+                T::Error,
+                T::InputStreamError,
+                T::OutputStreamError,
+            >,
+        {
+            let headers = self.headers.clone();
+            let dispatch = Arc::clone(&self.dispatch);
+            let dispatcher = Arc::new(move |req: TestRequest| {
+                let req = into_request(req.with_default_headers(&headers));
+                let service = crate::actix::get_server_fn_service(
+                    req.path(),
+                    req.method(),
+                );
+                let res = service.map(|service| dispatch(service, req));
+                // the request stays on this thread, like it would in Actix
+                Box::pin(SendWrapper::new(async move {
+                    Some(from_response(res?.await))
+                })) as Pin<Box<dyn Future<Output = _> + Send>>
+            });
+            super::call(dispatcher, input).await
+        }
+    }
+
+    fn into_request(req: TestRequest) -> ActixRequest {
+        let mut test_req = actix_web::test::TestRequest::default()
+            .method(
+                actix_web::http::Method::from_bytes(
+                    req.method.as_str().as_bytes(),
+                )
+                .expect("a valid method is a valid Actix method"),
+            )
+            .uri(&req.uri.to_string())
+            // calls come from this process, like a client connected over loopback
+            .peer_addr(super::LOOPBACK);
+        for (name, value) in &req.headers {
+            test_req =
+                test_req.append_header((name.as_str(), value.as_bytes()));
+        }
+        let request = test_req.to_http_request();
+        let payload = match req.body {
+            TestBody::Bytes(bytes) => {
+                payload(&request, futures::stream::once(async { Ok(bytes) }))
+            }
+            TestBody::Stream(stream) => payload(&request, stream.map(Ok)),
+        };
+        ActixRequest::from((request, payload))
+    }
+
+    fn from_response(res: ActixResponse) -> TestResponse {
+        let (res, mut body) = res.take().into_parts();
+        let status = StatusCode::from_u16(res.status().as_u16())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        let headers = res
+            .headers()
+            .iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_str().as_bytes()).ok()?,
+                    HeaderValue::from_bytes(value.as_bytes()).ok()?,
+                ))
+            })
+            .collect();
+        let body = futures::stream::poll_fn(move |cx| {
+            Pin::new(&mut body).poll_next(cx)
+        })
+        .map_ok(Bytes::from)
+        .map_err(|e| e.to_string());
+        TestResponse::new(status, headers, SendWrapper::new(body))
+    }
+}
+
+#[cfg(all(test, feature = "axum-no-default"))]
+mod tests {
+    use super::axum::TestServer;
+    use crate::{
+        codec::{GetUrl, Json},
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Layer, Service},
+        Http, ServerFn, ServerFnError,
+    };
+    use axum::body::Body;
+    use bytes::Bytes;
+    use http::{HeaderValue, Request, Response, StatusCode};
+    use serde::{Deserialize, Serialize};
+    use std::{future::Future, pin::Pin, sync::Arc};
+
+    #[derive(Serialize, Deserialize)]
+    struct Divide {
+        a: i32,
+        b: i32,
+    }
+
+    impl ServerFn for Divide {
+        const PATH: &'static str = "/api/testing_divide";
+
+        type Client = super::TestClient;
+        type Server = crate::axum::AxumServerFnBackend;
+        type Protocol = Http<Json, Json>;
+        type Output = i32;
+        type Error = ServerFnError;
+        type InputStreamError = ServerFnError;
+        type OutputStreamError = ServerFnError;
+
+        fn middlewares() -> Vec<Arc<dyn Layer<Request<Body>, Response<Body>>>> {
+            vec![Arc::new(Tagged)]
+        }
+
+        async fn run_body(self) -> Result<i32, ServerFnError> {
+            self.a.checked_div(self.b).ok_or_else(|| {
+                ServerFnError::ServerError("division by zero".into())
+            })
+        }
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Missing {}
+
+    impl ServerFn for Missing {
+        const PATH: &'static str = "/api/testing_missing";
+
+        type Client = super::TestClient;
+        type Server = crate::axum::AxumServerFnBackend;
+        type Protocol = Http<GetUrl, Json>;
+        type Output = ();
+        type Error = ServerFnError;
+        type InputStreamError = ServerFnError;
+        type OutputStreamError = ServerFnError;
+
+        async fn run_body(self) -> Result<(), ServerFnError> {
+            Ok(())
+        }
+    }
+
+    /// Adds an `x-tagged` header to the response.
+    struct Tagged;
+
+    impl Layer<Request<Body>, Response<Body>> for Tagged {
+        fn layer(
+            &self,
+            inner: BoxedService<Request<Body>, Response<Body>>,
+        ) -> BoxedService<Request<Body>, Response<Body>> {
+            BoxedService::new(inner.ser, TaggedService(inner))
+        }
+    }
+
+    struct TaggedService(BoxedService<Request<Body>, Response<Body>>);
+
+    impl Service<Request<Body>, Response<Body>> for TaggedService {
+        fn run(
+            &mut self,
+            req: Request<Body>,
+            _ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = Response<Body>> + Send>> {
+            let fut = self.0.run(req);
+            Box::pin(async move {
+                let mut res = fut.await;
+                res.headers_mut()
+                    .insert("x-tagged", HeaderValue::from_static("yes"));
+                res
+            })
+        }
+    }
+
+    #[test]
+    fn calls_registered_server_fns() {
+        crate::axum::register_explicit::<Divide>();
+        futures::executor::block_on(async {
+            let server = TestServer::new();
+            assert_eq!(server.call(Divide { a: 6, b: 3 }).await, Ok(2));
+
+            let (output, res) =
+                server.call_with_response(Divide { a: 1, b: 0 }).await;
+            assert_eq!(
+                output,
+                Err(ServerFnError::ServerError("division by zero".into()))
+            );
+            let res = res.unwrap();
+            assert_eq!(res.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(res.headers()["x-tagged"], "yes");
+
+            let (output, res) = server.call_with_response(Missing {}).await;
+            assert!(matches!(
+                output,
+                Err(ServerFnError::Request(e)) if e.contains("testing_missing")
+            ));
+            assert!(res.is_none());
+        });
+    }
+
+    #[test]
+    fn runs_calls_with_dispatch() {
+        crate::axum::register_explicit::<Divide>();
+        futures::executor::block_on(async {
+            let server = TestServer::new()
+                .with_header(
+                    http::header::ACCEPT_LANGUAGE,
+                    HeaderValue::from_static("en"),
+                )
+                .with_dispatch(|mut service, req: Request<Body>| async move {
+                    assert_eq!(req.headers()["accept-language"], "en");
+                    let mut res = service.run(req).await;
+                    *res.status_mut() = StatusCode::ACCEPTED;
+                    res
+                });
+            let (output, res) =
+                server.call_with_response(Divide { a: 9, b: 3 }).await;
+            assert_eq!(output, Ok(3));
+            assert_eq!(res.unwrap().status(), StatusCode::ACCEPTED);
+        });
+    }
+    #[test]
+    fn spawned_tasks_send_to_the_test_server() {
+        use super::{TestClient, TestRequest, TestResponse, WithDispatcher};
+        use crate::{client::Client, request::ClientReq};
+        use http::HeaderMap;
+
+        let dispatcher: super::Dispatcher = Arc::new(|req: TestRequest| {
+            let path = req.uri.path().to_string();
+            Box::pin(async move {
+                Some(TestResponse::new(
+                    StatusCode::OK,
+                    HeaderMap::new(),
+                    futures::stream::once(async move { Ok(path.into()) }),
+                ))
+            })
+        });
+        let spawning = async {
+            let (tx, rx) = futures::channel::oneshot::channel();
+            <TestClient as Client<ServerFnError>>::spawn(async move {
+                let req =
+                    <TestRequest as ClientReq<ServerFnError>>::try_new_post(
+                        "/api/spawned",
+                        "text/plain",
+                        "text/plain",
+                        String::new(),
+                    )
+                    .unwrap();
+                let res =
+                    <TestClient as Client<ServerFnError>>::send(req).await;
+                _ = tx.send(res.map(|res| res.status));
+            });
+            rx.await.unwrap()
+        };
+        let status = futures::executor::block_on(WithDispatcher::new(
+            dispatcher, spawning,
+        ));
+        assert_eq!(status, Ok(StatusCode::OK));
+    }
+
+    #[cfg(feature = "multipart")]
+    #[test]
+    fn sends_multipart_data() {
+        use super::TestFormData;
+        use crate::codec::{MultipartData, MultipartFormData};
+
+        struct Upload(MultipartData);
+
+        impl From<MultipartData> for Upload {
+            fn from(data: MultipartData) -> Self {
+                Upload(data)
+            }
+        }
+
+        impl From<Upload> for MultipartData {
+            fn from(upload: Upload) -> Self {
+                upload.0
+            }
+        }
+
+        impl ServerFn for Upload {
+            const PATH: &'static str = "/api/testing_upload";
+
+            type Client = super::TestClient;
+            type Server = crate::axum::AxumServerFnBackend;
+            type Protocol = Http<MultipartFormData, Json>;
+            type Output = Vec<String>;
+            type Error = ServerFnError;
+            type InputStreamError = ServerFnError;
+            type OutputStreamError = ServerFnError;
+
+            async fn run_body(self) -> Result<Vec<String>, ServerFnError> {
+                let mut data = self.0.into_inner().unwrap();
+                let mut fields = Vec::new();
+                while let Some(field) = data.next_field().await? {
+                    let name = field.name().unwrap_or_default().to_string();
+                    let file = field.file_name().map(ToString::to_string);
+                    let value = field.text().await?;
+                    fields.push(match file {
+                        Some(file) => format!("{name}={file}:{value}"),
+                        None => format!("{name}={value}"),
+                    });
+                }
+                Ok(fields)
+            }
+        }
+
+        crate::axum::register_explicit::<Upload>();
+        futures::executor::block_on(async {
+            let data = TestFormData::new().text("title", "Notes").file(
+                "file",
+                "notes.txt",
+                "text/plain",
+                "hello",
+            );
+            let fields = TestServer::new().call(Upload(data.into())).await;
+            assert_eq!(
+                fields.unwrap(),
+                ["title=Notes", "file=notes.txt:hello"]
+            );
+        });
+    }
+}
+
+#[cfg(all(test, feature = "actix-no-default"))]
+mod actix_tests {
+    use super::actix::TestServer;
+    use crate::{codec::Json, Http, ServerFn, ServerFnError};
+    use http::{HeaderValue, StatusCode};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize)]
+    struct Greet {
+        name: String,
+    }
+
+    impl ServerFn for Greet {
+        const PATH: &'static str = "/api/testing_greet";
+
+        type Client = super::TestClient;
+        type Server = crate::actix::ActixServerFnBackend;
+        type Protocol = Http<Json, Json>;
+        type Output = String;
+        type Error = ServerFnError;
+        type InputStreamError = ServerFnError;
+        type OutputStreamError = ServerFnError;
+
+        async fn run_body(self) -> Result<String, ServerFnError> {
+            if self.name.is_empty() {
+                return Err(ServerFnError::Args("no name".into()));
+            }
+            Ok(format!("Hello, {}!", self.name))
+        }
+    }
+
+    #[actix_web::test]
+    async fn calls_registered_server_fns() {
+        crate::actix::register_explicit::<Greet>();
+        let server =
+            TestServer::new().with_dispatch(|mut service, req| async move {
+                assert_eq!(req.headers().get("x-test").unwrap(), "yes");
+                service.run(req).await
+            });
+        let server = server.with_header(
+            http::HeaderName::from_static("x-test"),
+            HeaderValue::from_static("yes"),
+        );
+
+        let (output, res) = server
+            .call_with_response(Greet {
+                name: "Actix".into(),
+            })
+            .await;
+        assert_eq!(output.unwrap(), "Hello, Actix!");
+        assert_eq!(res.unwrap().status(), StatusCode::OK);
+
+        let (output, res) = server
+            .call_with_response(Greet {
+                name: String::new(),
+            })
+            .await;
+        assert_eq!(output, Err(ServerFnError::Args("no name".into())));
+        assert_eq!(res.unwrap().status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
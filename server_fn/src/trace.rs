@@ -0,0 +1,557 @@
+//! Propagates [W3C trace context](https://www.w3.org/TR/trace-context/) from the client to the
+//! server, so that a server function call shows up as one trace.
+//!
+//! With the `trace-propagation` feature, the built-in clients send a `traceparent` header (and
+//! a `tracestate` header, if there is one) with each request. The Axum and Actix integrations
+//! read these headers and run each server function in a `server_fn` span that continues the
+//! client's trace. Browsers send a CORS preflight for cross-origin requests with these
+//! headers, so a server that is called from another origin must allow them. The span records:
+//!
+//! - `server_fn.path`: the path of the server function
+//! - `server_fn.codec`: the content type of the request (or the accepted content type, for
+//!   requests without a body)
+//! - `server_fn.payload_size`: the size of the request body, if it is known
+//! - `trace_id`, `span_id` and `parent_span_id`: the trace context of the call
+//! - `http.response.status_code`
+//! - `error.type` and `error.message`, if the server function returned an error. The type is
+//!   given by [`FromServerFnError::error_type`](crate::error::FromServerFnError::error_type),
+//!   for example `ServerError`.
+//!
+//! The trace context of the current call is available with [`current`], and is the parent of
+//! any request sent from within it. Outside of a call, each request starts a new trace, unless
+//! a provider has been set with [`set_trace_context_provider`]:
+//!
+//! ```rust
+//! use server_fn::trace::{set_trace_context_provider, TraceContext};
+//!
+//! set_trace_context_provider(|| {
+//!     // look up the ids of the active OpenTelemetry span here
+//!     let trace_id = 0x4bf92f3577b34da6a3ce929d0e0e4736;
+//!     let span_id = 0x00f067aa0ba902b7;
+//!     Some(TraceContext::new(trace_id, span_id, true))
+//! });
+//! ```
+//!
+//! # OpenTelemetry
+//!
+//! With the `opentelemetry` feature, the trace context is propagated with OpenTelemetry
+//! instead, as long as a [`tracing-opentelemetry`](https://docs.rs/tracing-opentelemetry) layer
+//! is installed:
+//!
+//! - The `server_fn` span is given the context extracted from the request headers as its
+//!   parent, and its own ids are the ids of the span that OpenTelemetry exports.
+//! - Requests sent from within a span carry the context of that span.
+//!
+//! The headers are read and written by the global propagator (see
+//! `opentelemetry::global::set_text_map_propagator`). If no propagator has been set, the
+//! W3C `traceparent` and `tracestate` headers are used. Without an OpenTelemetry layer, trace
+//! context is propagated as if the feature was disabled.
+
+use crate::error::FromServerFnError;
+#[cfg(all(
+    feature = "trace-propagation",
+    any(feature = "browser", feature = "reqwest")
+))]
+use crate::request::ClientReq;
+use pin_project_lite::pin_project;
+use std::{
+    cell::RefCell,
+    future::Future,
+    pin::Pin,
+    sync::{PoisonError, RwLock},
+    task::{Context, Poll},
+};
+use tracing::{field, Span};
+
+/// The header that carries the trace and parent span of a request.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// The header that carries vendor-specific trace information.
+pub const TRACESTATE_HEADER: &str = "tracestate";
+
+const SAMPLED: u8 = 0x01;
+
+type Provider = fn() -> Option<TraceContext>;
+
+static PROVIDER: RwLock<Option<Provider>> = RwLock::new(None);
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = const { RefCell::new(None) };
+}
+
+/// The position of a call within a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    flags: u8,
+    state: Option<String>,
+}
+
+impl TraceContext {
+    /// Creates a trace context from the ids of a span, for example one created by OpenTelemetry.
+    pub fn new(trace_id: u128, span_id: u64, sampled: bool) -> Self {
+        Self {
+            trace_id,
+            span_id,
+            flags: if sampled { SAMPLED } else { 0 },
+            state: None,
+        }
+    }
+
+    /// Starts a new, sampled trace.
+    pub fn new_root() -> Self {
+        let trace_id =
+            (u128::from(random_id()) << 64) | u128::from(random_id());
+        Self::new(trace_id, random_id(), true)
+    }
+
+    /// A new span in the same trace, with this span as its parent.
+    pub fn child(&self) -> Self {
+        Self {
+            span_id: random_id(),
+            ..self.clone()
+        }
+    }
+
+    /// Sets the vendor-specific trace information sent in the `tracestate` header.
+    pub fn with_state(mut self, state: impl Into<String>) -> Self {
+        self.state = Some(state.into());
+        self
+    }
+
+    /// Parses the values of the `traceparent` and `tracestate` headers.
+    ///
+    /// Returns `None` if the `traceparent` header is not valid.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let mut parts = traceparent.trim().split('-');
+        let version = parts.next().filter(|v| is_hex(v, 2) && *v != "ff")?;
+        let trace_id = parts.next().filter(|id| is_hex(id, 32))?;
+        let span_id = parts.next().filter(|id| is_hex(id, 16))?;
+        let flags = parts.next().filter(|flags| is_hex(flags, 2))?;
+        // later versions may add fields, but version 00 has exactly four
+        if version == "00" && parts.next().is_some() {
+            return None;
+        }
+
+        let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+        let span_id = u64::from_str_radix(span_id, 16).ok()?;
+        if trace_id == 0 || span_id == 0 {
+            return None;
+        }
+        Some(Self {
+            trace_id,
+            span_id,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            state: tracestate
+                .map(str::trim)
+                .filter(|state| !state.is_empty())
+                .map(ToOwned::to_owned),
+        })
+    }
+
+    /// The id of the trace.
+    pub fn trace_id(&self) -> u128 {
+        self.trace_id
+    }
+
+    /// The id of the span.
+    pub fn span_id(&self) -> u64 {
+        self.span_id
+    }
+
+    /// Whether the caller may have recorded this trace.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & SAMPLED != 0
+    }
+
+    /// The vendor-specific trace information, if any.
+    pub fn state(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    /// The value of the `traceparent` header for this span.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{:032x}-{:016x}-{:02x}",
+            self.trace_id, self.span_id, self.flags
+        )
+    }
+}
+
+fn is_hex(value: &str, len: usize) -> bool {
+    value.len() == len
+        && value
+            .bytes()
+            .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn random_id() -> u64 {
+    // zero is not a valid id
+    crate::random::next_u64().max(1)
+}
+
+/// Sets the function that returns the trace context sent with requests made outside of a
+/// server function call.
+pub fn set_trace_context_provider(provider: fn() -> Option<TraceContext>) {
+    *PROVIDER.write().unwrap_or_else(PoisonError::into_inner) = Some(provider);
+}
+
+/// Returns the trace context of the server function call that is currently running, if any.
+pub fn current() -> Option<TraceContext> {
+    CURRENT.with_borrow(Clone::clone)
+}
+
+/// Runs the future with the given trace context, which is returned by [`current`] while it
+/// is polled.
+pub fn with_trace_context<F: Future>(
+    context: TraceContext,
+    fut: F,
+) -> WithTraceContext<F> {
+    WithTraceContext {
+        context: Some(context),
+        inner: fut,
+    }
+}
+
+pin_project! {
+    /// A future that runs with a [`TraceContext`]. Created by [`with_trace_context`].
+    pub struct WithTraceContext<F> {
+        context: Option<TraceContext>,
+        #[pin]
+        inner: F,
+    }
+}
+
+impl<F: Future> Future for WithTraceContext<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        struct Restore(Option<TraceContext>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let prev = self.0.take();
+                CURRENT.with(|current| *current.borrow_mut() = prev);
+            }
+        }
+
+        let this = self.project();
+        let prev =
+            CURRENT.with(|current| current.replace(this.context.clone()));
+        let _restore = Restore(prev);
+        this.inner.poll(cx)
+    }
+}
+
+/// Adds the trace context headers to a request sent by a client.
+#[cfg(all(
+    feature = "trace-propagation",
+    any(feature = "browser", feature = "reqwest")
+))]
+pub(crate) fn inject<E>(req: &mut impl ClientReq<E>) {
+    for (name, value) in headers() {
+        _ = req.try_set_header(&name, &value);
+    }
+}
+
+/// The trace context headers for a request sent from the current context.
+#[cfg(feature = "trace-propagation")]
+pub(crate) fn headers() -> Vec<(String, String)> {
+    #[cfg(feature = "opentelemetry")]
+    if let Some(headers) = otel::headers() {
+        return headers;
+    }
+    let provider = *PROVIDER.read().unwrap_or_else(PoisonError::into_inner);
+    let context = current()
+        .map(|context| context.child())
+        .or_else(|| provider.and_then(|provider| provider()))
+        .unwrap_or_else(TraceContext::new_root);
+    w3c_headers(&context)
+}
+
+#[cfg(feature = "trace-propagation")]
+fn w3c_headers(context: &TraceContext) -> Vec<(String, String)> {
+    let mut headers =
+        vec![(TRACEPARENT_HEADER.to_string(), context.traceparent())];
+    if let Some(state) = context.state() {
+        headers.push((TRACESTATE_HEADER.to_string(), state.to_string()));
+    }
+    headers
+}
+
+/// Creates the span for a server function call, from the path and headers of its request.
+///
+/// Returns the span, and the trace context of the call, which continues the trace of the
+/// client if it sent a valid `traceparent` header. This is used by the server integrations.
+pub fn server_span<'a>(
+    path: &str,
+    header: impl Fn(&str) -> Option<&'a str>,
+) -> (Span, TraceContext) {
+    let parent = header(TRACEPARENT_HEADER).and_then(|traceparent| {
+        TraceContext::parse(traceparent, header(TRACESTATE_HEADER))
+    });
+    let codec = header("content-type").or_else(|| header("accept"));
+    let payload_size = header("content-length")
+        .and_then(|length| length.trim().parse::<u64>().ok());
+
+    let span = tracing::info_span!(
+        "server_fn",
+        otel.kind = "server",
+        otel.name = path,
+        server_fn.path = path,
+        server_fn.codec = codec,
+        server_fn.payload_size = payload_size,
+        trace_id = field::Empty,
+        span_id = field::Empty,
+        parent_span_id = field::Empty,
+        http.response.status_code = field::Empty,
+        error.type = field::Empty,
+        error.message = field::Empty,
+    );
+
+    let context = parent
+        .as_ref()
+        .map(TraceContext::child)
+        .unwrap_or_else(TraceContext::new_root);
+    let parent_span_id = parent.as_ref().map(TraceContext::span_id);
+    #[cfg(feature = "opentelemetry")]
+    let (context, parent_span_id) =
+        otel::continue_trace(&span, &header, parent.as_ref())
+            .unwrap_or((context, parent_span_id));
+
+    span.record("trace_id", format!("{:032x}", context.trace_id));
+    span.record("span_id", format!("{:016x}", context.span_id));
+    if let Some(parent_span_id) = parent_span_id {
+        span.record("parent_span_id", format!("{parent_span_id:016x}"));
+    }
+    (span, context)
+}
+
+/// Records an error returned by a server function on the current span.
+pub(crate) fn record_error<E: FromServerFnError>(err: &E) {
+    let span = Span::current();
+    span.record("error.type", err.error_type());
+    span.record("error.message", format!("{err:?}"));
+}
+
+#[cfg(feature = "opentelemetry")]
+mod otel {
+    use super::{w3c_headers, TraceContext};
+    use opentelemetry::{
+        global,
+        propagation::{Extractor, Injector},
+        trace::{
+            SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId,
+            TraceState,
+        },
+        Context,
+    };
+    use std::marker::PhantomData;
+    use tracing::Span;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    struct RequestHeaders<'a, F>(F, PhantomData<&'a str>);
+
+    impl<'a, F> Extractor for RequestHeaders<'a, F>
+    where
+        F: Fn(&str) -> Option<&'a str>,
+    {
+        fn get(&self, key: &str) -> Option<&str> {
+            (self.0)(key)
+        }
+
+        // the integrations can only look headers up by name
+        fn keys(&self) -> Vec<&str> {
+            Vec::new()
+        }
+    }
+
+    struct Headers(Vec<(String, String)>);
+
+    impl Injector for Headers {
+        fn set(&mut self, key: &str, value: String) {
+            self.0.push((key.to_string(), value));
+        }
+    }
+
+    /// Makes the span continue the trace of the request, and returns the trace context of the
+    /// span and the id of its parent, or `None` if there is no OpenTelemetry layer.
+    pub(super) fn continue_trace<'a>(
+        span: &Span,
+        header: impl Fn(&str) -> Option<&'a str>,
+        w3c_parent: Option<&TraceContext>,
+    ) -> Option<(TraceContext, Option<u64>)> {
+        let mut parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&RequestHeaders(&header, PhantomData))
+        });
+        // without a propagator, fall back to the W3C headers
+        if !parent.span().span_context().is_valid() {
+            if let Some(w3c_parent) = w3c_parent {
+                parent = Context::new()
+                    .with_remote_span_context(span_context(w3c_parent));
+            }
+        }
+        let parent_span_id = trace_context(parent.span().span_context())
+            .map(|parent| parent.span_id);
+        span.set_parent(parent).ok()?;
+        let context = span.context();
+        let context = trace_context(context.span().span_context())?;
+        Some((context, parent_span_id))
+    }
+
+    /// The trace context headers for the current span, or `None` if there is no
+    /// OpenTelemetry layer.
+    pub(super) fn headers() -> Option<Vec<(String, String)>> {
+        let context = Span::current().context();
+        let current = trace_context(context.span().span_context())?;
+        let mut headers = Headers(Vec::new());
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut headers)
+        });
+        // without a propagator, fall back to the W3C headers
+        if headers.0.is_empty() {
+            Some(w3c_headers(&current))
+        } else {
+            Some(headers.0)
+        }
+    }
+
+    fn trace_context(span_context: &SpanContext) -> Option<TraceContext> {
+        span_context.is_valid().then(|| TraceContext {
+            trace_id: u128::from_be_bytes(span_context.trace_id().to_bytes()),
+            span_id: u64::from_be_bytes(span_context.span_id().to_bytes()),
+            flags: span_context.trace_flags().to_u8(),
+            state: Some(span_context.trace_state().header())
+                .filter(|state| !state.is_empty()),
+        })
+    }
+
+    fn span_context(context: &TraceContext) -> SpanContext {
+        SpanContext::new(
+            TraceId::from_bytes(context.trace_id.to_be_bytes()),
+            SpanId::from_bytes(context.span_id.to_be_bytes()),
+            TraceFlags::new(context.flags),
+            true,
+            context
+                .state()
+                .and_then(|state| state.parse::<TraceState>().ok())
+                .unwrap_or_default(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_traceparent() {
+        let context = TraceContext::parse(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            Some("congo=t61rcWkgMzE"),
+        )
+        .unwrap();
+        assert_eq!(context.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+        assert_eq!(context.span_id(), 0x00f067aa0ba902b7);
+        assert!(context.is_sampled());
+        assert_eq!(context.state(), Some("congo=t61rcWkgMzE"));
+        assert_eq!(
+            context.traceparent(),
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        );
+
+        let child = context.child();
+        assert_eq!(child.trace_id(), context.trace_id());
+        assert_ne!(child.span_id(), context.span_id());
+
+        // later versions may add fields
+        assert!(TraceContext::parse(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra",
+            None
+        )
+        .is_some_and(|context| !context.is_sampled()));
+    }
+
+    #[test]
+    fn new_roots_have_different_ids() {
+        let [a, b] = [TraceContext::new_root(), TraceContext::new_root()];
+        assert_ne!(a.trace_id(), b.trace_id());
+        assert_ne!(a.span_id(), b.span_id());
+    }
+
+    #[test]
+    fn records_error_variants() {
+        use crate::{error::ServerFnErrorErr, ServerFnError};
+
+        #[derive(Debug, serde::Serialize, serde::Deserialize)]
+        struct NotFound(ServerFnErrorErr);
+
+        impl FromServerFnError for NotFound {
+            type Encoder = crate::codec::JsonEncoding;
+
+            fn from_server_fn_error(value: ServerFnErrorErr) -> Self {
+                Self(value)
+            }
+        }
+
+        let err = ServerFnError::new("something went wrong");
+        assert_eq!(err.error_type(), "ServerError");
+        let err: ServerFnError =
+            ServerFnError::TooManyRequests { retry_after: None };
+        assert_eq!(err.error_type(), "TooManyRequests");
+        let err = NotFound(ServerFnErrorErr::MissingArg("id".into()));
+        assert_eq!(err.error_type(), "NotFound");
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    #[test]
+    fn continues_opentelemetry_traces() {
+        use opentelemetry::trace::{TraceContextExt, TracerProvider};
+        use opentelemetry_sdk::trace::SdkTracerProvider;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let tracer = SdkTracerProvider::builder().build().tracer("test");
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, || {
+            let traceparent =
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+            let (span, context) = server_span("/api/my_fn", |name| {
+                (name == TRACEPARENT_HEADER).then_some(traceparent)
+            });
+
+            // the call has the ids of the exported span, in the client's trace
+            let otel = span.context();
+            let otel = otel.span().span_context().clone();
+            assert_eq!(context.trace_id(), 0x4bf92f3577b34da6a3ce929d0e0e4736);
+            assert_eq!(
+                context.span_id().to_be_bytes(),
+                otel.span_id().to_bytes()
+            );
+
+            // requests sent from within the span continue it
+            assert_eq!(
+                span.in_scope(headers),
+                [(TRACEPARENT_HEADER.to_string(), context.traceparent())]
+            );
+        });
+    }
+
+    #[test]
+    fn rejects_invalid_traceparent() {
+        for traceparent in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01",
+            "00-+bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+        ] {
+            assert_eq!(TraceContext::parse(traceparent, None), None);
+        }
+    }
+}
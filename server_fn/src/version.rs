@@ -0,0 +1,191 @@
+//! Server function versioning, and detecting clients from an older build.
+//!
+//! Server function URLs are derived from the function's name and module, so a client
+//! bundle from a previous deployment may call URLs that the current server no longer
+//! serves, or send arguments in an outdated shape. There are two tools for this:
+//!
+//! 1. The `version` argument to the `#[server]` macro adds a `/v{N}` segment to the URL
+//!    after the prefix. To keep serving clients of an old version, keep the old function
+//!    registered alongside the new one, using a shared `endpoint`:
+//!
+//! ```rust,ignore
+//! // served at `/api/v2/list_posts`
+//! #[server(endpoint = "list_posts", version = 2)]
+//! pub async fn list_posts(page: Page) -> Result<Vec<Post>, ServerFnError> {
+//!     // ...
+//! }
+//!
+//! // still served at `/api/v1/list_posts`, for clients that have not reloaded yet
+//! #[server(endpoint = "list_posts", version = 1)]
+//! pub async fn list_posts_v1(page: usize) -> Result<Vec<Post>, ServerFnError> {
+//!     list_posts(Page { number: page, size: 20 }).await
+//! }
+//! ```
+//!
+//! 2. If a build ID is set (see [`build_id`]), the server advertises it in the
+//!    [`BUILD_ID_HEADER`] of every server function response, including batch responses
+//!    and responses for unknown server functions. When the built-in clients see a build ID that differs from
+//!    their own, they call the hook set with
+//!    [`set_stale_build_hook`](crate::client::set_stale_build_hook), so the app can ask the
+//!    user to reload.
+
+use std::sync::OnceLock;
+
+/// The response header in which the server advertises its build ID.
+pub const BUILD_ID_HEADER: &str = "x-server-fn-build";
+
+static BUILD_ID: OnceLock<&'static str> = OnceLock::new();
+
+/// Sets the build ID of this binary, overriding the `SERVER_FN_BUILD_ID` environment
+/// variable from compile time. Returns `Err(_)` if the build ID has already been set.
+pub fn set_build_id(id: &'static str) -> Result<(), &'static str> {
+    BUILD_ID.set(id)
+}
+
+/// The build ID of this binary, if any.
+///
+/// This is the value passed to [`set_build_id`] or, if that has not been called, the value
+/// of the `SERVER_FN_BUILD_ID` environment variable when this crate was compiled. For the
+/// client and server to detect each other's builds, both need the same ID: usually, the
+/// build sets `SERVER_FN_BUILD_ID` to a commit hash or timestamp for both.
+pub fn build_id() -> Option<&'static str> {
+    BUILD_ID
+        .get()
+        .copied()
+        .or(option_env!("SERVER_FN_BUILD_ID"))
+        .filter(|id| !id.is_empty())
+}
+
+#[cfg(feature = "axum-no-default")]
+pub(crate) mod axum {
+    use super::{build_id, BUILD_ID_HEADER};
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Service},
+    };
+    use axum::body::Body;
+    use bytes::Bytes;
+    use http::{HeaderMap, HeaderValue, Request, Response};
+    use std::{future::Future, pin::Pin};
+
+    /// Adds the [`BUILD_ID_HEADER`] to the headers, if a build ID is set.
+    pub(crate) fn insert_build_id(headers: &mut HeaderMap) {
+        if let Some(id) =
+            build_id().and_then(|id| HeaderValue::from_str(id).ok())
+        {
+            headers.insert(BUILD_ID_HEADER, id);
+        }
+    }
+
+    /// Wraps the service so that its responses advertise the build ID.
+    pub(crate) fn advertise_build_id(
+        inner: BoxedService<Request<Body>, Response<Body>>,
+    ) -> BoxedService<Request<Body>, Response<Body>> {
+        if build_id().is_none() {
+            return inner;
+        }
+        BoxedService::new(inner.ser, AdvertiseBuildId { inner })
+    }
+
+    struct AdvertiseBuildId {
+        inner: BoxedService<Request<Body>, Response<Body>>,
+    }
+
+    impl Service<Request<Body>, Response<Body>> for AdvertiseBuildId {
+        fn run(
+            &mut self,
+            req: Request<Body>,
+            _ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = Response<Body>> + Send>> {
+            let inner = self.inner.run(req);
+            Box::pin(async move {
+                let mut res = inner.await;
+                insert_build_id(res.headers_mut());
+                res
+            })
+        }
+    }
+}
+
+#[cfg(feature = "actix-no-default")]
+pub(crate) mod actix {
+    use super::{build_id, BUILD_ID_HEADER};
+    use crate::{
+        error::ServerFnErrorErr,
+        middleware::{BoxedService, Service},
+        request::actix::ActixRequest,
+        response::actix::ActixResponse,
+    };
+    use actix_web::http::header::{HeaderMap, HeaderValue};
+    use bytes::Bytes;
+    use std::{future::Future, pin::Pin};
+
+    /// Adds the [`BUILD_ID_HEADER`] to the headers, if a build ID is set.
+    pub(crate) fn insert_build_id(headers: &mut HeaderMap) {
+        if let Some(id) =
+            build_id().and_then(|id| HeaderValue::from_str(id).ok())
+        {
+            headers.insert(
+                actix_web::http::header::HeaderName::from_static(
+                    BUILD_ID_HEADER,
+                ),
+                id,
+            );
+        }
+    }
+
+    /// Wraps the service so that its responses advertise the build ID.
+    pub(crate) fn advertise_build_id(
+        inner: BoxedService<ActixRequest, ActixResponse>,
+    ) -> BoxedService<ActixRequest, ActixResponse> {
+        if build_id().is_none() {
+            return inner;
+        }
+        BoxedService::new(inner.ser, AdvertiseBuildId { inner })
+    }
+
+    struct AdvertiseBuildId {
+        inner: BoxedService<ActixRequest, ActixResponse>,
+    }
+
+    impl Service<ActixRequest, ActixResponse> for AdvertiseBuildId {
+        fn run(
+            &mut self,
+            req: ActixRequest,
+            _ser: fn(ServerFnErrorErr) -> Bytes,
+        ) -> Pin<Box<dyn Future<Output = ActixResponse> + Send>> {
+            let inner = self.inner.run(req);
+            Box::pin(async move {
+                let mut res = inner.await;
+                insert_build_id(res.0.headers_mut());
+                res
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{check_build_id, set_stale_build_hook};
+    use std::sync::Mutex;
+
+    #[test]
+    fn stale_builds_are_reported_once() {
+        static REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+        _ = set_build_id("client-build");
+        set_stale_build_hook(|id| REPORTED.lock().unwrap().push(id.into()))
+            .ok()
+            .unwrap();
+
+        check_build_id(None);
+        check_build_id(Some("client-build"));
+        assert!(REPORTED.lock().unwrap().is_empty());
+
+        check_build_id(Some("server-build"));
+        check_build_id(Some("server-build"));
+        check_build_id(Some("newer-build"));
+        assert_eq!(*REPORTED.lock().unwrap(), ["server-build"]);
+    }
+}
@@ -0,0 +1,1138 @@
+//! Heartbeats, reconnection and send buffering for the [`Websocket`](crate::Websocket)
+//! protocol.
+//!
+//! By default, a websocket server function opens one connection and ends its output stream
+//! when that connection closes. A [`WebsocketConfig`] makes the connection more resilient:
+//!
+//! - **Heartbeats** send a ping at a regular interval. The server answers each ping, and if
+//!   nothing arrives within the timeout, the connection is treated as lost.
+//! - **Reconnection** opens a new connection when the connection is lost, following a
+//!   [`ReconnectPolicy`]. Messages that have not been sent yet are kept and sent on the new
+//!   connection, and the [`on_reconnect`](WebsocketConfig::on_reconnect) hook lets the
+//!   application resume where it left off, by sending messages (for example, the id of the
+//!   last message it received) before any others.
+//! - **Send buffering** holds messages from the input stream until they can be sent, up to a
+//!   fixed capacity. An [`OverflowPolicy`] decides what happens when the buffer is full.
+//!
+//! When the server closes the connection normally, the output stream ends. If the connection
+//! is closed with any other code or is lost, and it is not reopened, the output stream yields a
+//! [`ServerFnErrorErr::WebsocketClosed`] error with the close code and reason.
+//!
+//! The configuration can be set for all websocket calls with [`set_websocket_config`], for one
+//! server function with the `websocket` argument of the `#[server]` macro (see
+//! [`ServerFn::websocket_config`](crate::ServerFn::websocket_config)), or for the calls made
+//! while running a future with [`with_websocket_config`]. A configuration set with
+//! [`with_websocket_config`] takes precedence over the one set for the server function, which
+//! takes precedence over the global one.
+//!
+//! ```rust
+//! use server_fn::{
+//!     codec::JsonEncoding,
+//!     websocket::{
+//!         set_websocket_config, OverflowPolicy, ReconnectPolicy,
+//!         WebsocketConfig,
+//!     },
+//! };
+//! use std::{
+//!     sync::{
+//!         atomic::{AtomicU64, Ordering},
+//!         Arc,
+//!     },
+//!     time::Duration,
+//! };
+//!
+//! // updated by the application as messages are received
+//! let last_seen = Arc::new(AtomicU64::new(0));
+//!
+//! set_websocket_config(
+//!     WebsocketConfig::new()
+//!         .with_heartbeat(Duration::from_secs(15), Duration::from_secs(5))
+//!         .with_reconnect(ReconnectPolicy::new().with_max_attempts(10))
+//!         .with_send_buffer(256, OverflowPolicy::DropOldest)
+//!         .on_reconnect(move |reconnections, sender| {
+//!             println!("reconnected ({reconnections} times so far)");
+//!             let resume_from = last_seen.load(Ordering::Relaxed);
+//!             _ = sender.send::<JsonEncoding, _>(&resume_from);
+//!         }),
+//! );
+//! ```
+//!
+//! ```rust,ignore
+//! use server_fn::websocket::{ReconnectPolicy, WebsocketConfig};
+//!
+//! #[server(
+//!     protocol = Websocket<JsonEncoding, JsonEncoding>,
+//!     websocket = WebsocketConfig::new().with_reconnect(ReconnectPolicy::new())
+//! )]
+//! async fn chat(
+//!     input: BoxedStream<Message, ServerFnError>,
+//! ) -> Result<BoxedStream<Message, ServerFnError>, ServerFnError> {
+//!     // ...
+//! }
+//! ```
+
+use crate::{
+    client::{waited, Client},
+    error::{FromServerFnError, ServerFnErrorErr},
+    random, serialize_result, Encodes,
+};
+use bytes::Bytes;
+use futures::{
+    channel::mpsc, stream::BoxStream, Sink, SinkExt, Stream, StreamExt,
+};
+use pin_project_lite::pin_project;
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::{self, Debug},
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, PoisonError, RwLock},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// The close code used when a connection was lost without being closed.
+pub const ABNORMAL_CLOSURE: u16 = 1006;
+
+// the tags of the heartbeat frames, which follow the tags of serialized results
+pub(crate) const PING: u8 = 2;
+pub(crate) const PONG: u8 = 3;
+
+// how many received messages are held for the output stream
+const OUTPUT_BUFFER: usize = 16;
+
+static GLOBAL_CONFIG: RwLock<Option<WebsocketConfig>> = RwLock::new(None);
+
+thread_local! {
+    static SCOPED_CONFIG: RefCell<Option<WebsocketConfig>> = const { RefCell::new(None) };
+}
+
+/// How websocket connections are kept alive, reopened and buffered.
+///
+/// See the [module documentation](crate::websocket) for details.
+#[derive(Clone)]
+pub struct WebsocketConfig {
+    heartbeat: Option<(Duration, Duration)>,
+    reconnect: Option<ReconnectPolicy>,
+    send_buffer: usize,
+    overflow: OverflowPolicy,
+    on_reconnect: Option<Arc<ReconnectHook>>,
+}
+
+type ReconnectHook = dyn Fn(u32, &mut ReconnectSender) + Send + Sync;
+
+impl Debug for WebsocketConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebsocketConfig")
+            .field("heartbeat", &self.heartbeat)
+            .field("reconnect", &self.reconnect)
+            .field("send_buffer", &self.send_buffer)
+            .field("overflow", &self.overflow)
+            .field("on_reconnect", &self.on_reconnect.is_some())
+            .finish()
+    }
+}
+
+impl Default for WebsocketConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebsocketConfig {
+    /// No heartbeats, no reconnection, and a send buffer of 32 messages that waits for
+    /// space when it is full. This is the default.
+    pub const fn new() -> Self {
+        Self {
+            heartbeat: None,
+            reconnect: None,
+            send_buffer: 32,
+            overflow: OverflowPolicy::Block,
+            on_reconnect: None,
+        }
+    }
+
+    /// Sends a ping every `interval`, and treats the connection as lost if nothing is
+    /// received within `timeout` of a ping.
+    ///
+    /// Heartbeats need a client whose [`Client::sleep`] waits; they are turned off for
+    /// clients that use its default implementation.
+    pub const fn with_heartbeat(
+        mut self,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Self {
+        self.heartbeat = Some((interval, timeout));
+        self
+    }
+
+    /// Reopens the connection when it is lost.
+    pub const fn with_reconnect(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = Some(policy);
+        self
+    }
+
+    /// Sets how many messages are held until they can be sent, and what happens when
+    /// more are waiting.
+    pub const fn with_send_buffer(
+        mut self,
+        capacity: usize,
+        overflow: OverflowPolicy,
+    ) -> Self {
+        self.send_buffer = if capacity == 0 { 1 } else { capacity };
+        self.overflow = overflow;
+        self
+    }
+
+    /// Calls `hook` each time the connection has been reopened, with the number of times it
+    /// has been reopened so far.
+    ///
+    /// The messages the hook sends with the [`ReconnectSender`] are sent on the new connection
+    /// before any buffered messages, and never count towards the send buffer.
+    pub fn on_reconnect(
+        mut self,
+        hook: impl Fn(u32, &mut ReconnectSender) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_reconnect = Some(Arc::new(hook));
+        self
+    }
+
+    /// The interval and timeout of heartbeats, if they are enabled.
+    pub const fn heartbeat(&self) -> Option<(Duration, Duration)> {
+        self.heartbeat
+    }
+
+    /// The policy for reopening lost connections, if any.
+    pub const fn reconnect(&self) -> Option<ReconnectPolicy> {
+        self.reconnect
+    }
+}
+
+/// Sends messages on a reopened connection, from the
+/// [`on_reconnect`](WebsocketConfig::on_reconnect) hook.
+#[derive(Debug, Default)]
+pub struct ReconnectSender {
+    frames: Vec<Bytes>,
+}
+
+impl ReconnectSender {
+    /// Sends `message`, encoded with `Encoding`, which should be the input encoding of the
+    /// server function.
+    pub fn send<Encoding, T>(
+        &mut self,
+        message: &T,
+    ) -> Result<(), Encoding::Error>
+    where
+        Encoding: Encodes<T>,
+    {
+        let message = Encoding::encode(message)?;
+        self.frames.push(serialize_result(Ok(message)));
+        Ok(())
+    }
+}
+
+/// How often, and how quickly, a lost websocket connection is reopened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectPolicy {
+    max_attempts: Option<u32>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReconnectPolicy {
+    /// Keeps trying to reconnect, backing off exponentially from 250ms up to 10s, with
+    /// jitter.
+    pub const fn new() -> Self {
+        Self {
+            max_attempts: None,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+        }
+    }
+
+    /// Gives up after `max_attempts` failed attempts in a row.
+    pub const fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    /// Sets the delay before the first attempt, and the maximum delay between attempts.
+    pub const fn with_backoff(
+        mut self,
+        initial: Duration,
+        max: Duration,
+    ) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Sets whether the delay between attempts is randomized. Defaults to `true`.
+    pub const fn with_jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Whether another attempt is made after `attempt` attempts have failed.
+    pub fn can_reconnect(&self, attempt: u32) -> bool {
+        self.max_attempts.is_none_or(|max| attempt < max)
+    }
+
+    /// The delay before the next attempt, after `attempt` attempts have failed.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << attempt.min(31))
+            .min(self.max_backoff);
+        if self.jitter {
+            backoff / 2 + backoff.mul_f64(random::fraction() / 2.0)
+        } else {
+            backoff
+        }
+    }
+}
+
+/// What happens when a message is sent while the send buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// The oldest message in the buffer is dropped to make room.
+    DropOldest,
+    /// The input stream is not read until there is room. This is the default.
+    #[default]
+    Block,
+    /// The connection is closed, and the output stream yields a
+    /// [`ServerFnErrorErr::Request`] error.
+    Error,
+}
+
+/// Sets the websocket configuration used for all connections that are not opened within
+/// [`with_websocket_config`], or by a server function with its own configuration.
+pub fn set_websocket_config(config: WebsocketConfig) {
+    *GLOBAL_CONFIG
+        .write()
+        .unwrap_or_else(PoisonError::into_inner) = Some(config);
+}
+
+/// Returns the websocket configuration that applies to connections opened from the current
+/// context.
+pub fn websocket_config() -> WebsocketConfig {
+    SCOPED_CONFIG
+        .with_borrow(Clone::clone)
+        .or_else(|| {
+            GLOBAL_CONFIG
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        })
+        .unwrap_or_default()
+}
+
+/// Runs the future (usually a websocket server function call) with the given configuration.
+pub fn with_websocket_config<F: Future>(
+    config: WebsocketConfig,
+    fut: F,
+) -> WithWebsocketConfig<F> {
+    WithWebsocketConfig {
+        config: Some(config),
+        overrides: true,
+        inner: fut,
+    }
+}
+
+/// Runs the future with the given configuration, unless a configuration has already been set
+/// for the surrounding call.
+pub(crate) fn with_default_websocket_config<F: Future>(
+    config: Option<WebsocketConfig>,
+    fut: F,
+) -> WithWebsocketConfig<F> {
+    WithWebsocketConfig {
+        config,
+        overrides: false,
+        inner: fut,
+    }
+}
+
+pin_project! {
+    /// A future that runs with a [`WebsocketConfig`]. Created by [`with_websocket_config`].
+    pub struct WithWebsocketConfig<F> {
+        config: Option<WebsocketConfig>,
+        overrides: bool,
+        #[pin]
+        inner: F,
+    }
+}
+
+impl<F: Future> Future for WithWebsocketConfig<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        struct Restore(Option<WebsocketConfig>);
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let prev = self.0.take();
+                SCOPED_CONFIG.with(|scoped| *scoped.borrow_mut() = prev);
+            }
+        }
+
+        let this = self.project();
+        let prev = SCOPED_CONFIG.with_borrow(Clone::clone);
+        let config = if *this.overrides || prev.is_none() {
+            this.config.clone()
+        } else {
+            prev.clone()
+        };
+        SCOPED_CONFIG.with(|scoped| *scoped.borrow_mut() = config);
+        let _restore = Restore(prev);
+        this.inner.poll(cx)
+    }
+}
+
+/// Opens a websocket connection that sends the frames of `input`, and returns a stream of the
+/// frames received, kept alive according to the current [`WebsocketConfig`].
+pub(crate) async fn connect<C, E, IS, OS>(
+    path: &str,
+    input: impl Stream<Item = Bytes> + Send + 'static,
+) -> Result<impl Stream<Item = Result<Bytes, Bytes>> + Send + 'static, E>
+where
+    C: Client<E, IS, OS> + 'static,
+    E: Send + 'static,
+    IS: Send + 'static,
+    OS: FromServerFnError + Send + 'static,
+{
+    let config = websocket_config();
+    let socket = open::<C, E, IS, OS>(path.to_string()).await?;
+    let (output, received) = mpsc::channel(OUTPUT_BUFFER);
+    let heartbeat = config.heartbeat.map(|(interval, timeout)| {
+        Heartbeat::new::<C, E, IS, OS>(interval, timeout)
+    });
+    C::spawn(Driver::<C, E, IS, OS> {
+        path: path.to_string(),
+        input: Some(input.boxed()),
+        buffer: VecDeque::new(),
+        control: VecDeque::new(),
+        state: State::Open(socket),
+        heartbeat,
+        output,
+        outbox: VecDeque::new(),
+        reconnections: 0,
+        config,
+        ty: PhantomData,
+    });
+    Ok(received)
+}
+
+type Timer = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct Socket {
+    stream: BoxStream<'static, Result<Bytes, Bytes>>,
+    sink: Pin<Box<dyn Sink<Bytes, Error = ()> + Send>>,
+}
+
+async fn open<C, E, IS, OS>(path: String) -> Result<Socket, E>
+where
+    C: Client<E, IS, OS>,
+{
+    let (stream, sink) = C::open_websocket(&path).await?;
+    Ok(Socket {
+        stream: stream.boxed(),
+        sink: Box::pin(sink.sink_map_err(|_| ())),
+    })
+}
+
+enum State {
+    Open(Socket),
+    Reconnecting {
+        attempt: u32,
+        // the error that closed the connection, reported if it can't be reopened
+        error: Bytes,
+        connecting: Pin<Box<dyn Future<Output = Reopened> + Send>>,
+    },
+    Closed,
+}
+
+enum Reopened {
+    Open(Socket),
+    Failed,
+    // the client's timer does not wait, so further attempts would retry in a busy loop
+    Unsupported,
+}
+
+struct Heartbeat {
+    interval: Duration,
+    timeout: Duration,
+    sleep: fn(Duration) -> Timer,
+    ping: Timer,
+    // set while waiting for any message after a ping
+    deadline: Option<Timer>,
+}
+
+enum Beat {
+    Ping,
+    TimedOut,
+    // the client's timer does not wait
+    Unsupported,
+}
+
+impl Heartbeat {
+    fn new<C, E, IS, OS>(interval: Duration, timeout: Duration) -> Self
+    where
+        C: Client<E, IS, OS> + 'static,
+        E: 'static,
+        IS: 'static,
+        OS: 'static,
+    {
+        let sleep: fn(Duration) -> Timer =
+            |duration| Box::pin(C::sleep(duration));
+        Self {
+            interval,
+            timeout,
+            sleep,
+            ping: sleep(interval),
+            deadline: None,
+        }
+    }
+
+    fn received(&mut self) {
+        self.deadline = None;
+    }
+
+    fn poll(&mut self, cx: &mut Context<'_>) -> Option<Beat> {
+        if let Some(deadline) = &mut self.deadline {
+            if deadline.as_mut().poll(cx).is_ready() {
+                return Some(Beat::TimedOut);
+            }
+        }
+        if self.ping.as_mut().poll(cx).is_pending() {
+            return None;
+        }
+        self.ping = (self.sleep)(self.interval);
+        if self.ping.as_mut().poll(cx).is_ready() {
+            return Some(Beat::Unsupported);
+        }
+        if self.deadline.is_none() {
+            let mut deadline = (self.sleep)(self.timeout);
+            _ = deadline.as_mut().poll(cx);
+            self.deadline = Some(deadline);
+        }
+        Some(Beat::Ping)
+    }
+}
+
+// Drives a connection in the background, moving messages between the input stream, the
+// socket and the output stream.
+struct Driver<C, E, IS, OS> {
+    path: String,
+    config: WebsocketConfig,
+    input: Option<BoxStream<'static, Bytes>>,
+    buffer: VecDeque<Bytes>,
+    // heartbeats and the messages sent by the reconnect hook, which are sent before the
+    // buffered messages and are never dropped to make room for them
+    control: VecDeque<Bytes>,
+    state: State,
+    heartbeat: Option<Heartbeat>,
+    output: mpsc::Sender<Result<Bytes, Bytes>>,
+    // received messages (or a final error) waiting for room in the output stream
+    outbox: VecDeque<Result<Bytes, Bytes>>,
+    reconnections: u32,
+    #[allow(clippy::type_complexity)]
+    ty: PhantomData<fn() -> (C, E, IS, OS)>,
+}
+
+impl<C, E, IS, OS> Driver<C, E, IS, OS>
+where
+    C: Client<E, IS, OS> + 'static,
+    E: Send + 'static,
+    IS: Send + 'static,
+    OS: FromServerFnError + 'static,
+{
+    fn poll_input(&mut self, cx: &mut Context<'_>) -> bool {
+        let mut progress = false;
+        while let Some(input) = &mut self.input {
+            let full = self.buffer.len() >= self.config.send_buffer;
+            if full && self.config.overflow == OverflowPolicy::Block {
+                break;
+            }
+            match input.poll_next_unpin(cx) {
+                Poll::Ready(Some(frame)) => {
+                    progress = true;
+                    if full {
+                        match self.config.overflow {
+                            OverflowPolicy::DropOldest => {
+                                self.buffer.pop_front();
+                            }
+                            _ => {
+                                let err = OS::from_server_fn_error(
+                                    ServerFnErrorErr::Request(
+                                        "the websocket send buffer is full"
+                                            .into(),
+                                    ),
+                                );
+                                self.close(Some(err.ser()));
+                                break;
+                            }
+                        }
+                    }
+                    self.buffer.push_back(frame);
+                }
+                Poll::Ready(None) => {
+                    progress = true;
+                    self.input = None;
+                }
+                Poll::Pending => break,
+            }
+        }
+        progress
+    }
+
+    fn poll_socket(&mut self, cx: &mut Context<'_>) -> bool {
+        let State::Open(socket) = &mut self.state else {
+            return false;
+        };
+        let mut progress = false;
+        let mut lost = None;
+        let mut closed = false;
+
+        // send buffered messages
+        let mut sent = Ok(());
+        while !self.control.is_empty() || !self.buffer.is_empty() {
+            match socket.sink.as_mut().poll_ready(cx) {
+                Poll::Ready(Ok(())) => {
+                    progress = true;
+                    let frame = self
+                        .control
+                        .pop_front()
+                        .or_else(|| self.buffer.pop_front())
+                        .unwrap();
+                    sent = socket.sink.as_mut().start_send(frame);
+                    if sent.is_err() {
+                        break;
+                    }
+                }
+                Poll::Ready(Err(())) => {
+                    sent = Err(());
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+        if sent.is_ok() {
+            if let Poll::Ready(Err(())) = socket.sink.as_mut().poll_flush(cx) {
+                sent = Err(());
+            }
+        }
+        if sent.is_err() {
+            lost = Some(closed_error::<OS>(
+                ABNORMAL_CLOSURE,
+                "the connection was lost",
+            ));
+        }
+
+        // receive messages, as long as the output stream has room for them
+        while lost.is_none() && self.outbox.is_empty() {
+            match socket.stream.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(frame))) => {
+                    progress = true;
+                    if let Some(heartbeat) = &mut self.heartbeat {
+                        heartbeat.received();
+                    }
+                    if frame.first() != Some(&PONG) {
+                        self.outbox.push_back(Ok(frame));
+                    }
+                }
+                Poll::Ready(Some(Err(err))) => lost = Some(err),
+                Poll::Ready(None) => {
+                    closed = true;
+                    break;
+                }
+                Poll::Pending => break,
+            }
+        }
+
+        if lost.is_none() && !closed {
+            match self.heartbeat.as_mut().and_then(|h| h.poll(cx)) {
+                Some(Beat::Ping) => {
+                    progress = true;
+                    // one unsent ping is enough
+                    if !self.control.iter().any(|frame| frame[..] == [PING]) {
+                        self.control.push_back(Bytes::from_static(&[PING]));
+                    }
+                }
+                Some(Beat::TimedOut) => {
+                    lost = Some(closed_error::<OS>(
+                        ABNORMAL_CLOSURE,
+                        "no heartbeat was received",
+                    ))
+                }
+                Some(Beat::Unsupported) => self.heartbeat = None,
+                None => {}
+            }
+        }
+
+        if let Some(err) = lost {
+            self.disconnect(err);
+            progress = true;
+        } else if closed {
+            self.close(None);
+            progress = true;
+        }
+        progress
+    }
+
+    fn poll_reconnect(&mut self, cx: &mut Context<'_>) -> bool {
+        let State::Reconnecting {
+            attempt,
+            error,
+            connecting,
+        } = &mut self.state
+        else {
+            return false;
+        };
+        match connecting.as_mut().poll(cx) {
+            Poll::Ready(Reopened::Open(socket)) => {
+                self.state = State::Open(socket);
+                self.reconnections += 1;
+                self.heartbeat =
+                    self.config.heartbeat.map(|(interval, timeout)| {
+                        Heartbeat::new::<C, E, IS, OS>(interval, timeout)
+                    });
+                if let Some(hook) = &self.config.on_reconnect {
+                    let mut sender = ReconnectSender::default();
+                    hook(self.reconnections, &mut sender);
+                    self.control.extend(sender.frames);
+                }
+            }
+            Poll::Ready(Reopened::Unsupported) => {
+                let error = std::mem::take(error);
+                self.close(Some(error));
+            }
+            Poll::Ready(Reopened::Failed) => {
+                let attempt = *attempt + 1;
+                let error = std::mem::take(error);
+                match self.config.reconnect {
+                    Some(policy) if policy.can_reconnect(attempt) => {
+                        self.reconnect(attempt, policy.delay(attempt), error)
+                    }
+                    _ => self.close(Some(error)),
+                }
+            }
+            Poll::Pending => return false,
+        }
+        true
+    }
+
+    fn disconnect(&mut self, error: Bytes) {
+        // heartbeats and resumed messages only make sense on the connection they were meant for
+        self.control.clear();
+        match self.config.reconnect {
+            Some(policy) if policy.can_reconnect(0) => {
+                self.reconnect(0, policy.delay(0), error)
+            }
+            _ => self.close(Some(error)),
+        }
+    }
+
+    fn reconnect(&mut self, attempt: u32, delay: Duration, error: Bytes) {
+        let path = self.path.clone();
+        self.state = State::Reconnecting {
+            attempt,
+            error,
+            connecting: Box::pin(async move {
+                if !waited(delay, C::sleep(delay)).await {
+                    return Reopened::Unsupported;
+                }
+                match open::<C, E, IS, OS>(path).await {
+                    Ok(socket) => Reopened::Open(socket),
+                    Err(_) => Reopened::Failed,
+                }
+            }),
+        };
+    }
+
+    fn close(&mut self, error: Option<Bytes>) {
+        self.state = State::Closed;
+        self.input = None;
+        self.outbox.extend(error.map(Err));
+    }
+}
+
+impl<C, E, IS, OS> Future for Driver<C, E, IS, OS>
+where
+    C: Client<E, IS, OS> + 'static,
+    E: Send + 'static,
+    IS: Send + 'static,
+    OS: FromServerFnError + 'static,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        loop {
+            while !this.outbox.is_empty() {
+                match this.output.poll_ready(cx) {
+                    Poll::Ready(Ok(())) => {
+                        let item = this.outbox.pop_front().unwrap();
+                        if this.output.start_send(item).is_err() {
+                            return Poll::Ready(());
+                        }
+                    }
+                    // the output stream has been dropped
+                    Poll::Ready(Err(_)) => return Poll::Ready(()),
+                    Poll::Pending => break,
+                }
+            }
+            if this.output.is_closed()
+                || (matches!(this.state, State::Closed)
+                    && this.outbox.is_empty())
+            {
+                return Poll::Ready(());
+            }
+
+            let mut progress = this.poll_input(cx);
+            progress |= this.poll_socket(cx);
+            progress |= this.poll_reconnect(cx);
+            if !progress {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+/// A [`ServerFnErrorErr::WebsocketClosed`] error, encoded as `E`.
+pub(crate) fn closed_error<E: FromServerFnError>(
+    code: u16,
+    reason: impl Into<String>,
+) -> Bytes {
+    E::from_server_fn_error(ServerFnErrorErr::WebsocketClosed {
+        code,
+        reason: reason.into(),
+    })
+    .ser()
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::{
+        codec::JsonEncoding,
+        error::IntoAppError,
+        testing::{TestRequest, TestResponse},
+        ServerFnError,
+    };
+    use futures::executor::block_on;
+    use std::{collections::HashMap, sync::Mutex};
+
+    type Connection = (
+        mpsc::UnboundedReceiver<Result<Bytes, Bytes>>,
+        mpsc::Sender<Bytes>,
+    );
+
+    static CONNECTIONS: Mutex<Option<HashMap<String, VecDeque<Connection>>>> =
+        Mutex::new(None);
+
+    // lets the next connection to `path` be opened, and returns the server's side of it
+    fn accept(
+        path: &str,
+    ) -> (
+        mpsc::UnboundedSender<Result<Bytes, Bytes>>,
+        mpsc::Receiver<Bytes>,
+    ) {
+        let (to_client, received) = mpsc::unbounded();
+        let (sent, from_client) = mpsc::channel(0);
+        CONNECTIONS
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .entry(path.to_string())
+            .or_default()
+            .push_back((received, sent));
+        (to_client, from_client)
+    }
+
+    struct FakeClient;
+
+    impl<E: FromServerFnError, IS, OS> Client<E, IS, OS> for FakeClient {
+        type Request = TestRequest;
+        type Response = TestResponse;
+
+        async fn send(_req: TestRequest) -> Result<TestResponse, E> {
+            unreachable!()
+        }
+
+        async fn open_websocket(
+            path: &str,
+        ) -> Result<
+            (
+                impl Stream<Item = Result<Bytes, Bytes>> + Send + 'static,
+                impl Sink<Bytes> + Send + 'static,
+            ),
+            E,
+        > {
+            CONNECTIONS
+                .lock()
+                .unwrap()
+                .get_or_insert_with(HashMap::new)
+                .get_mut(path)
+                .and_then(VecDeque::pop_front)
+                .ok_or_else(|| {
+                    ServerFnErrorErr::Request("connection refused".into())
+                        .into_app_error()
+                })
+        }
+
+        fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+            std::thread::spawn(move || block_on(future));
+        }
+    }
+
+    // the fake client, with a timer
+    struct TimerClient;
+
+    impl<E: FromServerFnError, IS, OS> Client<E, IS, OS> for TimerClient {
+        type Request = TestRequest;
+        type Response = TestResponse;
+
+        async fn send(_req: TestRequest) -> Result<TestResponse, E> {
+            unreachable!()
+        }
+
+        async fn open_websocket(
+            path: &str,
+        ) -> Result<
+            (
+                impl Stream<Item = Result<Bytes, Bytes>> + Send + 'static,
+                impl Sink<Bytes> + Send + 'static,
+            ),
+            E,
+        > {
+            <FakeClient as Client<E, IS, OS>>::open_websocket(path).await
+        }
+
+        fn spawn(future: impl Future<Output = ()> + Send + 'static) {
+            std::thread::spawn(move || block_on(future));
+        }
+
+        fn sleep(duration: Duration) -> impl Future<Output = ()> + Send {
+            let (done, wait) = futures::channel::oneshot::channel();
+            std::thread::spawn(move || {
+                std::thread::sleep(duration);
+                _ = done.send(());
+            });
+            async move { _ = wait.await }
+        }
+    }
+
+    async fn open(
+        path: &str,
+        config: WebsocketConfig,
+        input: mpsc::UnboundedReceiver<Bytes>,
+    ) -> impl Stream<Item = Result<Bytes, Bytes>> {
+        with_websocket_config(
+            config,
+            connect::<FakeClient, ServerFnError, ServerFnError, ServerFnError>(
+                path, input,
+            ),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn scoped_config_overrides_server_fn_config() {
+        block_on(async {
+            let reconnect = || websocket_config().reconnect().is_some();
+            let server_fn = Some(
+                WebsocketConfig::new().with_reconnect(ReconnectPolicy::new()),
+            );
+            let configured =
+                with_default_websocket_config(server_fn.clone(), async {
+                    reconnect()
+                });
+            assert!(configured.await);
+            let overridden = with_websocket_config(
+                WebsocketConfig::new(),
+                with_default_websocket_config(server_fn, async { reconnect() }),
+            );
+            assert!(!overridden.await);
+        });
+    }
+
+    #[test]
+    fn reconnect_delays_are_spread_out() {
+        let policy = ReconnectPolicy::new()
+            .with_backoff(Duration::from_secs(1), Duration::from_secs(1));
+        let delays = [policy.delay(0), policy.delay(0)];
+        assert_ne!(delays[0], delays[1]);
+        assert!(delays.iter().all(|delay| {
+            (Duration::from_millis(500)..=Duration::from_secs(1))
+                .contains(delay)
+        }));
+        let policy = policy.with_jitter(false);
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn reconnects_and_keeps_sending() {
+        block_on(async {
+            let (server, mut sent) = accept("/reconnect");
+            let (next_server, mut next_sent) = accept("/reconnect");
+            let (reconnected_tx, mut reconnected) = mpsc::unbounded();
+            let config = WebsocketConfig::new()
+                .with_reconnect(
+                    ReconnectPolicy::new()
+                        .with_backoff(Duration::ZERO, Duration::ZERO),
+                )
+                .on_reconnect(move |n, sender| {
+                    sender.send::<JsonEncoding, _>(&"resume").unwrap();
+                    _ = reconnected_tx.unbounded_send(n);
+                });
+            let (input, frames) = mpsc::unbounded();
+            let mut output = open("/reconnect", config, frames).await;
+
+            input.unbounded_send(Bytes::from_static(b"a")).unwrap();
+            assert_eq!(sent.next().await.unwrap(), "a");
+            server.unbounded_send(Ok(Bytes::from_static(b"x"))).unwrap();
+            assert_eq!(output.next().await.unwrap().unwrap(), "x");
+
+            // the connection is lost, and reopened
+            let lost = closed_error::<ServerFnError>(ABNORMAL_CLOSURE, "lost");
+            server.unbounded_send(Err(lost)).unwrap();
+            assert_eq!(reconnected.next().await, Some(1));
+
+            // the hook's messages are sent first
+            input.unbounded_send(Bytes::from_static(b"b")).unwrap();
+            assert_eq!(next_sent.next().await.unwrap(), "\0\"resume\"");
+            assert_eq!(next_sent.next().await.unwrap(), "b");
+            next_server
+                .unbounded_send(Ok(Bytes::from_static(b"y")))
+                .unwrap();
+            assert_eq!(output.next().await.unwrap().unwrap(), "y");
+
+            // a normal closure ends the stream
+            drop(next_server);
+            assert!(output.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn does_not_reconnect_without_a_timer() {
+        block_on(async {
+            let (server, _sent) = accept("/no-timer");
+            // the fake client's timer does not wait, so a delay can't be honored
+            let config = WebsocketConfig::new().with_reconnect(
+                ReconnectPolicy::new().with_backoff(
+                    Duration::from_secs(1),
+                    Duration::from_secs(1),
+                ),
+            );
+            let (_input, frames) = mpsc::unbounded();
+            let mut output = open("/no-timer", config, frames).await;
+
+            let lost = closed_error::<ServerFnError>(ABNORMAL_CLOSURE, "lost");
+            server.unbounded_send(Err(lost)).unwrap();
+            let err = output.next().await.unwrap().unwrap_err();
+            assert_eq!(
+                <ServerFnError>::de(err),
+                ServerFnError::WebsocketClosed {
+                    code: ABNORMAL_CLOSURE,
+                    reason: "lost".into()
+                }
+            );
+            assert!(output.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn reports_close_reasons() {
+        block_on(async {
+            let (server, _sent) = accept("/close");
+            let (_input, frames) = mpsc::unbounded();
+            let mut output =
+                open("/close", WebsocketConfig::new(), frames).await;
+
+            let closed = closed_error::<ServerFnError>(4000, "going away");
+            server.unbounded_send(Err(closed)).unwrap();
+            let err = output.next().await.unwrap().unwrap_err();
+            assert_eq!(
+                <ServerFnError>::de(err),
+                ServerFnError::WebsocketClosed {
+                    code: 4000,
+                    reason: "going away".into()
+                }
+            );
+            assert!(output.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn applies_overflow_policy() {
+        block_on(async {
+            let frames = |n: u8| {
+                let (input, frames) = mpsc::unbounded();
+                for i in 1..=n {
+                    input.unbounded_send(Bytes::from(vec![i])).unwrap();
+                }
+                frames
+            };
+
+            // nothing is sent until the buffer is full, so only the newest frames are kept
+            let (_server, mut sent) = accept("/drop-oldest");
+            let config = WebsocketConfig::new()
+                .with_send_buffer(2, OverflowPolicy::DropOldest);
+            let _output = open("/drop-oldest", config, frames(5)).await;
+            assert_eq!(sent.next().await.unwrap(), [4].as_slice());
+            assert_eq!(sent.next().await.unwrap(), [5].as_slice());
+
+            // heartbeats are not dropped to make room for messages
+            let (_server, mut sent) = accept("/ping");
+            let config = WebsocketConfig::new()
+                .with_heartbeat(
+                    Duration::from_millis(20),
+                    Duration::from_secs(10),
+                )
+                .with_send_buffer(1, OverflowPolicy::DropOldest);
+            let (input, pending) = mpsc::unbounded();
+            let _output = with_websocket_config(
+                config,
+                connect::<
+                    TimerClient,
+                    ServerFnError,
+                    ServerFnError,
+                    ServerFnError,
+                >("/ping", pending),
+            )
+            .await
+            .unwrap();
+            // the first message waits in the socket, so the ping is queued behind it
+            input.unbounded_send(Bytes::from_static(b"a")).unwrap();
+            std::thread::sleep(Duration::from_millis(100));
+            input.unbounded_send(Bytes::from_static(b"b")).unwrap();
+            input.unbounded_send(Bytes::from_static(b"c")).unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+            assert_eq!(sent.next().await.unwrap(), "a");
+            assert_eq!(sent.next().await.unwrap(), [PING].as_slice());
+            assert_eq!(sent.next().await.unwrap(), "c");
+
+            let (_server, _sent) = accept("/error");
+            let config = WebsocketConfig::new()
+                .with_send_buffer(2, OverflowPolicy::Error);
+            let mut output = open("/error", config, frames(3)).await;
+            let err = output.next().await.unwrap().unwrap_err();
+            assert!(matches!(
+                <ServerFnError>::de(err),
+                ServerFnError::Request(_)
+            ));
+            assert!(output.next().await.is_none());
+        });
+    }
+}
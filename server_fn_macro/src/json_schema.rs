@@ -0,0 +1,155 @@
+//! Describes the arguments of server functions for the OpenAPI document.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse::Parser, punctuated::Punctuated, Attribute, Expr, ExprLit,
+    GenericArgument, Lit, Meta, PathArguments, Result, Token, Type,
+};
+
+/// Describes the arguments of a server function as an object with one property per argument.
+///
+/// Argument types that do not implement `JsonSchema` are described with an empty schema.
+pub(crate) fn arguments_schema<'a>(
+    openapi: &TokenStream2,
+    args: impl IntoIterator<Item = (&'a [Attribute], String, &'a Type)>,
+) -> Result<TokenStream2> {
+    let mut properties = Vec::new();
+    let mut flattened = Vec::new();
+    for (attrs, name, ty) in args {
+        let field = FieldAttrs::parse(attrs)?;
+        if field.skip {
+            continue;
+        }
+        let schema =
+            quote! { (&#openapi::SchemaOf::<#ty>::new()).schema(generator) };
+        if field.flatten {
+            flattened.push(quote! {
+                let flattened = #schema;
+                #openapi::__private::flatten_into(
+                    generator,
+                    &mut schema,
+                    flattened,
+                );
+            });
+            continue;
+        }
+        let name = field.rename.unwrap_or(name);
+        let required = !(field.default || is_option(ty));
+        let schema = match docs(attrs) {
+            Some(docs) => quote! {
+                #openapi::__private::with_description(#schema, #docs)
+            },
+            None => schema,
+        };
+        properties.push(quote! { (#name, #schema, #required) });
+    }
+    let mutability = (!flattened.is_empty()).then(|| quote! { mut });
+    Ok(quote! {{
+        let #mutability schema = #openapi::object_schema([#(#properties),*]);
+        #(#flattened)*
+        schema
+    }})
+}
+
+/// The documentation comments in the attributes, if any.
+pub(crate) fn docs(attrs: &[Attribute]) -> Option<String> {
+    let docs = attrs
+        .iter()
+        .filter_map(|attr| {
+            let Meta::NameValue(attr) = &attr.meta else {
+                return None;
+            };
+            if !attr.path.is_ident("doc") {
+                return None;
+            }
+            match &attr.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(doc), ..
+                }) => Some(doc.value()),
+                _ => None,
+            }
+        })
+        .map(|line| line.strip_prefix(' ').map(str::to_string).unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let docs = docs.trim();
+    (!docs.is_empty()).then(|| docs.to_string())
+}
+
+/// Whether the type is an `Option`, which `serde` allows to be missing.
+pub(crate) fn is_option(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+    path.path.segments.last().is_some_and(|segment| {
+        segment.ident == "Option"
+            && matches!(
+                &segment.arguments,
+                PathArguments::AngleBracketed(args)
+                    if matches!(args.args.first(), Some(GenericArgument::Type(_)))
+            )
+    })
+}
+
+/// Iterates over the items in `#[serde(...)]` attributes.
+fn serde_metas(attrs: &[Attribute]) -> Result<Vec<Meta>> {
+    let mut metas = Vec::new();
+    for attr in attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+        let Meta::List(list) = &attr.meta else {
+            continue;
+        };
+        let parsed = Punctuated::<Meta, Token![,]>::parse_terminated
+            .parse2(list.tokens.clone())?;
+        metas.extend(parsed);
+    }
+    Ok(metas)
+}
+
+fn lit_str(meta: &Meta) -> Option<syn::LitStr> {
+    match meta {
+        Meta::NameValue(meta) => match &meta.value {
+            Expr::Lit(ExprLit {
+                lit: Lit::Str(value),
+                ..
+            }) => Some(value.clone()),
+            _ => None,
+        },
+        // `rename(serialize = "...", deserialize = "...")` is not supported
+        _ => None,
+    }
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    rename: Option<String>,
+    default: bool,
+    skip: bool,
+    flatten: bool,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut this = Self::default();
+        for meta in serde_metas(attrs)? {
+            let path = meta.path();
+            if path.is_ident("rename") {
+                this.rename = lit_str(&meta).map(|value| value.value());
+            } else if path.is_ident("default")
+                || path.is_ident("skip_serializing_if")
+            {
+                this.default = true;
+            } else if path.is_ident("skip")
+                || path.is_ident("skip_deserializing")
+            {
+                this.skip = true;
+            } else if path.is_ident("flatten") {
+                this.flatten = true;
+            }
+        }
+        Ok(this)
+    }
+}
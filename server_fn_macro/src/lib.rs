@@ -6,6 +6,8 @@
 //!
 //! This crate contains the implementation of the `server_fn` macro. [`server_macro_impl`] can be used to implement custom versions of the macro for different frameworks that allow users to pass a custom context from the server to the server function.
 
+mod json_schema;
+
 use convert_case::{Case, Converter};
 use proc_macro2::{Span, TokenStream as TokenStream2};
 use quote::{format_ident, quote, quote_spanned, ToTokens};
@@ -140,7 +142,7 @@ impl ServerFnCall {
     /// Get the client type to use for the server function.
     pub fn client_type(&self) -> Type {
         let server_fn_path = self.server_fn_path();
-        if let Some(client) = self.args.client.clone() {
+        let client = if let Some(client) = self.args.client.clone() {
             client
         } else if cfg!(feature = "reqwest") {
             parse_quote! {
@@ -150,6 +152,14 @@ impl ServerFnCall {
             parse_quote! {
                 #server_fn_path::client::browser::BrowserClient
             }
+        };
+        let batch = self.args.batch.as_ref().map(|v| v.value).unwrap_or(false);
+        if batch {
+            parse_quote! {
+                #server_fn_path::client::batch::Batched<#client>
+            }
+        } else {
+            client
         }
     }
 
@@ -288,6 +298,17 @@ impl ServerFnCall {
         }
     }
 
+    fn sse_protocol(&self) -> bool {
+        if let Type::Path(path) = self.protocol() {
+            path.path
+                .segments
+                .iter()
+                .any(|segment| segment.ident == "Sse")
+        } else {
+            false
+        }
+    }
+
     fn serde_path(&self) -> String {
         let path = self
             .server_fn_path()
@@ -343,7 +364,11 @@ impl ServerFnCall {
             ),
             Some("MultipartFormData")
             | Some("Streaming")
-            | Some("StreamingText") => (PathInfo::None, quote! {}),
+            | Some("StreamingText")
+            | Some("StreamingJson")
+            | Some("StreamingCbor")
+            | Some("StreamingPostcard")
+            | Some("Framed") => (PathInfo::None, quote! {}),
             Some("SerdeLite") => (
                 PathInfo::Serde,
                 quote! {
@@ -512,11 +537,18 @@ impl ServerFnCall {
             quote! { "" }
         };
 
+        // an explicit version is added as a path segment after the prefix
+        let version = match &self.args.version {
+            Some(version) => format!("/v{}", version.base10_digits()),
+            None => String::new(),
+        };
+
         let fn_name_as_str = self.fn_name_as_str();
         if let Some(fn_path) = fn_path {
             quote! {
                 #server_fn_path::const_format::concatcp!(
                     #prefix,
+                    #version,
                     #mod_path,
                     #fn_path
                 )
@@ -525,6 +557,7 @@ impl ServerFnCall {
             quote! {
                 #server_fn_path::const_format::concatcp!(
                     #prefix,
+                    #version,
                     "/",
                     #mod_path,
                     #fn_name_as_str,
@@ -579,15 +612,16 @@ impl ServerFnCall {
         } else {
             error_ty.clone()
         };
-        let error_ws_out_ty = if self.websocket_protocol() {
-            self.body
-                .error_ws_out_ty
-                .as_ref()
-                .map(ToTokens::to_token_stream)
-                .unwrap_or(error_ty.clone())
-        } else {
-            error_ty.clone()
-        };
+        let error_ws_out_ty =
+            if self.websocket_protocol() || self.sse_protocol() {
+                self.body
+                    .error_ws_out_ty
+                    .as_ref()
+                    .map(ToTokens::to_token_stream)
+                    .unwrap_or(error_ty.clone())
+            } else {
+                error_ty.clone()
+            };
         let field_names = self.field_names();
 
         // run_body in the trait implementation
@@ -642,16 +676,28 @@ impl ServerFnCall {
         let path = self.server_fn_url();
 
         let middlewares = if cfg!(feature = "ssr") {
+            let cache = self.cache_middleware();
+            let limits = self.limit_middlewares();
             quote! {
                 vec![
                     #(
-                        std::sync::Arc::new(#middlewares)
-                    ),*
+                        std::sync::Arc::new(#middlewares),
+                    )*
+                    #cache
+                    #limits
                 ]
             }
         } else {
             quote! { vec![] }
         };
+        let guard = if cfg!(feature = "ssr") {
+            self.guard()
+        } else {
+            None
+        };
+        let retry_policy = self.retry_policy();
+        let websocket_config = self.websocket_config();
+        let openapi_schema = self.openapi_schema(&output_ty, &error_ty);
         let wrapped_struct_name = self.wrapped_struct_name();
 
         quote! {
@@ -670,11 +716,174 @@ impl ServerFnCall {
                     #middlewares
                 }
 
+                #guard
+
+                #retry_policy
+
+                #websocket_config
+
+                #openapi_schema
+
                 #run_body
             }
         }
     }
 
+    /// The caching middleware, if a `cache` policy was given in the macro arguments.
+    ///
+    /// An integer literal is shorthand for a `max-age` in seconds.
+    fn cache_middleware(&self) -> Option<TokenStream2> {
+        let server_fn_path = self.server_fn_path();
+        let policy = match self.args.cache.as_ref()? {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(seconds),
+                ..
+            }) => quote! {
+                #server_fn_path::middleware::cache::CachePolicy::max_age(#seconds)
+            },
+            policy => quote! { #policy },
+        };
+        Some(quote! {
+            std::sync::Arc::new(
+                #server_fn_path::middleware::cache::ServerFnCache::new(#policy)
+            ),
+        })
+    }
+
+    /// The concurrency and rate limiting middleware, if `max_concurrency` or `rate_limit`
+    /// were given in the macro arguments.
+    ///
+    /// The rate limit is applied last, so that requests it rejects never count towards the
+    /// concurrency limit. An integer literal is shorthand for a number of requests per second.
+    fn limit_middlewares(&self) -> TokenStream2 {
+        let server_fn_path = self.server_fn_path();
+        let concurrency = self.args.max_concurrency.as_ref().map(|max| {
+            quote! {
+                std::sync::Arc::new(
+                    #server_fn_path::middleware::limit::ServerFnConcurrencyLimit::new(#max)
+                ),
+            }
+        });
+        let rate_limit = self.args.rate_limit.as_ref().map(|limit| {
+            let limit = match limit {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Int(requests),
+                    ..
+                }) => quote! {
+                    #server_fn_path::middleware::limit::RateLimit::per_second(#requests)
+                },
+                limit => quote! { #limit },
+            };
+            quote! {
+                std::sync::Arc::new(
+                    #server_fn_path::middleware::limit::ServerFnRateLimit::new(#limit)
+                ),
+            }
+        });
+        quote! {
+            #concurrency
+            #rate_limit
+        }
+    }
+
+    /// Checks each request with the function given as `guard` in the macro arguments, before
+    /// the arguments are deserialized.
+    fn guard(&self) -> Option<TokenStream2> {
+        let server_fn_path = self.server_fn_path();
+        let guard = self.args.guard.as_ref()?;
+        Some(quote! {
+            const GUARDED: bool = true;
+
+            fn guard() -> impl ::core::future::Future<
+                Output = ::core::result::Result<(), #server_fn_path::error::ServerFnErrorErr>
+            > + Send {
+                async { #guard().await.map_err(::core::convert::Into::into) }
+            }
+        })
+    }
+
+    /// Overrides the client-side retry policy, if a `retry` policy was given in the macro
+    /// arguments.
+    ///
+    /// An integer literal is shorthand for a maximum number of attempts.
+    fn retry_policy(&self) -> Option<TokenStream2> {
+        let server_fn_path = self.server_fn_path();
+        let policy = match self.args.retry.as_ref()? {
+            Expr::Lit(ExprLit {
+                lit: Lit::Int(attempts),
+                ..
+            }) => quote! {
+                #server_fn_path::client::retry::RetryPolicy::new(#attempts)
+            },
+            policy => quote! { ::core::convert::Into::into(#policy) },
+        };
+        Some(quote! {
+            fn retry_policy() -> Option<#server_fn_path::client::retry::RetryPolicy> {
+                Some(#policy)
+            }
+        })
+    }
+
+    /// Overrides the client-side websocket configuration, if a `websocket` configuration was
+    /// given in the macro arguments.
+    fn websocket_config(&self) -> Option<TokenStream2> {
+        let server_fn_path = self.server_fn_path();
+        let config = self.args.websocket.as_ref()?;
+        Some(quote! {
+            fn websocket_config() -> Option<#server_fn_path::websocket::WebsocketConfig> {
+                Some(#config)
+            }
+        })
+    }
+
+    /// Describes the types of the server function for the OpenAPI document, if the `openapi`
+    /// feature is enabled.
+    fn openapi_schema(
+        &self,
+        output_ty: &TokenStream2,
+        error_ty: &TokenStream2,
+    ) -> Option<TokenStream2> {
+        if !cfg!(feature = "openapi") {
+            return None;
+        }
+        let server_fn_path = self.server_fn_path();
+        let openapi = quote! { #server_fn_path::openapi };
+        let name = self.fn_name_as_str();
+        let docs = self
+            .body
+            .docs
+            .iter()
+            .map(|(doc, _)| doc.strip_prefix(' ').unwrap_or(doc))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let input = json_schema::arguments_schema(
+            &openapi,
+            self.body.inputs.iter().map(|input| {
+                let name = match &*input.arg.pat {
+                    Pat::Ident(pat) => pat.ident.to_string(),
+                    pat => pat.to_token_stream().to_string(),
+                };
+                (input.server_fn_attributes.as_slice(), name, &*input.arg.ty)
+            }),
+        )
+        .unwrap_or_else(|e| e.to_compile_error());
+        Some(quote! {
+            fn openapi_schema(
+                generator: &mut #openapi::SchemaGenerator,
+            ) -> #openapi::ServerFnSchema {
+                #[allow(unused_imports)]
+                use #openapi::{ViaAnySchema as _, ViaJsonSchema as _};
+                #openapi::ServerFnSchema {
+                    name: #name,
+                    docs: #docs,
+                    input: #input,
+                    output: (&#openapi::SchemaOf::<#output_ty>::new()).schema(generator),
+                    error: (&#openapi::SchemaOf::<#error_ty>::new()).schema(generator),
+                }
+            }
+        })
+    }
+
     /// Return the name and type of the first field if there is only one field.
     fn single_field(&self) -> Option<(&Pat, &Type)> {
         self.body
@@ -1028,6 +1237,23 @@ pub struct ServerFnArgs {
     pub impl_deref: Option<LitBool>,
     /// The protocol to use for the server function implementation.
     pub protocol: Option<Type>,
+    /// The HTTP caching policy to apply to the server function's responses.
+    pub cache: Option<Expr>,
+    /// The policy for retrying failed calls to the server function from the client.
+    pub retry: Option<Expr>,
+    /// How the client keeps the server function's websocket connection alive.
+    pub websocket: Option<Expr>,
+    /// The rate limit to apply to each client of the server function.
+    pub rate_limit: Option<Expr>,
+    /// The maximum number of concurrent requests to the server function.
+    pub max_concurrency: Option<Expr>,
+    /// An async function that checks whether a request may call the server function.
+    pub guard: Option<Path>,
+    /// The version of the server function, which is added to its URL.
+    pub version: Option<LitInt>,
+    /// Whether calls made within a millisecond of each other are sent together as one batch
+    /// request.
+    pub batch: Option<LitBool>,
     builtin_encoding: bool,
 }
 
@@ -1049,6 +1275,14 @@ impl Parse for ServerFnArgs {
         let mut impl_from: Option<LitBool> = None;
         let mut impl_deref: Option<LitBool> = None;
         let mut protocol: Option<Type> = None;
+        let mut cache: Option<Expr> = None;
+        let mut retry: Option<Expr> = None;
+        let mut websocket: Option<Expr> = None;
+        let mut rate_limit: Option<Expr> = None;
+        let mut max_concurrency: Option<Expr> = None;
+        let mut guard: Option<Path> = None;
+        let mut version: Option<LitInt> = None;
+        let mut batch: Option<LitBool> = None;
 
         let mut use_key_and_value = false;
         let mut arg_pos = 0;
@@ -1180,6 +1414,70 @@ impl Parse for ServerFnArgs {
                             ));
                         }
                         protocol = Some(stream.parse()?);
+                    } else if key == "cache" {
+                        if cache.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `cache`",
+                            ));
+                        }
+                        cache = Some(stream.parse()?);
+                    } else if key == "retry" {
+                        if retry.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `retry`",
+                            ));
+                        }
+                        retry = Some(stream.parse()?);
+                    } else if key == "websocket" {
+                        if websocket.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `websocket`",
+                            ));
+                        }
+                        websocket = Some(stream.parse()?);
+                    } else if key == "rate_limit" {
+                        if rate_limit.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `rate_limit`",
+                            ));
+                        }
+                        rate_limit = Some(stream.parse()?);
+                    } else if key == "max_concurrency" {
+                        if max_concurrency.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `max_concurrency`",
+                            ));
+                        }
+                        max_concurrency = Some(stream.parse()?);
+                    } else if key == "guard" {
+                        if guard.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `guard`",
+                            ));
+                        }
+                        guard = Some(stream.parse()?);
+                    } else if key == "version" {
+                        if version.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `version`",
+                            ));
+                        }
+                        version = Some(stream.parse()?);
+                    } else if key == "batch" {
+                        if batch.is_some() {
+                            return Err(syn::Error::new(
+                                key.span(),
+                                "keyword argument repeated: `batch`",
+                            ));
+                        }
+                        batch = Some(stream.parse()?);
                     } else {
                         return Err(lookahead.error());
                     }
@@ -1277,6 +1575,14 @@ impl Parse for ServerFnArgs {
             impl_from,
             impl_deref,
             protocol,
+            cache,
+            retry,
+            websocket,
+            rate_limit,
+            max_concurrency,
+            guard,
+            version,
+            batch,
         })
     }
 }